@@ -0,0 +1,90 @@
+//! Benchmarks of PDU codecs and framing
+//!
+//! Run them with `cargo bench --features bench`, which exposes the frame codecs.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use modbus::bench::{decode_rtu_frame, decode_tcp_frame, encode_rtu_frame, encode_tcp_frame};
+use modbus::prelude::*;
+
+const MAX_COILS: usize = 2000;
+const MAX_REGISTERS: usize = 125;
+
+fn coils() -> Vec<bool> {
+    (0..MAX_COILS).map(|i| i % 3 == 0).collect()
+}
+
+fn registers() -> Vec<u16> {
+    (0..MAX_REGISTERS as u16).map(|i| i.wrapping_mul(0x0101)).collect()
+}
+
+fn pdu(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pdu");
+
+    let req = ReadHldRegRequest::new(0x1000, MAX_REGISTERS as u16);
+    group.bench_function("encode read holding registers request", |b| b.iter(|| black_box(&req).encode().unwrap()));
+
+    let mut buf = Vec::with_capacity(256);
+    group.bench_function("encode read holding registers request into buffer", |b| b.iter(|| {
+        buf.clear();
+        black_box(&req).encode_into(&mut buf).unwrap();
+    }));
+
+    let rsp_pdu = ReadHldRegResponse::new(&registers()).encode().unwrap();
+    group.throughput(Throughput::Bytes(rsp_pdu.len() as u64));
+    group.bench_function("decode read holding registers response", |b| b.iter(|| {
+        ReadHldRegResponse::decode_response(black_box(&rsp_pdu)).unwrap()
+    }));
+
+    let values = registers();
+    let req = WriteMultiRegRequest::new(0x1000, &values[..123]);
+    let req_pdu = req.encode().unwrap();
+    group.throughput(Throughput::Bytes(req_pdu.len() as u64));
+    group.bench_function("encode write multiple registers request", |b| b.iter(|| black_box(&req).encode().unwrap()));
+    group.bench_function("decode write multiple registers request", |b| b.iter(|| {
+        WriteMultiRegRequest::decode(black_box(&req_pdu)).unwrap()
+    }));
+
+    group.finish();
+}
+
+fn bit_packing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bit packing");
+    group.throughput(Throughput::Elements(MAX_COILS as u64));
+
+    let rsp = ReadCoilsResponse::new(&coils());
+    group.bench_function("encode read coils response", |b| b.iter(|| black_box(&rsp).encode().unwrap()));
+
+    let rsp_pdu = rsp.encode().unwrap();
+    group.bench_function("decode read coils response", |b| b.iter(|| {
+        ReadCoilsResponse::decode_response(black_box(&rsp_pdu)).unwrap()
+    }));
+
+    group.bench_function("create read coils response", |b| b.iter_batched(coils,
+        |coils| ReadCoilsResponse::new(black_box(&coils)), BatchSize::SmallInput));
+
+    group.finish();
+}
+
+fn framing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("framing");
+
+    let pdu = ReadCoilsResponse::new(&coils()).encode().unwrap();
+    group.throughput(Throughput::Bytes(pdu.len() as u64));
+
+    group.bench_function("encode rtu frame", |b| b.iter(|| encode_rtu_frame(0x0a, black_box(&pdu)).unwrap()));
+    let rtu_frame = encode_rtu_frame(0x0a, &pdu).unwrap();
+    group.bench_function("decode rtu frame", |b| b.iter(|| decode_rtu_frame(black_box(&rtu_frame)).unwrap()));
+
+    group.bench_function("encode tcp frame", |b| b.iter(|| encode_tcp_frame(0x1234, 0x0a, black_box(&pdu)).unwrap()));
+    let tcp_frame = encode_tcp_frame(0x1234, 0x0a, &pdu).unwrap();
+    group.bench_function("decode tcp frame", |b| b.iter(|| decode_tcp_frame(black_box(&tcp_frame)).unwrap()));
+
+    let req_pdu = ReadCoilsRequest::new(0x0000, MAX_COILS as u16).encode().unwrap();
+    group.throughput(Throughput::Bytes(req_pdu.len() as u64));
+    group.bench_function("encode read coils request rtu frame", |b| b.iter(|| encode_rtu_frame(0x0a, black_box(&req_pdu)).unwrap()));
+
+    group.finish();
+}
+
+criterion_group!(benches, pdu, bit_packing, framing);
+criterion_main!(benches);
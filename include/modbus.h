@@ -0,0 +1,139 @@
+#ifndef MODBUS_H
+#define MODBUS_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit manually */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Operation succeeded
+#define MODBUS_OK 0
+
+// Invalid argument, like a null pointer or a quantity out of the allowed range
+#define MODBUS_ERR_INVALID_ARGUMENT -1
+
+// Error reported by the underlying socket or serial port
+#define MODBUS_ERR_IO -2
+
+// The device did not respond
+#define MODBUS_ERR_NO_RESPONSE -3
+
+// The device responded with a malformed response or a response not matching the request
+#define MODBUS_ERR_INVALID_RESPONSE -4
+
+// Modbus client handle used by the C API
+//
+// The handle is created with [modbus_tcp_new] or [modbus_rtu_new] and shall be released with
+// [modbus_free].
+typedef struct ModbusClient ModbusClient;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create a Modbus TCP client connecting to the server at given IP address
+//
+// `port` equal to 0 selects the default Modbus port 502.
+// Returns a null pointer if `ip_addr` is not a valid IPv4 or IPv6 address.
+//
+// # Safety
+// `ip_addr` shall be a null-terminated string.
+struct ModbusClient *modbus_tcp_new(const char *ip_addr, uint16_t port);
+
+#if defined(MODBUS_SERIAL)
+// Create a Modbus RTU client communicating through given serial port
+//
+// The port is configured with 8 data bits, no parity and 1 stop bit.
+// Returns a null pointer if the port cannot be opened.
+//
+// # Safety
+// `port` shall be a null-terminated string.
+struct ModbusClient *modbus_rtu_new(const char *port, uint32_t baud_rate);
+#endif
+
+// Release a client created with [modbus_tcp_new] or [modbus_rtu_new]
+//
+// Passing a null pointer is allowed and does nothing.
+//
+// # Safety
+// `client` shall be a pointer returned by one of the constructors, not released before.
+void modbus_free(struct ModbusClient *client);
+
+// Read `quantity` coils starting at `address`, storing each of them as 0 or 1 in `values`
+//
+// # Safety
+// `client` shall be a valid client and `values` shall point to at least `quantity` bytes.
+int modbus_read_coils(struct ModbusClient *client,
+                      uint8_t unit_id,
+                      uint16_t address,
+                      uint16_t quantity,
+                      uint8_t *values);
+
+// Read `quantity` discrete inputs starting at `address`, storing each of them as 0 or 1 in `values`
+//
+// # Safety
+// `client` shall be a valid client and `values` shall point to at least `quantity` bytes.
+int modbus_read_discrete_inputs(struct ModbusClient *client,
+                                uint8_t unit_id,
+                                uint16_t address,
+                                uint16_t quantity,
+                                uint8_t *values);
+
+// Read `quantity` holding registers starting at `address` to `values`
+//
+// # Safety
+// `client` shall be a valid client and `values` shall point to at least `quantity` registers.
+int modbus_read_holding_registers(struct ModbusClient *client,
+                                  uint8_t unit_id,
+                                  uint16_t address,
+                                  uint16_t quantity,
+                                  uint16_t *values);
+
+// Read `quantity` input registers starting at `address` to `values`
+//
+// # Safety
+// `client` shall be a valid client and `values` shall point to at least `quantity` registers.
+int modbus_read_input_registers(struct ModbusClient *client,
+                                uint8_t unit_id,
+                                uint16_t address,
+                                uint16_t quantity,
+                                uint16_t *values);
+
+// Write a single coil at `address`, any nonzero `value` sets the coil
+//
+// # Safety
+// `client` shall be a valid client.
+int modbus_write_single_coil(struct ModbusClient *client,
+                             uint8_t unit_id,
+                             uint16_t address,
+                             uint8_t value);
+
+// Write a single holding register at `address`
+//
+// # Safety
+// `client` shall be a valid client.
+int modbus_write_single_register(struct ModbusClient *client,
+                                 uint8_t unit_id,
+                                 uint16_t address,
+                                 uint16_t value);
+
+// Write `quantity` holding registers starting at `address` from `values`
+//
+// # Safety
+// `client` shall be a valid client and `values` shall point to at least `quantity` registers.
+int modbus_write_multiple_registers(struct ModbusClient *client,
+                                    uint8_t unit_id,
+                                    uint16_t address,
+                                    uint16_t quantity,
+                                    const uint16_t *values);
+
+// Get a static, null-terminated description of a status returned by the functions above
+const char *modbus_strerror(int status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MODBUS_H */
@@ -0,0 +1,139 @@
+//! Derive macros for the modbus crate
+//!
+//! The macros are re-exported by the modbus crate with the `derive` feature enabled.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, LitInt, LitStr};
+
+/// Layout of a field in the registers
+struct FieldLayout {
+    skip: u16,
+    word_order: Option<TokenStream2>,
+}
+
+fn parse_field_layout(field: &Field) -> syn::Result<FieldLayout> {
+    let mut layout = FieldLayout {skip: 0, word_order: None};
+
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("modbus")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                layout.skip = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                Ok(())
+            } else if meta.path.is_ident("word_order") {
+                let word_order: LitStr = meta.value()?.parse()?;
+                layout.word_order = Some(match word_order.value().as_str() {
+                    "high_word_first" => quote!(::modbus::value::WordOrder::HighWordFirst),
+                    "low_word_first" => quote!(::modbus::value::WordOrder::LowWordFirst),
+                    _ => return Err(syn::Error::new_spanned(word_order, "expected \"high_word_first\" or \"low_word_first\"")),
+                });
+                Ok(())
+            } else {
+                Err(meta.error("unsupported modbus attribute, expected `skip` or `word_order`"))
+            }
+        })?;
+    }
+
+    Ok(layout)
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(syn::Error::new_spanned(input, "ModbusLayout can be derived only for structs")),
+    };
+
+    let value = quote!(::modbus::value::RegisterValue);
+    let mut offset = quote!(0u16);
+    let mut decoders = Vec::new();
+    let mut encoders = Vec::new();
+
+    for (index, field) in fields.iter().enumerate() {
+        let layout = parse_field_layout(field)?;
+        let ty = &field.ty;
+        let skip = layout.skip;
+        let word_order = layout.word_order.unwrap_or(quote!(word_order));
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = syn::Index::from(index);
+                quote!(#index)
+            }
+        };
+
+        offset = quote!(#offset + #skip);
+        let start = offset.clone();
+        offset = quote!(#offset + <#ty as #value>::SIZE);
+
+        decoders.push(quote! {
+            #member: <#ty as #value>::from_registers(&registers[(#start) as usize..(#offset) as usize], #word_order)?
+        });
+        encoders.push(quote! {
+            registers.resize((#start) as usize, 0);
+            registers.extend(#value::to_registers(&self.#member, #word_order));
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let construct = match fields {
+        Fields::Named(_) | Fields::Unnamed(_) => quote!(Self {#(#decoders),*}),
+        Fields::Unit => quote!(Self),
+    };
+
+    Ok(quote! {
+        impl #impl_generics #value for #name #ty_generics #where_clause {
+            const SIZE: u16 = #offset;
+
+            fn from_registers(registers: &[u16], word_order: ::modbus::value::WordOrder) -> ::core::result::Result<Self, ::modbus::Error> {
+                if registers.len() != <Self as #value>::SIZE as usize {
+                    return ::core::result::Result::Err(::modbus::Error::InvalidDataLength);
+                }
+
+                ::core::result::Result::Ok(#construct)
+            }
+
+            fn to_registers(&self, word_order: ::modbus::value::WordOrder) -> ::modbus::__private::Vec<u16> {
+                let mut registers = ::modbus::__private::Vec::with_capacity(<Self as #value>::SIZE as usize);
+                #(#encoders)*
+                registers
+            }
+        }
+    })
+}
+
+/// Derive `modbus::value::RegisterValue` for a struct kept in consecutive registers
+///
+/// Fields are kept one after another in the order of declaration. Each field type shall implement
+/// `RegisterValue`, which defines how many registers it takes and how it is encoded, e.g. `u16`,
+/// `f32` or `Bcd<u32>`. Fields accept attributes:
+/// * `#[modbus(skip = N)]` leaves `N` reserved registers before the field, zeroed when encoding,
+/// * `#[modbus(word_order = "low_word_first")]` or `"high_word_first"` overrides the word order
+///   passed to `from_registers` and `to_registers`.
+///
+/// # Examples
+/// ```
+/// use modbus::value::{ModbusLayout, RegisterValue, WordOrder};
+///
+/// #[derive(ModbusLayout)]
+/// struct Meter {
+///     voltage: f32,
+///     #[modbus(skip = 2)]
+///     status: u16,
+///     #[modbus(word_order = "low_word_first")]
+///     energy: u32,
+/// }
+///
+/// let registers = [0x4366, 0x0000, 0xffff, 0xffff, 0x0001, 0x5678, 0x1234];
+/// let meter = Meter::from_registers(&registers, WordOrder::HighWordFirst).unwrap();
+/// assert_eq!(Meter::SIZE, 7);
+/// assert_eq!(meter.voltage, 230.0);
+/// assert_eq!(meter.status, 1);
+/// assert_eq!(meter.energy, 0x12345678);
+/// ```
+#[proc_macro_derive(ModbusLayout, attributes(modbus))]
+pub fn derive_modbus_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
@@ -0,0 +1,183 @@
+//! Conversions between the Modicon notation and addresses used by PDUs
+//!
+//! Device documentation often refers to coils and registers with numbers like `40001`,
+//! where the leading digit selects the table and the remaining digits are a one-based number.
+//! PDUs carry zero-based addresses of the selected table, so `40001` is the holding register
+//! at address 0. The 5-digit notation covers the first 9999 items of each table, the 6-digit
+//! notation like `400001` covers all 65536 of them.
+//!
+//! | Leading digit | Table                    |
+//! |---------------|--------------------------|
+//! | 0             | [Table::Coils]            |
+//! | 1             | [Table::DiscreteInputs]   |
+//! | 3             | [Table::InputRegisters]   |
+//! | 4             | [Table::HoldingRegisters] |
+//!
+//! # Examples
+//! ```
+//! use modbus::address::{ModiconAddress, Table};
+//!
+//! let reg: ModiconAddress = "30017".parse().unwrap();
+//! assert_eq!(reg.get_table(), Table::InputRegisters);
+//! assert_eq!(reg.get_address(), 16);
+//!
+//! let coil = ModiconAddress::new(Table::Coils, 12);
+//! assert_eq!(coil.to_string(), "00013");
+//! ```
+
+use crate::error::Error;
+use core::fmt;
+use core::str::FromStr;
+
+const SHORT_DIGITS: usize = 5;
+const LONG_DIGITS: usize = 6;
+const SHORT_MAX_NUMBER: u32 = 9999;
+const LONG_MAX_NUMBER: u32 = 65536;
+
+/// Table of the Modbus data model
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Table {
+    Coils,
+    DiscreteInputs,
+    HoldingRegisters,
+    InputRegisters,
+}
+
+impl Table {
+    /// Check if the table keeps single bits rather than 16-bit registers
+    pub fn is_bit(&self) -> bool {
+        matches!(self, Table::Coils | Table::DiscreteInputs)
+    }
+
+    fn get_modicon_digit(&self) -> u8 {
+        match self {
+            Table::Coils => 0,
+            Table::DiscreteInputs => 1,
+            Table::InputRegisters => 3,
+            Table::HoldingRegisters => 4,
+        }
+    }
+
+    fn from_modicon_digit(digit: u8) -> Option<Self> {
+        match digit {
+            0 => Some(Table::Coils),
+            1 => Some(Table::DiscreteInputs),
+            3 => Some(Table::InputRegisters),
+            4 => Some(Table::HoldingRegisters),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Table::Coils => f.write_str("coils"),
+            Table::DiscreteInputs => f.write_str("discrete_inputs"),
+            Table::HoldingRegisters => f.write_str("holding_registers"),
+            Table::InputRegisters => f.write_str("input_registers"),
+        }
+    }
+}
+
+/// Coil or register identified by its table and zero-based address
+///
+/// It is parsed from and displayed in the Modicon notation. Addresses up to 9998 are displayed
+/// in the 5-digit notation, higher ones in the 6-digit notation.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ModiconAddress {
+    table: Table,
+    address: u16,
+}
+
+impl ModiconAddress {
+    /// Create a reference to the item at given zero-based address of given table
+    pub fn new(table: Table, address: u16) -> Self {
+        Self {table, address}
+    }
+
+    /// Get table of the referenced item
+    pub fn get_table(&self) -> Table {
+        self.table
+    }
+
+    /// Get zero-based address of the referenced item, as used by PDUs
+    pub fn get_address(&self) -> u16 {
+        self.address
+    }
+
+    /// Get one-based number of the referenced item within its table
+    pub fn get_number(&self) -> u32 {
+        self.address as u32 + 1
+    }
+}
+
+impl FromStr for ModiconAddress {
+    type Err = Error;
+
+    /// Parse the 5-digit or 6-digit Modicon notation
+    ///
+    /// It fails with [Error::InvalidValue] if the notation has other number of digits, an unknown
+    /// table digit, or a number out of the range of the notation.
+    fn from_str(notation: &str) -> Result<Self, Self::Err> {
+        let notation = notation.trim();
+        let max_number = match notation.len() {
+            SHORT_DIGITS => SHORT_MAX_NUMBER,
+            LONG_DIGITS => LONG_MAX_NUMBER,
+            _ => return Err(Error::InvalidValue),
+        };
+        if !notation.bytes().all(|digit| digit.is_ascii_digit()) {
+            return Err(Error::InvalidValue);
+        }
+
+        let table = Table::from_modicon_digit(notation.as_bytes()[0] - b'0').ok_or(Error::InvalidValue)?;
+        let number: u32 = notation[1..].parse().map_err(|_| Error::InvalidValue)?;
+        if !(1..=max_number).contains(&number) {
+            return Err(Error::InvalidValue);
+        }
+
+        Ok(Self::new(table, (number - 1) as u16))
+    }
+}
+
+impl fmt::Display for ModiconAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let number = self.get_number();
+        let width = if number <= SHORT_MAX_NUMBER { SHORT_DIGITS } else { LONG_DIGITS } - 1;
+        write!(f, "{}{:0width$}", self.table.get_modicon_digit(), number, width = width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("40001".parse::<ModiconAddress>().unwrap(), ModiconAddress::new(Table::HoldingRegisters, 0));
+        assert_eq!("30017".parse::<ModiconAddress>().unwrap(), ModiconAddress::new(Table::InputRegisters, 16));
+        assert_eq!("00013".parse::<ModiconAddress>().unwrap(), ModiconAddress::new(Table::Coils, 12));
+        assert_eq!("19999".parse::<ModiconAddress>().unwrap(), ModiconAddress::new(Table::DiscreteInputs, 9998));
+        assert_eq!("400001".parse::<ModiconAddress>().unwrap(), ModiconAddress::new(Table::HoldingRegisters, 0));
+        assert_eq!("465536".parse::<ModiconAddress>().unwrap(), ModiconAddress::new(Table::HoldingRegisters, 0xffff));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for notation in ["40000", "400000", "465537", "20001", "4001", "4000001", "4x001", "+4001"] {
+            assert!(matches!(notation.parse::<ModiconAddress>(), Err(Error::InvalidValue)), "{}", notation);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(ModiconAddress::new(Table::HoldingRegisters, 0).to_string(), "40001");
+        assert_eq!(ModiconAddress::new(Table::Coils, 12).to_string(), "00013");
+        assert_eq!(ModiconAddress::new(Table::InputRegisters, 9998).to_string(), "39999");
+        assert_eq!(ModiconAddress::new(Table::InputRegisters, 9999).to_string(), "310000");
+        assert_eq!(ModiconAddress::new(Table::DiscreteInputs, 0xffff).to_string(), "165536");
+    }
+}
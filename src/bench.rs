@@ -0,0 +1,29 @@
+//! Entry points to internal codecs used by the benchmarks in `benches/`
+//!
+//! This module is available with the `bench` feature. It is not a part of the public API and may
+//! change without notice.
+
+use crate::error::Error;
+use crate::transport::rtu::frame::Frame as RtuFrame;
+use crate::transport::tcp::frame::Frame as TcpFrame;
+use alloc::vec::Vec;
+
+/// Encode an RTU frame including its CRC
+pub fn encode_rtu_frame(address: u8, pdu: &[u8]) -> Result<Vec<u8>, Error> {
+    RtuFrame::new(address, pdu).encode()
+}
+
+/// Decode an RTU frame verifying its CRC and return its PDU
+pub fn decode_rtu_frame(data: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(RtuFrame::decode(data)?.get_pdu())
+}
+
+/// Encode a TCP frame with an MBAP header
+pub fn encode_tcp_frame(transaction_id: u16, unit_id: u8, pdu: &[u8]) -> Result<Vec<u8>, Error> {
+    TcpFrame::with_transaction_id(transaction_id, unit_id, pdu).encode()
+}
+
+/// Decode a TCP frame with an MBAP header and return its PDU
+pub fn decode_tcp_frame(data: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(TcpFrame::decode(data)?.get_pdu())
+}
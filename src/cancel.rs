@@ -0,0 +1,76 @@
+//! Cooperative cancellation of long-running operations
+
+use crate::error::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Delay after the second consecutive failure of a serving loop, doubled after each next one
+const MIN_BACKOFF: Duration = Duration::from_millis(10);
+/// Longest delay between consecutive failures of a serving loop
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Token used to request cancellation of a long-running operation
+///
+/// The token can be cloned and sent to another thread. Cancelling any of the
+/// clones cancels all of them.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Create a new token which is not cancelled
+    ///
+    /// # Examples
+    /// ```
+    /// let token = modbus::CancelToken::new();
+    /// let clone = token.clone();
+    ///
+    /// clone.cancel();
+    /// assert!(token.is_cancelled());
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of operations using this token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Check if cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Call given function serving a single request until the token or the transport is cancelled
+///
+/// Failed requests do not stop the loop, as a single misbehaving master may cause them. Repeated
+/// failures are followed by increasing delays, so that a persistently failing transport, like one
+/// with a closed listener, does not keep the CPU busy.
+pub(crate) fn serve_until<F>(cancel_token: &CancelToken, mut process_req: F) -> Result<(), Error>
+where
+    F: FnMut() -> Result<(), Error>,
+{
+    let mut backoff = None;
+
+    while !cancel_token.is_cancelled() {
+        match process_req() {
+            Ok(()) => backoff = None,
+            Err(Error::Cancelled) => break,
+            Err(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_err, "Failed to serve request");
+                if let Some(delay) = backoff {
+                    thread::sleep(delay);
+                }
+                backoff = Some(backoff.map_or(MIN_BACKOFF, |delay: Duration| (delay * 2).min(MAX_BACKOFF)));
+            }
+        }
+    }
+
+    Ok(())
+}
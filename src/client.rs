@@ -0,0 +1,593 @@
+//! Modbus masters issuing requests through shared references
+//!
+//! [Transport] methods require exclusive references, which makes transports hard to store in
+//! state shared by different parts of an application. Implementations of the [Client] trait own
+//! a transport working in the master mode and issue requests through shared references:
+//! * [LocalClient] borrows the transport dynamically and is intended for a single thread,
+//! * [SharedClient] locks the transport and serializes transactions requested by multiple threads,
+//!   serving the waiting ones in the order of their [Priority].
+//!
+//! Requests and responses of any client can be observed or modified by wrapping its transport
+//! in an [InterceptingTransport](crate::intercept::InterceptingTransport).
+
+use crate::error::Error;
+use crate::pdu::raw::Decoded;
+use crate::pdu::{DecodeMode, ExceptionCode, Request, Setter};
+use crate::transport::Transport;
+use crate::value::{RegisterValue, WordOrder};
+use crate::{MaskWriteRegRequest, ReadHldRegRequest, WriteMultiRegRequest, WriteSingleRegRequest};
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Method of modifying selected bits of a holding register
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum BitWriteMode {
+    /// Use Mask Write Register, falling back to read-modify-write if the slave does not support it
+    #[default]
+    MaskWrite,
+    /// Read the register, modify it and write it back with Write Single Register
+    ///
+    /// Other masters may modify the register between the read and the write.
+    ReadModifyWrite,
+}
+
+/// Get mask of given bit of a register
+fn bit_mask(bit: u8) -> Result<u16, Error> {
+    1u16.checked_shl(bit as u32).ok_or(Error::InvalidValue)
+}
+
+/// Modbus master issuing requests through shared references
+pub trait Client {
+    /// Transport used to exchange transactions
+    type Transport: Transport;
+
+    /// Execute given function with exclusive access to the transport
+    ///
+    /// It allows performing multiple transactions without other transactions in between,
+    /// or configuring the transport. The function shall not use the client, as it would
+    /// deadlock or panic.
+    fn with_transport<R, F: FnOnce(&mut Self::Transport) -> R>(&self, f: F) -> R;
+
+    /// Write a request frame and read a response frame.
+    ///
+    /// See [Transport::write_req_read_rsp].
+    fn write_req_read_rsp<Req: Request>(&self, dst: &<Self::Transport as Transport>::Dst, req: &Req)
+        -> Result<Option<Req::Rsp>, Error>
+    {
+        self.with_transport(|transport| transport.write_req_read_rsp(dst, req))
+    }
+
+    /// Write a request frame and read a response frame decoded in the given mode.
+    ///
+    /// See [Transport::write_req_read_rsp_with_mode].
+    fn write_req_read_rsp_with_mode<Req: Request>(&self, dst: &<Self::Transport as Transport>::Dst, req: &Req,
+                                                  mode: DecodeMode) -> Result<Option<Req::Rsp>, Error>
+    {
+        self.with_transport(|transport| transport.write_req_read_rsp_with_mode(dst, req, mode))
+    }
+
+    /// Write a request frame and read a response frame decoded in the given mode, keeping its raw PDU.
+    ///
+    /// See [Transport::write_req_read_rsp_raw].
+    fn write_req_read_rsp_raw<Req: Request>(&self, dst: &<Self::Transport as Transport>::Dst, req: &Req,
+                                            mode: DecodeMode) -> Result<Option<Decoded<Req::Rsp>>, Error>
+    {
+        self.with_transport(|transport| transport.write_req_read_rsp_raw(dst, req, mode))
+    }
+
+    /// Write a setter request and read a response frame.
+    ///
+    /// See [Transport::write_setter_req].
+    fn write_setter_req<Req: Setter>(&self, dst: &<Self::Transport as Transport>::Dst, req: &Req) -> Result<(), Error>
+        where Req::Rsp: PartialEq
+    {
+        self.with_transport(|transport| transport.write_setter_req(dst, req))
+    }
+
+    /// Check health of the link with given destination and measure the round-trip latency
+    ///
+    /// The [ping request of the transport](Transport::get_ping_pdu) is sent, Diagnostics Return
+    /// Query Data by default. Any response, including an exception response, proves the link works.
+    /// Broadcast destinations fail with [Error::InvalidValue], as they never respond.
+    fn ping(&self, dst: &<Self::Transport as Transport>::Dst) -> Result<Duration, Error> {
+        if Self::Transport::is_broadcast(dst) {
+            return Err(Error::InvalidValue);
+        }
+
+        self.with_transport(|transport| {
+            let req_pdu = transport.get_ping_pdu().to_vec();
+            let start = Instant::now();
+            let mut stream = transport.write_req_pdu(dst, &req_pdu)?;
+            transport.read_rsp_pdu(&mut stream, dst)?;
+            Ok(start.elapsed())
+        })
+    }
+
+    /// Read a typed value from consecutive holding registers starting at given address
+    fn read_value<V: RegisterValue>(&self, dst: &<Self::Transport as Transport>::Dst, address: u16,
+                                    word_order: WordOrder) -> Result<V, Error>
+    {
+        let rsp = self.write_req_read_rsp(dst, &ReadHldRegRequest::new(address, V::SIZE))?.ok_or(Error::NoResponse)?;
+        V::from_registers(rsp.get_registers(), word_order)
+    }
+
+    /// Write a typed value to consecutive holding registers starting at given address
+    fn write_value<V: RegisterValue>(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, value: V,
+                                     word_order: WordOrder) -> Result<(), Error>
+    {
+        self.write_setter_req(dst, &WriteMultiRegRequest::new(address, &value.to_registers(word_order)))
+    }
+
+    /// Read bits of a holding register selected by given mask
+    ///
+    /// Bits not selected by the mask are cleared in the returned value.
+    fn read_register_bits(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, mask: u16) -> Result<u16, Error> {
+        let rsp = self.write_req_read_rsp(dst, &ReadHldRegRequest::new(address, 1))?.ok_or(Error::NoResponse)?;
+        Ok(rsp.get_registers()[0] & mask)
+    }
+
+    /// Read a single bit, numbered from 0 for the least significant one, of a holding register
+    fn read_register_bit(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, bit: u8) -> Result<bool, Error> {
+        let mask = bit_mask(bit)?;
+        Ok(self.read_register_bits(dst, address, mask)? != 0)
+    }
+
+    /// Set bits of a holding register selected by given mask to the bits of given value
+    ///
+    /// Other bits of the register are not modified. The read-modify-write sequence is executed
+    /// with exclusive access to the transport, so it is not interleaved with other requests of
+    /// this client.
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::client::{BitWriteMode, Client, LocalClient};
+    /// use modbus::mock::MockTransport;
+    ///
+    /// let mut mb = MockTransport::new();
+    /// mb.expect(&[0x16, 0x00, 0x04, 0xff, 0x0f, 0x00, 0x50], &[0x96, 0x01]);
+    /// mb.expect(&[0x03, 0x00, 0x04, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x34]);
+    /// mb.expect(&[0x06, 0x00, 0x04, 0x12, 0x54], &[0x06, 0x00, 0x04, 0x12, 0x54]);
+    ///
+    /// let client = LocalClient::new(mb);
+    /// client.set_register_bits(&1, 0x0004, 0x00f0, 0x0050, BitWriteMode::MaskWrite).unwrap();
+    /// assert!(client.into_inner().is_complete());
+    /// ```
+    fn set_register_bits(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, mask: u16, value: u16,
+                         mode: BitWriteMode) -> Result<(), Error>
+    {
+        let req = MaskWriteRegRequest::with_bits(address, mask, value);
+
+        self.with_transport(|transport| {
+            if mode == BitWriteMode::MaskWrite {
+                match transport.write_setter_req(dst, &req) {
+                    Err(err) if matches!(err.get_root(), Error::ExceptionResponse(ExceptionCode::IllegalFunction)) => {}
+                    result => return result,
+                }
+            }
+
+            let rsp = transport.write_req_read_rsp(dst, &ReadHldRegRequest::new(address, 1))?.ok_or(Error::NoResponse)?;
+            transport.write_setter_req(dst, &WriteSingleRegRequest::new(address, req.apply(rsp.get_registers()[0])))
+        })
+    }
+
+    /// Set a single bit, numbered from 0 for the least significant one, of a holding register
+    ///
+    /// See [Client::set_register_bits].
+    fn set_register_bit(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, bit: u8, value: bool) -> Result<(), Error> {
+        let mask = bit_mask(bit)?;
+        self.set_register_bits(dst, address, mask, if value { mask } else { 0 }, BitWriteMode::default())
+    }
+
+    /// Read a 32-bit float from two holding registers starting at given address
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::client::{Client, LocalClient};
+    /// use modbus::mock::MockTransport;
+    /// use modbus::value::WordOrder;
+    ///
+    /// let mut mb = MockTransport::new();
+    /// mb.expect(&[0x03, 0x00, 0x10, 0x00, 0x02], &[0x03, 0x04, 0x3f, 0xc0, 0x00, 0x00]);
+    ///
+    /// let client = LocalClient::new(mb);
+    /// assert_eq!(client.read_f32(&1, 0x0010, WordOrder::HighWordFirst).unwrap(), 1.5);
+    /// ```
+    fn read_f32(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, word_order: WordOrder) -> Result<f32, Error> {
+        self.read_value(dst, address, word_order)
+    }
+
+    /// Write a 32-bit float to two holding registers starting at given address
+    fn write_f32(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, value: f32, word_order: WordOrder) -> Result<(), Error> {
+        self.write_value(dst, address, value, word_order)
+    }
+
+    /// Read an unsigned 32-bit integer from two holding registers starting at given address
+    fn read_u32(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, word_order: WordOrder) -> Result<u32, Error> {
+        self.read_value(dst, address, word_order)
+    }
+
+    /// Write an unsigned 32-bit integer to two holding registers starting at given address
+    fn write_u32(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, value: u32, word_order: WordOrder) -> Result<(), Error> {
+        self.write_value(dst, address, value, word_order)
+    }
+
+    /// Read a signed 32-bit integer from two holding registers starting at given address
+    fn read_i32(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, word_order: WordOrder) -> Result<i32, Error> {
+        self.read_value(dst, address, word_order)
+    }
+
+    /// Write a signed 32-bit integer to two holding registers starting at given address
+    fn write_i32(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, value: i32, word_order: WordOrder) -> Result<(), Error> {
+        self.write_value(dst, address, value, word_order)
+    }
+
+    /// Read an unsigned 64-bit integer from four holding registers starting at given address
+    fn read_u64(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, word_order: WordOrder) -> Result<u64, Error> {
+        self.read_value(dst, address, word_order)
+    }
+
+    /// Write an unsigned 64-bit integer to four holding registers starting at given address
+    fn write_u64(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, value: u64, word_order: WordOrder) -> Result<(), Error> {
+        self.write_value(dst, address, value, word_order)
+    }
+
+    /// Read a signed 64-bit integer from four holding registers starting at given address
+    fn read_i64(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, word_order: WordOrder) -> Result<i64, Error> {
+        self.read_value(dst, address, word_order)
+    }
+
+    /// Write a signed 64-bit integer to four holding registers starting at given address
+    fn write_i64(&self, dst: &<Self::Transport as Transport>::Dst, address: u16, value: i64, word_order: WordOrder) -> Result<(), Error> {
+        self.write_value(dst, address, value, word_order)
+    }
+}
+
+/// Client of a transport used by a single thread
+///
+/// # Examples
+/// ```
+/// use modbus::client::{Client, LocalClient};
+/// use modbus::mock::MockTransport;
+/// use std::rc::Rc;
+///
+/// struct Dashboard {
+///     client: Rc<LocalClient<MockTransport>>,
+/// }
+///
+/// let mut mb = MockTransport::new();
+/// mb.expect(&[0x03, 0x00, 0x00, 0x00, 0x01], &[0x03, 0x02, 0x00, 0x2a]);
+///
+/// let dashboard = Dashboard {client: Rc::new(LocalClient::new(mb))};
+/// let rsp = dashboard.client.write_req_read_rsp(&1, &modbus::ReadHldRegRequest::new(0x0000, 1)).unwrap();
+/// assert_eq!(rsp.unwrap().get_registers(), &[42]);
+/// ```
+pub struct LocalClient<T: Transport> {
+    transport: RefCell<T>,
+}
+
+impl<T: Transport> LocalClient<T> {
+    /// Create a client of given transport
+    ///
+    /// The transport shall be already started in the master mode.
+    pub fn new(transport: T) -> Self {
+        Self {transport: RefCell::new(transport)}
+    }
+
+    /// Get back the transport
+    pub fn into_inner(self) -> T {
+        self.transport.into_inner()
+    }
+}
+
+impl<T: Transport> Client for LocalClient<T> {
+    type Transport = T;
+
+    fn with_transport<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        f(&mut self.transport.borrow_mut())
+    }
+}
+
+impl<T: Transport> fmt::Debug for LocalClient<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LocalClient").finish_non_exhaustive()
+    }
+}
+
+/// Priority of transactions waiting for a transport shared by a [SharedClient]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    /// Routine transactions, e.g. periodic polling
+    Low,
+    #[default]
+    Normal,
+    /// Urgent transactions, e.g. commands of an operator
+    High,
+}
+
+const PRIORITIES: usize = 3;
+
+/// Transactions using and waiting for a shared transport
+#[derive(Default)]
+struct Queue {
+    busy: bool,
+    waiting: [usize; PRIORITIES],
+}
+
+struct Shared<T> {
+    transport: Mutex<T>,
+    queue: Mutex<Queue>,
+    released: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn lock_queue(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Turn of a transaction to use the shared transport, passed to the next one when dropped
+struct Turn<'a, T> {
+    shared: &'a Shared<T>,
+}
+
+impl<T> Drop for Turn<'_, T> {
+    fn drop(&mut self) {
+        self.shared.lock_queue().busy = false;
+        self.shared.released.notify_all();
+    }
+}
+
+/// Handle of a transport shared between threads
+///
+/// Cloned handles share the same transport. Each transaction locks the transport until
+/// the response is read, so transactions of different threads never interleave.
+/// When the transport is released, the waiting transaction of the highest [Priority] is
+/// executed next.
+///
+/// # Examples
+/// ```
+/// use modbus::client::{Client, SharedClient};
+/// use modbus::mock::MockTransport;
+/// use modbus::Transport;
+///
+/// let mut mb = MockTransport::new();
+/// mb.expect(&[0x06, 0x00, 0x01, 0x00, 0x03], &[0x06, 0x00, 0x01, 0x00, 0x03]);
+/// mb.start_master().unwrap();
+///
+/// let client = SharedClient::new(mb);
+/// let worker = client.clone();
+/// std::thread::spawn(move || {
+///     worker.write_setter_req(&1, &modbus::WriteSingleRegRequest::new(0x0001, 3)).unwrap();
+/// }).join().unwrap();
+///
+/// assert!(client.with_transport(|mb| mb.is_complete()));
+/// ```
+pub struct SharedClient<T: Transport> {
+    shared: Arc<Shared<T>>,
+    priority: Priority,
+}
+
+impl<T: Transport> SharedClient<T> {
+    /// Create a client sharing given transport
+    ///
+    /// The transport shall be already started in the master mode.
+    pub fn new(transport: T) -> Self {
+        let shared = Shared {transport: Mutex::new(transport), queue: Mutex::new(Queue::default()), released: Condvar::new()};
+        Self {shared: Arc::new(shared), priority: Priority::default()}
+    }
+
+    /// Set priority of transactions requested through this handle, [Priority::Normal] by default
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::client::{Priority, SharedClient};
+    /// use modbus::mock::MockTransport;
+    ///
+    /// let poller = SharedClient::new(MockTransport::new()).with_priority(Priority::Low);
+    /// let operator = poller.clone().with_priority(Priority::High);
+    /// ```
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Get priority of transactions requested through this handle
+    pub fn get_priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Wait until no transaction uses the transport and no transaction of higher priority waits for it
+    fn wait_for_turn(&self) -> Turn<'_, T> {
+        let priority = self.priority as usize;
+        let mut queue = self.shared.lock_queue();
+        queue.waiting[priority] += 1;
+
+        while queue.busy || queue.waiting[priority + 1..].iter().any(|waiting| *waiting > 0) {
+            queue = self.shared.released.wait(queue).unwrap_or_else(PoisonError::into_inner);
+        }
+
+        queue.waiting[priority] -= 1;
+        queue.busy = true;
+        Turn {shared: &self.shared}
+    }
+
+    fn lock(&self) -> MutexGuard<'_, T> {
+        // A panic of another thread does not leave the transport in an unusable state
+        self.shared.transport.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Transport> Client for SharedClient<T> {
+    type Transport = T;
+
+    fn with_transport<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        let _turn = self.wait_for_turn();
+        f(&mut self.lock())
+    }
+}
+
+impl<T: Transport> Clone for SharedClient<T> {
+    fn clone(&self) -> Self {
+        Self {shared: self.shared.clone(), priority: self.priority}
+    }
+}
+
+impl<T: Transport> fmt::Debug for SharedClient<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedClient")
+            .field("handles", &Arc::strong_count(&self.shared))
+            .field("priority", &self.priority)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::{ReadHldRegRequest, WriteSingleRegRequest};
+    use std::thread;
+
+    const THREADS: u16 = 4;
+    const TRANSACTIONS: u16 = 25;
+
+    #[test]
+    fn test_concurrent_transactions() {
+        let mut mb = MockTransport::new();
+        for _ in 0..THREADS * TRANSACTIONS * 2 {
+            mb.expect_fn(Box::new(|_, req_pdu| match req_pdu[0] {
+                0x03 => Some(vec![0x03, 0x02, req_pdu[1], req_pdu[2]]),
+                _ => Some(req_pdu.to_vec()),
+            }));
+        }
+        let client = SharedClient::new(mb);
+
+        let workers: Vec<_> = (0..THREADS).map(|thread_id| {
+            let client = client.clone();
+            thread::spawn(move || {
+                for i in 0..TRANSACTIONS {
+                    let address = thread_id * TRANSACTIONS + i;
+                    client.write_setter_req(&1, &WriteSingleRegRequest::new(address, i)).unwrap();
+                    let rsp = client.write_req_read_rsp(&1, &ReadHldRegRequest::new(address, 1)).unwrap().unwrap();
+                    assert_eq!(rsp.get_registers(), &[address]);
+                }
+            })
+        }).collect();
+        workers.into_iter().for_each(|worker| worker.join().unwrap());
+
+        assert!(client.with_transport(|mb| mb.is_complete()));
+    }
+
+    #[test]
+    fn test_priority() {
+        let mut mb = MockTransport::new();
+        mb.expect_fn(Box::new(|_, req_pdu| Some(req_pdu.to_vec())));
+        mb.expect_fn(Box::new(|_, req_pdu| Some(req_pdu.to_vec())));
+        let client = SharedClient::new(mb);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let (low, high) = client.with_transport(|_| {
+            let spawn = |priority: Priority, delay: u64| {
+                let client = client.clone().with_priority(priority);
+                let order = order.clone();
+                thread::spawn(move || {
+                    thread::sleep(std::time::Duration::from_millis(delay));
+                    client.with_transport(|mb| {
+                        order.lock().unwrap().push(priority);
+                        mb.write_req_read_rsp(&1, &WriteSingleRegRequest::new(priority as u16, 0)).unwrap();
+                    });
+                })
+            };
+
+            // Both transactions wait until the transport is released, the routine one longer
+            let low = spawn(Priority::Low, 0);
+            let high = spawn(Priority::High, 50);
+            thread::sleep(std::time::Duration::from_millis(200));
+            (low, high)
+        });
+        low.join().unwrap();
+        high.join().unwrap();
+
+        assert_eq!(*order.lock().unwrap(), vec![Priority::High, Priority::Low]);
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<C: Send + Sync>() {}
+        assert_send_sync::<SharedClient<MockTransport>>();
+    }
+
+    fn read_register<C: Client<Transport = MockTransport>>(client: &C, address: u16) -> u16 {
+        let rsp = client.write_req_read_rsp(&1, &ReadHldRegRequest::new(address, 1)).unwrap().unwrap();
+        rsp.get_registers()[0]
+    }
+
+    #[test]
+    fn test_f32() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x10, 0x00, 0x20, 0x00, 0x02, 0x04, 0x00, 0x00, 0xc0, 0x10], &[0x10, 0x00, 0x20, 0x00, 0x02]);
+        mb.expect(&[0x03, 0x00, 0x20, 0x00, 0x02], &[0x03, 0x04, 0x00, 0x00, 0xc0, 0x10]);
+        let client = LocalClient::new(mb);
+
+        client.write_f32(&1, 0x0020, -2.25, WordOrder::LowWordFirst).unwrap();
+        assert_eq!(client.read_f32(&1, 0x0020, WordOrder::LowWordFirst).unwrap(), -2.25);
+        assert!(client.into_inner().is_complete());
+    }
+
+    #[test]
+    fn test_integers() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x03, 0x00, 0x30, 0x00, 0x04], &[0x03, 0x08, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03]);
+        mb.expect(&[0x10, 0x00, 0x40, 0x00, 0x02, 0x04, 0xff, 0xff, 0xff, 0xfe], &[0x10, 0x00, 0x40, 0x00, 0x02]);
+        let client = LocalClient::new(mb);
+
+        assert_eq!(client.read_u64(&1, 0x0030, WordOrder::HighWordFirst).unwrap(), 0x0000_0001_0002_0003);
+        client.write_i32(&1, 0x0040, -2, WordOrder::HighWordFirst).unwrap();
+        assert!(client.into_inner().is_complete());
+    }
+
+    #[test]
+    fn test_register_bits() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x03, 0x00, 0x05, 0x00, 0x01], &[0x03, 0x02, 0x80, 0x01]);
+        mb.expect(&[0x16, 0x00, 0x05, 0x7f, 0xff, 0x00, 0x00], &[0x16, 0x00, 0x05, 0x7f, 0xff, 0x00, 0x00]);
+        mb.expect(&[0x03, 0x00, 0x05, 0x00, 0x01], &[0x03, 0x02, 0x00, 0x01]);
+        mb.expect(&[0x06, 0x00, 0x05, 0x00, 0x09], &[0x06, 0x00, 0x05, 0x00, 0x09]);
+        let client = LocalClient::new(mb);
+
+        assert!(client.read_register_bit(&1, 0x0005, 15).unwrap());
+        client.set_register_bit(&1, 0x0005, 15, false).unwrap();
+        client.set_register_bits(&1, 0x0005, 0x000f, 0x0009, BitWriteMode::ReadModifyWrite).unwrap();
+        assert!(matches!(client.set_register_bit(&1, 0x0005, 16, true), Err(Error::InvalidValue)));
+        assert!(client.into_inner().is_complete());
+    }
+
+    #[test]
+    fn test_ping() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x08, 0x00, 0x00, 0x00, 0x00], &[0x08, 0x00, 0x00, 0x00, 0x00]);
+        mb.expect(&[0x08, 0x00, 0x00, 0x00, 0x00], &[0x88, 0x01]);
+        mb.expect_no_response(&[0x08, 0x00, 0x00, 0x00, 0x00]);
+        let client = LocalClient::new(mb);
+
+        client.ping(&1).unwrap();
+        client.ping(&1).unwrap();
+        assert!(matches!(client.ping(&1), Err(Error::NoResponse)));
+        assert!(matches!(client.ping(&0), Err(Error::InvalidValue)));
+        assert!(client.into_inner().is_complete());
+    }
+
+    #[test]
+    fn test_local_client() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x00, 0x05]);
+        mb.expect(&[0x03, 0x00, 0x11, 0x00, 0x01], &[0x03, 0x02, 0x00, 0x06]);
+        let client = LocalClient::new(mb);
+
+        let values = [read_register(&client, 0x0010), read_register(&client, 0x0011)];
+        assert_eq!(values, [5, 6]);
+        assert!(client.into_inner().is_complete());
+    }
+}
@@ -0,0 +1,142 @@
+//! Discovery of devices and their identification
+//!
+//! [sweep] sends Read Device Identification requests to a list of destinations, like all unit
+//! ids of a serial bus or a range of hosts of a TCP/IP network, and reports the vendor, product
+//! and revision of each device that answered.
+//!
+//! # Examples
+//! ```no_run
+//! use modbus::client::LocalClient;
+//! use modbus::discovery::sweep;
+//! use modbus::tcp::{Dst, Tcp};
+//! use std::net::{IpAddr, Ipv4Addr};
+//!
+//! let client = LocalClient::new(Tcp::new());
+//! let dsts = (1..=254).map(|host| Dst::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, host)), 1));
+//!
+//! for device in sweep(&client, dsts) {
+//!     println!("{} {} {}", device.get_vendor_name(), device.get_product_code(), device.get_revision());
+//! }
+//! ```
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::transport::Transport;
+use crate::{ReadDeviceIdCode, ReadDeviceIdRequest, ReadDeviceIdResponse};
+
+/// Basic identification of a device
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeviceIdentification<D> {
+    dst: D,
+    vendor_name: String,
+    product_code: String,
+    revision: String,
+}
+
+impl<D> DeviceIdentification<D> {
+    /// Get the destination of the device
+    pub fn get_dst(&self) -> &D {
+        &self.dst
+    }
+
+    /// Get the vendor name reported by the device
+    pub fn get_vendor_name(&self) -> &str {
+        &self.vendor_name
+    }
+
+    /// Get the product code reported by the device
+    pub fn get_product_code(&self) -> &str {
+        &self.product_code
+    }
+
+    /// Get the major and minor revision reported by the device
+    pub fn get_revision(&self) -> &str {
+        &self.revision
+    }
+}
+
+/// Read the basic identification of the device at given destination
+///
+/// The basic objects are read in as many transactions as the device requires.
+pub fn identify<C: Client>(client: &C, dst: &<C::Transport as Transport>::Dst)
+    -> Result<DeviceIdentification<<C::Transport as Transport>::Dst>, Error>
+    where <C::Transport as Transport>::Dst: Clone
+{
+    let mut identification = DeviceIdentification {dst: dst.clone(), vendor_name: String::new(),
+                                                    product_code: String::new(), revision: String::new()};
+    let mut object_id = ReadDeviceIdResponse::VENDOR_NAME;
+
+    loop {
+        let rsp = client.write_req_read_rsp(dst, &ReadDeviceIdRequest::new(ReadDeviceIdCode::Basic, object_id))?
+            .ok_or(Error::NoResponse)?;
+
+        for (id, value) in rsp.get_objects() {
+            let value = String::from_utf8_lossy(value).into_owned();
+            match *id {
+                ReadDeviceIdResponse::VENDOR_NAME => identification.vendor_name = value,
+                ReadDeviceIdResponse::PRODUCT_CODE => identification.product_code = value,
+                ReadDeviceIdResponse::MAJOR_MINOR_REVISION => identification.revision = value,
+                _ => {}
+            }
+        }
+
+        match rsp.get_next_object_id() {
+            Some(next_object_id) if next_object_id > object_id => object_id = next_object_id,
+            Some(_) => return Err(Error::InvalidResponse),
+            None => return Ok(identification),
+        }
+    }
+}
+
+/// Identify devices at given destinations, skipping the ones that did not answer
+///
+/// Broadcast destinations are skipped. Devices answering with an exception response, for
+/// example the ones not supporting Read Device Identification, are not reported either.
+pub fn sweep<C, I>(client: &C, dsts: I) -> Vec<DeviceIdentification<<C::Transport as Transport>::Dst>>
+    where C: Client, I: IntoIterator<Item = <C::Transport as Transport>::Dst>, <C::Transport as Transport>::Dst: Clone
+{
+    dsts.into_iter()
+        .filter(|dst| !C::Transport::is_broadcast(dst))
+        .filter_map(|dst| identify(client, &dst).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::LocalClient;
+    use crate::mock::MockTransport;
+    use crate::Function;
+
+    #[test]
+    fn test_sweep() {
+        let mut mb = MockTransport::new();
+        let rsp = ReadDeviceIdResponse::new(ReadDeviceIdCode::Basic, 0x01, &[(0x00, b"Vendor"), (0x01, b"PLC")])
+            .with_next_object_id(0x02);
+        mb.expect(&[0x2b, 0x0e, 0x01, 0x00], &rsp.encode().unwrap());
+        let rsp = ReadDeviceIdResponse::new(ReadDeviceIdCode::Basic, 0x01, &[(0x02, b"V1.2")]);
+        mb.expect(&[0x2b, 0x0e, 0x01, 0x02], &rsp.encode().unwrap());
+        mb.expect_no_response(&[0x2b, 0x0e, 0x01, 0x00]);
+        mb.expect(&[0x2b, 0x0e, 0x01, 0x00], &[0xab, 0x01]);
+
+        let client = LocalClient::new(mb);
+        let devices = sweep(&client, 0..=3);
+
+        assert!(client.into_inner().is_complete());
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].get_dst(), &1);
+        assert_eq!(devices[0].get_vendor_name(), "Vendor");
+        assert_eq!(devices[0].get_product_code(), "PLC");
+        assert_eq!(devices[0].get_revision(), "V1.2");
+    }
+
+    #[test]
+    fn test_identify_looping_device() {
+        let mut mb = MockTransport::new();
+        let rsp = ReadDeviceIdResponse::new(ReadDeviceIdCode::Basic, 0x01, &[(0x00, b"Vendor")]).with_next_object_id(0x00);
+        mb.expect(&[0x2b, 0x0e, 0x01, 0x00], &rsp.encode().unwrap());
+
+        let client = LocalClient::new(mb);
+        assert!(matches!(identify(&client, &1), Err(Error::InvalidResponse)));
+    }
+}
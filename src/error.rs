@@ -1,29 +1,147 @@
 use crate::pdu::ExceptionCode;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "serial")]
 use serialport::Error as SerialError;
-use std::convert::From;
+#[cfg(feature = "std")]
 use std::error::Error as StdError;
-use std::fmt;
+#[cfg(feature = "std")]
 use std::io::Error as IoError;
 
+/// Stage of a transaction at which a protocol error occurred
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Stage {
+    /// Reading the response frame from the transport, e.g. a timeout or a corrupted frame
+    ReadResponse,
+    /// Decoding the response PDU, e.g. an exception response or a malformed PDU
+    DecodeResponse,
+    /// Verifying the decoded response carries the data requested by the request
+    VerifyResponse,
+    /// Decoding a request PDU read by a slave
+    DecodeRequest,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stage::ReadResponse => f.write_str("reading the response"),
+            Stage::DecodeResponse => f.write_str("decoding the response"),
+            Stage::VerifyResponse => f.write_str("verifying the response"),
+            Stage::DecodeRequest => f.write_str("decoding the request"),
+        }
+    }
+}
+
+/// Context of a protocol error: the transaction and the stage at which it occurred
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ErrorContext {
+    function_code: u8,
+    unit_id: Option<u8>,
+    stage: Stage,
+}
+
+impl ErrorContext {
+    /// Get function code of the request
+    pub fn get_function_code(&self) -> u8 {
+        self.function_code
+    }
+
+    /// Get unit id of the transaction, if known to the transport
+    pub fn get_unit_id(&self) -> Option<u8> {
+        self.unit_id
+    }
+
+    /// Get stage of the transaction at which the error occurred
+    pub fn get_stage(&self) -> Stage {
+        self.stage
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "function code 0x{:02X}", self.function_code)?;
+        if let Some(unit_id) = self.unit_id {
+            write!(f, ", unit id {}", unit_id)?;
+        }
+        write!(f, ", while {}", self.stage)
+    }
+}
+
 /// The error types used by the modbus library
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     InvalidValue,
 
     TooShortData,
     InvalidData,
     InvalidDataLength,
+    FrameTooLong,
     InvalidFunction,
 
     InvalidResponse,
+    /// Response carrying other function code than the request, with its raw PDU
+    UnexpectedFunctionCode {expected: u8, received: u8, pdu: Vec<u8>},
     NoResponse,
+    TransactionMismatch,
     ExceptionResponse(ExceptionCode),
 
     InvalidRequest,
     MissingReqHandler,
 
+    /// Blocking operation aborted from another thread with a cancellation token
+    Cancelled,
+
+    #[cfg(feature = "std")]
     IoError(IoError),
+    #[cfg(feature = "serial")]
     SerialError(SerialError),
+    #[cfg(feature = "embedded")]
+    SerialHalError(embedded_hal_nb::serial::ErrorKind),
+    /// Invalid configuration file, with the description of the problem
+    #[cfg(feature = "config")]
+    ConfigError(String),
+
+    /// Protocol error with the context of the transaction it occurred in
+    Context(Box<Error>, ErrorContext),
+}
+
+impl Error {
+    /// Get the error without the attached [context](ErrorContext)
+    /// 
+    /// # Examples
+    /// ```
+    /// let err = modbus::Error::InvalidData;
+    /// assert!(matches!(err.get_root(), modbus::Error::InvalidData));
+    /// ```
+    pub fn get_root(&self) -> &Error {
+        match self {
+            Error::Context(error, _) => error.get_root(),
+            _ => self,
+        }
+    }
+
+    /// Get context of the transaction the error occurred in, if attached
+    pub fn get_context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::Context(_, context) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Attach context to protocol errors, other errors are returned intact
+    pub(crate) fn with_context(self, function_code: u8, unit_id: Option<u8>, stage: Stage) -> Self {
+        match self {
+            Error::TooShortData | Error::InvalidData | Error::InvalidDataLength | Error::FrameTooLong |
+            Error::InvalidFunction | Error::InvalidResponse | Error::UnexpectedFunctionCode {..} |
+            Error::TransactionMismatch => {
+                Error::Context(Box::new(self), ErrorContext {function_code, unit_id, stage})
+            }
+            _ => self,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -33,28 +151,88 @@ impl fmt::Display for Error {
             Error::TooShortData => f.write_str("Too short data in the buffer"),
             Error::InvalidData => f.write_str("Invalid data"),
             Error::InvalidDataLength => f.write_str("Invalid data length"),
+            Error::FrameTooLong => f.write_str("Frame exceeds the maximal length"),
             Error::InvalidFunction => f.write_str("Invalid function code"),
             Error::InvalidResponse => f.write_str("Invalid response"),
+            Error::UnexpectedFunctionCode {expected, received, pdu} =>
+                write!(f, "Unexpected function code 0x{:02X} in response {:02X?}, expected 0x{:02X}", received, pdu, expected),
             Error::NoResponse => f.write_str("No response"),
+            Error::TransactionMismatch => f.write_str("Response header does not match the request transaction"),
             Error::InvalidRequest => f.write_str("Invalid request"),
             Error::MissingReqHandler => f.write_str("Missing request handler for given request"),
-            Error::ExceptionResponse(code) => f.write_str(&format!("Exception response: {}", code)),
-            Error::IoError(error) => f.write_str(&format!("IO error: {}", error)),
-            Error::SerialError(error) => f.write_str(&format!("Serial error: {}", error)),
+            Error::Cancelled => f.write_str("Operation cancelled"),
+            Error::ExceptionResponse(code) => write!(f, "Exception response: {}", code),
+            #[cfg(feature = "std")]
+            Error::IoError(error) => write!(f, "IO error: {}", error),
+            #[cfg(feature = "serial")]
+            Error::SerialError(error) => write!(f, "Serial error: {}", error),
+            #[cfg(feature = "embedded")]
+            Error::SerialHalError(kind) => write!(f, "Serial error: {:?}", kind),
+            #[cfg(feature = "config")]
+            Error::ConfigError(description) => write!(f, "Config error: {}", description),
+            Error::Context(error, context) => write!(f, "{} ({})", error, context),
         }
     }
 }
 
-impl StdError for Error {}
+#[cfg(feature = "std")]
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::IoError(error) => Some(error),
+            #[cfg(feature = "serial")]
+            Error::SerialError(error) => Some(error),
+            Error::Context(error, _) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
 
+#[cfg(feature = "serial")]
 impl From<SerialError> for Error {
     fn from(error: SerialError) -> Self {
         Self::SerialError(error)
     }
 }
 
+#[cfg(feature = "std")]
 impl From<IoError> for Error {
     fn from(error: IoError) -> Self {
         Self::IoError(error)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_context() {
+        let err = Error::InvalidDataLength.with_context(0x03, Some(10), Stage::DecodeResponse);
+
+        assert!(matches!(err.get_root(), Error::InvalidDataLength));
+        assert_eq!(err.get_context().unwrap().get_function_code(), 0x03);
+        assert_eq!(err.get_context().unwrap().get_unit_id(), Some(10));
+        assert_eq!(err.get_context().unwrap().get_stage(), Stage::DecodeResponse);
+        assert_eq!(format!("{}", err), "Invalid data length (function code 0x03, unit id 10, while decoding the response)");
+    }
+
+    #[test]
+    fn test_with_context_not_protocol_error() {
+        let err = Error::NoResponse.with_context(0x03, None, Stage::ReadResponse);
+
+        assert!(matches!(err, Error::NoResponse));
+        assert!(err.get_context().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_source() {
+        let err = Error::from(std::io::Error::other("broken"));
+        assert_eq!(err.source().unwrap().to_string(), "broken");
+
+        let err = Error::InvalidData.with_context(0x03, None, Stage::ReadResponse);
+        assert!(matches!(err.source().unwrap().downcast_ref::<Error>(), Some(Error::InvalidData)));
+        assert!(Error::InvalidData.source().is_none());
+    }
+}
@@ -0,0 +1,321 @@
+//! Export of polled tag values to JSON lines and CSV
+//!
+//! [Samples](Sample) returned by [RegisterMap::poll](crate::register_map::RegisterMap::poll)
+//! are written by an [Exporter], one sample per line.
+//!
+//! # Examples
+//! ```
+//! use modbus::export::{CsvExporter, Exporter, Quality, Sample};
+//! use modbus::register_map::{Table, Tag};
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! let tag = Tag::new("speed", 1, Table::HoldingRegisters, 0x0010).with_scale(0.1);
+//! let sample = Sample::new(&tag, UNIX_EPOCH + Duration::from_millis(1500), Ok(vec![123]));
+//! assert_eq!(sample.get_quality(), Quality::Good);
+//!
+//! let mut exporter = CsvExporter::new(Vec::new());
+//! exporter.export(&sample).unwrap();
+//! assert_eq!(String::from_utf8(exporter.into_inner()).unwrap(),
+//!            "timestamp,tag,unit,table,address,raw,value,quality\n\
+//!             1500,speed,1,holding_registers,16,123,12.3,good\n");
+//! ```
+
+use crate::error::Error;
+use crate::register_map::{Table, Tag};
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Quality of a polled value
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quality {
+    /// The value was read successfully
+    Good,
+    /// The device responded with an exception response
+    Exception,
+    /// The device did not respond
+    NoResponse,
+    /// The value could not be read because of a communication or decoding error
+    Bad,
+}
+
+impl Quality {
+    fn from_error(error: &Error) -> Self {
+        match error.get_root() {
+            Error::ExceptionResponse(_) => Quality::Exception,
+            Error::NoResponse => Quality::NoResponse,
+            _ => Quality::Bad,
+        }
+    }
+}
+
+impl fmt::Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Quality::Good => f.write_str("good"),
+            Quality::Exception => f.write_str("exception"),
+            Quality::NoResponse => f.write_str("no_response"),
+            Quality::Bad => f.write_str("bad"),
+        }
+    }
+}
+
+/// Value of a tag polled at some moment
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Sample {
+    /// Milliseconds since the Unix epoch
+    timestamp: u64,
+    tag: String,
+    unit: u8,
+    table: Table,
+    address: u16,
+    raw: Vec<u16>,
+    value: Option<f64>,
+    quality: Quality,
+}
+
+impl Sample {
+    /// Create a sample of a tag from the result of reading its raw coils or registers
+    ///
+    /// Coils are passed as registers equal to 0 or 1. A successful read of raw data not
+    /// matching the type of the tag results in a sample of [Quality::Bad].
+    pub fn new(tag: &Tag, timestamp: SystemTime, raw: Result<Vec<u16>, Error>) -> Self {
+        let (raw, value, quality) = match raw {
+            Ok(raw) => match tag.decode(&raw) {
+                Ok(value) => (raw, Some(value), Quality::Good),
+                Err(_) => (raw, None, Quality::Bad),
+            },
+            Err(error) => (Vec::new(), None, Quality::from_error(&error)),
+        };
+
+        Self {
+            timestamp: timestamp.duration_since(UNIX_EPOCH).map_or(0, |t| t.as_millis() as u64),
+            tag: tag.get_name().to_string(),
+            unit: tag.get_unit_id(),
+            table: tag.get_table(),
+            address: tag.get_address(),
+            raw,
+            value,
+            quality,
+        }
+    }
+
+    /// Get milliseconds elapsed since the Unix epoch to the moment of the poll
+    pub fn get_timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Get name of the polled tag
+    pub fn get_tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Get unit id of the polled device
+    pub fn get_unit_id(&self) -> u8 {
+        self.unit
+    }
+
+    /// Get table of the polled tag
+    pub fn get_table(&self) -> Table {
+        self.table
+    }
+
+    /// Get address of the polled tag
+    pub fn get_address(&self) -> u16 {
+        self.address
+    }
+
+    /// Get raw coils or registers, empty if the tag could not be read
+    pub fn get_raw(&self) -> &[u16] {
+        &self.raw
+    }
+
+    /// Get scaled value, if the tag was read successfully
+    pub fn get_value(&self) -> Option<f64> {
+        self.value
+    }
+
+    /// Get quality of the value
+    pub fn get_quality(&self) -> Quality {
+        self.quality
+    }
+}
+
+/// Sink of polled samples
+pub trait Exporter {
+    /// Write a single sample
+    fn export(&mut self, sample: &Sample) -> Result<(), Error>;
+
+    /// Write all given samples
+    fn export_all(&mut self, samples: &[Sample]) -> Result<(), Error> {
+        samples.iter().try_for_each(|sample| self.export(sample))
+    }
+}
+
+/// Exporter writing each sample as a JSON object in a separate line
+///
+/// # Examples
+/// ```
+/// use modbus::export::{Exporter, JsonLinesExporter, Sample};
+/// use modbus::register_map::{Table, Tag};
+/// use std::time::UNIX_EPOCH;
+///
+/// let tag = Tag::new("pump", 2, Table::Coils, 0x0005);
+/// let mut exporter = JsonLinesExporter::new(Vec::new());
+/// exporter.export(&Sample::new(&tag, UNIX_EPOCH, Err(modbus::Error::NoResponse))).unwrap();
+/// assert_eq!(String::from_utf8(exporter.into_inner()).unwrap(),
+///            "{\"timestamp\":0,\"tag\":\"pump\",\"unit\":2,\"table\":\"coils\",\"address\":5,\
+///             \"raw\":[],\"value\":null,\"quality\":\"no_response\"}\n");
+/// ```
+#[derive(Debug)]
+pub struct JsonLinesExporter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesExporter<W> {
+    /// Create an exporter writing to given writer
+    pub fn new(writer: W) -> Self {
+        Self {writer}
+    }
+
+    /// Get reference to the writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get back the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Exporter for JsonLinesExporter<W> {
+    fn export(&mut self, sample: &Sample) -> Result<(), Error> {
+        let mut line = serde_json::to_vec(sample).map_err(|err| Error::IoError(err.into()))?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        Ok(())
+    }
+}
+
+const CSV_HEADER: &str = "timestamp,tag,unit,table,address,raw,value,quality\n";
+
+/// Exporter writing samples as CSV rows preceded by a header row
+///
+/// Raw registers of a sample are separated with spaces. Missing values are left empty.
+#[derive(Debug)]
+pub struct CsvExporter<W: Write> {
+    writer: W,
+    header: bool,
+}
+
+impl<W: Write> CsvExporter<W> {
+    /// Create an exporter writing to given writer
+    pub fn new(writer: W) -> Self {
+        Self {writer, header: true}
+    }
+
+    /// Select if the header row is written before the first sample
+    ///
+    /// The header can be disabled, e.g. when appending to an existing file. It is enabled by default.
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Get reference to the writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get back the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl<W: Write> Exporter for CsvExporter<W> {
+    fn export(&mut self, sample: &Sample) -> Result<(), Error> {
+        if self.header {
+            self.writer.write_all(CSV_HEADER.as_bytes())?;
+            self.header = false;
+        }
+
+        let raw: Vec<String> = sample.raw.iter().map(u16::to_string).collect();
+        let value = sample.value.map_or(String::new(), |value| value.to_string());
+
+        writeln!(self.writer, "{},{},{},{},{},{},{},{}", sample.timestamp, csv_field(&sample.tag), sample.unit,
+                 sample.table, sample.address, raw.join(" "), value, sample.quality)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdu::ExceptionCode;
+    use crate::register_map::DataType;
+    use std::time::Duration;
+
+    fn timestamp() -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(1_700_000_000_123)
+    }
+
+    #[test]
+    fn test_sample_quality() {
+        let tag = Tag::new("flow", 1, Table::InputRegisters, 0x0000).with_data_type(DataType::U32);
+
+        let sample = Sample::new(&tag, timestamp(), Ok(vec![0x0001, 0x0002]));
+        assert_eq!(sample.get_value(), Some(65538.0));
+        assert_eq!(sample.get_timestamp(), 1_700_000_000_123);
+
+        let sample = Sample::new(&tag, timestamp(), Ok(vec![0x0001]));
+        assert_eq!((sample.get_value(), sample.get_quality()), (None, Quality::Bad));
+
+        let sample = Sample::new(&tag, timestamp(), Err(Error::ExceptionResponse(ExceptionCode::IllegalDataAddress)));
+        assert_eq!((sample.get_raw(), sample.get_quality()), (&[][..], Quality::Exception));
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let tag = Tag::new("level", 3, Table::HoldingRegisters, 0x0002).with_offset(-10.0);
+        let mut exporter = JsonLinesExporter::new(Vec::new());
+        exporter.export_all(&[Sample::new(&tag, timestamp(), Ok(vec![15])),
+                              Sample::new(&tag, timestamp(), Err(Error::InvalidData))]).unwrap();
+
+        let output = String::from_utf8(exporter.into_inner()).unwrap();
+        let lines: Vec<serde_json::Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["value"], 5.0);
+        assert_eq!(lines[0]["raw"], serde_json::json!([15]));
+        assert_eq!(lines[1]["quality"], "bad");
+    }
+
+    #[test]
+    fn test_csv() {
+        let tag = Tag::new("tank \"A\", top", 1, Table::DiscreteInputs, 0x0007);
+        let mut exporter = CsvExporter::new(Vec::new());
+        exporter.export(&Sample::new(&tag, timestamp(), Ok(vec![1]))).unwrap();
+        exporter.export(&Sample::new(&tag, timestamp(), Err(Error::NoResponse))).unwrap();
+
+        assert_eq!(String::from_utf8(exporter.into_inner()).unwrap(),
+                   "timestamp,tag,unit,table,address,raw,value,quality\n\
+                    1700000000123,\"tank \"\"A\"\", top\",1,discrete_inputs,7,1,1,good\n\
+                    1700000000123,\"tank \"\"A\"\", top\",1,discrete_inputs,7,,,no_response\n");
+
+        let mut exporter = CsvExporter::new(Vec::new()).with_header(false);
+        exporter.export(&Sample::new(&tag, timestamp(), Ok(vec![0]))).unwrap();
+        assert_eq!(String::from_utf8(exporter.into_inner()).unwrap(),
+                   "1700000000123,\"tank \"\"A\"\", top\",1,discrete_inputs,7,0,0,good\n");
+    }
+}
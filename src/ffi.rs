@@ -0,0 +1,408 @@
+//! C bindings of the Modbus master
+//!
+//! The functions below expose a stable C ABI to create a Modbus client, read and write
+//! coils and registers, and free the client. The matching header is `include/modbus.h`,
+//! generated from this module with `cbindgen --config cbindgen.toml --output include/modbus.h src/ffi.rs`.
+//!
+//! To link the crate with a C or C++ application build it as a shared or static library:
+//! `cargo rustc --release --features ffi --crate-type cdylib` or `--crate-type staticlib`.
+//!
+//! All functions return [MODBUS_OK] on success, a negative `MODBUS_ERR_*` status on failure,
+//! or a positive [exception code](crate::ExceptionCode) reported by the addressed device.
+
+use crate::error::Error;
+use crate::pdu::{Request, Setter};
+#[cfg(feature = "serial")]
+use crate::rtu::Rtu;
+use crate::tcp::{Dst, Tcp};
+use crate::transport::Transport;
+use crate::{ReadCoilsRequest, ReadDscrInRequest, ReadHldRegRequest, ReadInRegRequest};
+use crate::{WriteMultiRegRequest, WriteSingleCoilRequest, WriteSingleRegRequest};
+use std::ffi::CStr;
+use std::net::IpAddr;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::slice;
+
+/// Operation succeeded
+pub const MODBUS_OK: c_int = 0;
+/// Invalid argument, like a null pointer or a quantity out of the allowed range
+pub const MODBUS_ERR_INVALID_ARGUMENT: c_int = -1;
+/// Error reported by the underlying socket or serial port
+pub const MODBUS_ERR_IO: c_int = -2;
+/// The device did not respond
+pub const MODBUS_ERR_NO_RESPONSE: c_int = -3;
+/// The device responded with a malformed response or a response not matching the request
+pub const MODBUS_ERR_INVALID_RESPONSE: c_int = -4;
+
+const TCP_PORT: u16 = 502;
+
+enum Link {
+    Tcp(Box<Tcp>, IpAddr, u16),
+    #[cfg(feature = "serial")]
+    Rtu(Rtu),
+}
+
+/// Modbus client handle used by the C API
+///
+/// The handle is created with [modbus_tcp_new] or [modbus_rtu_new] and shall be released with
+/// [modbus_free].
+pub struct ModbusClient {
+    link: Link,
+}
+
+impl ModbusClient {
+    fn read<Req: Request>(&mut self, unit_id: u8, req: &Req) -> Result<Req::Rsp, Error> {
+        let rsp = match &mut self.link {
+            Link::Tcp(tcp, ip_addr, port) => tcp.write_req_read_rsp(&Dst::new(*ip_addr, unit_id).with_port(*port), req)?,
+            #[cfg(feature = "serial")]
+            Link::Rtu(rtu) => rtu.write_req_read_rsp(&unit_id, req)?,
+        };
+
+        // Broadcast requests are left without response
+        rsp.ok_or(Error::NoResponse)
+    }
+
+    fn write<Req: Setter>(&mut self, unit_id: u8, req: &Req) -> Result<(), Error>
+        where Req::Rsp: PartialEq
+    {
+        match &mut self.link {
+            Link::Tcp(tcp, ip_addr, port) => tcp.write_setter_req(&Dst::new(*ip_addr, unit_id).with_port(*port), req),
+            #[cfg(feature = "serial")]
+            Link::Rtu(rtu) => rtu.write_setter_req(&unit_id, req),
+        }
+    }
+}
+
+fn status(error: &Error) -> c_int {
+    match error.get_root() {
+        Error::ExceptionResponse(code) => *code as c_int,
+        Error::InvalidValue | Error::InvalidRequest => MODBUS_ERR_INVALID_ARGUMENT,
+        Error::IoError(_) => MODBUS_ERR_IO,
+        #[cfg(feature = "serial")]
+        Error::SerialError(_) => MODBUS_ERR_IO,
+        Error::NoResponse => MODBUS_ERR_NO_RESPONSE,
+        _ => MODBUS_ERR_INVALID_RESPONSE,
+    }
+}
+
+fn to_status(result: Result<(), Error>) -> c_int {
+    match result {
+        Ok(()) => MODBUS_OK,
+        Err(error) => status(&error),
+    }
+}
+
+unsafe fn to_str<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        None
+    } else {
+        CStr::from_ptr(value).to_str().ok()
+    }
+}
+
+unsafe fn read_into<T, F>(client: *mut ModbusClient, quantity: u16, dst: *mut T, read: F) -> c_int
+    where F: FnOnce(&mut ModbusClient) -> Result<Vec<T>, Error>
+{
+    let client = match client.as_mut() {
+        Some(client) if !dst.is_null() => client,
+        _ => return MODBUS_ERR_INVALID_ARGUMENT,
+    };
+
+    to_status(read(client).map(|values| {
+        let dst = slice::from_raw_parts_mut(dst, quantity as usize);
+        for (dst, value) in dst.iter_mut().zip(values) {
+            *dst = value;
+        }
+    }))
+}
+
+/// Create a Modbus TCP client connecting to the server at given IP address
+///
+/// `port` equal to 0 selects the default Modbus port 502.
+/// Returns a null pointer if `ip_addr` is not a valid IPv4 or IPv6 address.
+///
+/// # Safety
+/// `ip_addr` shall be a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn modbus_tcp_new(ip_addr: *const c_char, port: u16) -> *mut ModbusClient {
+    let ip_addr = match to_str(ip_addr).and_then(|ip_addr| ip_addr.parse().ok()) {
+        Some(ip_addr) => ip_addr,
+        None => return ptr::null_mut(),
+    };
+    let port = if port == 0 { TCP_PORT } else { port };
+
+    Box::into_raw(Box::new(ModbusClient {link: Link::Tcp(Box::default(), ip_addr, port)}))
+}
+
+/// Create a Modbus RTU client communicating through given serial port
+///
+/// The port is configured with 8 data bits, no parity and 1 stop bit.
+/// Returns a null pointer if the port cannot be opened.
+///
+/// # Safety
+/// `port` shall be a null-terminated string.
+#[cfg(feature = "serial")]
+#[no_mangle]
+pub unsafe extern "C" fn modbus_rtu_new(port: *const c_char, baud_rate: u32) -> *mut ModbusClient {
+    let port = match to_str(port) {
+        Some(port) => port,
+        None => return ptr::null_mut(),
+    };
+    let settings = serialport::SerialPortSettings {baud_rate, ..Default::default()};
+
+    match Rtu::conn(port, &settings) {
+        Ok(rtu) => Box::into_raw(Box::new(ModbusClient {link: Link::Rtu(rtu)})),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Release a client created with [modbus_tcp_new] or [modbus_rtu_new]
+///
+/// Passing a null pointer is allowed and does nothing.
+///
+/// # Safety
+/// `client` shall be a pointer returned by one of the constructors, not released before.
+#[no_mangle]
+pub unsafe extern "C" fn modbus_free(client: *mut ModbusClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Read `quantity` coils starting at `address`, storing each of them as 0 or 1 in `values`
+///
+/// # Safety
+/// `client` shall be a valid client and `values` shall point to at least `quantity` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn modbus_read_coils(client: *mut ModbusClient, unit_id: u8, address: u16, quantity: u16,
+                                           values: *mut u8) -> c_int {
+    read_into(client, quantity, values, |client| {
+        let rsp = client.read(unit_id, &ReadCoilsRequest::new(address, quantity))?;
+        Ok(rsp.get_coils().iter().map(u8::from).collect())
+    })
+}
+
+/// Read `quantity` discrete inputs starting at `address`, storing each of them as 0 or 1 in `values`
+///
+/// # Safety
+/// `client` shall be a valid client and `values` shall point to at least `quantity` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn modbus_read_discrete_inputs(client: *mut ModbusClient, unit_id: u8, address: u16,
+                                                     quantity: u16, values: *mut u8) -> c_int {
+    read_into(client, quantity, values, |client| {
+        let rsp = client.read(unit_id, &ReadDscrInRequest::new(address, quantity))?;
+        Ok(rsp.get_inputs().iter().map(u8::from).collect())
+    })
+}
+
+/// Read `quantity` holding registers starting at `address` to `values`
+///
+/// # Safety
+/// `client` shall be a valid client and `values` shall point to at least `quantity` registers.
+#[no_mangle]
+pub unsafe extern "C" fn modbus_read_holding_registers(client: *mut ModbusClient, unit_id: u8, address: u16,
+                                                       quantity: u16, values: *mut u16) -> c_int {
+    read_into(client, quantity, values, |client| {
+        let rsp = client.read(unit_id, &ReadHldRegRequest::new(address, quantity))?;
+        Ok(rsp.get_registers().to_vec())
+    })
+}
+
+/// Read `quantity` input registers starting at `address` to `values`
+///
+/// # Safety
+/// `client` shall be a valid client and `values` shall point to at least `quantity` registers.
+#[no_mangle]
+pub unsafe extern "C" fn modbus_read_input_registers(client: *mut ModbusClient, unit_id: u8, address: u16,
+                                                     quantity: u16, values: *mut u16) -> c_int {
+    read_into(client, quantity, values, |client| {
+        let rsp = client.read(unit_id, &ReadInRegRequest::new(address, quantity))?;
+        Ok(rsp.get_registers().to_vec())
+    })
+}
+
+/// Write a single coil at `address`, any nonzero `value` sets the coil
+///
+/// # Safety
+/// `client` shall be a valid client.
+#[no_mangle]
+pub unsafe extern "C" fn modbus_write_single_coil(client: *mut ModbusClient, unit_id: u8, address: u16,
+                                                  value: u8) -> c_int {
+    match client.as_mut() {
+        Some(client) => to_status(client.write(unit_id, &WriteSingleCoilRequest::new(address, value != 0))),
+        None => MODBUS_ERR_INVALID_ARGUMENT,
+    }
+}
+
+/// Write a single holding register at `address`
+///
+/// # Safety
+/// `client` shall be a valid client.
+#[no_mangle]
+pub unsafe extern "C" fn modbus_write_single_register(client: *mut ModbusClient, unit_id: u8, address: u16,
+                                                      value: u16) -> c_int {
+    match client.as_mut() {
+        Some(client) => to_status(client.write(unit_id, &WriteSingleRegRequest::new(address, value))),
+        None => MODBUS_ERR_INVALID_ARGUMENT,
+    }
+}
+
+/// Write `quantity` holding registers starting at `address` from `values`
+///
+/// # Safety
+/// `client` shall be a valid client and `values` shall point to at least `quantity` registers.
+#[no_mangle]
+pub unsafe extern "C" fn modbus_write_multiple_registers(client: *mut ModbusClient, unit_id: u8, address: u16,
+                                                         quantity: u16, values: *const u16) -> c_int {
+    match client.as_mut() {
+        Some(client) if !values.is_null() => {
+            let values = slice::from_raw_parts(values, quantity as usize);
+            to_status(client.write(unit_id, &WriteMultiRegRequest::new(address, values)))
+        }
+        _ => MODBUS_ERR_INVALID_ARGUMENT,
+    }
+}
+
+/// Get a static, null-terminated description of a status returned by the functions above
+#[no_mangle]
+pub extern "C" fn modbus_strerror(status: c_int) -> *const c_char {
+    let description: &'static [u8] = match status {
+        MODBUS_OK => b"Success\0",
+        MODBUS_ERR_INVALID_ARGUMENT => b"Invalid argument\0",
+        MODBUS_ERR_IO => b"IO error\0",
+        MODBUS_ERR_NO_RESPONSE => b"No response\0",
+        MODBUS_ERR_INVALID_RESPONSE => b"Invalid response\0",
+        0x01 => b"Illegal function\0",
+        0x02 => b"Illegal data address\0",
+        0x03 => b"Illegal data value\0",
+        0x04 => b"Server device failure\0",
+        0x05 => b"Acknowledge\0",
+        0x06 => b"Server device busy\0",
+        0x08 => b"Memory parity error\0",
+        0x0A => b"Gateway path unavailable\0",
+        0x0B => b"Gateway target device failed to respond\0",
+        _ => b"Unknown status\0",
+    };
+    description.as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Serve each request PDU with the paired response PDU, or leave it without response,
+    /// accepting a new connection for every request like a non-persistent master opens
+    fn serve(exchanges: Vec<(Vec<u8>, Option<Vec<u8>>)>) -> (u16, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let slave = thread::spawn(move || {
+            for (req_pdu, rsp_pdu) in exchanges {
+                let (mut socket, _) = listener.accept().unwrap();
+                let mut header = [0u8; 7];
+                socket.read_exact(&mut header).unwrap();
+                let mut pdu = vec![0u8; u16::from_be_bytes([header[4], header[5]]) as usize - 1];
+                socket.read_exact(&mut pdu).unwrap();
+                assert_eq!(pdu, req_pdu);
+
+                if let Some(rsp_pdu) = rsp_pdu {
+                    let len = (rsp_pdu.len() + 1) as u16;
+                    let mut rsp = vec![header[0], header[1], 0x00, 0x00];
+                    rsp.extend_from_slice(&len.to_be_bytes());
+                    rsp.push(header[6]);
+                    rsp.extend_from_slice(&rsp_pdu);
+                    socket.write_all(&rsp).unwrap();
+                }
+            }
+        });
+        (port, slave)
+    }
+
+    fn create_client(port: u16) -> *mut ModbusClient {
+        let client = unsafe { modbus_tcp_new(b"127.0.0.1\0".as_ptr() as *const c_char, port) };
+        assert!(!client.is_null());
+        client
+    }
+
+    #[test]
+    fn test_read_holding_registers() {
+        let (port, slave) = serve(vec![
+            (vec![0x03, 0x00, 0x10, 0x00, 0x02], Some(vec![0x03, 0x04, 0x12, 0x34, 0xab, 0xcd])),
+            (vec![0x03, 0x00, 0x10, 0x00, 0x02], Some(vec![0x83, 0x02])),
+        ]);
+        let client = create_client(port);
+        let mut values = [0u16; 2];
+
+        unsafe {
+            assert_eq!(modbus_read_holding_registers(client, 10, 0x0010, 2, values.as_mut_ptr()), MODBUS_OK);
+            assert_eq!(values, [0x1234, 0xabcd]);
+            assert_eq!(modbus_read_holding_registers(client, 10, 0x0010, 2, values.as_mut_ptr()), 0x02);
+            assert_eq!(modbus_read_holding_registers(client, 10, 0x0010, 2, ptr::null_mut()), MODBUS_ERR_INVALID_ARGUMENT);
+            modbus_free(client);
+        }
+        slave.join().unwrap();
+    }
+
+    #[test]
+    fn test_read_coils() {
+        let (port, slave) = serve(vec![
+            (vec![0x01, 0x00, 0x00, 0x00, 0x03], Some(vec![0x01, 0x01, 0x05])),
+            (vec![0x01, 0x00, 0x00, 0x00, 0x03], None),
+        ]);
+        let client = create_client(port);
+        let mut values = [0xffu8; 4];
+
+        unsafe {
+            assert_eq!(modbus_read_coils(client, 10, 0x0000, 3, values.as_mut_ptr()), MODBUS_OK);
+            assert_eq!(values, [1, 0, 1, 0xff]);
+            // Broadcast requests are left without response
+            assert_eq!(modbus_read_coils(client, 0, 0x0000, 3, values.as_mut_ptr()), MODBUS_ERR_NO_RESPONSE);
+            modbus_free(client);
+        }
+        slave.join().unwrap();
+    }
+
+    #[test]
+    fn test_write_registers() {
+        let (port, slave) = serve(vec![
+            (vec![0x06, 0x00, 0x01, 0x00, 0x03], Some(vec![0x06, 0x00, 0x01, 0x00, 0x03])),
+            (vec![0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0a, 0x01, 0x02], Some(vec![0x10, 0x00, 0x01, 0x00, 0x02])),
+            (vec![0x05, 0x00, 0xac, 0xff, 0x00], Some(vec![0x05, 0x00, 0xac, 0x00, 0x00])),
+        ]);
+        let client = create_client(port);
+
+        unsafe {
+            assert_eq!(modbus_write_single_register(client, 10, 0x0001, 0x0003), MODBUS_OK);
+            assert_eq!(modbus_write_multiple_registers(client, 10, 0x0001, 2, [0x000a, 0x0102].as_ptr()), MODBUS_OK);
+            assert_eq!(modbus_write_single_coil(client, 10, 0x00ac, 1), MODBUS_ERR_INVALID_RESPONSE);
+            modbus_free(client);
+        }
+        slave.join().unwrap();
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        unsafe {
+            assert!(modbus_tcp_new(ptr::null(), 502).is_null());
+            assert!(modbus_tcp_new(b"not an address\0".as_ptr() as *const c_char, 502).is_null());
+            assert_eq!(modbus_write_single_register(ptr::null_mut(), 10, 0x0001, 0x0003), MODBUS_ERR_INVALID_ARGUMENT);
+            modbus_free(ptr::null_mut());
+
+            let client = modbus_tcp_new(b"127.0.0.1\0".as_ptr() as *const c_char, 0);
+            assert!(!client.is_null());
+            modbus_free(client);
+        }
+    }
+
+    #[test]
+    fn test_strerror() {
+        let description = unsafe { CStr::from_ptr(modbus_strerror(MODBUS_ERR_NO_RESPONSE)) };
+        assert_eq!(description.to_str(), Ok("No response"));
+        let description = unsafe { CStr::from_ptr(modbus_strerror(0x02)) };
+        assert_eq!(description.to_str(), Ok("Illegal data address"));
+    }
+}
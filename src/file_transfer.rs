@@ -0,0 +1,232 @@
+//! Transfer of byte blobs of any size through file records
+//!
+//! Read File Record and Write File Record functions access a group of up to about 120 records
+//! of a file in a single transaction, and each file keeps up to 10000 records of 16 bits.
+//! A [FileTransfer] splits a blob into chunks of a configured number of records, continues in
+//! the following files when the blob does not fit into the first one, and reports progress after
+//! each chunk. A failed transfer keeps its position, so it can be resumed later.
+//!
+//! # Examples
+//! ```
+//! use modbus::client::LocalClient;
+//! use modbus::file_transfer::FileTransfer;
+//! use modbus::mock::MockTransport;
+//!
+//! let mut mb = MockTransport::new();
+//! mb.expect_fn(Box::new(|_, req_pdu| Some(req_pdu.to_vec())));
+//! mb.expect_fn(Box::new(|_, req_pdu| Some(req_pdu.to_vec())));
+//! let client = LocalClient::new(mb);
+//!
+//! let mut transfer = FileTransfer::new(1).with_record_length(2);
+//! transfer.write(&client, &1, b"firmware", |done, total| println!("{}/{} bytes", done, total)).unwrap();
+//! assert_eq!(transfer.get_position(), 8);
+//! ```
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::pdu::file_access::FILE_RECORDS;
+use crate::pdu::file_access::write_file_record::MAX_LENGTH;
+use crate::transport::Transport;
+use crate::{ReadFileRecordRequest, WriteFileRecordRequest};
+use std::convert::TryFrom;
+
+const RECORD_SIZE: usize = 2;
+
+/// Transfer of a byte blob to or from consecutive records of consecutive files
+///
+/// The blob starts at the first record of the configured file. Each record keeps two bytes of
+/// the blob, the first one in the most significant byte. A blob of an odd length is padded with
+/// a zero byte when written.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileTransfer {
+    file_number: u16,
+    record_length: u16,
+    position: usize,
+}
+
+impl FileTransfer {
+    /// Create a transfer of a blob starting in given file
+    pub fn new(file_number: u16) -> Self {
+        Self {file_number, record_length: MAX_LENGTH as u16, position: 0}
+    }
+
+    /// Set maximal number of records transferred in a single transaction, 122 by default
+    pub fn with_record_length(mut self, record_length: u16) -> Self {
+        self.record_length = record_length;
+        self
+    }
+
+    /// Resume a transfer that has already transferred given number of bytes
+    ///
+    /// The position shall be taken from [FileTransfer::get_position] of the interrupted transfer.
+    pub fn with_position(mut self, position: usize) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Get number of bytes transferred so far
+    pub fn get_position(&self) -> usize {
+        self.position
+    }
+
+    /// Get file number, record number and number of records of the next chunk
+    fn next_chunk(&self, remaining: usize) -> Result<(u16, u16, u16), Error> {
+        if self.record_length == 0 || self.record_length as usize > MAX_LENGTH || !self.position.is_multiple_of(RECORD_SIZE) {
+            return Err(Error::InvalidValue);
+        }
+
+        let record = self.position / RECORD_SIZE;
+        let file_number = u16::try_from(record / FILE_RECORDS as usize).ok()
+            .and_then(|file_offset| self.file_number.checked_add(file_offset))
+            .ok_or(Error::InvalidValue)?;
+        let record_number = (record % FILE_RECORDS as usize) as u16;
+        let record_length = self.record_length
+            .min(FILE_RECORDS - record_number)
+            .min(remaining.div_ceil(RECORD_SIZE) as u16);
+
+        Ok((file_number, record_number, record_length))
+    }
+
+    /// Write given blob, calling `progress` with the number of written and all bytes after each chunk
+    ///
+    /// Writing starts at the current position, so an interrupted transfer continues where it failed
+    /// when called again with the same blob.
+    pub fn write<C, F>(&mut self, client: &C, dst: &<C::Transport as Transport>::Dst, data: &[u8], mut progress: F)
+        -> Result<(), Error>
+        where C: Client, F: FnMut(usize, usize)
+    {
+        while self.position < data.len() {
+            let (file_number, record_number, record_length) = self.next_chunk(data.len() - self.position)?;
+            let end = data.len().min(self.position + record_length as usize * RECORD_SIZE);
+            let chunk = &data[self.position..end];
+            let registers: Vec<u16> = chunk.chunks(RECORD_SIZE)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes.get(1).copied().unwrap_or_default()]))
+                .collect();
+
+            client.write_setter_req(dst, &WriteFileRecordRequest::new(file_number, record_number, &registers))?;
+            self.position = end;
+            progress(self.position, data.len());
+        }
+
+        Ok(())
+    }
+
+    /// Read a blob of given length into `buf`, calling `progress` with the number of read and all bytes after each chunk
+    ///
+    /// Reading starts at the current position and the read bytes are appended to `buf`, so an
+    /// interrupted transfer continues where it failed when called again with the same buffer.
+    pub fn read<C, F>(&mut self, client: &C, dst: &<C::Transport as Transport>::Dst, len: usize, buf: &mut Vec<u8>,
+                      mut progress: F) -> Result<(), Error>
+        where C: Client, F: FnMut(usize, usize)
+    {
+        while self.position < len {
+            let (file_number, record_number, record_length) = self.next_chunk(len - self.position)?;
+            let rsp = client.write_req_read_rsp(dst, &ReadFileRecordRequest::new(file_number, record_number, record_length))?
+                .ok_or(Error::NoResponse)?;
+            let chunk_len = (len - self.position).min(record_length as usize * RECORD_SIZE);
+
+            buf.extend_from_slice(&rsp.get_bytes()[..chunk_len]);
+            self.position += chunk_len;
+            progress(self.position, len);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::LocalClient;
+    use crate::mock::MockTransport;
+    use crate::{Function, ReadFileRecordResponse};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    type Files = Arc<Mutex<HashMap<(u16, u16), u16>>>;
+
+    /// Create a mock serving file records from given files for given number of transactions
+    fn serve_files(files: &Files, transactions: usize) -> MockTransport {
+        let mut mb = MockTransport::new();
+        for _ in 0..transactions {
+            let files = files.clone();
+            mb.expect_fn(Box::new(move |_, req_pdu| {
+                let mut files = files.lock().unwrap();
+                if let Ok(req) = WriteFileRecordRequest::decode(req_pdu) {
+                    for (i, value) in req.get_registers().iter().enumerate() {
+                        files.insert((req.get_file_number(), req.get_record_number() + i as u16), *value);
+                    }
+                    return Some(req_pdu.to_vec());
+                }
+
+                let req = ReadFileRecordRequest::decode(req_pdu).unwrap();
+                let registers: Vec<u16> = (0..req.get_record_length())
+                    .map(|i| files[&(req.get_file_number(), req.get_record_number() + i)])
+                    .collect();
+                ReadFileRecordResponse::new(&registers).encode().ok()
+            }));
+        }
+        mb
+    }
+
+    #[test]
+    fn test_write_read() {
+        let files = Files::default();
+        let data: Vec<u8> = (0..=10u8).collect();
+        let mut progress = Vec::new();
+
+        let client = LocalClient::new(serve_files(&files, 6));
+        FileTransfer::new(3).with_record_length(2).write(&client, &1, &data, |done, _| progress.push(done)).unwrap();
+        assert_eq!(progress, vec![4, 8, 11]);
+        assert_eq!(files.lock().unwrap()[&(3, 5)], 0x0a00);
+
+        let mut buf = Vec::new();
+        FileTransfer::new(3).with_record_length(2).read(&client, &1, data.len(), &mut buf, |_, _| ()).unwrap();
+        assert_eq!(buf, data);
+        assert!(client.into_inner().is_complete());
+    }
+
+    #[test]
+    fn test_next_file() {
+        let files = Files::default();
+        let mut data = vec![0u8; 2 * FILE_RECORDS as usize + 4];
+        data[2 * FILE_RECORDS as usize - 2..].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let client = LocalClient::new(serve_files(&files, 2));
+
+        let mut transfer = FileTransfer::new(3).with_position(2 * FILE_RECORDS as usize - 2);
+        transfer.write(&client, &1, &data, |_, _| ()).unwrap();
+
+        let files = files.lock().unwrap();
+        assert_eq!(files[&(3, FILE_RECORDS - 1)], 0x0102);
+        assert_eq!(files[&(4, 0)], 0x0304);
+        assert_eq!(files[&(4, 1)], 0x0506);
+    }
+
+    #[test]
+    fn test_resume() {
+        let files = Files::default();
+        let data: Vec<u8> = (0..8u8).collect();
+
+        // The device stops responding after the first chunk
+        let mut mb = serve_files(&files, 1);
+        mb.expect_no_response(&[0x15, 0x0b, 0x06, 0x00, 0x01, 0x00, 0x02, 0x00, 0x02, 0x04, 0x05, 0x06, 0x07]);
+        let client = LocalClient::new(mb);
+        let mut transfer = FileTransfer::new(1).with_record_length(2);
+        assert!(transfer.write(&client, &1, &data, |_, _| ()).is_err());
+        assert_eq!(transfer.get_position(), 4);
+
+        let client = LocalClient::new(serve_files(&files, 1));
+        let mut transfer = FileTransfer::new(1).with_record_length(2).with_position(transfer.get_position());
+        transfer.write(&client, &1, &data, |_, _| ()).unwrap();
+        assert_eq!(files.lock().unwrap()[&(1, 3)], 0x0607);
+    }
+
+    #[test]
+    fn test_invalid_position() {
+        let client = LocalClient::new(MockTransport::new());
+        let mut transfer = FileTransfer::new(1).with_position(1);
+        assert!(matches!(transfer.write(&client, &1, &[0; 4], |_, _| ()), Err(Error::InvalidValue)));
+        let mut transfer = FileTransfer::new(1).with_record_length(0);
+        assert!(matches!(transfer.read(&client, &1, 4, &mut Vec::new(), |_, _| ()), Err(Error::InvalidValue)));
+    }
+}
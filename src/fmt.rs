@@ -0,0 +1,110 @@
+//! Formatting helpers for logging Modbus traffic
+//!
+//! Requests and responses implement [Display](core::fmt::Display) printing the function name
+//! and its fields. The helpers below format raw frames and PDUs.
+
+use core::convert::TryFrom;
+use core::fmt;
+use crate::pdu::{FunctionCode, EXC_FUNCTION_CODE_FLAG};
+
+/// Hex dump of raw bytes, formatted as space separated pairs of hex digits
+///
+/// # Examples
+/// ```
+/// let dump = modbus::fmt::HexDump::new(&[0x03, 0x00, 0x10, 0x00, 0x02]);
+/// assert_eq!(dump.to_string(), "03 00 10 00 02");
+/// ```
+#[derive(Clone, Copy)]
+pub struct HexDump<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> HexDump<'a> {
+    /// Create a hex dump of given bytes
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {data}
+    }
+}
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, byte) in self.data.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}]", self)
+    }
+}
+
+/// Raw PDU formatted as the name of its function followed by a hex dump of its bytes
+///
+/// # Examples
+/// ```
+/// let pdu = modbus::fmt::Pdu::new(&[0x83, 0x02]);
+/// assert_eq!(pdu.to_string(), "Read Holding Registers exception: 83 02");
+/// ```
+#[derive(Clone, Copy)]
+pub struct Pdu<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Pdu<'a> {
+    /// Create a formatter of given PDU
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {data}
+    }
+}
+
+impl fmt::Display for Pdu<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let function_code = match self.data.first() {
+            Some(function_code) => *function_code,
+            None => return f.write_str("Empty PDU"),
+        };
+
+        match FunctionCode::try_from(function_code) {
+            Ok(function_code) => write!(f, "{}", function_code)?,
+            Err(_) => {
+                write!(f, "Function 0x{:02x}", function_code & !EXC_FUNCTION_CODE_FLAG)?;
+                if function_code & EXC_FUNCTION_CODE_FLAG != 0 {
+                    f.write_str(" exception")?;
+                }
+            }
+        }
+        write!(f, ": {}", HexDump::new(self.data))
+    }
+}
+
+impl fmt::Debug for Pdu<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_dump() {
+        assert_eq!(format!("{}", HexDump::new(&[])), "");
+        assert_eq!(format!("{}", HexDump::new(&[0x0a, 0xff, 0x00])), "0a ff 00");
+        assert_eq!(format!("{:?}", HexDump::new(&[0x0a, 0xff])), "[0a ff]");
+    }
+
+    #[test]
+    fn test_pdu() {
+        assert_eq!(format!("{}", Pdu::new(&[0x10, 0x00, 0x01, 0x00, 0x01])), "Write Multiple Registers: 10 00 01 00 01");
+        assert_eq!(format!("{}", Pdu::new(&[0x2b, 0x0e])), "Function 0x2b: 2b 0e");
+        assert_eq!(format!("{}", Pdu::new(&[0xab, 0x01])), "Function 0x2b exception: ab 01");
+        assert_eq!(format!("{}", Pdu::new(&[])), "Empty PDU");
+    }
+}
@@ -0,0 +1,186 @@
+//! Modbus gateway forwarding requests between transports
+//!
+//! The [Gateway] serves requests received through a slave transport, typically Modbus TCP/IP,
+//! by forwarding them to devices reached through a master transport, typically a serial RTU
+//! bus. Unit ids of the requests are mapped to addresses of the downstream devices.
+
+use crate::cancel::CancelToken;
+use crate::error::Error;
+use crate::pdu::{encode_exc_rsp, ExceptionCode};
+use crate::transport::Transport;
+
+/// Gateway forwarding requests from a slave transport to a master transport
+///
+/// A downstream device that does not respond, or responds with a corrupted frame,
+/// is reported to the requester with [ExceptionCode::GatewayTargetDeviceFailedToRespond].
+/// A request that cannot be sent downstream is reported with
+/// [ExceptionCode::GatewayPathUnavailable].
+pub struct Gateway<S: Transport, M: Transport<Dst = u8>> {
+    slave: S,
+    master: M,
+    routes: Vec<(u8, u8)>,
+    started: bool,
+}
+
+impl<S: Transport, M: Transport<Dst = u8>> Gateway<S, M> {
+    /// Create a new gateway receiving requests with `slave` and forwarding them with `master`
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # #[cfg(all(feature = "serial", feature = "tcp"))] {
+    /// use modbus::gateway::Gateway;
+    ///
+    /// let rtu = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &Default::default()).unwrap();
+    /// let mut gateway = Gateway::new(modbus::tcp::Tcp::new(), rtu);
+    /// gateway.add_route(1, 1).unwrap();
+    /// gateway.add_route(2, 5).unwrap();
+    /// gateway.start().unwrap();
+    /// gateway.serve_forever().unwrap();
+    /// # }
+    /// ```
+    pub fn new(slave: S, master: M) -> Self {
+        Self {slave, master, routes: Vec::new(), started: false}
+    }
+
+    /// Forward requests addressed to `unit_id` to the downstream device at `address`
+    ///
+    /// Routes shall be added before the gateway is started.
+    pub fn add_route(&mut self, unit_id: u8, address: u8) -> Result<(), Error> {
+        if self.started || self.routes.iter().any(|(id, _)| *id == unit_id) {
+            return Err(Error::InvalidValue);
+        }
+
+        self.routes.push((unit_id, address));
+        Ok(())
+    }
+
+    /// Start serving requests addressed to unit ids of all added routes
+    pub fn start(&mut self) -> Result<(), Error> {
+        let unit_ids: Vec<u8> = self.routes.iter().map(|(unit_id, _)| *unit_id).collect();
+
+        self.slave.start_slave_units(&unit_ids)?;
+        self.master.start_master()?;
+        self.started = true;
+        Ok(())
+    }
+
+    /// Read a single request, forward it downstream and write back the response
+    pub fn process_req(&mut self) -> Result<(), Error> {
+        let (req_pdu, mut stream) = self.slave.read_req_pdu()?;
+        if req_pdu.is_empty() {
+            return Err(Error::InvalidDataLength);
+        }
+
+        let unit_id = S::get_unit_id(&stream);
+        let address = self.routes.iter()
+            .find(|(id, _)| *id == unit_id)
+            .map(|(_, address)| *address)
+            .ok_or(Error::MissingReqHandler)?;
+
+        if let Some(rsp_pdu) = self.forward(address, &req_pdu) {
+            self.slave.write_rsp_pdu(&mut stream, &rsp_pdu)?;
+        }
+        Ok(())
+    }
+
+    fn forward(&mut self, address: u8, req_pdu: &[u8]) -> Option<Vec<u8>> {
+        let function_code = req_pdu[0];
+
+        let mut stream = match self.master.write_req_pdu(&address, req_pdu) {
+            Ok(stream) => stream,
+            Err(_) => return Some(encode_exc_rsp(function_code, ExceptionCode::GatewayPathUnavailable)),
+        };
+        if M::is_broadcast(&address) {
+            return None;
+        }
+
+        match self.master.read_rsp_pdu(&mut stream, &address) {
+            Ok(rsp_pdu) => Some(rsp_pdu),
+            Err(_) => Some(encode_exc_rsp(function_code, ExceptionCode::GatewayTargetDeviceFailedToRespond)),
+        }
+    }
+
+    /// Serve requests until the process is terminated
+    ///
+    /// Failures of single requests are ignored and the gateway keeps serving next requests.
+    /// Repeated failures are followed by increasing delays, up to 1 second, so that a persistently
+    /// failing slave transport does not keep the CPU busy.
+    /// This method returns only if the gateway was not started with [Gateway::start].
+    pub fn serve_forever(&mut self) -> Result<(), Error> {
+        self.serve_until(&CancelToken::new())
+    }
+
+    /// Serve requests until given token is cancelled
+    ///
+    /// The token is checked between requests. To interrupt waiting for a request, the same token
+    /// shall be set on the slave transport, like with
+    /// [Tcp::set_cancel_token](crate::tcp::Tcp::set_cancel_token).
+    pub fn serve_until(&mut self, cancel_token: &CancelToken) -> Result<(), Error> {
+        if !self.started {
+            return Err(Error::InvalidValue);
+        }
+
+        crate::cancel::serve_until(cancel_token, || self.process_req())
+    }
+
+    /// Release the slave and the master transports
+    pub fn release(self) -> (S, M) {
+        (self.slave, self.master)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+
+    fn create_gateway(unit_id: u8, req_pdu: &[u8]) -> Gateway<MockTransport, MockTransport> {
+        let mut gateway = Gateway::new(MockTransport::new(), MockTransport::new());
+        gateway.add_route(10, 2).unwrap();
+        gateway.add_route(11, 0).unwrap();
+        gateway.start().unwrap();
+        gateway.slave.push_req(unit_id, req_pdu);
+        gateway
+    }
+
+    #[test]
+    fn test_add_route_twice() {
+        let mut gateway = Gateway::new(MockTransport::new(), MockTransport::new());
+        gateway.add_route(10, 2).unwrap();
+
+        assert!(gateway.add_route(10, 3).is_err());
+    }
+
+    #[test]
+    fn test_forward() {
+        let mut gateway = create_gateway(10, &[0x03, 0x00, 0x04, 0x00, 0x01]);
+        gateway.master.expect(&[0x03, 0x00, 0x04, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x34]);
+
+        gateway.process_req().unwrap();
+
+        let (slave, master) = gateway.release();
+        assert!(master.is_complete());
+        assert_eq!(slave.get_rsps(), &[(10, vec![0x03, 0x02, 0x12, 0x34])]);
+    }
+
+    #[test]
+    fn test_forward_no_response() {
+        let mut gateway = create_gateway(10, &[0x03, 0x00, 0x04, 0x00, 0x01]);
+        gateway.master.expect_no_response(&[0x03, 0x00, 0x04, 0x00, 0x01]);
+
+        gateway.process_req().unwrap();
+
+        assert_eq!(gateway.slave.get_rsps(), &[(10, vec![0x83, 0x0B])]);
+    }
+
+    #[test]
+    fn test_forward_broadcast() {
+        let mut gateway = create_gateway(11, &[0x06, 0x00, 0x01, 0x00, 0x02]);
+        gateway.master.expect_no_response(&[0x06, 0x00, 0x01, 0x00, 0x02]);
+
+        gateway.process_req().unwrap();
+
+        assert!(gateway.master.is_complete());
+        assert!(gateway.slave.get_rsps().is_empty());
+    }
+}
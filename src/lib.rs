@@ -1,14 +1,57 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[macro_use]
+extern crate alloc;
 extern crate num;
 #[macro_use]
 extern crate num_derive;
+#[cfg(feature = "derive")]
+extern crate self as modbus;
 
+pub mod address;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
+#[cfg(feature = "std")]
+mod cancel;
+#[cfg(feature = "std")]
+pub mod client;
 mod error;
+#[cfg(feature = "std")]
+pub mod discovery;
+#[cfg(feature = "config")]
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod file_transfer;
+pub mod fmt;
+#[cfg(feature = "std")]
+pub mod gateway;
+#[cfg(feature = "config")]
+pub mod logger;
 mod pdu;
+pub mod prelude;
+#[cfg(feature = "config")]
+pub mod register_map;
+#[cfg(feature = "std")]
+mod rng;
+#[cfg(feature = "std")]
+pub mod server;
+#[cfg(feature = "std")]
+pub mod simulator;
+#[cfg(feature = "metrics")]
+pub mod telemetry;
 mod transport;
+pub mod value;
 
-pub use error::Error;
-pub use pdu::{Request, Setter};
-pub use pdu::RequestData;
+#[cfg(feature = "std")]
+pub use cancel::CancelToken;
+pub use error::{Error, ErrorContext, Stage};
+pub use pdu::{DecodeMode, ExceptionCode, Function, FunctionCode, Request, Response, Setter};
+pub use pdu::{RequestData, ResponseData};
+pub use pdu::raw::Decoded;
+pub use pdu::bit_access::bits::Bits;
 
 pub use pdu::bit_access::read_coils::Request as ReadCoilsRequest;
 pub use pdu::bit_access::read_dscr_in::Request as ReadDscrInRequest;
@@ -17,6 +60,10 @@ pub use pdu::hex_access::read_in_reg::Request as ReadInRegRequest;
 pub use pdu::bit_access::write_single_coil::Message as WriteSingleCoilRequest;
 pub use pdu::hex_access::write_single_reg::Message as WriteSingleRegRequest;
 pub use pdu::hex_access::write_multi_reg::Request as WriteMultiRegRequest;
+pub use pdu::hex_access::mask_write_reg::Message as MaskWriteRegRequest;
+pub use pdu::file_access::read_file_record::Request as ReadFileRecordRequest;
+pub use pdu::file_access::write_file_record::Message as WriteFileRecordRequest;
+pub use pdu::read_device_id::Request as ReadDeviceIdRequest;
 
 pub use pdu::bit_access::read_coils::Response as ReadCoilsResponse;
 pub use pdu::bit_access::read_dscr_in::Response as ReadDscrInResponse;
@@ -25,7 +72,59 @@ pub use pdu::hex_access::read_in_reg::Response as ReadInRegResponse;
 pub use pdu::bit_access::write_single_coil::Message as WriteSingleCoilResponse;
 pub use pdu::hex_access::write_single_reg::Message as WriteSingleRegResponse;
 pub use pdu::hex_access::write_multi_reg::Response as WriteMultiRegResponse;
+pub use pdu::hex_access::mask_write_reg::Message as MaskWriteRegResponse;
+pub use pdu::file_access::read_file_record::Response as ReadFileRecordResponse;
+pub use pdu::file_access::write_file_record::Message as WriteFileRecordResponse;
+pub use pdu::read_device_id::Response as ReadDeviceIdResponse;
+pub use pdu::read_device_id::ReadDeviceIdCode;
+
+pub use pdu::hex_access::enron;
 
 pub use transport::Transport;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub use transport::AsyncTransport;
+#[cfg(any(feature = "serial", feature = "tcp"))]
+pub use transport::any;
+#[cfg(feature = "std")]
+pub use transport::actor;
+#[cfg(feature = "std")]
+pub use transport::capture;
+#[cfg(feature = "std")]
+pub use transport::delay;
+#[cfg(feature = "std")]
+pub use transport::fault;
+#[cfg(feature = "std")]
+pub use transport::intercept;
+#[cfg(feature = "std")]
+pub use transport::mock;
+#[cfg(feature = "std")]
+pub use transport::pace;
+#[cfg(feature = "std")]
+pub use transport::record;
+#[cfg(feature = "serial")]
 pub use transport::rtu::conn as rtu;
+#[cfg(feature = "serial")]
+pub use transport::rtu::bus as rtu_bus;
+#[cfg(feature = "embedded")]
+pub use transport::rtu::embedded as embedded_rtu;
+#[cfg(feature = "serial")]
+pub use transport::rtu::monitor as rtu_monitor;
+#[cfg(feature = "tcp")]
+pub use transport::rtu::tcp_conn as rtu_over_tcp;
+pub use transport::scan;
+pub use transport::target::Target;
+#[cfg(feature = "tcp")]
 pub use transport::tcp::conn as tcp;
+#[cfg(feature = "tokio")]
+pub use transport::tcp::async_conn as async_tcp;
+#[cfg(feature = "async-std")]
+pub use transport::tcp::async_std_conn as async_std_tcp;
+#[cfg(feature = "mio")]
+pub use transport::tcp::mio_conn as mio_tcp;
+
+/// Items used by code generated by the derive macros
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec::Vec;
+}
@@ -0,0 +1,359 @@
+//! Data logger appending polled tag values to rotating files
+//!
+//! A [Logger] polls [register maps](RegisterMap), each at its own interval, and appends
+//! the [samples](crate::export::Sample) to a CSV or JSON lines file until cancelled.
+//! When the file grows over the configured size, it is rotated: `values.csv` is renamed
+//! to `values.1.csv`, `values.1.csv` to `values.2.csv` and so on, and the oldest file is removed.
+//!
+//! # Examples
+//! ```no_run
+//! use modbus::{CancelToken, Transport};
+//! use modbus::logger::{Format, Logger};
+//! use modbus::register_map::RegisterMap;
+//! use std::net::{IpAddr, Ipv4Addr};
+//! use std::time::Duration;
+//!
+//! let fast = RegisterMap::load_from_file("fast.toml").unwrap();
+//! let slow = RegisterMap::load_from_file("slow.toml").unwrap();
+//!
+//! let mut logger = Logger::new("values.csv", Format::Csv)
+//!     .with_map(fast, Duration::from_secs(1))
+//!     .with_map(slow, Duration::from_secs(60))
+//!     .with_max_file_size(1024 * 1024);
+//!
+//! let mut mb = modbus::tcp::Tcp::new();
+//! mb.start_master().unwrap();
+//! let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 10));
+//! logger.run_until(&mut mb, |unit_id| modbus::tcp::Dst::new(ip, unit_id), &CancelToken::new()).unwrap();
+//! ```
+
+use crate::cancel::CancelToken;
+use crate::error::Error;
+use crate::export::{CsvExporter, Exporter, JsonLinesExporter, Sample};
+use crate::register_map::RegisterMap;
+use crate::transport::Transport;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_FILES: usize = 5;
+const CANCEL_CHECK_PERIOD: Duration = Duration::from_millis(100);
+
+/// Format of the log files
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Format {
+    /// CSV rows written by [CsvExporter]
+    Csv,
+    /// JSON objects written by [JsonLinesExporter]
+    JsonLines,
+}
+
+/// Writer counting bytes in the log file
+#[derive(Debug)]
+struct FileWriter {
+    file: LineWriter<File>,
+    len: u64,
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[derive(Debug)]
+enum Sink {
+    Csv(CsvExporter<FileWriter>),
+    JsonLines(JsonLinesExporter<FileWriter>),
+}
+
+impl Sink {
+    fn open(path: &Path, format: Format) -> Result<Self, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let len = file.metadata()?.len();
+        let writer = FileWriter {file: LineWriter::new(file), len};
+
+        Ok(match format {
+            Format::Csv => Sink::Csv(CsvExporter::new(writer).with_header(len == 0)),
+            Format::JsonLines => Sink::JsonLines(JsonLinesExporter::new(writer)),
+        })
+    }
+
+    fn get_len(&self) -> u64 {
+        match self {
+            Sink::Csv(exporter) => exporter.get_ref().len,
+            Sink::JsonLines(exporter) => exporter.get_ref().len,
+        }
+    }
+
+    fn export_all(&mut self, samples: &[Sample]) -> Result<(), Error> {
+        match self {
+            Sink::Csv(exporter) => exporter.export_all(samples),
+            Sink::JsonLines(exporter) => exporter.export_all(samples),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Schedule {
+    map: RegisterMap,
+    interval: Duration,
+}
+
+/// Logger of tag values polled periodically
+#[derive(Debug)]
+pub struct Logger {
+    path: PathBuf,
+    format: Format,
+    schedules: Vec<Schedule>,
+    max_file_size: u64,
+    max_files: usize,
+
+    sink: Option<Sink>,
+}
+
+impl Logger {
+    /// Create a logger writing to a file at given path in given format
+    ///
+    /// The file is created when the first samples are written. Samples are appended to an existing file.
+    pub fn new<P: Into<PathBuf>>(path: P, format: Format) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            schedules: Vec::new(),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_files: DEFAULT_MAX_FILES,
+            sink: None,
+        }
+    }
+
+    /// Poll all tags of given register map at given interval
+    pub fn with_map(mut self, map: RegisterMap, interval: Duration) -> Self {
+        self.schedules.push(Schedule {map, interval});
+        self
+    }
+
+    /// Rotate the log file when it grows over given number of bytes
+    ///
+    /// The default size is 10 MiB.
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Keep given number of rotated files besides the current one
+    ///
+    /// If set to 0, the log file is removed when rotated. The default is 5 files.
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Get path of the rotated file with given index, 0 being the current file
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::logger::{Format, Logger};
+    /// use std::path::Path;
+    ///
+    /// let logger = Logger::new("log/values.csv", Format::Csv);
+    /// assert_eq!(logger.get_path(0), Path::new("log/values.csv"));
+    /// assert_eq!(logger.get_path(2), Path::new("log/values.2.csv"));
+    /// ```
+    pub fn get_path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            return self.path.clone();
+        }
+
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let file_name = match self.path.extension() {
+            Some(extension) => format!("{}.{}.{}", stem, index, extension.to_string_lossy()),
+            None => format!("{}.{}", stem, index),
+        };
+        self.path.with_file_name(file_name)
+    }
+
+    /// Poll all register maps once and append the samples to the log file
+    pub fn poll<T: Transport, F: FnMut(u8) -> T::Dst>(&mut self, transport: &mut T, mut dst: F) -> Result<(), Error> {
+        for index in 0..self.schedules.len() {
+            self.poll_map(index, transport, &mut dst)?;
+        }
+        Ok(())
+    }
+
+    /// Poll register maps at their intervals until given token is cancelled
+    ///
+    /// All maps are polled right after calling this method. Failures to read tags are logged
+    /// as samples of bad quality, while failures to write the log file stop logging.
+    /// A logger without any register maps fails with [Error::InvalidValue].
+    pub fn run_until<T: Transport, F: FnMut(u8) -> T::Dst>(&mut self, transport: &mut T, mut dst: F,
+                                                           cancel_token: &CancelToken) -> Result<(), Error> {
+        if self.schedules.is_empty() {
+            return Err(Error::InvalidValue);
+        }
+
+        let mut deadlines = vec![Instant::now(); self.schedules.len()];
+
+        while !cancel_token.is_cancelled() {
+            let now = Instant::now();
+
+            for (index, deadline) in deadlines.iter_mut().enumerate() {
+                if *deadline <= now {
+                    self.poll_map(index, transport, &mut dst)?;
+                    // Skip polls missed because of a slow transport instead of catching up
+                    *deadline = (*deadline + self.schedules[index].interval).max(now);
+                }
+            }
+
+            let next = deadlines.iter().min().copied().unwrap_or(now);
+            sleep(next.saturating_duration_since(Instant::now()).min(CANCEL_CHECK_PERIOD));
+        }
+
+        Ok(())
+    }
+
+    fn poll_map<T: Transport, F: FnMut(u8) -> T::Dst>(&mut self, index: usize, transport: &mut T, dst: &mut F)
+        -> Result<(), Error>
+    {
+        let samples = self.schedules[index].map.poll(transport, &mut *dst);
+        self.write(&samples)
+    }
+
+    fn write(&mut self, samples: &[Sample]) -> Result<(), Error> {
+        let sink = match &mut self.sink {
+            Some(sink) => sink,
+            sink => sink.insert(Sink::open(&self.path, self.format)?),
+        };
+        sink.export_all(samples)?;
+
+        if sink.get_len() >= self.max_file_size {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        self.sink = None;
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+            return Ok(());
+        }
+
+        let oldest = self.get_path(self.max_files);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for index in (0..self.max_files).rev() {
+            let path = self.get_path(index);
+            if path.exists() {
+                fs::rename(path, self.get_path(index + 1))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+
+    fn create_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("modbus-logger-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn create_map() -> RegisterMap {
+        RegisterMap::from_json(r#"{"tags": [
+            {"name": "level", "unit": 1, "table": "holding_registers", "address": 0}
+        ]}"#).unwrap()
+    }
+
+    #[test]
+    fn test_poll() {
+        let dir = create_dir("poll");
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x03, 0x00, 0x00, 0x00, 0x01], &[0x03, 0x02, 0x00, 0x07]);
+        mb.expect_no_response(&[0x03, 0x00, 0x00, 0x00, 0x01]);
+
+        let mut logger = Logger::new(dir.join("values.csv"), Format::Csv).with_map(create_map(), Duration::from_secs(1));
+        logger.poll(&mut mb, |unit_id| unit_id).unwrap();
+        logger.poll(&mut mb, |unit_id| unit_id).unwrap();
+
+        let rows: Vec<String> = fs::read_to_string(dir.join("values.csv")).unwrap().lines().map(String::from).collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[1].ends_with(",level,1,holding_registers,0,7,7,good"));
+        assert!(rows[2].ends_with(",level,1,holding_registers,0,,,no_response"));
+
+        // Header is not repeated when appending to an existing file
+        let mut logger = Logger::new(dir.join("values.csv"), Format::Csv).with_map(create_map(), Duration::from_secs(1));
+        logger.poll(&mut mb, |unit_id| unit_id).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("values.csv")).unwrap().lines().count(), 4);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = create_dir("rotation");
+        let mut mb = MockTransport::new();
+        for value in 0..5_u8 {
+            mb.expect(&[0x03, 0x00, 0x00, 0x00, 0x01], &[0x03, 0x02, 0x00, value]);
+        }
+
+        let mut logger = Logger::new(dir.join("values.json"), Format::JsonLines)
+            .with_map(create_map(), Duration::from_secs(1))
+            .with_max_file_size(1)
+            .with_max_files(2);
+        for _ in 0..5 {
+            logger.poll(&mut mb, |unit_id| unit_id).unwrap();
+        }
+
+        assert!(!dir.join("values.json").exists());
+        assert!(fs::read_to_string(dir.join("values.1.json")).unwrap().contains("\"raw\":[4]"));
+        assert!(fs::read_to_string(dir.join("values.2.json")).unwrap().contains("\"raw\":[3]"));
+        assert!(!dir.join("values.3.json").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_until() {
+        let dir = create_dir("run");
+        let token = CancelToken::new();
+        let mut mb = MockTransport::new();
+        for value in 0..3_u8 {
+            let token = token.clone();
+            mb.expect_fn(Box::new(move |_, _| {
+                if value == 2 {
+                    token.cancel();
+                }
+                Some(vec![0x03, 0x02, 0x00, value])
+            }));
+        }
+
+        let mut logger = Logger::new(dir.join("values.csv"), Format::Csv)
+            .with_map(create_map(), Duration::from_millis(10));
+        logger.run_until(&mut mb, |unit_id| unit_id, &token).unwrap();
+
+        assert!(mb.is_complete());
+        assert_eq!(fs::read_to_string(dir.join("values.csv")).unwrap().lines().count(), 4);
+
+        assert!(matches!(Logger::new(dir.join("values.csv"), Format::Csv).run_until(&mut mb, |unit_id| unit_id, &token),
+                         Err(Error::InvalidValue)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use core::iter::FromIterator;
+use core::ops::Index;
+use alloc::vec::Vec;
+
+use super::DSCR_PER_BYTE;
+
+/// Sequence of bits packed into bytes as they are transferred in Modbus PDUs
+///
+/// The first bit is the least significant bit of the first byte. Unused bits of the last byte are always zero.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Vec<bool>", into = "Vec<bool>"))]
+pub struct Bits {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bits {
+    /// Create an empty sequence of bits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a sequence of bits from packed bytes
+    ///
+    /// All bits of given bytes are included in the sequence.
+    ///
+    /// # Examples
+    /// ```
+    /// let bits = modbus::Bits::from_bytes(&[0x05]);
+    /// assert_eq!(bits.len(), 8);
+    /// assert_eq!(bits.get(2), Some(true));
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self {bytes: bytes.to_vec(), len: bytes.len() * DSCR_PER_BYTE}
+    }
+
+    /// Get number of bits in the sequence
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the sequence contains no bits
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get bit at given position or `None` if the position is out of the sequence
+    ///
+    /// # Examples
+    /// ```
+    /// let bits = modbus::Bits::from(&[true, false][..]);
+    /// assert_eq!(bits.get(0), Some(true));
+    /// assert_eq!(bits.get(1), Some(false));
+    /// assert_eq!(bits.get(2), None);
+    /// ```
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index < self.len {
+            Some(self.bytes[index / DSCR_PER_BYTE] & (1 << (index % DSCR_PER_BYTE)) != 0)
+        } else {
+            None
+        }
+    }
+
+    /// Append a bit to the end of the sequence
+    pub fn push(&mut self, value: bool) {
+        if self.len.is_multiple_of(DSCR_PER_BYTE) {
+            self.bytes.push(0);
+        }
+        if value {
+            self.bytes[self.len / DSCR_PER_BYTE] |= 1 << (self.len % DSCR_PER_BYTE);
+        }
+        self.len += 1;
+    }
+
+    /// Shorten the sequence to given number of bits
+    ///
+    /// Nothing happens if the sequence is not longer than `len`.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut bits = modbus::Bits::from_bytes(&[0xff, 0xff]);
+    /// bits.truncate(3);
+    /// assert_eq!(bits.as_bytes(), &[0x07]);
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+
+        self.bytes.truncate(len.div_ceil(DSCR_PER_BYTE));
+        if !len.is_multiple_of(DSCR_PER_BYTE) {
+            self.bytes[len / DSCR_PER_BYTE] &= (1 << (len % DSCR_PER_BYTE)) - 1;
+        }
+        self.len = len;
+    }
+
+    /// Get the packed bytes of the sequence
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Iterate over the bits of the sequence
+    ///
+    /// # Examples
+    /// ```
+    /// let bits = modbus::Bits::from_bytes(&[0x01]);
+    /// assert_eq!(bits.iter().filter(|bit| *bit).count(), 1);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(move |i| self.get(i).unwrap())
+    }
+
+    /// Copy the bits to a vector of `bool`
+    pub fn to_vec(&self) -> Vec<bool> {
+        self.iter().collect()
+    }
+}
+
+impl Index<usize> for Bits {
+    type Output = bool;
+
+    fn index(&self, index: usize) -> &bool {
+        match self.get(index) {
+            Some(true) => &true,
+            Some(false) => &false,
+            None => panic!("Bit index {} out of range for sequence of {} bits", index, self.len),
+        }
+    }
+}
+
+impl From<&[bool]> for Bits {
+    fn from(values: &[bool]) -> Self {
+        values.iter().copied().collect()
+    }
+}
+
+impl From<Vec<bool>> for Bits {
+    fn from(values: Vec<bool>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl From<Bits> for Vec<bool> {
+    fn from(bits: Bits) -> Self {
+        bits.to_vec()
+    }
+}
+
+impl FromIterator<bool> for Bits {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bits = Self::new();
+        for value in iter {
+            bits.push(value);
+        }
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bools() {
+        let bits = Bits::from(&[true, false, true, true, false, false, true, true, true, false, true][..]);
+        assert_eq!(bits.len(), 11);
+        assert_eq!(bits.as_bytes(), &[0xCD, 0x05]);
+        assert_eq!(bits.to_vec(), vec![true, false, true, true, false, false, true, true, true, false, true]);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut bits = Bits::from_bytes(&[0xff, 0xff, 0xff]);
+        bits.truncate(10);
+        assert_eq!(bits, Bits::from(&[true; 10][..]));
+        assert_eq!(bits.as_bytes(), &[0xff, 0x03]);
+    }
+
+    #[test]
+    fn test_index() {
+        let bits = Bits::from_bytes(&[0x02]);
+        assert!(!bits[0]);
+        assert!(bits[1]);
+    }
+}
@@ -1,5 +1,27 @@
+pub mod bits;
 pub mod read_coils;
 pub mod read_dscr_in;
 pub mod write_single_coil;
 
-const DSCR_PER_BYTE: usize = 8;
\ No newline at end of file
+use crate::Error;
+use crate::pdu::DecodeMode;
+use bits::Bits;
+
+const DSCR_PER_BYTE: usize = 8;
+
+/// Verify that bits decoded from a response cover the requested quantity
+/// 
+/// In the strict mode the response must consist of exactly as many bytes as needed for the quantity
+/// and padding bits of the last byte must be zero.
+fn check_bits(bits: &Bits, quantity: u16, mode: DecodeMode) -> Result<(), Error> {
+    let quantity = quantity as usize;
+    if bits.len() < quantity {
+        return Err(Error::InvalidResponse);
+    }
+
+    match mode {
+        DecodeMode::Strict if bits.len() != quantity.div_ceil(DSCR_PER_BYTE) * DSCR_PER_BYTE
+                               || bits.iter().skip(quantity).any(|bit| bit) => Err(Error::InvalidResponse),
+        _ => Ok(()),
+    }
+}
\ No newline at end of file
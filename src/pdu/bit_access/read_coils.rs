@@ -1,11 +1,16 @@
 use crate::error::Error;
-use crate::pdu::{Function, FunctionCode, MAX_SIZE, Request as ReqT, Response as RspT};
-use super::DSCR_PER_BYTE;
-use std::convert::TryInto;
-use std::vec::Vec;
+use crate::pdu::{is_range_valid, check_size, DecodeMode, Function, FunctionCode, MAX_SIZE, Request as ReqT, Response as RspT};
+use crate::fmt::HexDump;
+use super::bits::Bits;
+use super::check_bits;
+use core::convert::TryInto;
+use core::fmt;
+use core::ops::Index;
+use alloc::vec::Vec;
 
 /// Read Coils function request
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Request {
     address: u16,
     quantity: u16,
@@ -21,6 +26,7 @@ impl Request {
     /// ```
     pub fn new(address: u16, quantity: u16) -> Self {
         // TODO: debug_assert quantity > 0
+        debug_assert!(is_range_valid(address, quantity));
         Request{address, quantity}
     }
 
@@ -54,21 +60,20 @@ impl Request {
 }
 
 impl Function for Request {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
         match self.quantity {
-            1..=2000 => {
-                let mut result = Vec::new();
-                result.push(FunctionCode::ReadCoils as u8);
-                result.append(&mut self.address.to_be_bytes().to_vec());
-                result.append(&mut self.quantity.to_be_bytes().to_vec());
+            1..=2000 if is_range_valid(self.address, self.quantity) => {
+                buf.push(FunctionCode::ReadCoils as u8);
+                buf.extend_from_slice(&self.address.to_be_bytes());
+                buf.extend_from_slice(&self.quantity.to_be_bytes());
 
-                Ok(result)
+                Ok(())
             }
             _ => Err(Error::InvalidValue),
         }
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
         if data.len() != 5 {
             return Err(Error::InvalidDataLength);
         }
@@ -76,19 +81,29 @@ impl Function for Request {
             return Err(Error::InvalidData);
         }
 
-        Ok(Self {address: u16::from_be_bytes(data[1..=2].try_into().unwrap()), 
-                 quantity: u16::from_be_bytes(data[3..=4].try_into().unwrap())})
+        let address = u16::from_be_bytes(data[1..=2].try_into().unwrap());
+        let quantity = u16::from_be_bytes(data[3..=4].try_into().unwrap());
+        if mode == DecodeMode::Strict && !(1..=2000).contains(&quantity) {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(Self {address, quantity})
     }
 }
 
 impl ReqT for Request {
     type Rsp = Response;
+
+    fn check_response(&self, rsp: &Self::Rsp, mode: DecodeMode) -> Result<(), Error> {
+        check_bits(&rsp.coils, self.quantity, mode)
+    }
 }
 
 /// Read Coils function response
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Response {
-    coils: Vec<bool>,
+    coils: Bits,
 }
 
 impl Response {
@@ -99,85 +114,128 @@ impl Response {
     /// let response = modbus::ReadCoilsResponse::new(&[true, false]);
     /// ```
     pub fn new(coils: &[bool]) -> Self {
-        Self {coils: coils.to_vec()}
+        Self {coils: coils.into()}
+    }
+
+    /// Create a new Read Coils response from packed coils.
+    /// 
+    /// # Examples
+    /// ```
+    /// let response = modbus::ReadCoilsResponse::from_bits(modbus::Bits::from_bytes(&[0x05]));
+    /// assert!(response.get_coils()[2]);
+    /// ```
+    pub fn from_bits(coils: Bits) -> Self {
+        Self {coils}
     }
 
-    /// Get vector of coils from the given response.
+    /// Get coils from the given response.
     /// 
     /// # Examples
     /// ```
     /// let coil_values = [true, false, true, true, false, false, true, false];
     /// let response = modbus::ReadCoilsResponse::new(&coil_values);
     /// let new_coil_values = response.get_coils();
-    /// assert_eq!(&coil_values.to_vec(), new_coil_values)
+    /// assert_eq!(coil_values.to_vec(), new_coil_values.to_vec())
     /// ```
-    pub fn get_coils(&self) -> &Vec<bool> {
+    pub fn get_coils(&self) -> &Bits {
         &self.coils
     }
+
+    /// Get number of coils in the response
+    ///
+    /// A decoded response contains all bits of its bytes, so the number is rounded up to a multiple of 8.
+    pub fn len(&self) -> usize {
+        self.coils.len()
+    }
+
+    /// Check if the response contains no coils
+    pub fn is_empty(&self) -> bool {
+        self.coils.is_empty()
+    }
+
+    /// Get value of the coil at given position or `None` if the position is out of the response
+    ///
+    /// # Examples
+    /// ```
+    /// let response = modbus::ReadCoilsResponse::new(&[false, true]);
+    /// assert_eq!(response.get(1), Some(true));
+    /// assert_eq!(response.get(2), None);
+    /// ```
+    pub fn get(&self, index: usize) -> Option<bool> {
+        self.coils.get(index)
+    }
+
+    /// Iterate over the coils of the response
+    ///
+    /// # Examples
+    /// ```
+    /// let response = modbus::ReadCoilsResponse::new(&[true, false, true]);
+    /// assert_eq!(response.iter().filter(|value| *value).count(), 2);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        self.coils.iter()
+    }
+}
+
+impl Index<usize> for Response {
+    type Output = bool;
+
+    fn index(&self, index: usize) -> &bool {
+        &self.coils[index]
+    }
 }
 
 impl Function for Response {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
         const MAX_BYTE_COUNT: usize = MAX_SIZE - 2;
-        let byte_count = self.coils.len() / DSCR_PER_BYTE + if self.coils.len() % DSCR_PER_BYTE > 0 { 1 } else { 0 };
+        let bytes = self.coils.as_bytes();
 
-        match byte_count {
+        match bytes.len() {
             0 => Err(Error::InvalidValue),
             1..=MAX_BYTE_COUNT => {
-                let mut result = Vec::new();
-                result.push(FunctionCode::ReadCoils as u8);
-                result.push(byte_count as u8);
-
-                for byte_num in 0..byte_count {
-                    let mut byte: u8 = 0;
-
-                    for bit_num in 0..DSCR_PER_BYTE {
-                        let coil_id = byte_num * DSCR_PER_BYTE + bit_num;
-                        if coil_id >= self.coils.len() {
-                            break;
-                        }
-
-                        if self.coils[coil_id] {
-                            byte |= 1 << bit_num;
-                        }
-                    }
-
-                    result.push(byte);
-                }
+                buf.push(FunctionCode::ReadCoils as u8);
+                buf.push(bytes.len() as u8);
+                buf.extend_from_slice(bytes);
 
-                Ok(result)
+                Ok(())
             }
             _ => Err(Error::InvalidValue),
         }
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
         if data.len() < 3 {
             return Err(Error::InvalidDataLength);
         }
         if data[0] != FunctionCode::ReadCoils as u8 {
             return Err(Error::InvalidData);
         }
+        check_size(data.len())?;
 
         let byte_count = data[1] as usize;
-        if data.len() != byte_count + 2 {
+        if mode == DecodeMode::Strict && (byte_count == 0 || data.len() != byte_count + 2) {
             return Err(Error::InvalidDataLength);
         }
 
-        let mut result = Vec::with_capacity(byte_count * DSCR_PER_BYTE);
-        for byte_num in 0..byte_count {
-            for bit_num in 0..DSCR_PER_BYTE {
-                result.push(if data[2 + byte_num] & (1 << bit_num) != 0 { true } else { false });
-            }
-        }
-
-        Ok(Self {coils: result})
+        Ok(Self {coils: Bits::from_bytes(&data[2..])})
     }
 }
 
 impl RspT for Response {
     fn get_exc_function_code() -> u8 {
-        FunctionCode::ExcReadCoils.try_into().unwrap()
+        FunctionCode::ExcReadCoils.into()
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read Coils request: address 0x{:04x}, quantity {}", self.address, self.quantity)
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read Coils response: {} coils, data: {}", self.coils.len(), HexDump::new(self.coils.as_bytes()))
     }
 }
 
@@ -192,41 +250,70 @@ mod tests {
         assert_eq!(pdu, expected_pdu);
     }
 
+    #[test]
+    fn test_encode_read_coils_request_into_buffer() {
+        let mut buf = vec![0xff];
+        Request{address: 0x1234, quantity: 0x00cd}.encode_into(&mut buf).unwrap();
+        assert_eq!(buf, vec![0xff, 0x01, 0x12, 0x34, 0x00, 0xcd]);
+
+        Request{address: 0x1234, quantity: 0}.encode_into(&mut buf).err().unwrap();
+        assert_eq!(buf, vec![0xff, 0x01, 0x12, 0x34, 0x00, 0xcd]);
+    }
+
     #[test]
     fn test_encode_read_zero_coils_request() {
         let result = Request{address: 0x1234, quantity: 0}.encode().err().unwrap();
         match result {
             Error::InvalidValue => {}
-            _ => panic!(format!("Expected InvalidValue, but got {:?}", result)),
+            _ => panic!("Expected InvalidValue, but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_encode_read_coils_request_address_overflow() {
+        let result = Request{address: 0xffff, quantity: 2}.encode().err().unwrap();
+        match result {
+            Error::InvalidValue => {}
+            _ => panic!("Expected InvalidValue, but got {:?}", result),
         }
     }
 
     #[test]
     fn test_encode_read_coils_response() {
-        let pdu = Response{coils: vec![true, false, true, true, false, false, true, true,
-                                       true, true, false, true, false, true, true, false,
-                                       true, false, true]}.encode().unwrap();
+        let pdu = Response::new(&[true, false, true, true, false, false, true, true,
+                                   true, true, false, true, false, true, true, false,
+                                   true, false, true]).encode().unwrap();
         let expected_pdu = vec![0x01, 0x03, 0xCD, 0x6B, 0x05];
         assert_eq!(pdu, expected_pdu);
     }
 
     #[test]
     fn test_encode_read_zero_coils_response() {
-        let result = Response{coils: vec![]}.encode().err().unwrap();
+        let result = Response::new(&[]).encode().err().unwrap();
         match result {
             Error::InvalidValue => {}
-            _ => panic!(format!("Expected InvalidValue, but got {:?}", result)),
+            _ => panic!("Expected InvalidValue, but got {:?}", result),
         }
     }
 
     #[test]
     fn test_decode_read_coils_request() {
         let pdu = [0x01, 0x12, 0x34, 0xab, 0xcd];
-        let result = Request::decode(&pdu).unwrap();
+        let result = Request::decode_with_mode(&pdu, DecodeMode::Lenient).unwrap();
         assert_eq!(result.address, 0x1234);
         assert_eq!(result.quantity, 0xabcd);
     }
 
+    #[test]
+    fn test_decode_read_coils_request_strict_quantity() {
+        let pdu = [0x01, 0x12, 0x34, 0xab, 0xcd];
+        let err = Request::decode(&pdu).err().unwrap();
+        match err {
+            Error::InvalidData => {}
+            _ => panic!("Expected InvalidData, but got {:?}", err),
+        }
+    }
+
     #[test]
     fn test_decode_read_coils_response() {
         let pdu = [0x01, 0x03, 0xCD, 0x6B, 0x05];
@@ -237,4 +324,76 @@ mod tests {
             assert_eq!(result.coils[i], *expected_value);
         }
     }
+
+    #[test]
+    fn test_decode_read_coils_response_byte_count_mismatch() {
+        let pdu = [0x01, 0x01, 0xCD, 0x6B];
+        let err = Response::decode(&pdu).err().unwrap();
+        match err {
+            Error::InvalidDataLength => {}
+            _ => panic!("Expected InvalidDataLength, but got {:?}", err),
+        }
+
+        let rsp = Response::decode_with_mode(&pdu, DecodeMode::Lenient).unwrap();
+        assert_eq!(rsp.coils.len(), 16);
+    }
+
+    #[test]
+    fn test_check_read_coils_response_padding() {
+        let req = Request::new(0x0000, 3);
+        let rsp = Response::decode(&[0x01, 0x01, 0x0D]).unwrap();
+
+        let err = req.check_response(&rsp, DecodeMode::Strict).err().unwrap();
+        match err {
+            Error::InvalidResponse => {}
+            _ => panic!("Expected InvalidResponse, but got {:?}", err),
+        }
+        req.check_response(&rsp, DecodeMode::Lenient).unwrap();
+
+        let rsp = Response::decode(&[0x01, 0x01, 0x05]).unwrap();
+        req.check_response(&rsp, DecodeMode::Strict).unwrap();
+    }
+
+    #[test]
+    fn test_check_read_coils_response_too_few_coils() {
+        let req = Request::new(0x0000, 9);
+        let rsp = Response::decode(&[0x01, 0x01, 0x05]).unwrap();
+
+        for mode in [DecodeMode::Strict, DecodeMode::Lenient] {
+            let err = req.check_response(&rsp, mode).err().unwrap();
+            match err {
+                Error::InvalidResponse => {}
+                _ => panic!("Expected InvalidResponse, but got {:?}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn test_display_read_coils() {
+        assert_eq!(Request::new(0x0123, 10).to_string(), "Read Coils request: address 0x0123, quantity 10");
+        assert_eq!(Response::new(&[true, false, true]).to_string(), "Read Coils response: 3 coils, data: 05");
+    }
+
+    #[test]
+    fn test_decode_oversized_read_coils_response() {
+        let mut pdu = vec![0x01, 0xFF];
+        pdu.extend_from_slice(&[0x00; 0xFF]);
+        let err = Response::decode(&pdu).err().unwrap();
+        match err {
+            Error::InvalidDataLength => {}
+            _ => panic!("Expected InvalidDataLength, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_read_coils_response_access() {
+        let rsp = Response::decode(&[0x01, 0x01, 0x05]).unwrap();
+
+        assert_eq!(rsp.len(), 8);
+        assert!(!rsp.is_empty());
+        assert!(rsp[0] && !rsp[1] && rsp[2]);
+        assert_eq!(rsp.get(7), Some(false));
+        assert_eq!(rsp.get(8), None);
+        assert_eq!(rsp.iter().take(3).collect::<Vec<bool>>(), vec![true, false, true]);
+    }
 }
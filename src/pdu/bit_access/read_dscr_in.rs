@@ -1,12 +1,17 @@
-use std::convert::TryInto;
-use std::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+use core::ops::Index;
+use alloc::vec::Vec;
 
 use crate::Error;
-use crate::pdu::{MAX_SIZE, Function, Request as ReqT, Response as RspT, FunctionCode};
-use super::DSCR_PER_BYTE;
+use crate::fmt::HexDump;
+use crate::pdu::{is_range_valid, check_size, DecodeMode, MAX_SIZE, Function, Request as ReqT, Response as RspT, FunctionCode};
+use super::bits::Bits;
+use super::check_bits;
 
 /// Read Discrete Inputs function request
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Request {
     address: u16,
     quantity: u16,
@@ -21,6 +26,7 @@ impl Request {
     /// let request = modbus::ReadDscrInRequest::new(0x000a, 0x0004);
     /// ```
     pub fn new(address: u16, quantity: u16) -> Self {
+        debug_assert!(is_range_valid(address, quantity));
         Request{address, quantity}
     }
 
@@ -54,20 +60,19 @@ impl Request {
 }
 
 impl Function for Request {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
         match self.quantity {
-            1..=2000 => {
-                let mut result = Vec::new();
-                result.push(FunctionCode::ReadDscrIn as u8);
-                result.append(&mut self.address.to_be_bytes().to_vec());
-                result.append(&mut self.quantity.to_be_bytes().to_vec());
-                Ok(result)
+            1..=2000 if is_range_valid(self.address, self.quantity) => {
+                buf.push(FunctionCode::ReadDscrIn as u8);
+                buf.extend_from_slice(&self.address.to_be_bytes());
+                buf.extend_from_slice(&self.quantity.to_be_bytes());
+                Ok(())
             }
             _ => Err(Error::InvalidValue)
         }
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> {
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
         if data.len() != 5 {
             return Err(Error::InvalidDataLength);
         }
@@ -75,19 +80,29 @@ impl Function for Request {
             return Err(Error::InvalidData);
         }
 
-        Ok(Self {address: u16::from_be_bytes(data[1..=2].try_into().unwrap()),
-                 quantity: u16::from_be_bytes(data[3..=4].try_into().unwrap())})
+        let address = u16::from_be_bytes(data[1..=2].try_into().unwrap());
+        let quantity = u16::from_be_bytes(data[3..=4].try_into().unwrap());
+        if mode == DecodeMode::Strict && !(1..=2000).contains(&quantity) {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(Self {address, quantity})
     }
 }
 
 impl ReqT for Request {
     type Rsp = Response;
+
+    fn check_response(&self, rsp: &Self::Rsp, mode: DecodeMode) -> Result<(), Error> {
+        check_bits(&rsp.inputs, self.quantity, mode)
+    }
 }
 
 /// Read Discrete Inputs function response
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Response {
-    inputs: Vec<bool>,
+    inputs: Bits,
 }
 
 impl Response {
@@ -98,83 +113,126 @@ impl Response {
     /// let response = modbus::ReadDscrInResponse::new(&[false, true, false]);
     /// ```
     pub fn new(inputs: &[bool]) -> Self {
-        Self {inputs: inputs.to_vec()}
+        Self {inputs: inputs.into()}
+    }
+
+    /// Create a new Read Discrete Inputs response from packed inputs
+    /// 
+    /// # Examples
+    /// ```
+    /// let response = modbus::ReadDscrInResponse::from_bits(modbus::Bits::from_bytes(&[0x02]));
+    /// assert!(response.get_inputs()[1]);
+    /// ```
+    pub fn from_bits(inputs: Bits) -> Self {
+        Self {inputs}
     }
 
-    /// Get list of inputs from the Read Discrete Inputs response
+    /// Get inputs from the Read Discrete Inputs response
     /// 
     /// # Examples
     /// ```
     /// let inputs = vec![true, true, false, false];
     /// let response = modbus::ReadDscrInResponse::new(&inputs);
-    /// assert_eq!(response.get_inputs(), &inputs);
+    /// assert_eq!(response.get_inputs().to_vec(), inputs);
     /// ```
-    pub fn get_inputs(&self) -> &Vec<bool> {
+    pub fn get_inputs(&self) -> &Bits {
         &self.inputs
     }
+
+    /// Get number of inputs in the response
+    ///
+    /// A decoded response contains all bits of its bytes, so the number is rounded up to a multiple of 8.
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Check if the response contains no inputs
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Get value of the input at given position or `None` if the position is out of the response
+    ///
+    /// # Examples
+    /// ```
+    /// let response = modbus::ReadDscrInResponse::new(&[false, true]);
+    /// assert_eq!(response.get(1), Some(true));
+    /// assert_eq!(response.get(2), None);
+    /// ```
+    pub fn get(&self, index: usize) -> Option<bool> {
+        self.inputs.get(index)
+    }
+
+    /// Iterate over the inputs of the response
+    ///
+    /// # Examples
+    /// ```
+    /// let response = modbus::ReadDscrInResponse::new(&[true, false, true]);
+    /// assert_eq!(response.iter().filter(|value| *value).count(), 2);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        self.inputs.iter()
+    }
+}
+
+impl Index<usize> for Response {
+    type Output = bool;
+
+    fn index(&self, index: usize) -> &bool {
+        &self.inputs[index]
+    }
 }
 
 impl Function for Response {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
-        let in_cnt = self.inputs.len();
-        let byte_count = in_cnt / DSCR_PER_BYTE + if in_cnt % DSCR_PER_BYTE != 0 { 1 } else { 0 };
-        const MAX_BYTE_COUNT: usize = MAX_SIZE - 3;
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let bytes = self.inputs.as_bytes();
+        const MAX_BYTE_COUNT: usize = MAX_SIZE - 2;
         
-        match byte_count {
+        match bytes.len() {
             1..=MAX_BYTE_COUNT => {
-                let mut result = Vec::new();
-                result.push(FunctionCode::ReadDscrIn as u8);
-                result.push(byte_count as u8);
-
-                for byte_num in 0..byte_count {
-                    let mut byte: u8 = 0;
-                    for bit_num in 0..DSCR_PER_BYTE {
-                        let i = byte_num * DSCR_PER_BYTE + bit_num;
-                        if i >= self.inputs.len() {
-                            break;
-                        }
-
-                        if self.inputs[i] {
-                            byte |= 1 << bit_num;
-                        }
-                    }
-
-                    result.push(byte);
-                }
-
-                Ok(result)
+                buf.push(FunctionCode::ReadDscrIn as u8);
+                buf.push(bytes.len() as u8);
+                buf.extend_from_slice(bytes);
+
+                Ok(())
             }
             _ => Err(Error::InvalidValue)
         }
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> {
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
         if data.len() < 2 {
             return Err(Error::InvalidDataLength);
         }
         if data[0] != FunctionCode::ReadDscrIn as u8 {
             return Err(Error::InvalidData);
         }
+        check_size(data.len())?;
 
         let byte_count = data[1] as usize;
-        if data.len() != byte_count + 2 {
+        if mode == DecodeMode::Strict && (byte_count == 0 || data.len() != byte_count + 2) {
             return Err(Error::InvalidDataLength);
         }
 
-        let mut result = Self{inputs: Vec::with_capacity(byte_count * DSCR_PER_BYTE)};
-        for byte_num in 2..2+byte_count {
-            for bit_num in 0..DSCR_PER_BYTE {
-                result.inputs.push(if data[byte_num] & (1 << bit_num) != 0 { true } else { false });
-            }
-        }
-
-        Ok(result)
+        Ok(Self {inputs: Bits::from_bytes(&data[2..])})
     }
 }
 
 impl RspT for Response {
     fn get_exc_function_code() -> u8 {
-        FunctionCode::ExcReadDscrIn.try_into().unwrap()
+        FunctionCode::ExcReadDscrIn.into()
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read Discrete Inputs request: address 0x{:04x}, quantity {}", self.address, self.quantity)
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read Discrete Inputs response: {} inputs, data: {}", self.inputs.len(), HexDump::new(self.inputs.as_bytes()))
     }
 }
 
@@ -202,9 +260,9 @@ mod tests {
 
     #[test]
     fn encode_rsp() {
-        let rsp = Response{inputs: vec![false, false, true, true, false, true, false, true,
-                                        true, true, false, true, true, false, true, true,
-                                        true, false, true, false, true, true]};
+        let rsp = Response::new(&[false, false, true, true, false, true, false, true,
+                                  true, true, false, true, true, false, true, true,
+                                  true, false, true, false, true, true]);
         let pdu = rsp.encode().unwrap();
         let expected_pdu = vec![0x02, 0x03, 0xAC, 0xDB, 0x35];
 
@@ -215,10 +273,20 @@ mod tests {
     fn decode_rsp() {
         let pdu = vec![0x02, 0x03, 0xAC, 0xDB, 0x35];
         let rsp = Response::decode(&pdu).unwrap();
-        let expected_rsp = Response{inputs: vec![false, false, true, true, false, true, false, true,
-                                                 true, true, false, true, true, false, true, true,
-                                                 true, false, true, false, true, true, false, false]};
+        let expected_rsp = Response::new(&[false, false, true, true, false, true, false, true,
+                                           true, true, false, true, true, false, true, true,
+                                           true, false, true, false, true, true, false, false]);
 
         assert_eq!(rsp, expected_rsp);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rsp_access() {
+        let rsp = Response::new(&[false, true, true]);
+
+        assert_eq!(rsp.len(), 3);
+        assert!(!rsp[0] && rsp[1] && rsp[2]);
+        assert_eq!(rsp.get(3), None);
+        assert_eq!(rsp.iter().collect::<Vec<bool>>(), vec![false, true, true]);
+    }
+}
@@ -1,8 +1,12 @@
 use crate::Error;
-use crate::pdu::{Function, FunctionCode, Request, Response, Setter};
-use std::convert::{Infallible, TryFrom, TryInto};
-
-#[derive(Clone, Copy, Debug, FromPrimitive, PartialEq)]
+use crate::pdu::{DecodeMode, Function, FunctionCode, Request, Response, Setter};
+use core::convert::{TryFrom, TryInto};
+use core::fmt;
+use alloc::vec::Vec;
+
+#[derive(Clone, Copy, Debug, Eq, FromPrimitive, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "bool", into = "bool"))]
 enum Value {
     Off = 0x0000,
     On  = 0xFF00,
@@ -33,30 +37,27 @@ impl TryFrom<&[u8]> for Value {
     }
 }
 
-impl TryFrom<bool> for Value {
-    type Error = Infallible;
-
-    fn try_from(value: bool) -> Result<Self, Self::Error> {
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
         match value {
-            true => Ok(Value::On),
-            false => Ok(Value::Off),
+            true => Value::On,
+            false => Value::Off,
         }
     }
 }
 
-impl TryFrom<Value> for bool {
-    type Error = Infallible;
-
-    fn try_from(value: Value) -> Result<Self, Self::Error> {
+impl From<Value> for bool {
+    fn from(value: Value) -> Self {
         match value {
-            Value::On => Ok(true),
-            Value::Off => Ok(false),
+            Value::On => true,
+            Value::Off => false,
         }
     }
 }
 
 /// Write Single Coil request or response function
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message {
     address: u16,
     value: Value,
@@ -71,7 +72,7 @@ impl Message {
     /// let rsp = modbus::WriteSingleCoilResponse::new(0x0123, false);
     /// ```
     pub fn new(address: u16, value: bool) -> Self {
-        Message{address, value: value.try_into().unwrap()}
+        Message{address, value: value.into()}
     }
 
     /// Get address of the coil from the Write Single Coil function
@@ -95,21 +96,20 @@ impl Message {
     /// assert_eq!(req.get_value(), value);
     /// ```
     pub fn get_value(&self) -> bool {
-        self.value.try_into().unwrap()
+        self.value.into()
     }
 }
 
 impl Function for Message {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        result.push(FunctionCode::WriteSingleCoil as u8);
-        result.append(&mut self.address.to_be_bytes().to_vec());
-        result.append(&mut (self.value as u16).to_be_bytes().to_vec());
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        buf.push(FunctionCode::WriteSingleCoil as u8);
+        buf.extend_from_slice(&self.address.to_be_bytes());
+        buf.extend_from_slice(&(self.value as u16).to_be_bytes());
 
-        Ok(result)
+        Ok(())
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> {
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
         if data.len() != 5 {
             return Err(Error::InvalidDataLength);
         }
@@ -117,8 +117,12 @@ impl Function for Message {
             return Err(Error::InvalidData);
         }
         
-        Ok(Self{address: u16::from_be_bytes(data[1..=2].try_into().unwrap()),
-                value: data[3..=4].try_into()?})
+        let value = match mode {
+            DecodeMode::Strict => data[3..=4].try_into()?,
+            DecodeMode::Lenient => (data[3..=4] != [0x00, 0x00]).into(),
+        };
+
+        Ok(Self{address: u16::from_be_bytes(data[1..=2].try_into().unwrap()), value})
     }
 }
 
@@ -128,7 +132,7 @@ impl Request for Message {
 
 impl Response for Message {
     fn get_exc_function_code() -> u8 {
-        FunctionCode::ExcWriteSingleCoil.try_into().unwrap()
+        FunctionCode::ExcWriteSingleCoil.into()
     }
 }
 
@@ -138,6 +142,16 @@ impl Setter for Message {
     }
 }
 
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self.value {
+            Value::On => "ON",
+            Value::Off => "OFF",
+        };
+        write!(f, "Write Single Coil: address 0x{:04x}, value {}", self.address, value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,7 +189,7 @@ mod tests {
         let err = Message::decode(&pdu).err().unwrap();
         match err {
             Error::InvalidData => {}
-            _ => panic!(format!("Expected InvalidData, but got {:?}", err)),
+            _ => panic!("Expected InvalidData, but got {:?}", err),
         }
     }
 
@@ -188,4 +202,20 @@ mod tests {
         assert_eq!(rsp, expected_rsp);
     }
 
+    #[test]
+    fn test_display() {
+        assert_eq!(Message::new(0x0123, true).to_string(), "Write Single Coil: address 0x0123, value ON");
+    }
+
+    #[test]
+    fn test_decode_lenient_request() {
+        let pdu = vec![0x05, 0x01, 0x23, 0x00, 0x01];
+        let req = Message::decode_with_mode(&pdu, DecodeMode::Lenient).unwrap();
+        assert!(req.get_value());
+
+        let pdu = vec![0x05, 0x01, 0x23, 0x00, 0x00];
+        let req = Message::decode_with_mode(&pdu, DecodeMode::Lenient).unwrap();
+        assert!(!req.get_value());
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,12 @@
+pub mod read_file_record;
+pub mod write_file_record;
+
+/// Reference type of file records required by the specification
+const REFERENCE_TYPE: u8 = 6;
+/// Number of records in a file, each record keeping a single register
+pub(crate) const FILE_RECORDS: u16 = 10000;
+
+/// Check if given quantity of records starting at given record number fits in a file
+fn is_record_range_valid(record_number: u16, record_length: u16) -> bool {
+    record_number as u32 + record_length as u32 <= FILE_RECORDS as u32
+}
@@ -0,0 +1,225 @@
+use crate::Error;
+use crate::pdu::{check_size, DecodeMode, Function, FunctionCode, Request as ReqT, Response as RspT};
+use crate::pdu::hex_access::Registers;
+use crate::fmt::HexDump;
+use super::{is_record_range_valid, REFERENCE_TYPE};
+use core::convert::TryInto;
+use core::fmt;
+use alloc::vec::Vec;
+
+const MIN_LENGTH: u16 = 1;
+pub(crate) const MAX_LENGTH: u16 = 124;
+
+/// Read File Record function request
+///
+/// The request reads a single group of consecutive records of a file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Request {
+    file_number: u16,
+    record_number: u16,
+    record_length: u16,
+}
+
+impl Request {
+    /// Create a new Read File Record request
+    ///
+    /// # Examples
+    /// ```
+    /// let req = modbus::ReadFileRecordRequest::new(4, 1, 2);
+    /// ```
+    pub fn new(file_number: u16, record_number: u16, record_length: u16) -> Self {
+        debug_assert!(is_record_range_valid(record_number, record_length));
+        Self {file_number, record_number, record_length}
+    }
+
+    /// Get number of the file from the request
+    pub fn get_file_number(&self) -> u16 {
+        self.file_number
+    }
+
+    /// Get number of the first record from the request
+    pub fn get_record_number(&self) -> u16 {
+        self.record_number
+    }
+
+    /// Get quantity of the records in the request
+    pub fn get_record_length(&self) -> u16 {
+        self.record_length
+    }
+}
+
+impl Function for Request {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        match self.record_length {
+            MIN_LENGTH..=MAX_LENGTH if is_record_range_valid(self.record_number, self.record_length) => {
+                buf.push(FunctionCode::ReadFileRecord as u8);
+                buf.push(7);
+                buf.push(REFERENCE_TYPE);
+                buf.extend_from_slice(&self.file_number.to_be_bytes());
+                buf.extend_from_slice(&self.record_number.to_be_bytes());
+                buf.extend_from_slice(&self.record_length.to_be_bytes());
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidValue),
+        }
+    }
+
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() != 9 {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FunctionCode::ReadFileRecord as u8 || data[2] != REFERENCE_TYPE {
+            return Err(Error::InvalidData);
+        }
+        if data[1] != 7 {
+            return Err(Error::InvalidDataLength);
+        }
+
+        let file_number = u16::from_be_bytes(data[3..=4].try_into().unwrap());
+        let record_number = u16::from_be_bytes(data[5..=6].try_into().unwrap());
+        let record_length = u16::from_be_bytes(data[7..=8].try_into().unwrap());
+        if mode == DecodeMode::Strict &&
+           (!(MIN_LENGTH..=MAX_LENGTH).contains(&record_length) || !is_record_range_valid(record_number, record_length)) {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(Self {file_number, record_number, record_length})
+    }
+}
+
+impl ReqT for Request {
+    type Rsp = Response;
+
+    fn check_response(&self, rsp: &Self::Rsp, mode: DecodeMode) -> Result<(), Error> {
+        let length = self.record_length as usize;
+        match mode {
+            DecodeMode::Strict if rsp.registers.get().len() != length => Err(Error::InvalidResponse),
+            DecodeMode::Lenient if rsp.registers.get().len() < length => Err(Error::InvalidResponse),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Read File Record function response
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response {
+    registers: Registers,
+}
+
+impl Response {
+    /// Create a new Read File Record response
+    ///
+    /// # Examples
+    /// ```
+    /// let rsp = modbus::ReadFileRecordResponse::new(&[0x0df4, 0x03e8]);
+    /// ```
+    pub fn new(registers: &[u16]) -> Self {
+        Self {registers: Registers::new(registers)}
+    }
+
+    /// Get values of the records from the response
+    pub fn get_registers(&self) -> &[u16] {
+        self.registers.get()
+    }
+
+    /// Get values of the records from the response as bytes, in the order they are sent
+    pub fn get_bytes(&self) -> &[u8] {
+        self.registers.get_bytes()
+    }
+}
+
+impl Function for Response {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let bytes = self.registers.get_bytes();
+        if bytes.is_empty() || bytes.len() > MAX_LENGTH as usize * 2 {
+            return Err(Error::InvalidValue);
+        }
+
+        buf.push(FunctionCode::ReadFileRecord as u8);
+        buf.push((bytes.len() + 2) as u8);
+        buf.push((bytes.len() + 1) as u8);
+        buf.push(REFERENCE_TYPE);
+        buf.extend_from_slice(bytes);
+
+        Ok(())
+    }
+
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() < 4 {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FunctionCode::ReadFileRecord as u8 || data[3] != REFERENCE_TYPE {
+            return Err(Error::InvalidData);
+        }
+        check_size(data.len())?;
+
+        let data_len = data[1] as usize;
+        let file_rsp_len = data[2] as usize;
+        if mode == DecodeMode::Strict {
+            if data_len != data.len() - 2 || file_rsp_len != data_len - 1 {
+                return Err(Error::InvalidDataLength);
+            }
+            if file_rsp_len < 3 || file_rsp_len.is_multiple_of(2) {
+                return Err(Error::InvalidData);
+            }
+        }
+
+        let end = data.len().min(3 + file_rsp_len);
+        Ok(Self {registers: Registers::from_bytes(&data[4..end.max(4)])})
+    }
+}
+
+impl RspT for Response {
+    fn get_exc_function_code() -> u8 {
+        FunctionCode::ExcReadFileRecord.into()
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read File Record request: file {}, record {}, length {}",
+               self.file_number, self.record_number, self.record_length)
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read File Record response: {} records, data: {}", self.registers.get().len(), HexDump::new(self.registers.get_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_request() {
+        let pdu = Request::new(0x0004, 0x0001, 0x0002).encode().unwrap();
+        assert_eq!(pdu, vec![0x14, 0x07, 0x06, 0x00, 0x04, 0x00, 0x01, 0x00, 0x02]);
+
+        assert!(Request::new(0x0004, 9999, 1).encode().is_ok());
+        assert!(Request::new(0x0004, 0, MAX_LENGTH + 1).encode().is_err());
+    }
+
+    #[test]
+    fn test_decode_response() {
+        let pdu = vec![0x14, 0x06, 0x05, 0x06, 0x0d, 0xfe, 0x00, 0x20];
+        let rsp = Response::decode(&pdu).unwrap();
+        assert_eq!(rsp.get_registers(), &[0x0dfe, 0x0020]);
+        assert_eq!(rsp.encode().unwrap(), pdu);
+
+        Request::new(0x0004, 0x0001, 0x0002).check_response(&rsp, DecodeMode::Strict).unwrap();
+    }
+
+    #[test]
+    fn test_decode_invalid_response() {
+        let pdu = vec![0x14, 0x06, 0x05, 0x07, 0x0d, 0xfe, 0x00, 0x20];
+        assert!(matches!(Response::decode(&pdu), Err(Error::InvalidData)));
+
+        let pdu = vec![0x14, 0x06, 0x05, 0x06, 0x0d, 0xfe, 0x00];
+        assert!(matches!(Response::decode(&pdu), Err(Error::InvalidDataLength)));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::Error;
+use crate::pdu::{check_size, DecodeMode, Function, FunctionCode, Request, Response, Setter};
+use crate::pdu::hex_access::Registers;
+use crate::fmt::HexDump;
+use super::{is_record_range_valid, REFERENCE_TYPE};
+use core::convert::TryInto;
+use core::fmt;
+use alloc::vec::Vec;
+
+const MIN_LENGTH: usize = 1;
+pub(crate) const MAX_LENGTH: usize = 122;
+
+/// Write File Record request or response function
+///
+/// The function writes a single group of consecutive records of a file.
+/// The response echoes the request.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Message {
+    file_number: u16,
+    record_number: u16,
+    registers: Registers,
+}
+
+impl Message {
+    /// Create a new Write File Record function
+    ///
+    /// # Examples
+    /// ```
+    /// let req = modbus::WriteFileRecordRequest::new(4, 7, &[0x06af, 0x04be, 0x100d]);
+    /// ```
+    pub fn new(file_number: u16, record_number: u16, registers: &[u16]) -> Self {
+        assert!(registers.len() >= MIN_LENGTH);
+        assert!(registers.len() <= MAX_LENGTH);
+        assert!(is_record_range_valid(record_number, registers.len() as u16));
+
+        Self {file_number, record_number, registers: Registers::new(registers)}
+    }
+
+    /// Get number of the file from the Write File Record function
+    pub fn get_file_number(&self) -> u16 {
+        self.file_number
+    }
+
+    /// Get number of the first record from the Write File Record function
+    pub fn get_record_number(&self) -> u16 {
+        self.record_number
+    }
+
+    /// Get values of the records from the Write File Record function
+    pub fn get_registers(&self) -> &[u16] {
+        self.registers.get()
+    }
+
+    /// Get values of the records from the Write File Record function as bytes, in the order they are sent
+    pub fn get_bytes(&self) -> &[u8] {
+        self.registers.get_bytes()
+    }
+}
+
+impl Function for Message {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let bytes = self.registers.get_bytes();
+        let length = self.registers.get().len();
+        match length {
+            MIN_LENGTH..=MAX_LENGTH if is_record_range_valid(self.record_number, length as u16) => {
+                buf.push(FunctionCode::WriteFileRecord as u8);
+                buf.push((7 + bytes.len()) as u8);
+                buf.push(REFERENCE_TYPE);
+                buf.extend_from_slice(&self.file_number.to_be_bytes());
+                buf.extend_from_slice(&self.record_number.to_be_bytes());
+                buf.extend_from_slice(&(length as u16).to_be_bytes());
+                buf.extend_from_slice(bytes);
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidValue),
+        }
+    }
+
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() < 9 {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FunctionCode::WriteFileRecord as u8 || data[2] != REFERENCE_TYPE {
+            return Err(Error::InvalidData);
+        }
+        check_size(data.len())?;
+
+        let file_number = u16::from_be_bytes(data[3..=4].try_into().unwrap());
+        let record_number = u16::from_be_bytes(data[5..=6].try_into().unwrap());
+        let length = u16::from_be_bytes(data[7..=8].try_into().unwrap()) as usize;
+        let end = 9 + 2 * length;
+
+        if mode == DecodeMode::Strict {
+            if data[1] as usize != data.len() - 2 || data.len() != end {
+                return Err(Error::InvalidDataLength);
+            }
+            if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) || !is_record_range_valid(record_number, length as u16) {
+                return Err(Error::InvalidData);
+            }
+        } else if data.len() < end {
+            return Err(Error::InvalidDataLength);
+        }
+
+        Ok(Self {file_number, record_number, registers: Registers::from_bytes(&data[9..end])})
+    }
+}
+
+impl Request for Message {
+    type Rsp = Message;
+}
+
+impl Response for Message {
+    fn get_exc_function_code() -> u8 {
+        FunctionCode::ExcWriteFileRecord.into()
+    }
+}
+
+impl Setter for Message {
+    fn create_expected_response(&self) -> Self::Rsp {
+        self.clone()
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Write File Record: file {}, record {}, length {}, data: {}",
+               self.file_number, self.record_number, self.registers.get().len(), HexDump::new(self.registers.get_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_request() {
+        // Example from the Modbus application protocol specification
+        let pdu = Message::new(0x0004, 0x0007, &[0x06af, 0x04be, 0x100d]).encode().unwrap();
+        let expected_pdu = vec![0x15, 0x0d, 0x06, 0x00, 0x04, 0x00, 0x07, 0x00, 0x03, 0x06, 0xaf, 0x04, 0xbe, 0x10, 0x0d];
+
+        assert_eq!(pdu, expected_pdu);
+    }
+
+    #[test]
+    fn test_decode_response() {
+        let pdu = vec![0x15, 0x0d, 0x06, 0x00, 0x04, 0x00, 0x07, 0x00, 0x03, 0x06, 0xaf, 0x04, 0xbe, 0x10, 0x0d];
+        let rsp = Message::decode(&pdu).unwrap();
+
+        assert_eq!(rsp, Message::new(0x0004, 0x0007, &[0x06af, 0x04be, 0x100d]));
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let pdu = vec![0x15, 0x0b, 0x06, 0x00, 0x04, 0x00, 0x07, 0x00, 0x03, 0x06, 0xaf, 0x04, 0xbe];
+        for mode in [DecodeMode::Strict, DecodeMode::Lenient] {
+            assert!(matches!(Message::decode_with_mode(&pdu, mode), Err(Error::InvalidDataLength)));
+        }
+    }
+}
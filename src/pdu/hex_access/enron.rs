@@ -0,0 +1,459 @@
+//! Enron (Daniel) Modbus variant with 32-bit registers
+//!
+//! Flow computers implementing this variant keep 32-bit long integers in registers
+//! 5001-5999 and 32-bit floats in registers 7001-7999. Requests accessing these registers
+//! reuse Read Holding Registers and Write Multiple Registers function codes, but their
+//! quantities count 32-bit registers and each value takes four bytes of data.
+//! Register numbers are sent as addresses without any offset.
+//!
+//! # Examples
+//! ```
+//! use modbus::{Function, Response};
+//! use modbus::enron::{ReadRequest, ReadResponse};
+//!
+//! let req = ReadRequest::new(7001, 2);
+//! assert_eq!(req.encode().unwrap(), vec![0x03, 0x1b, 0x59, 0x00, 0x02]);
+//!
+//! let rsp = ReadResponse::decode_response(&[0x03, 0x08, 0x3f, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a]).unwrap();
+//! assert_eq!(rsp.get_f32(0), Some(1.5));
+//! assert_eq!(rsp.get_values()[1], 42);
+//! ```
+
+use crate::Error;
+use crate::pdu::{is_range_valid, check_size, DecodeMode, Function, FunctionCode, Request as ReqT, Response as RspT, Setter};
+use crate::fmt::HexDump;
+use core::convert::TryInto;
+use core::fmt;
+use core::ops::RangeInclusive;
+use alloc::vec::Vec;
+
+/// Registers keeping 32-bit long integers
+pub const LONG_REGISTERS: RangeInclusive<u16> = 5001..=5999;
+/// Registers keeping 32-bit floats
+pub const FLOAT_REGISTERS: RangeInclusive<u16> = 7001..=7999;
+
+const MIN_QUANTITY: u16 = 1;
+const MAX_READ_QUANTITY: u16 = 62;
+const MAX_WRITE_QUANTITY: u16 = 61;
+const VALUE_SIZE: usize = 4;
+
+/// Check if given register is 32-bit in the Enron variant
+///
+/// # Examples
+/// ```
+/// assert!(modbus::enron::is_32_bit(5001));
+/// assert!(modbus::enron::is_32_bit(7001));
+/// assert!(!modbus::enron::is_32_bit(3001));
+/// ```
+pub fn is_32_bit(address: u16) -> bool {
+    LONG_REGISTERS.contains(&address) || FLOAT_REGISTERS.contains(&address)
+}
+
+fn decode_values(data: &[u8]) -> Vec<u32> {
+    data.chunks_exact(VALUE_SIZE)
+        .map(|value| u32::from_be_bytes(value.try_into().unwrap()))
+        .collect()
+}
+
+/// Read Holding Registers request for 32-bit registers
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadRequest {
+    address: u16,
+    quantity: u16,
+}
+
+impl ReadRequest {
+    /// Create a new request reading given quantity of 32-bit registers
+    ///
+    /// # Examples
+    /// ```
+    /// let req = modbus::enron::ReadRequest::new(5001, 4);
+    /// ```
+    pub fn new(address: u16, quantity: u16) -> Self {
+        debug_assert!(is_range_valid(address, quantity));
+        Self {address, quantity}
+    }
+
+    /// Get address of the first register from the request
+    pub fn get_address(&self) -> u16 {
+        self.address
+    }
+
+    /// Get quantity of the 32-bit registers in the request
+    pub fn get_quantity(&self) -> u16 {
+        self.quantity
+    }
+}
+
+impl Function for ReadRequest {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        match self.quantity {
+            MIN_QUANTITY..=MAX_READ_QUANTITY if is_range_valid(self.address, self.quantity) => {
+                buf.push(FunctionCode::ReadHldReg as u8);
+                buf.extend_from_slice(&self.address.to_be_bytes());
+                buf.extend_from_slice(&self.quantity.to_be_bytes());
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidValue),
+        }
+    }
+
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() != 5 {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FunctionCode::ReadHldReg as u8 {
+            return Err(Error::InvalidData);
+        }
+
+        let address = u16::from_be_bytes(data[1..=2].try_into().unwrap());
+        let quantity = u16::from_be_bytes(data[3..=4].try_into().unwrap());
+        if mode == DecodeMode::Strict && !(MIN_QUANTITY..=MAX_READ_QUANTITY).contains(&quantity) {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(Self {address, quantity})
+    }
+}
+
+impl ReqT for ReadRequest {
+    type Rsp = ReadResponse;
+
+    fn check_response(&self, rsp: &Self::Rsp, mode: DecodeMode) -> Result<(), Error> {
+        let quantity = self.quantity as usize;
+        match mode {
+            DecodeMode::Strict if rsp.values.len() != quantity => Err(Error::InvalidResponse),
+            DecodeMode::Lenient if rsp.values.len() < quantity => Err(Error::InvalidResponse),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Read Holding Registers response carrying 32-bit registers
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadResponse {
+    values: Vec<u32>,
+}
+
+impl ReadResponse {
+    /// Create a new response carrying given values of 32-bit registers
+    ///
+    /// # Examples
+    /// ```
+    /// let rsp = modbus::enron::ReadResponse::new(&[0x0001_0000, 1.5f32.to_bits()]);
+    /// ```
+    pub fn new(values: &[u32]) -> Self {
+        Self {values: values.to_vec()}
+    }
+
+    /// Get values of the 32-bit registers from the response
+    pub fn get_values(&self) -> &[u32] {
+        &self.values
+    }
+
+    /// Get value of the 32-bit register at given index of the response interpreted as a float
+    pub fn get_f32(&self, index: usize) -> Option<f32> {
+        self.values.get(index).map(|value| f32::from_bits(*value))
+    }
+}
+
+impl Function for ReadResponse {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        if self.values.is_empty() || self.values.len() > MAX_READ_QUANTITY as usize {
+            return Err(Error::InvalidValue);
+        }
+
+        buf.push(FunctionCode::ReadHldReg as u8);
+        buf.push((self.values.len() * VALUE_SIZE) as u8);
+        for value in &self.values {
+            buf.extend_from_slice(&value.to_be_bytes());
+        }
+
+        Ok(())
+    }
+
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() < 2 {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FunctionCode::ReadHldReg as u8 {
+            return Err(Error::InvalidData);
+        }
+        check_size(data.len())?;
+
+        let num_bytes = data[1] as usize;
+        if mode == DecodeMode::Strict {
+            if num_bytes == 0 || !num_bytes.is_multiple_of(VALUE_SIZE) {
+                return Err(Error::InvalidData);
+            }
+            if num_bytes != data.len() - 2 {
+                return Err(Error::InvalidDataLength);
+            }
+        }
+
+        Ok(Self {values: decode_values(&data[2..])})
+    }
+}
+
+impl RspT for ReadResponse {
+    fn get_exc_function_code() -> u8 {
+        FunctionCode::ExcReadHldReg.into()
+    }
+}
+
+/// Write Multiple Registers request for 32-bit registers
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WriteRequest {
+    address: u16,
+    values: Vec<u32>,
+}
+
+impl WriteRequest {
+    /// Create a new request writing given values to consecutive 32-bit registers
+    ///
+    /// # Examples
+    /// ```
+    /// let req = modbus::enron::WriteRequest::new(7001, &[2.5f32.to_bits()]);
+    /// ```
+    pub fn new(address: u16, values: &[u32]) -> Self {
+        assert!(values.len() >= MIN_QUANTITY as usize);
+        assert!(values.len() <= MAX_WRITE_QUANTITY as usize);
+        assert!(is_range_valid(address, values.len() as u16));
+
+        Self {address, values: values.to_vec()}
+    }
+
+    /// Get address of the first register from the request
+    pub fn get_address(&self) -> u16 {
+        self.address
+    }
+
+    /// Get values of the 32-bit registers from the request
+    pub fn get_values(&self) -> &[u32] {
+        &self.values
+    }
+}
+
+impl Function for WriteRequest {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let quantity = self.values.len() as u16;
+        match quantity {
+            MIN_QUANTITY..=MAX_WRITE_QUANTITY if is_range_valid(self.address, quantity) => {
+                buf.push(FunctionCode::WriteMultiReg as u8);
+                buf.extend_from_slice(&self.address.to_be_bytes());
+                buf.extend_from_slice(&quantity.to_be_bytes());
+                buf.push((self.values.len() * VALUE_SIZE) as u8);
+
+                for value in &self.values {
+                    buf.extend_from_slice(&value.to_be_bytes());
+                }
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidValue),
+        }
+    }
+
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() < 6 {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FunctionCode::WriteMultiReg as u8 {
+            return Err(Error::InvalidData);
+        }
+        check_size(data.len())?;
+
+        let address = u16::from_be_bytes(data[1..=2].try_into().unwrap());
+        let quantity = u16::from_be_bytes(data[3..=4].try_into().unwrap());
+        let num_bytes = data[5] as usize;
+        let data_len = quantity as usize * VALUE_SIZE;
+
+        if mode == DecodeMode::Strict {
+            if num_bytes != data_len || data.len() != 6 + num_bytes {
+                return Err(Error::InvalidDataLength);
+            }
+            if !(MIN_QUANTITY..=MAX_WRITE_QUANTITY).contains(&quantity) {
+                return Err(Error::InvalidData);
+            }
+        } else if data.len() < 6 + data_len {
+            return Err(Error::InvalidDataLength);
+        }
+
+        Ok(Self {address, values: decode_values(&data[6..6 + data_len])})
+    }
+}
+
+impl ReqT for WriteRequest {
+    type Rsp = WriteResponse;
+}
+
+impl Setter for WriteRequest {
+    fn create_expected_response(&self) -> Self::Rsp {
+        WriteResponse::new(self.address, self.values.len() as u16)
+    }
+}
+
+/// Write Multiple Registers response for 32-bit registers
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WriteResponse {
+    address: u16,
+    quantity: u16,
+}
+
+impl WriteResponse {
+    /// Create a new response confirming writing given quantity of 32-bit registers
+    pub fn new(address: u16, quantity: u16) -> Self {
+        Self {address, quantity}
+    }
+
+    /// Get address of the first register from the response
+    pub fn get_address(&self) -> u16 {
+        self.address
+    }
+
+    /// Get quantity of the 32-bit registers from the response
+    pub fn get_quantity(&self) -> u16 {
+        self.quantity
+    }
+}
+
+impl Function for WriteResponse {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        match self.quantity {
+            MIN_QUANTITY..=MAX_WRITE_QUANTITY => {
+                buf.push(FunctionCode::WriteMultiReg as u8);
+                buf.extend_from_slice(&self.address.to_be_bytes());
+                buf.extend_from_slice(&self.quantity.to_be_bytes());
+
+                Ok(())
+            }
+            _ => Err(Error::InvalidValue),
+        }
+    }
+
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() != 5 {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FunctionCode::WriteMultiReg as u8 {
+            return Err(Error::InvalidData);
+        }
+
+        let address = u16::from_be_bytes(data[1..=2].try_into().unwrap());
+        let quantity = u16::from_be_bytes(data[3..=4].try_into().unwrap());
+        if mode == DecodeMode::Strict && !(MIN_QUANTITY..=MAX_WRITE_QUANTITY).contains(&quantity) {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(Self {address, quantity})
+    }
+}
+
+impl RspT for WriteResponse {
+    fn get_exc_function_code() -> u8 {
+        FunctionCode::ExcWriteMultiReg.into()
+    }
+}
+
+impl fmt::Display for ReadRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Enron Read Holding Registers request: address {}, quantity {}", self.address, self.quantity)
+    }
+}
+
+impl fmt::Display for ReadResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Enron Read Holding Registers response: {} registers, data:", self.values.len())?;
+        for value in &self.values {
+            write!(f, " {}", HexDump::new(&value.to_be_bytes()))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for WriteRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Enron Write Multiple Registers request: address {}, quantity {}, data:", self.address, self.values.len())?;
+        for value in &self.values {
+            write!(f, " {}", HexDump::new(&value.to_be_bytes()))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for WriteResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Enron Write Multiple Registers response: address {}, quantity {}", self.address, self.quantity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_32_bit() {
+        assert!(!is_32_bit(5000));
+        assert!(is_32_bit(5001));
+        assert!(is_32_bit(5999));
+        assert!(!is_32_bit(7000));
+        assert!(is_32_bit(7001));
+        assert!(is_32_bit(7999));
+        assert!(!is_32_bit(8000));
+    }
+
+    #[test]
+    fn test_encode_read_request() {
+        let pdu = ReadRequest::new(5001, 3).encode().unwrap();
+        assert_eq!(pdu, vec![0x03, 0x13, 0x89, 0x00, 0x03]);
+
+        assert!(ReadRequest::new(5001, MAX_READ_QUANTITY + 1).encode().is_err());
+    }
+
+    #[test]
+    fn test_decode_read_response() {
+        let pdu = vec![0x03, 0x08, 0xde, 0xad, 0xbe, 0xef, 0x00, 0x00, 0x00, 0x01];
+        let rsp = ReadResponse::decode(&pdu).unwrap();
+        assert_eq!(rsp.get_values(), &[0xdeadbeef, 0x00000001]);
+
+        let req = ReadRequest::new(5001, 2);
+        req.check_response(&rsp, DecodeMode::Strict).unwrap();
+        assert!(matches!(ReadRequest::new(5001, 3).check_response(&rsp, DecodeMode::Strict), Err(Error::InvalidResponse)));
+    }
+
+    #[test]
+    fn test_decode_16_bit_read_response() {
+        // A response with 16-bit registers is rejected instead of being misinterpreted
+        let pdu = vec![0x03, 0x06, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03];
+        let err = ReadResponse::decode(&pdu).err().unwrap();
+        match err {
+            Error::InvalidData => {}
+            _ => panic!("Expected InvalidData, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_encode_write_request() {
+        let pdu = WriteRequest::new(7001, &[1.5f32.to_bits()]).encode().unwrap();
+        assert_eq!(pdu, vec![0x10, 0x1b, 0x59, 0x00, 0x01, 0x04, 0x3f, 0xc0, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_decode_write_request() {
+        let pdu = vec![0x10, 0x13, 0x89, 0x00, 0x02, 0x08, 0x00, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xfe];
+        let req = WriteRequest::decode(&pdu).unwrap();
+        assert_eq!(req, WriteRequest::new(5001, &[1, 0xfffffffe]));
+    }
+
+    #[test]
+    fn test_write_response() {
+        let req = WriteRequest::new(5001, &[1, 2]);
+        let rsp = WriteResponse::decode(&[0x10, 0x13, 0x89, 0x00, 0x02]).unwrap();
+        assert_eq!(rsp, req.create_expected_response());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::Error;
+use crate::pdu::{DecodeMode, Function, FunctionCode, Request, Response, Setter};
+use core::convert::TryInto;
+use core::fmt;
+use alloc::vec::Vec;
+
+/// Mask Write Register request or response function
+///
+/// The register is modified to `(current AND and_mask) OR (or_mask AND (NOT and_mask))`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Message {
+    address: u16,
+    and_mask: u16,
+    or_mask: u16,
+}
+
+impl Message {
+    /// Create a new Mask Write Register function
+    ///
+    /// # Examples
+    /// ```
+    /// let req = modbus::MaskWriteRegRequest::new(0x0004, 0x00f2, 0x0025);
+    /// let rsp = modbus::MaskWriteRegResponse::new(0x0004, 0x00f2, 0x0025);
+    /// ```
+    pub fn new(address: u16, and_mask: u16, or_mask: u16) -> Self {
+        Message{address, and_mask, or_mask}
+    }
+
+    /// Create a Mask Write Register function setting bits selected by `mask` to the bits of `value`
+    ///
+    /// Other bits of the register are not modified.
+    ///
+    /// # Examples
+    /// ```
+    /// let req = modbus::MaskWriteRegRequest::with_bits(0x0004, 0x00f0, 0x0050);
+    /// assert_eq!(req.apply(0x1234), 0x1254);
+    /// ```
+    pub fn with_bits(address: u16, mask: u16, value: u16) -> Self {
+        Self::new(address, !mask, value & mask)
+    }
+
+    /// Get address of the register from the Mask Write Register function
+    pub fn get_address(&self) -> u16 {
+        self.address
+    }
+
+    /// Get AND mask from the Mask Write Register function
+    pub fn get_and_mask(&self) -> u16 {
+        self.and_mask
+    }
+
+    /// Get OR mask from the Mask Write Register function
+    pub fn get_or_mask(&self) -> u16 {
+        self.or_mask
+    }
+
+    /// Get value of a register with given current value after applying the masks
+    pub fn apply(&self, current: u16) -> u16 {
+        (current & self.and_mask) | (self.or_mask & !self.and_mask)
+    }
+}
+
+impl Function for Message {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        buf.push(FunctionCode::MaskWriteReg as u8);
+        buf.extend_from_slice(&self.address.to_be_bytes());
+        buf.extend_from_slice(&self.and_mask.to_be_bytes());
+        buf.extend_from_slice(&self.or_mask.to_be_bytes());
+
+        Ok(())
+    }
+
+    fn decode_with_mode(data: &[u8], _mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() != 7 {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FunctionCode::MaskWriteReg as u8 {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(Self{address: u16::from_be_bytes(data[1..=2].try_into().unwrap()),
+                and_mask: u16::from_be_bytes(data[3..=4].try_into().unwrap()),
+                or_mask: u16::from_be_bytes(data[5..=6].try_into().unwrap())})
+    }
+}
+
+impl Request for Message {
+    type Rsp = Message;
+}
+
+impl Response for Message {
+    fn get_exc_function_code() -> u8 {
+        FunctionCode::ExcMaskWriteReg.into()
+    }
+}
+
+impl Setter for Message {
+    fn create_expected_response(&self) -> Self::Rsp {
+        self.clone()
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mask Write Register: address 0x{:04x}, AND mask 0x{:04x}, OR mask 0x{:04x}",
+               self.address, self.and_mask, self.or_mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_request() {
+        let req = Message::new(0x0004, 0x00f2, 0x0025);
+        let pdu = req.encode().unwrap();
+        let expected_pdu = vec![0x16, 0x00, 0x04, 0x00, 0xf2, 0x00, 0x25];
+
+        assert_eq!(pdu, expected_pdu);
+    }
+
+    #[test]
+    fn test_decode_response() {
+        let pdu = vec![0x16, 0x00, 0x04, 0x00, 0xf2, 0x00, 0x25];
+        let rsp = Message::decode(&pdu).unwrap();
+        let expected_rsp = Message::new(0x0004, 0x00f2, 0x0025);
+
+        assert_eq!(rsp, expected_rsp);
+    }
+
+    #[test]
+    fn test_decode_invalid_length() {
+        let pdu = vec![0x16, 0x00, 0x04, 0x00, 0xf2, 0x00];
+        let err = Message::decode(&pdu).err().unwrap();
+        match err {
+            Error::InvalidDataLength => {}
+            _ => panic!("Expected InvalidDataLength, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        // Example from the Modbus application protocol specification
+        assert_eq!(Message::new(0x0004, 0x00f2, 0x0025).apply(0x0012), 0x0017);
+        assert_eq!(Message::with_bits(0x0004, 0x8001, 0xffff).apply(0x0000), 0x8001);
+        assert_eq!(Message::with_bits(0x0004, 0x8001, 0x0000).apply(0xffff), 0x7ffe);
+    }
+}
@@ -1,4 +1,68 @@
+pub mod enron;
+pub mod mask_write_reg;
 pub mod read_hld_reg;
 pub mod read_in_reg;
 pub mod write_multi_reg;
-pub mod write_single_reg;
\ No newline at end of file
+pub mod write_single_reg;
+
+use crate::Error;
+use crate::pdu::DecodeMode;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+/// Values of registers kept together with their big-endian on-wire bytes
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Vec<u16>", into = "Vec<u16>"))]
+pub(crate) struct Registers {
+    values: Vec<u16>,
+    bytes: Vec<u8>,
+}
+
+impl Registers {
+    pub(crate) fn new(values: &[u16]) -> Self {
+        let bytes = values.iter().flat_map(|value| value.to_be_bytes()).collect();
+        Self {values: values.to_vec(), bytes}
+    }
+
+    /// Decode registers from on-wire bytes, ignoring a trailing odd byte
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        let bytes = &bytes[..bytes.len() / 2 * 2];
+        let values = bytes.chunks_exact(2)
+            .map(|value| u16::from_be_bytes(value.try_into().unwrap()))
+            .collect();
+        Self {values, bytes: bytes.to_vec()}
+    }
+
+    pub(crate) fn get(&self) -> &[u16] {
+        &self.values
+    }
+
+    pub(crate) fn get_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<Vec<u16>> for Registers {
+    fn from(values: Vec<u16>) -> Self {
+        Self::new(&values)
+    }
+}
+
+impl From<Registers> for Vec<u16> {
+    fn from(registers: Registers) -> Self {
+        registers.values
+    }
+}
+
+/// Verify that registers decoded from a response cover the requested quantity
+/// 
+/// In the strict mode the response must contain exactly the requested quantity of registers.
+fn check_registers(registers: &[u16], quantity: u16, mode: DecodeMode) -> Result<(), Error> {
+    let quantity = quantity as usize;
+    match mode {
+        DecodeMode::Strict if registers.len() != quantity => Err(Error::InvalidResponse),
+        DecodeMode::Lenient if registers.len() < quantity => Err(Error::InvalidResponse),
+        _ => Ok(()),
+    }
+}
\ No newline at end of file
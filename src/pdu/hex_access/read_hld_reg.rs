@@ -1,13 +1,17 @@
 use crate::error::Error;
-use crate::pdu::{Function, FunctionCode, Request as ReqT, Response as RspT};
-use std::convert::TryInto;
-use std::vec::Vec;
+use crate::pdu::{is_range_valid, check_size, DecodeMode, Function, FunctionCode, Request as ReqT, Response as RspT};
+use crate::fmt::HexDump;
+use super::{check_registers, Registers};
+use core::convert::TryInto;
+use core::fmt;
+use alloc::vec::Vec;
 
 const MIN_QUANTITY: u16 = 1;
 const MAX_QUANTITY: u16 = 125;
 
 /// Read Holding Registers function request
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Request {
     address: u16,
     quantity: u16,
@@ -21,6 +25,7 @@ impl Request {
     /// let req = modbus::ReadHldRegRequest::new(0x0102, 0x0001);
     /// ```
     pub fn new(address: u16, quantity: u16) -> Self {
+        debug_assert!(is_range_valid(address, quantity));
         Self {address, quantity}
     }
 
@@ -54,21 +59,20 @@ impl Request {
 }
 
 impl Function for Request {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
         match self.quantity {
-            MIN_QUANTITY..=MAX_QUANTITY => {
-                let mut result = Vec::new();
-                result.push(FunctionCode::ReadHldReg as u8);
-                result.append(&mut self.address.to_be_bytes().to_vec());
-                result.append(&mut self.quantity.to_be_bytes().to_vec());
+            MIN_QUANTITY..=MAX_QUANTITY if is_range_valid(self.address, self.quantity) => {
+                buf.push(FunctionCode::ReadHldReg as u8);
+                buf.extend_from_slice(&self.address.to_be_bytes());
+                buf.extend_from_slice(&self.quantity.to_be_bytes());
 
-                Ok(result)
+                Ok(())
             }
             _ => Err(Error::InvalidValue),
         }
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
         if data.len() != 5 {
             return Err(Error::InvalidDataLength);
         }
@@ -76,18 +80,29 @@ impl Function for Request {
             return Err(Error::InvalidData);
         }
 
-        Ok(Self {address: u16::from_be_bytes(data[1..=2].try_into().unwrap()), 
-                 quantity: u16::from_be_bytes(data[3..=4].try_into().unwrap())})
+        let address = u16::from_be_bytes(data[1..=2].try_into().unwrap());
+        let quantity = u16::from_be_bytes(data[3..=4].try_into().unwrap());
+        if mode == DecodeMode::Strict && !(MIN_QUANTITY..=MAX_QUANTITY).contains(&quantity) {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(Self {address, quantity})
     }
 }
 
 impl ReqT for Request {
     type Rsp = Response;
+
+    fn check_response(&self, rsp: &Self::Rsp, mode: DecodeMode) -> Result<(), Error> {
+        check_registers(rsp.registers.get(), self.quantity, mode)
+    }
 }
 
 /// Read Holding Registers function response
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Response {
-    registers: Vec<u16>,
+    registers: Registers,
 }
 
 impl Response {
@@ -99,7 +114,7 @@ impl Response {
     /// let rsp = modbus::ReadHldRegResponse::new(&registers);
     /// ```
     pub fn new(registers: &[u16]) -> Self {
-        Self{ registers: registers.to_vec() }
+        Self{ registers: Registers::new(registers) }
     }
 
     /// Get registers' values from the response.
@@ -110,53 +125,81 @@ impl Response {
     /// let rsp = modbus::ReadHldRegResponse::new(&registers);
     /// assert_eq!(rsp.get_registers(), &registers);
     /// ```
-    pub fn get_registers(&self) -> &Vec<u16> {
-        &self.registers
+    pub fn get_registers(&self) -> &[u16] {
+        self.registers.get()
+    }
+
+    /// Get registers' values from the response as bytes, in the order they are sent
+    /// 
+    /// The most significant byte of each register comes first. It allows interpreting opaque
+    /// structures or strings without converting the registers back into bytes.
+    /// 
+    /// # Examples
+    /// ```
+    /// let rsp = modbus::ReadHldRegResponse::new(&[0x4142, 0x4344]);
+    /// assert_eq!(rsp.get_bytes(), b"ABCD");
+    /// ```
+    pub fn get_bytes(&self) -> &[u8] {
+        self.registers.get_bytes()
     }
 }
 
 impl Function for Response {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        result.push(FunctionCode::ReadHldReg as u8);
-        result.push((self.registers.len() * 2) as u8);
-        for reg in &self.registers {
-            result.append(&mut reg.to_be_bytes().to_vec());
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let registers = self.registers.get();
+        if registers.is_empty() || registers.len() > MAX_QUANTITY as usize {
+            return Err(Error::InvalidValue);
         }
 
-        Ok(result)
+        buf.push(FunctionCode::ReadHldReg as u8);
+        buf.push((registers.len() * 2) as u8);
+        buf.extend_from_slice(self.registers.get_bytes());
+
+        Ok(())
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
         if data.len() < 2 {
             return Err(Error::InvalidDataLength);
         }
         if data[0] != FunctionCode::ReadHldReg as u8 {
             return Err(Error::InvalidData);
         }
+        check_size(data.len())?;
 
         let num_bytes = data[1];
-        if num_bytes % 2 != 0 {
-            return Err(Error::InvalidData);
-        }
-        if num_bytes as usize != data.len() - 2 {
-            return Err(Error::InvalidDataLength);
+        if mode == DecodeMode::Strict {
+            if num_bytes == 0 || !num_bytes.is_multiple_of(2) {
+                return Err(Error::InvalidData);
+            }
+            if num_bytes as usize != data.len() - 2 {
+                return Err(Error::InvalidDataLength);
+            }
         }
 
-        let num_registers = (num_bytes / 2) as usize;
-        let mut registers = Vec::with_capacity(num_registers);
-        for i in 0..num_registers {
-            let reg_idx = 2 + 2 * i;
-            let reg = u16::from_be_bytes(data[reg_idx..=(reg_idx+1)].try_into().unwrap());
-            registers.push(reg);
-        }
-        Ok(Self {registers})
+        Ok(Self {registers: Registers::from_bytes(&data[2..])})
     }
 }
 
 impl RspT for Response {
     fn get_exc_function_code() -> u8 {
-        FunctionCode::ExcReadHldReg.try_into().unwrap()
+        FunctionCode::ExcReadHldReg.into()
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read Holding Registers request: address 0x{:04x}, quantity {}", self.address, self.quantity)
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read Holding Registers response: {} registers, data:", self.registers.get().len())?;
+        for reg in self.registers.get() {
+            write!(f, " {}", HexDump::new(&reg.to_be_bytes()))?;
+        }
+        Ok(())
     }
 }
 
@@ -168,7 +211,7 @@ mod tests {
     fn encode_request() {
         let req = Request::new(0x0102, 0x0001);
         let pdu = req.encode().unwrap();
-        assert_eq!(pdu, vec![0x03 as u8, 0x01, 0x02, 0x00, 0x01]);
+        assert_eq!(pdu, vec![0x03_u8, 0x01, 0x02, 0x00, 0x01]);
     }
 
     #[test]
@@ -184,13 +227,66 @@ mod tests {
         let registers: [u16; 7] = [0x0123, 0x2345, 0xabcd, 0xedcb, 0x0000, 0xffff, 0x9876];
         let rsp = Response::new(&registers);
         let pdu = rsp.encode().unwrap();
-        assert_eq!(pdu, vec![0x03 as u8, 0x0e, 0x01, 0x23, 0x23, 0x45, 0xab, 0xcd, 0xed, 0xcb, 0x00, 0x00, 0xff, 0xff, 0x98, 0x76]);
+        assert_eq!(pdu, vec![0x03_u8, 0x0e, 0x01, 0x23, 0x23, 0x45, 0xab, 0xcd, 0xed, 0xcb, 0x00, 0x00, 0xff, 0xff, 0x98, 0x76]);
     }
 
     #[test]
     fn decode_response() {
         let pdu: [u8; 6] = [0x03, 0x04, 0xde, 0xad, 0xbe, 0xef];
         let rsp = Response::decode(&pdu).unwrap();
-        assert_eq!(rsp.get_registers(), &vec![0xdead as u16, 0xbeef]);
+        assert_eq!(rsp.get_registers(), &vec![0xdead_u16, 0xbeef]);
+    }
+
+    #[test]
+    fn decode_response_bytes() {
+        let pdu: [u8; 7] = [0x03, 0x04, 0x41, 0x42, 0x43, 0x00, 0xff];
+        let rsp = Response::decode_with_mode(&pdu, DecodeMode::Lenient).unwrap();
+        assert_eq!(rsp.get_bytes(), &[0x41, 0x42, 0x43, 0x00]);
+        assert_eq!(rsp.encode().unwrap(), &pdu[..6]);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Request::new(0x0010, 2).to_string(), "Read Holding Registers request: address 0x0010, quantity 2");
+        assert_eq!(Response::new(&[0xdead, 0x0001]).to_string(), "Read Holding Registers response: 2 registers, data: de ad 00 01");
+    }
+
+    #[test]
+    fn decode_request_strict_quantity() {
+        let pdu: [u8; 5] = [0x03, 0xab, 0xcd, 0x00, 0x7e];
+        let err = Request::decode(&pdu).err().unwrap();
+        match err {
+            Error::InvalidData => {}
+            _ => panic!("Expected InvalidData, but got {:?}", err),
+        }
+
+        let req = Request::decode_with_mode(&pdu, DecodeMode::Lenient).unwrap();
+        assert_eq!(req.get_quantity(), 0x007e);
+    }
+
+    #[test]
+    fn decode_response_odd_byte_count() {
+        let pdu: [u8; 7] = [0x03, 0x05, 0xde, 0xad, 0xbe, 0xef, 0x00];
+        let err = Response::decode(&pdu).err().unwrap();
+        match err {
+            Error::InvalidData => {}
+            _ => panic!("Expected InvalidData, but got {:?}", err),
+        }
+
+        let rsp = Response::decode_with_mode(&pdu, DecodeMode::Lenient).unwrap();
+        assert_eq!(rsp.get_registers(), &vec![0xdead_u16, 0xbeef]);
+    }
+
+    #[test]
+    fn check_response_quantity() {
+        let req = Request::new(0x0000, 2);
+        let rsp = Response::new(&[0xdead, 0xbeef, 0x0000]);
+
+        let err = req.check_response(&rsp, DecodeMode::Strict).err().unwrap();
+        match err {
+            Error::InvalidResponse => {}
+            _ => panic!("Expected InvalidResponse, but got {:?}", err),
+        }
+        req.check_response(&rsp, DecodeMode::Lenient).unwrap();
     }
 }
\ No newline at end of file
@@ -1,12 +1,16 @@
 use crate::Error;
-use crate::pdu::{Function, FunctionCode, Request as ReqT, Response as RspT, Setter};
-use std::convert::TryInto;
+use crate::pdu::{is_range_valid, check_size, DecodeMode, Function, FunctionCode, Request as ReqT, Response as RspT, Setter};
+use crate::fmt::HexDump;
+use core::convert::TryInto;
+use core::fmt;
+use alloc::vec::Vec;
 
 const MIN_QUANTITY: usize = 1;
 const MAX_QUANTITY: usize = 123;
 
 /// Write Multiple Registers request function
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Request {
     address: u16,
     values: Vec<u16>,
@@ -22,6 +26,7 @@ impl Request {
     pub fn new(address: u16, values: &[u16]) -> Self {
         assert!(values.len() >= MIN_QUANTITY);
         assert!(values.len() <= MAX_QUANTITY);
+        assert!(is_range_valid(address, values.len() as u16));
 
         Request{address, values: Vec::from(values)}
     }
@@ -52,43 +57,47 @@ impl Request {
 }
 
 impl Function for Request {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
         match self.values.len() {
-            MIN_QUANTITY..=MAX_QUANTITY => {
-                let mut result = Vec::new();
-                result.push(FunctionCode::WriteMultiReg as u8);
-                result.append(&mut self.address.to_be_bytes().to_vec());
-                result.append(&mut (self.values.len() as u16).to_be_bytes().to_vec());
-                result.push((self.values.len() as u8) * 2);
+            MIN_QUANTITY..=MAX_QUANTITY if is_range_valid(self.address, self.values.len() as u16) => {
+                buf.push(FunctionCode::WriteMultiReg as u8);
+                buf.extend_from_slice(&self.address.to_be_bytes());
+                buf.extend_from_slice(&(self.values.len() as u16).to_be_bytes());
+                buf.push((self.values.len() as u8) * 2);
 
                 for val in &self.values {
-                    result.append(&mut val.to_be_bytes().to_vec());
+                    buf.extend_from_slice(&val.to_be_bytes());
                 }
 
-                Ok(result)
+                Ok(())
             }
             _ => Err(Error::InvalidValue)
         }
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> {
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
         if data.len() < 6 {
             return Err(Error::InvalidDataLength);
         }
         if data[0] != FunctionCode::WriteMultiReg as u8 {
             return Err(Error::InvalidData);
         }
+        check_size(data.len())?;
         
         let address = u16::from_be_bytes(data[1..=2].try_into().unwrap());
         let quantity = u16::from_be_bytes(data[3..=4].try_into().unwrap());
         let data_cnt = data[5];
 
-        if data_cnt as u16 != quantity * 2 {
+        if mode == DecodeMode::Strict {
+            if data_cnt as u16 != quantity.wrapping_mul(2) || data.len() != 6 + data_cnt as usize {
+                return Err(Error::InvalidDataLength);
+            }
+            if (quantity as usize) < MIN_QUANTITY || (quantity as usize) > MAX_QUANTITY {
+                return Err(Error::InvalidData);
+            }
+        } else if data.len() < 6 + quantity as usize * 2 {
             return Err(Error::InvalidDataLength);
         }
-        if (quantity as usize) < MIN_QUANTITY || (quantity as usize) > MAX_QUANTITY {
-            return Err(Error::InvalidData);
-        }
 
         let mut values = Vec::with_capacity(quantity as usize);
 
@@ -112,7 +121,8 @@ impl Setter for Request {
 }
 
 /// Write Multiple Registers response function
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Response {
     address: u16,
     quantity: u16,
@@ -158,21 +168,20 @@ impl Response {
 }
 
 impl Function for Response {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
         match self.quantity as usize {
             MIN_QUANTITY..=MAX_QUANTITY => {
-                let mut result = Vec::new();
-                result.push(FunctionCode::WriteMultiReg as u8);
-                result.append(&mut self.address.to_be_bytes().to_vec());
-                result.append(&mut self.quantity.to_be_bytes().to_vec());
+                buf.push(FunctionCode::WriteMultiReg as u8);
+                buf.extend_from_slice(&self.address.to_be_bytes());
+                buf.extend_from_slice(&self.quantity.to_be_bytes());
 
-                Ok(result)
+                Ok(())
             }
             _ => Err(Error::InvalidValue)
         }
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> {
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
         if data.len() != 5 {
             return Err(Error::InvalidDataLength);
         }
@@ -182,6 +191,9 @@ impl Function for Response {
         
         let address = u16::from_be_bytes(data[1..=2].try_into().unwrap());
         let quantity = u16::from_be_bytes(data[3..=4].try_into().unwrap());
+        if mode == DecodeMode::Strict && ((quantity as usize) < MIN_QUANTITY || (quantity as usize) > MAX_QUANTITY) {
+            return Err(Error::InvalidData);
+        }
 
         Ok(Self{address, quantity})
     }
@@ -189,7 +201,23 @@ impl Function for Response {
 
 impl RspT for Response {
     fn get_exc_function_code() -> u8 {
-        FunctionCode::ExcWriteMultiReg.try_into().unwrap()
+        FunctionCode::ExcWriteMultiReg.into()
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Write Multiple Registers request: address 0x{:04x}, quantity {}, data:", self.address, self.values.len())?;
+        for val in &self.values {
+            write!(f, " {}", HexDump::new(&val.to_be_bytes()))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Write Multiple Registers response: address 0x{:04x}, quantity {}", self.address, self.quantity)
     }
 }
 
@@ -199,7 +227,7 @@ mod tests {
 
     #[test]
     fn test_encode_request() {
-        let req = Request::new(0xdead, &vec![0xfade, 0xface, 0x0000, 0x0001]);
+        let req = Request::new(0xdead, &[0xfade, 0xface, 0x0000, 0x0001]);
         let pdu = req.encode().unwrap();
         let expected_pdu = vec![0x10, 0xde, 0xad, 0x00, 0x04, 0x08, 
                                 0xfa, 0xde, 0xfa, 0xce, 0x00, 0x00, 0x00, 0x01];
@@ -220,7 +248,7 @@ mod tests {
     fn test_decode_request() {
         let pdu = vec![0x10, 0x00, 0x00, 0x00, 0x02, 0x04, 0x01, 0x02, 0xfe, 0xfd];
         let req = Request::decode(&pdu).unwrap();
-        let expected_req = Request::new(0x0000, &vec![0x0102, 0xfefd]);
+        let expected_req = Request::new(0x0000, &[0x0102, 0xfefd]);
 
         assert_eq!(req, expected_req);
     }
@@ -231,7 +259,17 @@ mod tests {
         let err = Request::decode(&pdu).err().unwrap();
         match err {
             Error::InvalidData => {}
-            _ => panic!(format!("Expected InvalidData, but got {:?}", err)),
+            _ => panic!("Expected InvalidData, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_decode_truncated_request() {
+        let pdu = vec![0x10, 0x01, 0x23, 0x00, 0x02, 0x04, 0x11, 0x12];
+        let err = Request::decode(&pdu).err().unwrap();
+        match err {
+            Error::InvalidDataLength => {}
+            _ => panic!("Expected InvalidDataLength, but got {:?}", err),
         }
     }
 
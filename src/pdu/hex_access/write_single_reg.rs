@@ -1,9 +1,12 @@
 use crate::Error;
-use crate::pdu::{Function, FunctionCode, Request, Response, Setter};
-use std::convert::TryInto;
+use crate::pdu::{DecodeMode, Function, FunctionCode, Request, Response, Setter};
+use core::convert::TryInto;
+use core::fmt;
+use alloc::vec::Vec;
 
 /// Write Single Register request or response function
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message {
     address: u16,
     value: u16,
@@ -47,16 +50,15 @@ impl Message {
 }
 
 impl Function for Message {
-    fn encode(&self) -> Result<Vec<u8>, Error> {
-        let mut result = Vec::new();
-        result.push(FunctionCode::WriteSingleReg as u8);
-        result.append(&mut self.address.to_be_bytes().to_vec());
-        result.append(&mut self.value.to_be_bytes().to_vec());
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        buf.push(FunctionCode::WriteSingleReg as u8);
+        buf.extend_from_slice(&self.address.to_be_bytes());
+        buf.extend_from_slice(&self.value.to_be_bytes());
 
-        Ok(result)
+        Ok(())
     }
 
-    fn decode(data: &[u8]) -> Result<Self, Error> {
+    fn decode_with_mode(data: &[u8], _mode: DecodeMode) -> Result<Self, Error> {
         if data.len() != 5 {
             return Err(Error::InvalidDataLength);
         }
@@ -75,7 +77,7 @@ impl Request for Message {
 
 impl Response for Message {
     fn get_exc_function_code() -> u8 {
-        FunctionCode::ExcWriteSingleReg.try_into().unwrap()
+        FunctionCode::ExcWriteSingleReg.into()
     }
 }
 
@@ -85,6 +87,12 @@ impl Setter for Message {
     }
 }
 
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Write Single Register: address 0x{:04x}, value 0x{:04x}", self.address, self.value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,7 +130,7 @@ mod tests {
         let err = Message::decode(&pdu).err().unwrap();
         match err {
             Error::InvalidData => {}
-            _ => panic!(format!("Expected InvalidData, but got {:?}", err)),
+            _ => panic!("Expected InvalidData, but got {:?}", err),
         }
     }
 
@@ -1,31 +1,101 @@
 pub mod bit_access;
+pub mod file_access;
 pub mod hex_access;
+pub mod raw;
+pub mod read_device_id;
+#[cfg(test)]
+mod proptests;
 
 use crate::Error;
 use num_enum::IntoPrimitive;
-use std::convert::TryFrom;
-use std::fmt;
+use core::convert::TryFrom;
+use core::fmt;
+use alloc::vec::Vec;
+
+pub(crate) const MAX_SIZE: usize = 253;
+pub(crate) const EXC_FUNCTION_CODE_FLAG: u8 = 0x80;
+
+/// Verify that a PDU of given length does not exceed [the maximal PDU size](MAX_SIZE)
+pub(crate) fn check_size(len: usize) -> Result<(), Error> {
+    if len > MAX_SIZE {
+        Err(Error::InvalidDataLength)
+    } else {
+        Ok(())
+    }
+}
 
-const MAX_SIZE: usize = 253;
+/// Check if given quantity of items starting at given address fits in the 16-bit address space
+pub(crate) fn is_range_valid(address: u16, quantity: u16) -> bool {
+    address as u32 + quantity as u32 <= 0x10000
+}
+
+/// Strictness of decoding received PDUs
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DecodeMode {
+    /// Reject any deviation from the specification
+    /// 
+    /// Nonzero padding bits of bit responses, odd register byte counts, byte counts
+    /// inconsistent with the PDU length and quantities out of the allowed ranges are rejected.
+    #[default]
+    Strict,
+    /// Tolerate common vendor quirks
+    /// 
+    /// Padding bits, inconsistent byte counts and out of range quantities are accepted as long
+    /// as the PDU carries the data, and any nonzero coil value is treated as ON.
+    Lenient,
+}
 
 pub trait Function {
-    fn encode(&self) -> Result<Vec<u8>, Error>;
-    fn decode(data: &[u8]) -> Result<Self, Error> where Self: Sized;
+    /// Encode the function and append it to the given buffer
+    /// 
+    /// Nothing is appended if the function cannot be encoded, so the buffer can be reused
+    /// between consecutive calls.
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error>;
+
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> where Self: Sized;
+
+    fn decode(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+        Self::decode_with_mode(data, DecodeMode::Strict)
+    }
 }
 
 pub trait Request: Function {
     type Rsp: Response;
+
+    /// Verify that a decoded response carries the data requested by this request
+    fn check_response(&self, _rsp: &Self::Rsp, _mode: DecodeMode) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 pub trait Response: Function + Sized {
     fn get_exc_function_code() -> u8;
 
     fn decode_response(data: &[u8]) -> Result<Self, Error> {
+        Self::decode_response_with_mode(data, DecodeMode::Strict)
+    }
+
+    fn decode_response_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
+        check_size(data.len())?;
         if let Ok(exc_code) = Self::decode_exc_rsp(data, Some(Self::get_exc_function_code())) {
             return Err(Error::ExceptionResponse(exc_code));
         }
 
-        Self::decode(data)
+        let expected = Self::get_exc_function_code() & !EXC_FUNCTION_CODE_FLAG;
+        if let Some(&received) = data.first() {
+            if received != expected {
+                return Err(Error::UnexpectedFunctionCode {expected, received, pdu: data.to_vec()});
+            }
+        }
+
+        Self::decode_with_mode(data, mode)
     }
 
     fn decode_exc_rsp(data: &[u8], exp_fnc_code: Option<u8>) -> Result<ExceptionCode, Error> {
@@ -48,7 +118,8 @@ pub trait Setter where Self: Request, Self::Rsp: PartialEq {
     fn create_expected_response(&self) -> Self::Rsp;
 }
 
-#[derive(Clone, Copy, FromPrimitive, IntoPrimitive, PartialEq)]
+/// Function codes supported by this crate, including function codes of exception responses
+#[derive(Clone, Copy, Debug, Eq, FromPrimitive, Hash, IntoPrimitive, PartialEq)]
 #[repr(u8)]
 pub enum FunctionCode {
     ReadCoils = 0x01,
@@ -58,6 +129,9 @@ pub enum FunctionCode {
     WriteSingleCoil = 0x05,
     WriteSingleReg = 0x06,
     WriteMultiReg = 0x10,
+    ReadFileRecord = 0x14,
+    WriteFileRecord = 0x15,
+    MaskWriteReg = 0x16,
 
     ExcReadCoils = 0x81,
     ExcReadDscrIn = 0x82,
@@ -66,9 +140,59 @@ pub enum FunctionCode {
     ExcWriteSingleCoil = 0x85,
     ExcWriteSingleReg = 0x86,
     ExcWriteMultiReg = 0x90,
+    ExcReadFileRecord = 0x94,
+    ExcWriteFileRecord = 0x95,
+    ExcMaskWriteReg = 0x96,
+}
+
+impl FunctionCode {
+    /// Check if the function code belongs to an exception response
+    /// 
+    /// # Examples
+    /// ```
+    /// use modbus::FunctionCode;
+    /// 
+    /// assert!(FunctionCode::ExcReadCoils.is_exception());
+    /// assert!(!FunctionCode::ReadCoils.is_exception());
+    /// ```
+    pub fn is_exception(&self) -> bool {
+        *self as u8 & EXC_FUNCTION_CODE_FLAG != 0
+    }
+}
+
+impl fmt::Display for FunctionCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FunctionCode::ReadCoils | FunctionCode::ExcReadCoils => "Read Coils",
+            FunctionCode::ReadDscrIn | FunctionCode::ExcReadDscrIn => "Read Discrete Inputs",
+            FunctionCode::ReadHldReg | FunctionCode::ExcReadHldReg => "Read Holding Registers",
+            FunctionCode::ReadInReg | FunctionCode::ExcReadInReg => "Read Input Registers",
+            FunctionCode::WriteSingleCoil | FunctionCode::ExcWriteSingleCoil => "Write Single Coil",
+            FunctionCode::WriteSingleReg | FunctionCode::ExcWriteSingleReg => "Write Single Register",
+            FunctionCode::WriteMultiReg | FunctionCode::ExcWriteMultiReg => "Write Multiple Registers",
+            FunctionCode::ReadFileRecord | FunctionCode::ExcReadFileRecord => "Read File Record",
+            FunctionCode::WriteFileRecord | FunctionCode::ExcWriteFileRecord => "Write File Record",
+            FunctionCode::MaskWriteReg | FunctionCode::ExcMaskWriteReg => "Mask Write Register",
+        };
+
+        if self.is_exception() {
+            write!(f, "{} exception", name)
+        } else {
+            f.write_str(name)
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+impl TryFrom<u8> for FunctionCode {
+    type Error = Error;
+
+    fn try_from(v: u8) -> Result<Self, Error> {
+        num::FromPrimitive::from_u8(v).ok_or(Error::InvalidFunction)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExceptionCode {
     IllegalFunction                    = 0x01,
     IllegalDataAddress                 = 0x02,
@@ -119,7 +243,8 @@ impl TryFrom<u8> for ExceptionCode {
 /// Enumeration of Modbus request functions.
 /// 
 /// This enumeration is used to report received request in the Modbus slave mode.
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RequestData {
     ReadCoils(bit_access::read_coils::Request),
     ReadDscrIn(bit_access::read_dscr_in::Request),
@@ -128,35 +253,156 @@ pub enum RequestData {
     WriteSingleCoil(bit_access::write_single_coil::Message),
     WriteSingleReg(hex_access::write_single_reg::Message),
     WriteMultiReg(hex_access::write_multi_reg::Request),
+    MaskWriteReg(hex_access::mask_write_reg::Message),
+}
+
+/// Enumeration of Modbus response functions.
+/// 
+/// This enumeration is used to pass responses created in the Modbus slave mode before they are encoded.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResponseData {
+    ReadCoils(bit_access::read_coils::Response),
+    ReadDscrIn(bit_access::read_dscr_in::Response),
+    ReadHldReg(hex_access::read_hld_reg::Response),
+    ReadInReg(hex_access::read_in_reg::Response),
+    WriteSingleCoil(bit_access::write_single_coil::Message),
+    WriteSingleReg(hex_access::write_single_reg::Message),
+    WriteMultiReg(hex_access::write_multi_reg::Response),
+    MaskWriteReg(hex_access::mask_write_reg::Message),
+}
+
+impl ResponseData {
+    /// Encode the response PDU
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            ResponseData::ReadCoils(rsp) => rsp.encode(),
+            ResponseData::ReadDscrIn(rsp) => rsp.encode(),
+            ResponseData::ReadHldReg(rsp) => rsp.encode(),
+            ResponseData::ReadInReg(rsp) => rsp.encode(),
+            ResponseData::WriteSingleCoil(rsp) => rsp.encode(),
+            ResponseData::WriteSingleReg(rsp) => rsp.encode(),
+            ResponseData::WriteMultiReg(rsp) => rsp.encode(),
+            ResponseData::MaskWriteReg(rsp) => rsp.encode(),
+        }
+    }
 }
 
 pub fn decode_req(pdu: &[u8]) -> Result<RequestData, Error> {
+    decode_req_with_mode(pdu, DecodeMode::Strict)
+}
+
+pub fn decode_req_with_mode(pdu: &[u8], mode: DecodeMode) -> Result<RequestData, Error> {
     if pdu.len() < 2 {
         return Err(Error::InvalidDataLength);
     }
+    check_size(pdu.len())?;
 
     match num::FromPrimitive::from_u8(pdu[0]) {
-        Some(FunctionCode::ReadCoils) => Ok(RequestData::ReadCoils(bit_access::read_coils::Request::decode(pdu)?)),
-        Some(FunctionCode::ReadDscrIn) => Ok(RequestData::ReadDscrIn(bit_access::read_dscr_in::Request::decode(pdu)?)),
-        Some(FunctionCode::ReadHldReg) => Ok(RequestData::ReadHldReg(hex_access::read_hld_reg::Request::decode(pdu)?)),
-        Some(FunctionCode::ReadInReg) => Ok(RequestData::ReadInReg(hex_access::read_in_reg::Request::decode(pdu)?)),
-        Some(FunctionCode::WriteSingleCoil) => Ok(RequestData::WriteSingleCoil(bit_access::write_single_coil::Message::decode(pdu)?)),
-        Some(FunctionCode::WriteSingleReg) => Ok(RequestData::WriteSingleReg(hex_access::write_single_reg::Message::decode(pdu)?)),
-        Some(FunctionCode::WriteMultiReg) => Ok(RequestData::WriteMultiReg(hex_access::write_multi_reg::Request::decode(pdu)?)),
+        Some(FunctionCode::ReadCoils) => Ok(RequestData::ReadCoils(bit_access::read_coils::Request::decode_with_mode(pdu, mode)?)),
+        Some(FunctionCode::ReadDscrIn) => Ok(RequestData::ReadDscrIn(bit_access::read_dscr_in::Request::decode_with_mode(pdu, mode)?)),
+        Some(FunctionCode::ReadHldReg) => Ok(RequestData::ReadHldReg(hex_access::read_hld_reg::Request::decode_with_mode(pdu, mode)?)),
+        Some(FunctionCode::ReadInReg) => Ok(RequestData::ReadInReg(hex_access::read_in_reg::Request::decode_with_mode(pdu, mode)?)),
+        Some(FunctionCode::WriteSingleCoil) => Ok(RequestData::WriteSingleCoil(bit_access::write_single_coil::Message::decode_with_mode(pdu, mode)?)),
+        Some(FunctionCode::WriteSingleReg) => Ok(RequestData::WriteSingleReg(hex_access::write_single_reg::Message::decode_with_mode(pdu, mode)?)),
+        Some(FunctionCode::WriteMultiReg) => Ok(RequestData::WriteMultiReg(hex_access::write_multi_reg::Request::decode_with_mode(pdu, mode)?)),
+        Some(FunctionCode::MaskWriteReg) => Ok(RequestData::MaskWriteReg(hex_access::mask_write_reg::Message::decode_with_mode(pdu, mode)?)),
         _ => Err(Error::InvalidData),
     }
 }
 
-/*
-fn encode_exc_rsp(function_code: &FunctionCode, exception_code: &ExceptionCode) -> Result<Vec<u8>, Error> {
-    let mut result = Vec::new();
-    result.push(*function_code as u8);
-    result.push(*exception_code as u8);
+/// Encode an exception response PDU for a request with given function code.
+pub fn encode_exc_rsp(function_code: u8, exception_code: ExceptionCode) -> Vec<u8> {
+    vec![function_code | EXC_FUNCTION_CODE_FLAG, exception_code as u8]
+}
 
-    Ok(result)
+impl fmt::Display for RequestData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestData::ReadCoils(req) => req.fmt(f),
+            RequestData::ReadDscrIn(req) => req.fmt(f),
+            RequestData::ReadHldReg(req) => req.fmt(f),
+            RequestData::ReadInReg(req) => req.fmt(f),
+            RequestData::WriteSingleCoil(req) => req.fmt(f),
+            RequestData::WriteSingleReg(req) => req.fmt(f),
+            RequestData::WriteMultiReg(req) => req.fmt(f),
+            RequestData::MaskWriteReg(req) => req.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for ResponseData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResponseData::ReadCoils(rsp) => rsp.fmt(f),
+            ResponseData::ReadDscrIn(rsp) => rsp.fmt(f),
+            ResponseData::ReadHldReg(rsp) => rsp.fmt(f),
+            ResponseData::ReadInReg(rsp) => rsp.fmt(f),
+            ResponseData::WriteSingleCoil(rsp) => rsp.fmt(f),
+            ResponseData::WriteSingleReg(rsp) => rsp.fmt(f),
+            ResponseData::WriteMultiReg(rsp) => rsp.fmt(f),
+            ResponseData::MaskWriteReg(rsp) => rsp.fmt(f),
+        }
+    }
 }
-*/
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_size() {
+        assert!(check_size(MAX_SIZE).is_ok());
+        assert!(check_size(MAX_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn test_is_range_valid() {
+        assert!(is_range_valid(0xFFFF, 1));
+        assert!(is_range_valid(0xF830, 2000));
+        assert!(!is_range_valid(0xFFFF, 2));
+        assert!(!is_range_valid(0xFFFF, 2000));
+    }
+
+    #[test]
+    fn test_function_code() {
+        assert_eq!(FunctionCode::try_from(0x10).unwrap(), FunctionCode::WriteMultiReg);
+        assert_eq!(FunctionCode::try_from(0x83).unwrap(), FunctionCode::ExcReadHldReg);
+        assert!(matches!(FunctionCode::try_from(0x2b), Err(Error::InvalidFunction)));
+
+        assert_eq!(FunctionCode::WriteMultiReg.to_string(), "Write Multiple Registers");
+        assert_eq!(FunctionCode::ExcReadHldReg.to_string(), "Read Holding Registers exception");
+    }
+
+    #[test]
+    fn test_encode_exc_rsp() {
+        let pdu = encode_exc_rsp(FunctionCode::ReadHldReg as u8, ExceptionCode::IllegalDataAddress);
+        assert_eq!(pdu, vec![0x83, 0x02]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let req = decode_req(&[0x05, 0x01, 0x23, 0xff, 0x00]).unwrap();
+        let json = serde_json::to_string(&req).unwrap();
+        assert_eq!(json, r#"{"WriteSingleCoil":{"address":291,"value":true}}"#);
+        match serde_json::from_str(&json).unwrap() {
+            RequestData::WriteSingleCoil(msg) => assert_eq!(msg, bit_access::write_single_coil::Message::new(0x0123, true)),
+            req => panic!("Expected WriteSingleCoil request, but got {:?}", req),
+        }
+
+        let rsp = bit_access::read_coils::Response::new(&[true, false, true]);
+        let json = serde_json::to_string(&rsp).unwrap();
+        assert_eq!(json, r#"{"coils":[true,false,true]}"#);
+        assert_eq!(serde_json::from_str::<bit_access::read_coils::Response>(&json).unwrap(), rsp);
+
+        let rsp = hex_access::read_hld_reg::Response::new(&[0x1234]);
+        let json = serde_json::to_string(&rsp).unwrap();
+        assert_eq!(json, r#"{"registers":[4660]}"#);
+        let rsp = serde_json::from_str::<hex_access::read_hld_reg::Response>(&json).unwrap();
+        assert_eq!(rsp.get_bytes(), &[0x12, 0x34]);
+
+        let exc: ExceptionCode = serde_json::from_str(r#""IllegalDataAddress""#).unwrap();
+        assert_eq!(exc, ExceptionCode::IllegalDataAddress);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,219 @@
+//! Property-based round-trip tests of all PDU types
+
+use super::*;
+use super::bit_access::bits::Bits;
+use super::bit_access::{read_coils, read_dscr_in, write_single_coil};
+use super::file_access::{read_file_record, write_file_record};
+use super::read_device_id;
+use super::hex_access::{mask_write_reg, read_hld_reg, read_in_reg, write_multi_reg, write_single_reg};
+use core::fmt::Debug;
+use proptest::prelude::*;
+
+const MAX_READ_BITS: u16 = 2000;
+const MAX_READ_REGS: u16 = 125;
+const MAX_WRITE_REGS: u16 = 123;
+
+/// Encode a PDU, verify it decodes back to the same value and return the encoded PDU
+fn round_trip<F: Function + Debug + PartialEq>(function: &F) -> Vec<u8> {
+    let pdu = function.encode().unwrap();
+    assert!(pdu.len() <= MAX_SIZE);
+    assert_eq!(&F::decode(&pdu).unwrap(), function);
+    assert_eq!(&F::decode_with_mode(&pdu, DecodeMode::Lenient).unwrap(), function);
+    pdu
+}
+
+/// Address and quantity of a range fitting in the address space
+fn range(max_quantity: u16) -> impl Strategy<Value = (u16, u16)> {
+    (1..=max_quantity).prop_flat_map(|quantity| ((0..=(0x10000 - quantity as u32) as u16), Just(quantity)))
+}
+
+fn check_read_req(pdu: &[u8], function_code: FunctionCode, address: u16, quantity: u16) {
+    assert_eq!(pdu.len(), 5);
+    assert_eq!(pdu[0], function_code as u8);
+    assert_eq!(u16::from_be_bytes([pdu[1], pdu[2]]), address);
+    assert_eq!(u16::from_be_bytes([pdu[3], pdu[4]]), quantity);
+}
+
+fn check_bits_rsp(pdu: &[u8], bits: &Bits, values: &[bool]) {
+    let byte_count = values.len().div_ceil(8);
+    assert_eq!(pdu[1] as usize, byte_count);
+    assert_eq!(pdu.len(), byte_count + 2);
+    assert_eq!(bits.len(), byte_count * 8);
+    assert!(bits.iter().take(values.len()).eq(values.iter().copied()));
+    assert!(bits.iter().skip(values.len()).all(|bit| !bit));
+}
+
+fn check_registers_rsp(pdu: &[u8], values: &[u16]) {
+    assert_eq!(pdu[1] as usize, values.len() * 2);
+    assert_eq!(pdu.len(), values.len() * 2 + 2);
+}
+
+proptest! {
+    #[test]
+    fn read_coils_request((address, quantity) in range(MAX_READ_BITS)) {
+        let pdu = round_trip(&read_coils::Request::new(address, quantity));
+        check_read_req(&pdu, FunctionCode::ReadCoils, address, quantity);
+    }
+
+    #[test]
+    fn read_dscr_in_request((address, quantity) in range(MAX_READ_BITS)) {
+        let pdu = round_trip(&read_dscr_in::Request::new(address, quantity));
+        check_read_req(&pdu, FunctionCode::ReadDscrIn, address, quantity);
+    }
+
+    #[test]
+    fn read_hld_reg_request((address, quantity) in range(MAX_READ_REGS)) {
+        let pdu = round_trip(&read_hld_reg::Request::new(address, quantity));
+        check_read_req(&pdu, FunctionCode::ReadHldReg, address, quantity);
+    }
+
+    #[test]
+    fn read_in_reg_request((address, quantity) in range(MAX_READ_REGS)) {
+        let pdu = round_trip(&read_in_reg::Request::new(address, quantity));
+        check_read_req(&pdu, FunctionCode::ReadInReg, address, quantity);
+    }
+
+    #[test]
+    fn read_request_invalid_quantity(quantity in (MAX_READ_REGS + 1)..) {
+        // Constructors accept only ranges fitting in the address space, quantity is checked on encoding
+        let address = 0xffff - (quantity - 1);
+        prop_assert!(read_hld_reg::Request::new(address, quantity).encode().is_err());
+        prop_assert!(read_in_reg::Request::new(address, quantity).encode().is_err());
+        prop_assert!(read_hld_reg::Request::new(address, 0).encode().is_err());
+        prop_assert!(read_in_reg::Request::new(address, 0).encode().is_err());
+        prop_assert!(read_coils::Request::new(address, 0).encode().is_err());
+        prop_assert!(read_dscr_in::Request::new(address, 0).encode().is_err());
+        if quantity > MAX_READ_BITS {
+            prop_assert!(read_coils::Request::new(address, quantity).encode().is_err());
+            prop_assert!(read_dscr_in::Request::new(address, quantity).encode().is_err());
+        }
+    }
+
+    #[test]
+    fn read_coils_response(values in proptest::collection::vec(any::<bool>(), 1..=MAX_READ_BITS as usize)) {
+        let rsp = read_coils::Response::new(&values);
+        let pdu = rsp.encode().unwrap();
+        let decoded = read_coils::Response::decode_response(&pdu).unwrap();
+
+        prop_assert_eq!(pdu[0], FunctionCode::ReadCoils as u8);
+        check_bits_rsp(&pdu, decoded.get_coils(), &values);
+        prop_assert!(read_coils::Request::new(0, values.len() as u16).check_response(&decoded, DecodeMode::Strict).is_ok());
+    }
+
+    #[test]
+    fn read_dscr_in_response(values in proptest::collection::vec(any::<bool>(), 1..=MAX_READ_BITS as usize)) {
+        let rsp = read_dscr_in::Response::new(&values);
+        let pdu = rsp.encode().unwrap();
+        let decoded = read_dscr_in::Response::decode_response(&pdu).unwrap();
+
+        prop_assert_eq!(pdu[0], FunctionCode::ReadDscrIn as u8);
+        check_bits_rsp(&pdu, decoded.get_inputs(), &values);
+        prop_assert!(read_dscr_in::Request::new(0, values.len() as u16).check_response(&decoded, DecodeMode::Strict).is_ok());
+    }
+
+    #[test]
+    fn read_hld_reg_response(values in proptest::collection::vec(any::<u16>(), 1..=MAX_READ_REGS as usize)) {
+        let rsp = read_hld_reg::Response::new(&values);
+        let pdu = round_trip(&rsp);
+
+        prop_assert_eq!(pdu[0], FunctionCode::ReadHldReg as u8);
+        check_registers_rsp(&pdu, &values);
+        prop_assert!(read_hld_reg::Request::new(0, values.len() as u16).check_response(&rsp, DecodeMode::Strict).is_ok());
+    }
+
+    #[test]
+    fn read_in_reg_response(values in proptest::collection::vec(any::<u16>(), 1..=MAX_READ_REGS as usize)) {
+        let rsp = read_in_reg::Response::new(&values);
+        let pdu = round_trip(&rsp);
+
+        prop_assert_eq!(pdu[0], FunctionCode::ReadInReg as u8);
+        check_registers_rsp(&pdu, &values);
+        prop_assert!(read_in_reg::Request::new(0, values.len() as u16).check_response(&rsp, DecodeMode::Strict).is_ok());
+    }
+
+    #[test]
+    fn write_single_coil(address: u16, value: bool) {
+        let req = write_single_coil::Message::new(address, value);
+        let pdu = round_trip(&req);
+
+        prop_assert_eq!(pdu.len(), 5);
+        prop_assert_eq!(&pdu[3..], if value { &[0xff, 0x00] } else { &[0x00, 0x00] });
+        prop_assert_eq!(req.create_expected_response(), req);
+    }
+
+    #[test]
+    fn write_single_reg(address: u16, value: u16) {
+        let req = write_single_reg::Message::new(address, value);
+        let pdu = round_trip(&req);
+
+        prop_assert_eq!(pdu.len(), 5);
+        prop_assert_eq!(u16::from_be_bytes([pdu[3], pdu[4]]), value);
+        prop_assert_eq!(req.create_expected_response(), req);
+    }
+
+    #[test]
+    fn write_multi_reg_request((address, quantity) in range(MAX_WRITE_REGS), seed: u16) {
+        let values: Vec<u16> = (0..quantity).map(|i| seed.wrapping_mul(i + 1)).collect();
+        let req = write_multi_reg::Request::new(address, &values);
+        let pdu = round_trip(&req);
+
+        prop_assert_eq!(u16::from_be_bytes([pdu[3], pdu[4]]), quantity);
+        prop_assert_eq!(pdu[5] as u16, quantity * 2);
+        prop_assert_eq!(pdu.len(), 6 + quantity as usize * 2);
+        prop_assert_eq!(req.create_expected_response(), write_multi_reg::Response::new(address, quantity));
+    }
+
+    #[test]
+    fn write_multi_reg_response((address, quantity) in range(MAX_WRITE_REGS)) {
+        let pdu = round_trip(&write_multi_reg::Response::new(address, quantity));
+        check_read_req(&pdu, FunctionCode::WriteMultiReg, address, quantity);
+    }
+
+    #[test]
+    fn mask_write_reg(address: u16, mask: u16, value: u16, current: u16) {
+        let req = mask_write_reg::Message::with_bits(address, mask, value);
+        let pdu = round_trip(&req);
+
+        prop_assert_eq!(pdu.len(), 7);
+        prop_assert_eq!(req.apply(current) & mask, value & mask);
+        prop_assert_eq!(req.apply(current) & !mask, current & !mask);
+        prop_assert_eq!(req.create_expected_response(), req);
+    }
+
+    #[test]
+    fn decode_arbitrary_data(data in proptest::collection::vec(any::<u8>(), 0..=300)) {
+        // Decoding shall reject invalid data without panicking
+        for mode in [DecodeMode::Strict, DecodeMode::Lenient] {
+            let _ = decode_req_with_mode(&data, mode);
+            let _ = read_coils::Response::decode_response_with_mode(&data, mode);
+            let _ = read_dscr_in::Response::decode_response_with_mode(&data, mode);
+            let _ = read_hld_reg::Response::decode_response_with_mode(&data, mode);
+            let _ = read_in_reg::Response::decode_response_with_mode(&data, mode);
+            let _ = write_single_coil::Message::decode_response_with_mode(&data, mode);
+            let _ = write_single_reg::Message::decode_response_with_mode(&data, mode);
+            let _ = write_multi_reg::Response::decode_response_with_mode(&data, mode);
+            let _ = mask_write_reg::Message::decode_response_with_mode(&data, mode);
+            let _ = read_file_record::Response::decode_response_with_mode(&data, mode);
+            let _ = write_file_record::Message::decode_response_with_mode(&data, mode);
+            let _ = read_device_id::Response::decode_response_with_mode(&data, mode);
+        }
+    }
+
+    #[test]
+    fn decode_valid_request(data in proptest::collection::vec(any::<u8>(), 2..=MAX_SIZE)) {
+        // Every request accepted in the strict mode is encoded back to the same PDU
+        if let Ok(req) = decode_req(&data) {
+            let pdu = match req {
+                RequestData::ReadCoils(req) => req.encode(),
+                RequestData::ReadDscrIn(req) => req.encode(),
+                RequestData::ReadHldReg(req) => req.encode(),
+                RequestData::ReadInReg(req) => req.encode(),
+                RequestData::WriteSingleCoil(req) => req.encode(),
+                RequestData::WriteSingleReg(req) => req.encode(),
+                RequestData::WriteMultiReg(req) => req.encode(),
+                RequestData::MaskWriteReg(req) => req.encode(),
+            };
+            prop_assert_eq!(pdu.unwrap(), data);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use alloc::vec::Vec;
+
+/// Decoded request or response together with the raw PDU it was decoded from
+///
+/// The raw PDU allows forwarding or storing the frame verbatim after inspecting the
+/// decoded message, without encoding it again.
+///
+/// # Examples
+/// ```
+/// use modbus::{Decoded, Function, ReadHldRegResponse};
+///
+/// let pdu = [0x03, 0x02, 0x12, 0x34];
+/// let rsp = Decoded::new(ReadHldRegResponse::decode(&pdu).unwrap(), pdu.to_vec());
+///
+/// assert_eq!(rsp.get().get_registers(), &[0x1234]);
+/// assert_eq!(rsp.get_pdu(), &pdu);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Decoded<T> {
+    value: T,
+    pdu: Vec<u8>,
+}
+
+impl<T> Decoded<T> {
+    /// Pair a decoded message with its raw PDU
+    pub fn new(value: T, pdu: Vec<u8>) -> Self {
+        Self {value, pdu}
+    }
+
+    /// Get the decoded message
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// Get the raw PDU of the message
+    pub fn get_pdu(&self) -> &[u8] {
+        &self.pdu
+    }
+
+    /// Get back the decoded message, dropping the raw PDU
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Get back the decoded message and the raw PDU
+    pub fn into_parts(self) -> (T, Vec<u8>) {
+        (self.value, self.pdu)
+    }
+}
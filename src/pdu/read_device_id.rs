@@ -0,0 +1,288 @@
+use crate::Error;
+use crate::pdu::{check_size, DecodeMode, Function, Request as ReqT, Response as RspT, EXC_FUNCTION_CODE_FLAG, MAX_SIZE};
+use crate::fmt::HexDump;
+use core::convert::TryFrom;
+use core::fmt;
+use alloc::vec::Vec;
+
+/// Function code of the Encapsulated Interface Transport function
+const FUNCTION_CODE: u8 = 0x2b;
+/// MEI type of the Read Device Identification interface
+const MEI_TYPE: u8 = 0x0e;
+const MORE_FOLLOWS: u8 = 0xff;
+const HEADER_LEN: usize = 7;
+
+/// Category of device identification objects requested by Read Device Identification
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReadDeviceIdCode {
+    /// Stream of the mandatory objects: vendor name, product code and revision
+    Basic = 0x01,
+    /// Stream of the basic and the optional objects
+    Regular = 0x02,
+    /// Stream of the basic, the optional and the private objects
+    Extended = 0x03,
+    /// A single object
+    Specific = 0x04,
+}
+
+impl TryFrom<u8> for ReadDeviceIdCode {
+    type Error = Error;
+
+    fn try_from(v: u8) -> Result<Self, Error> {
+        match v {
+            0x01 => Ok(ReadDeviceIdCode::Basic),
+            0x02 => Ok(ReadDeviceIdCode::Regular),
+            0x03 => Ok(ReadDeviceIdCode::Extended),
+            0x04 => Ok(ReadDeviceIdCode::Specific),
+            _ => Err(Error::InvalidData),
+        }
+    }
+}
+
+/// Read Device Identification function request
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Request {
+    code: ReadDeviceIdCode,
+    object_id: u8,
+}
+
+impl Request {
+    /// Create a new Read Device Identification request
+    ///
+    /// A stream request starts at given object id, a [ReadDeviceIdCode::Specific] request reads just this object.
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::{ReadDeviceIdCode, ReadDeviceIdRequest, ReadDeviceIdResponse};
+    ///
+    /// let req = ReadDeviceIdRequest::new(ReadDeviceIdCode::Basic, ReadDeviceIdResponse::VENDOR_NAME);
+    /// ```
+    pub fn new(code: ReadDeviceIdCode, object_id: u8) -> Self {
+        Self {code, object_id}
+    }
+
+    /// Get category of the objects requested by the request
+    pub fn get_code(&self) -> ReadDeviceIdCode {
+        self.code
+    }
+
+    /// Get id of the first requested object
+    pub fn get_object_id(&self) -> u8 {
+        self.object_id
+    }
+}
+
+impl Function for Request {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        buf.extend_from_slice(&[FUNCTION_CODE, MEI_TYPE, self.code as u8, self.object_id]);
+        Ok(())
+    }
+
+    fn decode_with_mode(data: &[u8], _mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() != 4 {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FUNCTION_CODE || data[1] != MEI_TYPE {
+            return Err(Error::InvalidData);
+        }
+
+        Ok(Self {code: ReadDeviceIdCode::try_from(data[2])?, object_id: data[3]})
+    }
+}
+
+impl ReqT for Request {
+    type Rsp = Response;
+}
+
+/// Read Device Identification function response
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response {
+    code: ReadDeviceIdCode,
+    conformity_level: u8,
+    next_object_id: Option<u8>,
+    objects: Vec<(u8, Vec<u8>)>,
+}
+
+impl Response {
+    /// Id of the vendor name object
+    pub const VENDOR_NAME: u8 = 0x00;
+    /// Id of the product code object
+    pub const PRODUCT_CODE: u8 = 0x01;
+    /// Id of the major and minor revision object
+    pub const MAJOR_MINOR_REVISION: u8 = 0x02;
+
+    /// Create a new Read Device Identification response carrying given objects
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::{ReadDeviceIdCode, ReadDeviceIdResponse};
+    ///
+    /// let rsp = ReadDeviceIdResponse::new(ReadDeviceIdCode::Basic, 0x01, &[
+    ///     (ReadDeviceIdResponse::VENDOR_NAME, b"Company"),
+    ///     (ReadDeviceIdResponse::PRODUCT_CODE, b"Product"),
+    ///     (ReadDeviceIdResponse::MAJOR_MINOR_REVISION, b"V1.0"),
+    /// ]);
+    /// ```
+    pub fn new(code: ReadDeviceIdCode, conformity_level: u8, objects: &[(u8, &[u8])]) -> Self {
+        let objects = objects.iter().map(|(id, value)| (*id, value.to_vec())).collect();
+        Self {code, conformity_level, next_object_id: None, objects}
+    }
+
+    /// Announce that more objects follow, starting at given object id
+    pub fn with_next_object_id(mut self, next_object_id: u8) -> Self {
+        self.next_object_id = Some(next_object_id);
+        self
+    }
+
+    /// Get category of the objects carried by the response
+    pub fn get_code(&self) -> ReadDeviceIdCode {
+        self.code
+    }
+
+    /// Get identification conformity level of the device
+    pub fn get_conformity_level(&self) -> u8 {
+        self.conformity_level
+    }
+
+    /// Get id of the object to request next if the objects did not fit in this response
+    pub fn get_next_object_id(&self) -> Option<u8> {
+        self.next_object_id
+    }
+
+    /// Get ids and values of the objects from the response
+    pub fn get_objects(&self) -> &[(u8, Vec<u8>)] {
+        &self.objects
+    }
+
+    /// Get value of the object with given id, if the response carries it
+    pub fn get_object(&self, object_id: u8) -> Option<&[u8]> {
+        self.objects.iter().find(|(id, _)| *id == object_id).map(|(_, value)| value.as_slice())
+    }
+}
+
+impl Function for Response {
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let len = HEADER_LEN + self.objects.iter().map(|(_, value)| 2 + value.len()).sum::<usize>();
+        if len > MAX_SIZE || self.objects.len() > u8::MAX as usize || self.objects.iter().any(|(_, value)| value.len() > u8::MAX as usize) {
+            return Err(Error::InvalidValue);
+        }
+
+        buf.extend_from_slice(&[FUNCTION_CODE, MEI_TYPE, self.code as u8, self.conformity_level]);
+        match self.next_object_id {
+            Some(next_object_id) => buf.extend_from_slice(&[MORE_FOLLOWS, next_object_id]),
+            None => buf.extend_from_slice(&[0x00, 0x00]),
+        }
+        buf.push(self.objects.len() as u8);
+        for (id, value) in &self.objects {
+            buf.push(*id);
+            buf.push(value.len() as u8);
+            buf.extend_from_slice(value);
+        }
+
+        Ok(())
+    }
+
+    fn decode_with_mode(data: &[u8], mode: DecodeMode) -> Result<Self, Error> {
+        if data.len() < HEADER_LEN {
+            return Err(Error::InvalidDataLength);
+        }
+        if data[0] != FUNCTION_CODE || data[1] != MEI_TYPE {
+            return Err(Error::InvalidData);
+        }
+        check_size(data.len())?;
+
+        let code = ReadDeviceIdCode::try_from(data[2])?;
+        let next_object_id = match data[4] {
+            0x00 => None,
+            MORE_FOLLOWS => Some(data[5]),
+            _ if mode == DecodeMode::Lenient => Some(data[5]),
+            _ => return Err(Error::InvalidData),
+        };
+
+        let mut objects = Vec::new();
+        let mut pos = HEADER_LEN;
+        for _ in 0..data[6] {
+            if data.len() < pos + 2 || data.len() < pos + 2 + data[pos + 1] as usize {
+                return Err(Error::InvalidDataLength);
+            }
+            let end = pos + 2 + data[pos + 1] as usize;
+            objects.push((data[pos], data[pos + 2..end].to_vec()));
+            pos = end;
+        }
+        if mode == DecodeMode::Strict && pos != data.len() {
+            return Err(Error::InvalidDataLength);
+        }
+
+        Ok(Self {code, conformity_level: data[3], next_object_id, objects})
+    }
+}
+
+impl RspT for Response {
+    fn get_exc_function_code() -> u8 {
+        FUNCTION_CODE | EXC_FUNCTION_CODE_FLAG
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read Device Identification request: {:?}, object {}", self.code, self.object_id)
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Read Device Identification response: {:?}, conformity level 0x{:02x}", self.code, self.conformity_level)?;
+        for (id, value) in &self.objects {
+            write!(f, ", object {}: {}", id, HexDump::new(value))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_request() {
+        let pdu = Request::new(ReadDeviceIdCode::Basic, Response::VENDOR_NAME).encode().unwrap();
+        assert_eq!(pdu, vec![0x2b, 0x0e, 0x01, 0x00]);
+        assert_eq!(Request::decode(&pdu).unwrap(), Request::new(ReadDeviceIdCode::Basic, 0));
+    }
+
+    #[test]
+    fn test_decode_response() {
+        // Example from the Modbus application protocol specification
+        let pdu = vec![0x2b, 0x0e, 0x01, 0x01, 0x00, 0x00, 0x03,
+                       0x00, 0x16, b'C', b'o', b'm', b'p', b'a', b'n', b'y', b' ', b'i', b'd', b'e', b'n', b't',
+                       b'i', b'f', b'i', b'c', b'a', b't', b'i', b'o', b'n',
+                       0x01, 0x0f, b'P', b'r', b'o', b'd', b'u', b'c', b't', b' ', b'c', b'o', b'd', b'e', b' ', b'X', b'X',
+                       0x02, 0x05, b'V', b'2', b'.', b'1', b'1'];
+        let rsp = Response::decode_response(&pdu).unwrap();
+
+        assert_eq!(rsp.get_object(Response::VENDOR_NAME), Some(&b"Company identification"[..]));
+        assert_eq!(rsp.get_object(Response::MAJOR_MINOR_REVISION), Some(&b"V2.11"[..]));
+        assert_eq!(rsp.get_next_object_id(), None);
+        assert_eq!(rsp.encode().unwrap(), pdu);
+    }
+
+    #[test]
+    fn test_more_follows() {
+        let rsp = Response::new(ReadDeviceIdCode::Regular, 0x82, &[(0x00, b"Vendor")]).with_next_object_id(0x01);
+        let pdu = rsp.encode().unwrap();
+        assert_eq!(&pdu[..7], &[0x2b, 0x0e, 0x02, 0x82, 0xff, 0x01, 0x01]);
+        assert_eq!(Response::decode(&pdu).unwrap(), rsp);
+    }
+
+    #[test]
+    fn test_decode_truncated_response() {
+        let pdu = vec![0x2b, 0x0e, 0x01, 0x01, 0x00, 0x00, 0x02, 0x00, 0x01, b'A', 0x01, 0x02, b'B'];
+        for mode in [DecodeMode::Strict, DecodeMode::Lenient] {
+            assert!(matches!(Response::decode_with_mode(&pdu, mode), Err(Error::InvalidDataLength)));
+        }
+        assert!(matches!(Response::decode_response(&[0xab, 0x01]), Err(Error::ExceptionResponse(_))));
+    }
+}
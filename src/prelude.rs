@@ -0,0 +1,39 @@
+//! Convenient imports of the most commonly used items
+//!
+//! # Examples
+//! ```no_run
+//! use modbus::prelude::*;
+//! use std::net::{IpAddr, Ipv4Addr};
+//!
+//! let mut mb = Tcp::new();
+//! let dst = TcpDst::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10);
+//! let rsp = mb.write_req_read_rsp(&dst, &ReadHldRegRequest::new(0x0000, 2));
+//! ```
+
+pub use crate::{Error, ExceptionCode, FunctionCode, DecodeMode, RequestData, ResponseData, Bits, Target};
+pub use crate::{Function, Request, Response, Setter};
+
+pub use crate::{ReadCoilsRequest, ReadDscrInRequest, ReadHldRegRequest, ReadInRegRequest};
+pub use crate::{WriteSingleCoilRequest, WriteSingleRegRequest, WriteMultiRegRequest, MaskWriteRegRequest};
+pub use crate::{ReadFileRecordRequest, WriteFileRecordRequest, ReadDeviceIdRequest, ReadDeviceIdCode};
+pub use crate::{ReadCoilsResponse, ReadDscrInResponse, ReadHldRegResponse, ReadInRegResponse};
+pub use crate::{WriteSingleCoilResponse, WriteSingleRegResponse, WriteMultiRegResponse, MaskWriteRegResponse};
+pub use crate::{ReadFileRecordResponse, WriteFileRecordResponse, ReadDeviceIdResponse};
+
+pub use crate::Transport;
+#[cfg(feature = "std")]
+pub use crate::client::Client;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub use crate::AsyncTransport;
+#[cfg(feature = "serial")]
+pub use crate::rtu::Rtu;
+#[cfg(feature = "tcp")]
+pub use crate::rtu_over_tcp::RtuOverTcp;
+#[cfg(feature = "tcp")]
+pub use crate::tcp::{Dst as TcpDst, Tcp};
+#[cfg(feature = "tokio")]
+pub use crate::async_tcp::AsyncTcp;
+#[cfg(feature = "async-std")]
+pub use crate::async_std_tcp::AsyncTcp as AsyncStdTcp;
+#[cfg(feature = "embedded")]
+pub use crate::embedded_rtu::EmbeddedRtu;
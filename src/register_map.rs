@@ -0,0 +1,627 @@
+//! Register map declared in a configuration file
+//!
+//! A [RegisterMap] is a database of named tags. Each tag describes a value kept by a
+//! Modbus device: the unit id, the table and address, the data type, scaling of the raw
+//! value and whether the master can write it. Maps are loaded at runtime from JSON or TOML,
+//! so device definitions can live outside compiled code.
+//!
+//! # Examples
+//! ```
+//! use modbus::register_map::RegisterMap;
+//!
+//! let map = RegisterMap::from_toml(r#"
+//!     [[tags]]
+//!     name = "temperature"
+//!     unit = 1
+//!     table = "input_registers"
+//!     address = 0x0010
+//!     type = "i16"
+//!     scale = 0.1
+//!
+//!     [[tags]]
+//!     name = "setpoint"
+//!     unit = 1
+//!     table = "holding_registers"
+//!     address = 0x0100
+//!     type = "f32"
+//! "#).unwrap();
+//!
+//! let mut store = map.create_store(1);
+//! store.write_in_reg(0x0010, &[0xff38]).unwrap();
+//! assert_eq!(map.read_value(&store, "temperature").unwrap(), -20.0);
+//! ```
+
+pub use crate::address::Table;
+use crate::error::Error;
+use crate::export::Sample;
+use crate::server::DataStore;
+use crate::transport::Transport;
+use crate::{ReadCoilsRequest, ReadDscrInRequest, ReadHldRegRequest, ReadInRegRequest};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Type of a value kept in one or more registers
+///
+/// Values spanning two registers are stored with the most significant word first.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataType {
+    Bool,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+}
+
+impl DataType {
+    /// Get number of coils or registers used by a value of this type
+    pub fn get_size(&self) -> u16 {
+        match self {
+            DataType::Bool | DataType::U16 | DataType::I16 => 1,
+            DataType::U32 | DataType::I32 | DataType::F32 => 2,
+        }
+    }
+
+    fn decode(&self, registers: &[u16]) -> f64 {
+        let dword = || (registers[0] as u32) << 16 | registers[1] as u32;
+
+        match self {
+            DataType::Bool => (registers[0] != 0) as u8 as f64,
+            DataType::U16 => registers[0] as f64,
+            DataType::I16 => registers[0] as i16 as f64,
+            DataType::U32 => dword() as f64,
+            DataType::I32 => dword() as i32 as f64,
+            DataType::F32 => f32::from_bits(dword()) as f64,
+        }
+    }
+
+    fn encode(&self, value: f64) -> Result<Vec<u16>, Error> {
+        let split = |dword: u32| vec![(dword >> 16) as u16, dword as u16];
+        let check = |min: f64, max: f64| {
+            let value = value.round();
+            if value >= min && value <= max {
+                Ok(value)
+            } else {
+                Err(Error::InvalidValue)
+            }
+        };
+
+        match self {
+            DataType::Bool => Ok(vec![(value != 0.0) as u16]),
+            DataType::U16 => Ok(vec![check(u16::MIN as f64, u16::MAX as f64)? as u16]),
+            DataType::I16 => Ok(vec![check(i16::MIN as f64, i16::MAX as f64)? as i16 as u16]),
+            DataType::U32 => Ok(split(check(u32::MIN as f64, u32::MAX as f64)? as u32)),
+            DataType::I32 => Ok(split(check(i32::MIN as f64, i32::MAX as f64)? as i32 as u32)),
+            DataType::F32 => Ok(split((value as f32).to_bits())),
+        }
+    }
+}
+
+/// Access to a tag granted to the master
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    Read,
+    #[default]
+    ReadWrite,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// Named value kept by a Modbus device
+///
+/// The value seen by the application is `raw * scale + offset`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    name: String,
+    #[serde(rename = "unit")]
+    unit_id: u8,
+    table: Table,
+    address: u16,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    data_type: Option<DataType>,
+    #[serde(default = "default_scale")]
+    scale: f64,
+    #[serde(default)]
+    offset: f64,
+    #[serde(default)]
+    access: Access,
+}
+
+impl Tag {
+    /// Create a tag of the default type: `bool` in bit tables, `u16` in register tables
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::register_map::{Access, DataType, Table, Tag};
+    ///
+    /// let tag = Tag::new("flow", 3, Table::HoldingRegisters, 0x0020)
+    ///     .with_data_type(DataType::U32)
+    ///     .with_scale(0.01)
+    ///     .with_access(Access::Read);
+    /// assert_eq!(tag.get_range(), 0x0020..=0x0021);
+    /// ```
+    pub fn new(name: &str, unit_id: u8, table: Table, address: u16) -> Self {
+        Self {
+            name: name.to_string(),
+            unit_id,
+            table,
+            address,
+            data_type: None,
+            scale: default_scale(),
+            offset: 0.0,
+            access: Access::default(),
+        }
+    }
+
+    /// Set type of the value
+    pub fn with_data_type(mut self, data_type: DataType) -> Self {
+        self.data_type = Some(data_type);
+        self
+    }
+
+    /// Set factor the raw value is multiplied by
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set offset added to the scaled raw value
+    pub fn with_offset(mut self, offset: f64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Set access granted to the master
+    pub fn with_access(mut self, access: Access) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Get name of the tag
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get unit id of the device keeping the value
+    pub fn get_unit_id(&self) -> u8 {
+        self.unit_id
+    }
+
+    /// Get table keeping the value
+    pub fn get_table(&self) -> Table {
+        self.table
+    }
+
+    /// Get address of the first coil or register of the value
+    pub fn get_address(&self) -> u16 {
+        self.address
+    }
+
+    /// Get type of the value
+    pub fn get_data_type(&self) -> DataType {
+        match self.data_type {
+            Some(data_type) => data_type,
+            None if self.table.is_bit() => DataType::Bool,
+            None => DataType::U16,
+        }
+    }
+
+    /// Get factor the raw value is multiplied by
+    pub fn get_scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Get offset added to the scaled raw value
+    pub fn get_offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// Get access granted to the master
+    ///
+    /// Discrete inputs and input registers are always read-only for the master.
+    pub fn get_access(&self) -> Access {
+        match self.table {
+            Table::DiscreteInputs | Table::InputRegisters => Access::Read,
+            Table::Coils | Table::HoldingRegisters => self.access,
+        }
+    }
+
+    /// Get addresses of all coils or registers used by the value
+    ///
+    /// The range ends at the last address 0xffff for values that do not fit in the address
+    /// space. Such tags are rejected by [RegisterMap::add_tag].
+    pub fn get_range(&self) -> RangeInclusive<u16> {
+        self.address..=self.address.saturating_add(self.get_data_type().get_size() - 1)
+    }
+
+    /// Convert raw coils or registers of the tag to the scaled value
+    ///
+    /// Coils are passed as registers equal to 0 or 1.
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::register_map::{DataType, Table, Tag};
+    ///
+    /// let tag = Tag::new("energy", 1, Table::InputRegisters, 0x0000)
+    ///     .with_data_type(DataType::U32)
+    ///     .with_scale(0.5);
+    /// assert_eq!(tag.decode(&[0x0001, 0x0000]).unwrap(), 32768.0);
+    /// ```
+    pub fn decode(&self, raw: &[u16]) -> Result<f64, Error> {
+        let data_type = self.get_data_type();
+        if raw.len() != data_type.get_size() as usize {
+            return Err(Error::InvalidDataLength);
+        }
+
+        Ok(data_type.decode(raw) * self.scale + self.offset)
+    }
+
+    /// Convert the scaled value to raw coils or registers of the tag
+    ///
+    /// Values not representable by the type of the tag result in [Error::InvalidValue].
+    pub fn encode(&self, value: f64) -> Result<Vec<u16>, Error> {
+        self.get_data_type().encode((value - self.offset) / self.scale)
+    }
+
+    fn is_valid(&self) -> bool {
+        let data_type = self.get_data_type();
+
+        !self.name.is_empty() &&
+            self.table.is_bit() == (data_type == DataType::Bool) &&
+            self.address as u32 + data_type.get_size() as u32 <= 0x10000 &&
+            self.scale.is_finite() && self.scale != 0.0 && self.offset.is_finite()
+    }
+}
+
+#[derive(Deserialize)]
+struct Config {
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+/// Database of named tags
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RegisterMap {
+    tags: Vec<Tag>,
+}
+
+impl RegisterMap {
+    /// Create an empty register map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a register map from a JSON document with an array of `tags`
+    ///
+    /// # Examples
+    /// ```
+    /// let map = modbus::register_map::RegisterMap::from_json(r#"{"tags": [
+    ///     {"name": "pump", "unit": 2, "table": "coils", "address": 5},
+    ///     {"name": "pressure", "unit": 2, "table": "input_registers", "address": 0, "scale": 0.01, "offset": -1.0}
+    /// ]}"#).unwrap();
+    /// assert_eq!(map.get_tags().len(), 2);
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let config: Config = serde_json::from_str(json).map_err(|err| Error::ConfigError(err.to_string()))?;
+        Self::from_config(config)
+    }
+
+    /// Load a register map from a TOML document with an array of `tags` tables
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        let config: Config = toml::from_str(toml).map_err(|err| Error::ConfigError(err.to_string()))?;
+        Self::from_config(config)
+    }
+
+    /// Load a register map from a `.json` or `.toml` file
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+
+        match path.extension().and_then(OsStr::to_str) {
+            Some("json") => Self::from_json(&content),
+            Some("toml") => Self::from_toml(&content),
+            _ => Err(Error::ConfigError(format!("Unknown format of {}", path.display()))),
+        }
+    }
+
+    fn from_config(config: Config) -> Result<Self, Error> {
+        let mut map = Self::new();
+        for tag in config.tags {
+            let name = tag.name.clone();
+            map.add_tag(tag).map_err(|_| Error::ConfigError(format!("Invalid or duplicated tag \"{}\"", name)))?;
+        }
+        Ok(map)
+    }
+
+    /// Add a tag to the map
+    ///
+    /// Tags with duplicated names, with a type not matching the table, crossing the end of
+    /// the address space or with zero scale are rejected with [Error::InvalidValue].
+    pub fn add_tag(&mut self, tag: Tag) -> Result<(), Error> {
+        if !tag.is_valid() || self.get_tag(&tag.name).is_some() {
+            return Err(Error::InvalidValue);
+        }
+
+        self.tags.push(tag);
+        Ok(())
+    }
+
+    /// Get all tags in the order they were added
+    pub fn get_tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /// Get tag with given name
+    pub fn get_tag(&self, name: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// Get sorted unit ids of all devices in the map
+    pub fn get_unit_ids(&self) -> Vec<u8> {
+        let mut unit_ids: Vec<u8> = self.tags.iter().map(|tag| tag.unit_id).collect();
+        unit_ids.sort_unstable();
+        unit_ids.dedup();
+        unit_ids
+    }
+
+    /// Create a data store of the device with given unit id
+    ///
+    /// The store covers addresses of all tags of the unit. Tags which are read-only for
+    /// the master are marked as read-only in the store.
+    pub fn create_store(&self, unit_id: u8) -> DataStore {
+        let tags = || self.tags.iter().filter(move |tag| tag.unit_id == unit_id);
+        let mut store = DataStore::new();
+
+        for table in [Table::Coils, Table::DiscreteInputs, Table::HoldingRegisters, Table::InputRegisters] {
+            for range in merge_ranges(tags().filter(|tag| tag.table == table).map(Tag::get_range).collect()) {
+                store = match table {
+                    Table::Coils => store.with_coils(range),
+                    Table::DiscreteInputs => store.with_dscr_in(range),
+                    Table::HoldingRegisters => store.with_hld_reg(range),
+                    Table::InputRegisters => store.with_in_reg(range),
+                };
+            }
+        }
+
+        for tag in tags().filter(|tag| tag.get_access() == Access::Read) {
+            store = match tag.table {
+                Table::Coils => store.with_read_only_coils(tag.get_range()),
+                Table::HoldingRegisters => store.with_read_only_hld_reg(tag.get_range()),
+                Table::DiscreteInputs | Table::InputRegisters => store,
+            };
+        }
+
+        store
+    }
+
+    /// Read the scaled value of a tag from a data store
+    ///
+    /// Unknown tags result in [Error::InvalidValue].
+    pub fn read_value(&self, store: &DataStore, name: &str) -> Result<f64, Error> {
+        let tag = self.get_tag(name).ok_or(Error::InvalidValue)?;
+        let quantity = tag.get_data_type().get_size();
+
+        let raw = match tag.table {
+            Table::Coils => store.read_coils(tag.address, quantity).map(bits_to_raw),
+            Table::DiscreteInputs => store.read_dscr_in(tag.address, quantity).map(bits_to_raw),
+            Table::HoldingRegisters => store.read_hld_reg(tag.address, quantity),
+            Table::InputRegisters => store.read_in_reg(tag.address, quantity),
+        }.map_err(Error::ExceptionResponse)?;
+
+        tag.decode(&raw)
+    }
+
+    /// Write the scaled value of a tag to a data store
+    ///
+    /// The value is written regardless of the access granted to the master.
+    /// Unknown tags and values not representable by the type of the tag result in [Error::InvalidValue].
+    pub fn write_value(&self, store: &mut DataStore, name: &str, value: f64) -> Result<(), Error> {
+        let tag = self.get_tag(name).ok_or(Error::InvalidValue)?;
+        let raw = tag.encode(value)?;
+
+        match tag.table {
+            Table::Coils => store.write_coils(tag.address, &raw_to_bits(&raw)),
+            Table::DiscreteInputs => store.write_dscr_in(tag.address, &raw_to_bits(&raw)),
+            Table::HoldingRegisters => store.write_hld_reg(tag.address, &raw),
+            Table::InputRegisters => store.write_in_reg(tag.address, &raw),
+        }.map_err(Error::ExceptionResponse)
+    }
+
+    /// Read all tags from devices through given transport
+    ///
+    /// Each tag is read with a separate request. `dst` creates the destination of the device
+    /// with given unit id. Tags which cannot be read are reported in samples of bad quality.
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::mock::MockTransport;
+    /// use modbus::register_map::RegisterMap;
+    ///
+    /// let map = RegisterMap::from_json(r#"{"tags": [
+    ///     {"name": "pressure", "unit": 2, "table": "input_registers", "address": 0, "scale": 0.01}
+    /// ]}"#).unwrap();
+    ///
+    /// let mut mb = MockTransport::new();
+    /// mb.expect(&[0x04, 0x00, 0x00, 0x00, 0x01], &[0x04, 0x02, 0x01, 0x00]);
+    ///
+    /// let samples = map.poll(&mut mb, |unit_id| unit_id);
+    /// assert_eq!(samples[0].get_value(), Some(2.56));
+    /// ```
+    pub fn poll<T: Transport, F: FnMut(u8) -> T::Dst>(&self, transport: &mut T, mut dst: F) -> Vec<Sample> {
+        self.tags.iter()
+            .map(|tag| {
+                let raw = read_raw(transport, &dst(tag.unit_id), tag);
+                Sample::new(tag, SystemTime::now(), raw)
+            })
+            .collect()
+    }
+}
+
+fn read_raw<T: Transport>(transport: &mut T, dst: &T::Dst, tag: &Tag) -> Result<Vec<u16>, Error> {
+    let quantity = tag.get_data_type().get_size();
+
+    let raw = match tag.table {
+        Table::Coils => transport.write_req_read_rsp(dst, &ReadCoilsRequest::new(tag.address, quantity))?
+            .map(|rsp| rsp.get_coils().iter().take(quantity as usize).map(u16::from).collect()),
+        Table::DiscreteInputs => transport.write_req_read_rsp(dst, &ReadDscrInRequest::new(tag.address, quantity))?
+            .map(|rsp| rsp.get_inputs().iter().take(quantity as usize).map(u16::from).collect()),
+        Table::HoldingRegisters => transport.write_req_read_rsp(dst, &ReadHldRegRequest::new(tag.address, quantity))?
+            .map(|rsp| rsp.get_registers().to_vec()),
+        Table::InputRegisters => transport.write_req_read_rsp(dst, &ReadInRegRequest::new(tag.address, quantity))?
+            .map(|rsp| rsp.get_registers().to_vec()),
+    };
+
+    // Broadcast requests are left without response
+    raw.ok_or(Error::NoResponse)
+}
+
+fn bits_to_raw(bits: Vec<bool>) -> Vec<u16> {
+    bits.into_iter().map(u16::from).collect()
+}
+
+fn raw_to_bits(raw: &[u16]) -> Vec<bool> {
+    raw.iter().map(|value| *value != 0).collect()
+}
+
+fn merge_ranges(mut ranges: Vec<RangeInclusive<u16>>) -> Vec<RangeInclusive<u16>> {
+    ranges.sort_unstable_by_key(|range| *range.start());
+
+    let mut merged: Vec<RangeInclusive<u16>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if *range.start() as u32 <= *last.end() as u32 + 1 => {
+                if range.end() > last.end() {
+                    *last = *last.start()..=*range.end();
+                }
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::Quality;
+    use crate::mock::MockTransport;
+    use crate::pdu::ExceptionCode;
+
+    const JSON: &str = r#"{"tags": [
+        {"name": "running", "unit": 1, "table": "coils", "address": 0},
+        {"name": "alarm", "unit": 1, "table": "coils", "address": 1, "access": "read"},
+        {"name": "speed", "unit": 1, "table": "holding_registers", "address": 16, "type": "u32", "scale": 0.1},
+        {"name": "voltage", "unit": 2, "table": "input_registers", "address": 0, "type": "i16", "offset": 230}
+    ]}"#;
+
+    #[test]
+    fn test_from_json() {
+        let map = RegisterMap::from_json(JSON).unwrap();
+
+        assert_eq!(map.get_tags().len(), 4);
+        assert_eq!(map.get_unit_ids(), vec![1, 2]);
+
+        let speed = map.get_tag("speed").unwrap();
+        assert_eq!(speed.get_table(), Table::HoldingRegisters);
+        assert_eq!(speed.get_data_type(), DataType::U32);
+        assert_eq!(speed.get_access(), Access::ReadWrite);
+        assert_eq!(map.get_tag("running").unwrap().get_data_type(), DataType::Bool);
+        assert_eq!(map.get_tag("voltage").unwrap().get_access(), Access::Read);
+    }
+
+    #[test]
+    fn test_from_toml() {
+        let map = RegisterMap::from_toml(r#"
+            [[tags]]
+            name = "level"
+            unit = 4
+            table = "input_registers"
+            address = 3
+            type = "f32"
+        "#).unwrap();
+
+        assert_eq!(map.get_tags(), &[Tag::new("level", 4, Table::InputRegisters, 3).with_data_type(DataType::F32)]);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(matches!(RegisterMap::from_json("{\"tags\": [{\"name\": \"x\"}]}"), Err(Error::ConfigError(_))));
+        assert!(matches!(RegisterMap::from_toml("tags = 1"), Err(Error::ConfigError(_))));
+
+        let mut map = RegisterMap::new();
+        map.add_tag(Tag::new("a", 1, Table::Coils, 0)).unwrap();
+        assert!(map.add_tag(Tag::new("a", 1, Table::Coils, 1)).is_err());
+        assert!(map.add_tag(Tag::new("b", 1, Table::Coils, 1).with_data_type(DataType::U16)).is_err());
+        let tag = Tag::new("c", 1, Table::HoldingRegisters, 0xffff).with_data_type(DataType::U32);
+        assert_eq!(tag.get_range(), 0xffff..=0xffff);
+        assert!(map.add_tag(tag).is_err());
+        assert!(map.add_tag(Tag::new("d", 1, Table::HoldingRegisters, 0).with_scale(0.0)).is_err());
+    }
+
+    #[test]
+    fn test_create_store() {
+        let map = RegisterMap::from_json(JSON).unwrap();
+        let mut store = map.create_store(1);
+
+        assert_eq!(store.read_coils(0, 2), Ok(vec![false, false]));
+        assert!(store.read_coils(2, 1).is_err());
+        assert_eq!(store.read_hld_reg(16, 2), Ok(vec![0, 0]));
+        assert!(store.read_in_reg(0, 1).is_err());
+
+        assert_eq!(store.remote_write_coils(0, &[true]), Ok(()));
+        assert_eq!(store.remote_write_coils(1, &[true]), Err(ExceptionCode::IllegalDataAddress));
+    }
+
+    #[test]
+    fn test_read_write_value() {
+        let map = RegisterMap::from_json(JSON).unwrap();
+        let mut store = map.create_store(1);
+
+        map.write_value(&mut store, "speed", 7000.0).unwrap();
+        assert_eq!(store.read_hld_reg(16, 2), Ok(vec![0x0001, 0x1170]));
+        assert!((map.read_value(&store, "speed").unwrap() - 7000.0).abs() < 1e-9);
+
+        map.write_value(&mut store, "alarm", 1.0).unwrap();
+        assert_eq!(map.read_value(&store, "alarm").unwrap(), 1.0);
+
+        assert!(map.write_value(&mut store, "speed", -1.0).is_err());
+        assert!(map.read_value(&store, "unknown").is_err());
+        assert!(matches!(map.read_value(&store, "voltage"), Err(Error::ExceptionResponse(ExceptionCode::IllegalDataAddress))));
+
+        let mut store = map.create_store(2);
+        map.write_value(&mut store, "voltage", 220.0).unwrap();
+        assert_eq!(store.read_in_reg(0, 1), Ok(vec![0xfff6]));
+    }
+
+    #[test]
+    fn test_poll() {
+        let map = RegisterMap::from_json(JSON).unwrap();
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x01, 0x00, 0x00, 0x00, 0x01], &[0x01, 0x01, 0x01]);
+        mb.expect(&[0x01, 0x00, 0x01, 0x00, 0x01], &[0x81, 0x02]);
+        mb.expect(&[0x03, 0x00, 0x10, 0x00, 0x02], &[0x03, 0x04, 0x00, 0x01, 0x11, 0x70]);
+        mb.expect_no_response(&[0x04, 0x00, 0x00, 0x00, 0x01]);
+
+        let samples = map.poll(&mut mb, |unit_id| unit_id);
+        assert!(mb.is_complete());
+
+        let results: Vec<_> = samples.iter().map(|sample| (sample.get_tag(), sample.get_quality())).collect();
+        assert_eq!(results, vec![("running", Quality::Good), ("alarm", Quality::Exception),
+                                 ("speed", Quality::Good), ("voltage", Quality::NoResponse)]);
+        assert_eq!(samples[0].get_raw(), &[1]);
+        assert!((samples[2].get_value().unwrap() - 7000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_merge_ranges() {
+        assert_eq!(merge_ranges(vec![5..=6, 0..=1, 2..=2, 6..=9, 20..=21]), vec![0..=2, 5..=9, 20..=21]);
+    }
+}
@@ -0,0 +1,73 @@
+//! Pseudo-random numbers for simulations and fault injection
+//!
+//! The generator is small and reproducible from a seed, which makes tests using it
+//! deterministic. It is not suitable for cryptography.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Xorshift64* pseudo-random number generator
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a generator producing the same sequence for the same seed
+    pub(crate) fn new(seed: u64) -> Self {
+        // Scramble the seed, as the state must not be zero and similar seeds shall produce different sequences
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self {state: (state ^ (state >> 31)).max(1)}
+    }
+
+    /// Create a generator seeded from the current time
+    pub(crate) fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Get a number uniformly distributed in range `[0, n)`, or 0 if `n` is 0
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            self.next_u64() % n
+        }
+    }
+
+    /// Get a number uniformly distributed in range `[0, 1)`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Return true with given probability
+    pub(crate) fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.next_f64() < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproducible() {
+        let mut rng = Rng::new(7);
+        let values: Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+
+        let mut same = Rng::new(7);
+        assert!(values.iter().all(|value| *value == same.next_u64()));
+        assert_ne!(Rng::new(8).next_u64(), values[0]);
+        assert!((0..1000).all(|_| rng.below(3) < 3));
+        assert!((0..1000).all(|_| (0.0..1.0).contains(&rng.next_f64())));
+        assert!(!rng.chance(0.0) && rng.chance(1.0));
+    }
+}
@@ -0,0 +1,226 @@
+//! Access control of requests served by a [Server](super::Server)
+//!
+//! An [Acl] restricts the masters allowed to send requests by their IP addresses, the unit ids
+//! they may address and whether they may write. It is checked before a request is executed,
+//! and rejected requests are answered with a configurable exception code.
+
+use crate::error::Error;
+use crate::pdu::ExceptionCode;
+use std::net::{IpAddr, SocketAddr};
+
+/// Function codes of requests modifying the data of a slave
+const WRITE_FUNCTION_CODES: [u8; 7] = [0x05, 0x06, 0x0F, 0x10, 0x15, 0x16, 0x17];
+
+/// Range of IP addresses sharing a common prefix
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Subnet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Subnet {
+    /// Create a subnet of addresses sharing the first `prefix_len` bits with given address
+    ///
+    /// Returns [Error::InvalidValue] if the prefix is longer than the address.
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::server::acl::Subnet;
+    /// use std::net::{IpAddr, Ipv4Addr};
+    ///
+    /// let subnet = Subnet::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 0)), 24).unwrap();
+    /// assert!(subnet.contains(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 17))));
+    /// assert!(!subnet.contains(IpAddr::V4(Ipv4Addr::new(192, 168, 2, 17))));
+    /// ```
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, Error> {
+        let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max_prefix_len {
+            return Err(Error::InvalidValue);
+        }
+
+        Ok(Self {addr, prefix_len})
+    }
+
+    /// Create a subnet containing only given address
+    pub fn host(addr: IpAddr) -> Self {
+        Self {addr, prefix_len: if addr.is_ipv4() { 32 } else { 128 }}
+    }
+
+    /// Check if given address belongs to the subnet
+    ///
+    /// IPv4 addresses mapped to IPv6 are treated as IPv4 addresses.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, addr.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Access control list of a [Server](super::Server)
+///
+/// By default requests from all masters to all unit ids are allowed.
+///
+/// # Examples
+/// ```
+/// use modbus::ExceptionCode;
+/// use modbus::server::acl::{Acl, Subnet};
+/// use modbus::server::{DataStore, Server};
+/// use std::net::{IpAddr, Ipv4Addr};
+///
+/// let acl = Acl::new()
+///     .allow_subnet(Subnet::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8).unwrap())
+///     .read_only(Subnet::new(IpAddr::V4(Ipv4Addr::new(10, 1, 0, 0)), 16).unwrap())
+///     .allow_unit_id(10)
+///     .with_exception(ExceptionCode::GatewayPathUnavailable);
+///
+/// let mut server = Server::new(modbus::mock::MockTransport::new(), DataStore::new().with_hld_reg(0x0000..=0x00ff));
+/// server.set_acl(Some(acl));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Acl {
+    subnets: Option<Vec<Subnet>>,
+    unit_ids: Option<Vec<u8>>,
+    read_only: Vec<Subnet>,
+    exc_code: ExceptionCode,
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Acl {
+    /// Create an access control list allowing all requests
+    pub fn new() -> Self {
+        Self {subnets: None, unit_ids: None, read_only: Vec::new(), exc_code: ExceptionCode::IllegalFunction}
+    }
+
+    /// Allow requests from masters in given subnet
+    ///
+    /// Once a subnet is allowed, requests from masters outside of all the allowed subnets are
+    /// rejected, including requests read through transports not providing peer addresses.
+    pub fn allow_subnet(mut self, subnet: Subnet) -> Self {
+        self.subnets.get_or_insert_with(Vec::new).push(subnet);
+        self
+    }
+
+    /// Allow requests addressed to given unit id
+    ///
+    /// Once a unit id is allowed, requests addressed to other unit ids are rejected.
+    pub fn allow_unit_id(mut self, unit_id: u8) -> Self {
+        self.unit_ids.get_or_insert_with(Vec::new).push(unit_id);
+        self
+    }
+
+    /// Reject write requests from masters in given subnet
+    pub fn read_only(mut self, subnet: Subnet) -> Self {
+        self.read_only.push(subnet);
+        self
+    }
+
+    /// Set exception code rejected requests are answered with, [ExceptionCode::IllegalFunction] by default
+    pub fn with_exception(mut self, exc_code: ExceptionCode) -> Self {
+        self.exc_code = exc_code;
+        self
+    }
+
+    /// Get exception code rejected requests are answered with
+    pub fn get_exception(&self) -> ExceptionCode {
+        self.exc_code
+    }
+
+    /// Check if a request with given function code from given master to given unit id is allowed
+    pub fn is_allowed(&self, peer_addr: Option<SocketAddr>, unit_id: u8, function_code: u8) -> bool {
+        let peer_ip = peer_addr.map(|addr| addr.ip());
+
+        if let Some(subnets) = &self.subnets {
+            if !peer_ip.is_some_and(|ip| subnets.iter().any(|subnet| subnet.contains(ip))) {
+                return false;
+            }
+        }
+        if let Some(unit_ids) = &self.unit_ids {
+            if !unit_ids.contains(&unit_id) {
+                return false;
+            }
+        }
+        if WRITE_FUNCTION_CODES.contains(&function_code) {
+            if let Some(ip) = peer_ip {
+                return !self.read_only.iter().any(|subnet| subnet.contains(ip));
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn peer(a: u8, b: u8, c: u8, d: u8) -> Option<SocketAddr> {
+        Some(SocketAddr::from(([a, b, c, d], 50000)))
+    }
+
+    #[test]
+    fn test_subnet_contains() {
+        let subnet = Subnet::new(IpAddr::V4(Ipv4Addr::new(10, 1, 0, 0)), 16).unwrap();
+
+        assert!(subnet.contains(IpAddr::V4(Ipv4Addr::new(10, 1, 255, 1))));
+        assert!(!subnet.contains(IpAddr::V4(Ipv4Addr::new(10, 2, 0, 1))));
+        assert!(subnet.contains(IpAddr::V6(Ipv4Addr::new(10, 1, 0, 1).to_ipv6_mapped())));
+        assert!(!subnet.contains(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    }
+
+    #[test]
+    fn test_subnet_prefix_len() {
+        let any = Subnet::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0).unwrap();
+        assert!(any.contains(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1))));
+
+        let host = Subnet::host(IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert!(host.contains(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert!(!host.contains(IpAddr::V6(Ipv6Addr::UNSPECIFIED)));
+
+        assert!(matches!(Subnet::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 33), Err(Error::InvalidValue)));
+    }
+
+    #[test]
+    fn test_default_allows_all() {
+        let acl = Acl::new();
+
+        assert!(acl.is_allowed(None, 1, 0x06));
+        assert!(acl.is_allowed(peer(192, 168, 0, 1), 255, 0x10));
+    }
+
+    #[test]
+    fn test_allowed_subnets_and_unit_ids() {
+        let acl = Acl::new()
+            .allow_subnet(Subnet::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8).unwrap())
+            .allow_unit_id(1)
+            .allow_unit_id(2);
+
+        assert!(acl.is_allowed(peer(10, 0, 0, 1), 2, 0x03));
+        assert!(!acl.is_allowed(peer(10, 0, 0, 1), 3, 0x03));
+        assert!(!acl.is_allowed(peer(11, 0, 0, 1), 1, 0x03));
+        assert!(!acl.is_allowed(None, 1, 0x03));
+    }
+
+    #[test]
+    fn test_read_only_peers() {
+        let acl = Acl::new().read_only(Subnet::host(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+
+        assert!(acl.is_allowed(peer(10, 0, 0, 1), 1, 0x03));
+        assert!(!acl.is_allowed(peer(10, 0, 0, 1), 1, 0x06));
+        assert!(!acl.is_allowed(peer(10, 0, 0, 1), 1, 0x16));
+        assert!(acl.is_allowed(peer(10, 0, 0, 2), 1, 0x06));
+    }
+}
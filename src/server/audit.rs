@@ -0,0 +1,219 @@
+//! Audit log of write requests served by a [Server](super::Server)
+//!
+//! Every write request, whether executed or rejected with an exception response, is described
+//! by a [WriteRecord] passed to an [AuditSink]. Sinks are implemented for closures, for senders
+//! of channels and for [AuditWriter] appending one line per record to a file or another writer.
+//! Write requests which cannot be decoded are answered with exception responses without
+//! being recorded.
+//!
+//! # Examples
+//! ```no_run
+//! use modbus::server::audit::AuditWriter;
+//! use modbus::server::{DataStore, Server};
+//! use std::fs::OpenOptions;
+//!
+//! let log = OpenOptions::new().create(true).append(true).open("writes.log").unwrap();
+//! let mut server = Server::new(modbus::tcp::Tcp::new(), DataStore::new().with_hld_reg(0x0000..=0x00ff));
+//! server.set_audit_sink(Some(Box::new(AuditWriter::new(log))));
+//! server.start(10).unwrap();
+//! server.serve_forever().unwrap();
+//! ```
+
+use crate::pdu::{decode_req, ExceptionCode, FunctionCode, RequestData, EXC_FUNCTION_CODE_FLAG};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Write request received by a server, with its outcome
+#[derive(Clone, Debug, PartialEq)]
+pub struct WriteRecord {
+    timestamp: SystemTime,
+    peer_addr: Option<SocketAddr>,
+    unit_id: u8,
+    function_code: FunctionCode,
+    address: u16,
+    values: Vec<u16>,
+    result: Result<(), ExceptionCode>,
+}
+
+impl WriteRecord {
+    /// Describe a transaction, `None` if the request is not a write request or cannot be decoded
+    pub(super) fn from_transaction(peer_addr: Option<SocketAddr>, unit_id: u8, req_pdu: &[u8], rsp_pdu: &[u8])
+        -> Option<Self>
+    {
+        let (function_code, address, values) = match decode_req(req_pdu).ok()? {
+            RequestData::WriteSingleCoil(req) =>
+                (FunctionCode::WriteSingleCoil, req.get_address(), vec![u16::from(req.get_value())]),
+            RequestData::WriteSingleReg(req) => (FunctionCode::WriteSingleReg, req.get_address(), vec![req.get_value()]),
+            RequestData::WriteMultiReg(req) => (FunctionCode::WriteMultiReg, req.get_address(), req.get_values().to_vec()),
+            RequestData::MaskWriteReg(req) =>
+                (FunctionCode::MaskWriteReg, req.get_address(), vec![req.get_and_mask(), req.get_or_mask()]),
+            _ => return None,
+        };
+
+        let result = match rsp_pdu.first() {
+            Some(code) if code & EXC_FUNCTION_CODE_FLAG != 0 => Err(rsp_pdu.get(1)
+                .and_then(|exc_code| ExceptionCode::try_from(*exc_code).ok())
+                .unwrap_or(ExceptionCode::ServerDeviceFailure)),
+            _ => Ok(()),
+        };
+
+        Some(Self {timestamp: SystemTime::now(), peer_addr, unit_id, function_code, address, values, result})
+    }
+
+    /// Get moment the request was served
+    pub fn get_timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// Get address of the master which sent the request, `None` if the transport does not provide it
+    pub fn get_peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Get unit id the request was addressed to
+    pub fn get_unit_id(&self) -> u8 {
+        self.unit_id
+    }
+
+    /// Get function code of the request
+    pub fn get_function_code(&self) -> FunctionCode {
+        self.function_code
+    }
+
+    /// Get address of the first written coil or register
+    pub fn get_address(&self) -> u16 {
+        self.address
+    }
+
+    /// Get written values
+    ///
+    /// Coils are passed as registers equal to 0 or 1. For [FunctionCode::MaskWriteReg] these are
+    /// the AND and the OR masks.
+    pub fn get_values(&self) -> &[u16] {
+        &self.values
+    }
+
+    /// Get outcome of the request, with the exception code of a rejected one
+    pub fn get_result(&self) -> Result<(), ExceptionCode> {
+        self.result
+    }
+}
+
+impl fmt::Display for WriteRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let timestamp = self.timestamp.duration_since(UNIX_EPOCH).map_or(0, |t| t.as_millis());
+        write!(f, "{} ", timestamp)?;
+        match self.peer_addr {
+            Some(peer_addr) => write!(f, "{}", peer_addr)?,
+            None => f.write_str("-")?,
+        }
+        write!(f, " unit {}: {} 0x{:04X} {:04X?} ", self.unit_id, self.function_code, self.address, self.values)?;
+        match self.result {
+            Ok(()) => f.write_str("ok"),
+            Err(exc_code) => write!(f, "rejected {}", exc_code),
+        }
+    }
+}
+
+/// Destination of the [records](WriteRecord) of an audit log
+///
+/// It is implemented for closures taking a record.
+pub trait AuditSink: Send {
+    /// Store given record
+    fn record(&mut self, record: &WriteRecord);
+}
+
+impl<F> AuditSink for F where F: FnMut(&WriteRecord) + Send {
+    fn record(&mut self, record: &WriteRecord) {
+        self(record)
+    }
+}
+
+/// Records are sent to the receiver, and dropped if the receiver is gone
+impl AuditSink for mpsc::Sender<WriteRecord> {
+    fn record(&mut self, record: &WriteRecord) {
+        let _ = self.send(record.clone());
+    }
+}
+
+/// Sink writing each record as a line of text, flushed immediately
+///
+/// Failures of the writer are reported by tracing, as the request has already been served.
+#[derive(Debug)]
+pub struct AuditWriter<W: Write + Send> {
+    writer: W,
+}
+
+impl<W: Write + Send> AuditWriter<W> {
+    /// Create a sink writing records to given writer
+    pub fn new(writer: W) -> Self {
+        Self {writer}
+    }
+
+    /// Get the writer back
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> AuditSink for AuditWriter<W> {
+    fn record(&mut self, record: &WriteRecord) {
+        if let Err(_err) = writeln!(self.writer, "{}", record).and_then(|_| self.writer.flush()) {
+            #[cfg(feature = "tracing")]
+            tracing::error!(error = %_err, "Failed to write audit record");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn create_record(req_pdu: &[u8], rsp_pdu: &[u8]) -> Option<WriteRecord> {
+        WriteRecord::from_transaction(Some(SocketAddr::from(([10, 0, 0, 1], 50000))), 1, req_pdu, rsp_pdu)
+    }
+
+    #[test]
+    fn test_write_records() {
+        let record = create_record(&[0x05, 0x00, 0x10, 0xff, 0x00], &[0x05, 0x00, 0x10, 0xff, 0x00]).unwrap();
+        assert_eq!(record.get_function_code(), FunctionCode::WriteSingleCoil);
+        assert_eq!((record.get_address(), record.get_values()), (0x0010, &[1][..]));
+        assert_eq!(record.get_result(), Ok(()));
+
+        let record = create_record(&[0x16, 0x00, 0x04, 0x00, 0xf2, 0x00, 0x25], &[0x96, 0x02]).unwrap();
+        assert_eq!(record.get_function_code(), FunctionCode::MaskWriteReg);
+        assert_eq!(record.get_values(), &[0x00f2, 0x0025]);
+        assert_eq!(record.get_result(), Err(ExceptionCode::IllegalDataAddress));
+    }
+
+    #[test]
+    fn test_not_recorded() {
+        assert!(create_record(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x00, 0x00]).is_none());
+        assert!(create_record(&[0x06, 0x00], &[0x86, 0x03]).is_none());
+    }
+
+    #[test]
+    fn test_audit_writer() {
+        let mut record = create_record(&[0x06, 0x01, 0x00, 0x12, 0x34], &[0x86, 0x01]).unwrap();
+        record.timestamp = UNIX_EPOCH + Duration::from_millis(1500);
+
+        let mut sink = AuditWriter::new(Vec::new());
+        sink.record(&record);
+        assert_eq!(String::from_utf8(sink.into_inner()).unwrap(),
+                   "1500 10.0.0.1:50000 unit 1: Write Single Register 0x0100 [1234] rejected [exc] Illegal function\n");
+    }
+
+    #[test]
+    fn test_channel_sink() {
+        let (mut sink, rx) = mpsc::channel();
+        let record = create_record(&[0x06, 0x01, 0x00, 0x12, 0x34], &[0x06, 0x01, 0x00, 0x12, 0x34]).unwrap();
+
+        sink.record(&record);
+        assert_eq!(rx.try_recv().unwrap(), record);
+    }
+}
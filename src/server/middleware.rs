@@ -0,0 +1,51 @@
+//! Request processing steps wrapped around the execution of requests by a [Server](super::Server)
+//!
+//! Each middleware receives a decoded request and the rest of the chain. It may inspect or
+//! modify the request, reject it with an exception code without calling the rest of the chain,
+//! or call the rest of the chain and inspect or modify the response before it is encoded.
+//! The first added middleware is the outermost one, so it sees requests first and responses last.
+
+use crate::pdu::{ExceptionCode, RequestData, ResponseData};
+
+/// Rest of the middleware chain, ending with the execution of the request on the data store
+pub type Next<'a> = &'a mut dyn FnMut(RequestData) -> Result<ResponseData, ExceptionCode>;
+
+/// Request processing step of a [Server](super::Server)
+///
+/// It is implemented for closures taking the unit id, the request and the rest of the chain.
+///
+/// # Examples
+/// ```
+/// use modbus::{ExceptionCode, RequestData};
+/// use modbus::server::{DataStore, Server};
+/// use modbus::server::middleware::Next;
+///
+/// let mut server = Server::new(modbus::mock::MockTransport::new(), DataStore::new().with_hld_reg(0x0000..=0x00ff));
+///
+/// // Reject writes to the first register
+/// server.add_middleware(Box::new(|_unit_id: u8, req: RequestData, next: Next| match &req {
+///     RequestData::WriteSingleReg(req) if req.get_address() == 0x0000 => Err(ExceptionCode::IllegalDataAddress),
+///     _ => next(req),
+/// }));
+/// ```
+pub trait Middleware: Send {
+    /// Process a request addressed to given unit id, calling `next` to continue the chain
+    fn call(&mut self, unit_id: u8, req: RequestData, next: Next) -> Result<ResponseData, ExceptionCode>;
+}
+
+impl<F> Middleware for F where F: FnMut(u8, RequestData, Next) -> Result<ResponseData, ExceptionCode> + Send {
+    fn call(&mut self, unit_id: u8, req: RequestData, next: Next) -> Result<ResponseData, ExceptionCode> {
+        self(unit_id, req, next)
+    }
+}
+
+/// Run a request through given middlewares, executing it with `execute` at the end of the chain
+pub(super) fn run<E>(middlewares: &mut [Box<dyn Middleware>], unit_id: u8, req: RequestData, execute: &mut E)
+    -> Result<ResponseData, ExceptionCode>
+    where E: FnMut(RequestData) -> Result<ResponseData, ExceptionCode>
+{
+    match middlewares.split_first_mut() {
+        Some((middleware, rest)) => middleware.call(unit_id, req, &mut |req| run(&mut *rest, unit_id, req, &mut *execute)),
+        None => execute(req),
+    }
+}
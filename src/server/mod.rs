@@ -0,0 +1,746 @@
+//! Modbus slave framework
+//!
+//! The [Server] reads requests through a [Transport], executes them on a
+//! [DataStore] and writes back responses. Requests which cannot be served are
+//! answered with exception responses.
+
+pub mod acl;
+pub mod audit;
+pub mod middleware;
+pub mod store;
+
+pub use acl::Acl;
+pub use audit::{AuditSink, WriteRecord};
+pub use middleware::Middleware;
+pub use store::{DataStore, Diff, Snapshot, WriteHook};
+
+use crate::cancel::CancelToken;
+use crate::error::Error;
+use crate::pdu::{decode_req, encode_exc_rsp, is_range_valid, ExceptionCode, FunctionCode, RequestData, ResponseData};
+use crate::transport::Transport;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use crate::{ReadCoilsResponse, ReadDscrInResponse, ReadHldRegResponse, ReadInRegResponse, WriteMultiRegResponse};
+
+const MAX_READ_BITS: u16 = 2000;
+const MAX_READ_REGS: u16 = 125;
+
+/// Function codes which a [Server] is able to execute on a [DataStore]
+pub const SERVED_FUNCTIONS: [FunctionCode; 8] = [
+    FunctionCode::ReadCoils,
+    FunctionCode::ReadDscrIn,
+    FunctionCode::ReadHldReg,
+    FunctionCode::ReadInReg,
+    FunctionCode::WriteSingleCoil,
+    FunctionCode::WriteSingleReg,
+    FunctionCode::WriteMultiReg,
+    FunctionCode::MaskWriteReg,
+];
+
+/// Filtering of unit ids of requests served by a [Server]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum UnitIdFilter {
+    /// Serve only requests addressed to the started unit id or one of the added units
+    #[default]
+    Strict,
+    /// Serve requests addressed to any unit id
+    ///
+    /// Requests addressed to unit ids without their own data store are served from the
+    /// data store passed to [Server::new]. It is common for devices connected directly to
+    /// a TCP/IP network, which are addressed with arbitrary unit ids.
+    Any,
+}
+
+/// Modbus slave serving requests from a [DataStore]
+///
+/// A single server can serve multiple unit ids, each of them with its own data store.
+pub struct Server<T: Transport> {
+    transport: T,
+    unit_id: u8,
+    store: DataStore,
+    units: Vec<(u8, DataStore)>,
+    unit_id_filter: UnitIdFilter,
+    functions: Vec<FunctionCode>,
+    middlewares: Vec<Box<dyn Middleware>>,
+    acl: Option<Acl>,
+    audit_sink: Option<Box<dyn AuditSink>>,
+    started: bool,
+
+    autosave: Option<Autosave>,
+}
+
+struct Autosave {
+    path: PathBuf,
+    interval: Duration,
+    last_save: Instant,
+}
+
+impl<T: Transport> Server<T> {
+    /// Create a new server using given transport and data store
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::server::{DataStore, Server};
+    ///
+    /// let store = DataStore::new().with_hld_reg(0x0000..=0x00ff);
+    /// let mut server = Server::new(modbus::tcp::Tcp::new(), store);
+    /// server.start(10).unwrap();
+    ///
+    /// loop {
+    ///     server.process_req().unwrap();
+    /// }
+    /// ```
+    pub fn new(transport: T, store: DataStore) -> Self {
+        Self {transport, unit_id: 0, store, units: Vec::new(), unit_id_filter: UnitIdFilter::default(),
+              functions: SERVED_FUNCTIONS.to_vec(), middlewares: Vec::new(), acl: None, audit_sink: None,
+              started: false, autosave: None}
+    }
+
+    /// Periodically save the data store passed to [Server::new] to given file
+    ///
+    /// The data store is saved after processing a request if at least `interval`
+    /// elapsed since the previous save.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::server::{DataStore, Server};
+    /// use std::time::Duration;
+    ///
+    /// let mut store = DataStore::new().with_hld_reg(0x0000..=0x00ff);
+    /// store.load_from_file("registers.bin").ok();
+    ///
+    /// let mut server = Server::new(modbus::tcp::Tcp::new(), store);
+    /// server.set_autosave("registers.bin", Duration::from_secs(10));
+    /// server.start(10).unwrap();
+    /// server.serve_forever().unwrap();
+    /// ```
+    pub fn set_autosave<P: Into<PathBuf>>(&mut self, path: P, interval: Duration) {
+        self.autosave = Some(Autosave {path: path.into(), interval, last_save: Instant::now()});
+    }
+
+    /// Add another unit id served by this server with its own data store
+    ///
+    /// Units shall be added before the server is started.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # #[cfg(feature = "serial")] {
+    /// use modbus::server::{DataStore, Server};
+    ///
+    /// let mut server = Server::new(modbus::rtu::Rtu::conn("/dev/ttyUSB0", &Default::default()).unwrap(),
+    ///                              DataStore::new().with_hld_reg(0x0000..=0x00ff));
+    /// server.add_unit(11, DataStore::new().with_coils(0x0000..=0x000f)).unwrap();
+    /// server.add_unit(12, DataStore::new().with_in_reg(0x0000..=0x000f)).unwrap();
+    /// server.start(10).unwrap();
+    /// # }
+    /// ```
+    pub fn add_unit(&mut self, unit_id: u8, store: DataStore) -> Result<(), Error> {
+        if self.started || self.units.iter().any(|(id, _)| *id == unit_id) {
+            return Err(Error::InvalidValue);
+        }
+
+        self.units.push((unit_id, store));
+        Ok(())
+    }
+
+    /// Select which unit ids are served, [UnitIdFilter::Strict] by default
+    ///
+    /// The filter shall be selected before the server is started. Accepting any unit id
+    /// requires a transport accepting all unit ids in the slave mode, like the TCP/IP one.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::server::{DataStore, Server, UnitIdFilter};
+    ///
+    /// let mut server = Server::new(modbus::tcp::Tcp::new(), DataStore::new().with_hld_reg(0x0000..=0x00ff));
+    /// server.add_unit(2, DataStore::new().with_in_reg(0x0000..=0x000f)).unwrap();
+    /// server.set_unit_id_filter(UnitIdFilter::Any).unwrap();
+    /// server.start(1).unwrap();
+    /// ```
+    pub fn set_unit_id_filter(&mut self, filter: UnitIdFilter) -> Result<(), Error> {
+        if self.started {
+            return Err(Error::InvalidValue);
+        }
+
+        self.unit_id_filter = filter;
+        Ok(())
+    }
+
+    /// Declare which function codes are supported, all [SERVED_FUNCTIONS] by default
+    ///
+    /// Requests with any other function code are answered with [ExceptionCode::IllegalFunction].
+    /// The functions shall be declared before the server is started, and only function codes
+    /// from [SERVED_FUNCTIONS] can be declared.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::FunctionCode;
+    /// use modbus::server::{DataStore, Server};
+    ///
+    /// let mut server = Server::new(modbus::tcp::Tcp::new(), DataStore::new().with_hld_reg(0x0000..=0x00ff));
+    /// server.set_supported_functions(&[FunctionCode::ReadHldReg]).unwrap();
+    /// server.start(1).unwrap();
+    /// ```
+    pub fn set_supported_functions(&mut self, functions: &[FunctionCode]) -> Result<(), Error> {
+        if self.started || functions.iter().any(|function| !SERVED_FUNCTIONS.contains(function)) {
+            return Err(Error::InvalidValue);
+        }
+
+        self.functions = functions.to_vec();
+        Ok(())
+    }
+
+    /// Get function codes supported by the server
+    pub fn get_supported_functions(&self) -> &[FunctionCode] {
+        &self.functions
+    }
+
+    /// Add a middleware processing decoded requests before they are executed and responses before they are encoded
+    ///
+    /// Middlewares are chained in the order they are added, see [middleware].
+    /// Requests with unsupported function codes and requests which cannot be decoded are
+    /// answered with exception responses before reaching the middlewares.
+    pub fn add_middleware(&mut self, middleware: Box<dyn Middleware>) {
+        self.middlewares.push(middleware);
+    }
+
+    /// Set access control list checked before requests are executed, or remove it with `None`
+    ///
+    /// Rejected requests are answered with the [exception code of the list](Acl::with_exception)
+    /// and do not reach the middlewares. See [acl].
+    pub fn set_acl(&mut self, acl: Option<Acl>) {
+        self.acl = acl;
+    }
+
+    /// Set sink recording all write requests with their outcome, or remove it with `None`
+    ///
+    /// See [audit].
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::server::{DataStore, Server, WriteRecord};
+    ///
+    /// let mut server = Server::new(modbus::mock::MockTransport::new(), DataStore::new().with_hld_reg(0x0000..=0x00ff));
+    /// server.set_audit_sink(Some(Box::new(|record: &WriteRecord| println!("{}", record))));
+    /// ```
+    pub fn set_audit_sink(&mut self, audit_sink: Option<Box<dyn AuditSink>>) {
+        self.audit_sink = audit_sink;
+    }
+
+    /// Start serving requests addressed to given unit id and all added units
+    ///
+    /// The data store passed to [Server::new] serves requests addressed to `unit_id`.
+    pub fn start(&mut self, unit_id: u8) -> Result<(), Error> {
+        if self.units.iter().any(|(id, _)| *id == unit_id) {
+            return Err(Error::InvalidValue);
+        }
+
+        let unit_ids = match self.unit_id_filter {
+            UnitIdFilter::Strict => {
+                let mut unit_ids = vec![unit_id];
+                unit_ids.extend(self.units.iter().map(|(id, _)| *id));
+                unit_ids
+            }
+            UnitIdFilter::Any => (0..=u8::MAX).collect(),
+        };
+
+        self.transport.start_slave_units(&unit_ids)?;
+        self.unit_id = unit_id;
+        self.started = true;
+        Ok(())
+    }
+
+    /// Get the data store of the server
+    pub fn get_store(&self) -> &DataStore {
+        &self.store
+    }
+
+    /// Get the mutable data store of the server
+    pub fn get_store_mut(&mut self) -> &mut DataStore {
+        &mut self.store
+    }
+
+    /// Get the data store serving given unit id
+    pub fn get_unit_store(&self, unit_id: u8) -> Option<&DataStore> {
+        if self.started && unit_id == self.unit_id {
+            return Some(&self.store);
+        }
+
+        self.units.iter().find(|(id, _)| *id == unit_id).map(|(_, store)| store)
+    }
+
+    /// Get the mutable data store serving given unit id
+    pub fn get_unit_store_mut(&mut self, unit_id: u8) -> Option<&mut DataStore> {
+        if self.started && unit_id == self.unit_id {
+            return Some(&mut self.store);
+        }
+
+        self.units.iter_mut().find(|(id, _)| *id == unit_id).map(|(_, store)| store)
+    }
+
+    /// Read a single request, execute it and write the response.
+    ///
+    /// Requests with unsupported function codes are answered with
+    /// [ExceptionCode::IllegalFunction], and requests accessing addresses out of
+    /// the configured ranges with [ExceptionCode::IllegalDataAddress].
+    pub fn process_req(&mut self) -> Result<(), Error> {
+        let (req_pdu, mut stream) = self.transport.read_req_pdu()?;
+        let rsp_pdu = self.execute_peer_req(T::get_peer_addr(&stream), T::get_unit_id(&stream), &req_pdu)?;
+        #[cfg(feature = "metrics")]
+        crate::telemetry::record_served(&rsp_pdu);
+        self.transport.write_rsp_pdu(&mut stream, &rsp_pdu)?;
+        self.autosave_if_due()
+    }
+
+    fn autosave_if_due(&mut self) -> Result<(), Error> {
+        if let Some(autosave) = &mut self.autosave {
+            if autosave.last_save.elapsed() >= autosave.interval {
+                autosave.last_save = Instant::now();
+                self.store.save_to_file(&autosave.path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Execute a request PDU addressed to given unit and create a response PDU.
+    pub(crate) fn execute_req(&mut self, unit_id: u8, req_pdu: &[u8]) -> Result<Vec<u8>, Error> {
+        self.execute_peer_req(None, unit_id, req_pdu)
+    }
+
+    /// Execute a request PDU sent by given master and record it in the audit log if it is a write request.
+    fn execute_peer_req(&mut self, peer_addr: Option<SocketAddr>, unit_id: u8, req_pdu: &[u8]) -> Result<Vec<u8>, Error> {
+        let rsp_pdu = self.execute_allowed_req(peer_addr, unit_id, req_pdu)?;
+
+        if let Some(audit_sink) = &mut self.audit_sink {
+            if let Some(record) = WriteRecord::from_transaction(peer_addr, unit_id, req_pdu, &rsp_pdu) {
+                audit_sink.record(&record);
+            }
+        }
+
+        Ok(rsp_pdu)
+    }
+
+    /// Execute a request PDU sent by given master, if it is allowed by the access control list.
+    fn execute_allowed_req(&mut self, peer_addr: Option<SocketAddr>, unit_id: u8, req_pdu: &[u8]) -> Result<Vec<u8>, Error> {
+        let function_code = *req_pdu.first().ok_or(Error::InvalidDataLength)?;
+        if let Some(acl) = self.acl.as_ref().filter(|acl| !acl.is_allowed(peer_addr, unit_id, function_code)) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(peer = ?peer_addr, unit_id, function_code, "Rejecting request denied by the ACL");
+            #[cfg(feature = "metrics")]
+            crate::telemetry::record_rejected(function_code);
+            return Ok(encode_exc_rsp(function_code, acl.get_exception()));
+        }
+
+        let unit_id = match self.unit_id_filter {
+            UnitIdFilter::Any if self.get_unit_store(unit_id).is_none() => self.unit_id,
+            _ => unit_id,
+        };
+        let functions = &self.functions;
+        let store = if self.started && unit_id == self.unit_id {
+            &mut self.store
+        } else {
+            self.units.iter_mut().find(|(id, _)| *id == unit_id).map(|(_, store)| store).ok_or(Error::MissingReqHandler)?
+        };
+        dispatch(store, functions, &mut self.middlewares, unit_id, req_pdu)
+    }
+
+    pub(crate) fn get_transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub(crate) fn is_started(&self) -> bool {
+        self.started
+    }
+
+    /// Serve requests until the process is terminated.
+    ///
+    /// Failures of single requests (decoding errors, broken connections) are
+    /// ignored and the server keeps serving next requests. Repeated failures are
+    /// followed by increasing delays, up to 1 second, so that a persistently
+    /// failing transport does not keep the CPU busy.
+    /// This method returns only if the server was not started with [Server::start].
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::server::{DataStore, Server};
+    ///
+    /// let store = DataStore::new()
+    ///     .with_coils(0x0000..=0x00ff)
+    ///     .with_hld_reg(0x0000..=0x00ff);
+    /// let mut server = Server::new(modbus::tcp::Tcp::new(), store);
+    /// server.start(10).unwrap();
+    /// server.serve_forever().unwrap();
+    /// ```
+    pub fn serve_forever(&mut self) -> Result<(), Error> {
+        self.serve_until(&CancelToken::new())
+    }
+
+    /// Serve requests until given token is cancelled.
+    ///
+    /// The token is checked between requests, so a request being currently
+    /// received is served before this method returns. To interrupt waiting for
+    /// a request, the same token shall be set on the transport, like with
+    /// [Tcp::set_cancel_token](crate::tcp::Tcp::set_cancel_token). Otherwise this
+    /// method returns only after the next request is served. Failures are handled
+    /// like in [Server::serve_forever].
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::CancelToken;
+    /// use modbus::server::{DataStore, Server};
+    ///
+    /// let token = CancelToken::new();
+    /// let server_token = token.clone();
+    ///
+    /// let handle = std::thread::spawn(move || {
+    ///     let store = DataStore::new().with_hld_reg(0x0000..=0x00ff);
+    ///     let mut tcp = modbus::tcp::Tcp::new();
+    ///     tcp.set_cancel_token(Some(server_token.clone()));
+    ///     let mut server = Server::new(tcp, store);
+    ///     server.start(10).unwrap();
+    ///     server.serve_until(&server_token).unwrap();
+    /// });
+    ///
+    /// token.cancel();
+    /// handle.join().unwrap();
+    /// ```
+    pub fn serve_until(&mut self, cancel_token: &CancelToken) -> Result<(), Error> {
+        if !self.started {
+            return Err(Error::InvalidValue);
+        }
+
+        crate::cancel::serve_until(cancel_token, || self.process_req())
+    }
+}
+
+fn check_quantity(quantity: u16, max: u16) -> Result<(), ExceptionCode> {
+    if quantity == 0 || quantity > max {
+        Err(ExceptionCode::IllegalDataValue)
+    } else {
+        Ok(())
+    }
+}
+
+fn check_range(address: u16, quantity: u16) -> Result<(), ExceptionCode> {
+    if is_range_valid(address, quantity) {
+        Ok(())
+    } else {
+        Err(ExceptionCode::IllegalDataAddress)
+    }
+}
+
+fn execute(store: &mut DataStore, req: RequestData) -> Result<ResponseData, ExceptionCode> {
+    let rsp = match req {
+        RequestData::ReadCoils(req) => {
+            check_quantity(req.get_quantity(), MAX_READ_BITS)?;
+            check_range(req.get_address(), req.get_quantity())?;
+            let coils = store.read_coils(req.get_address(), req.get_quantity())?;
+            ResponseData::ReadCoils(ReadCoilsResponse::new(&coils))
+        }
+        RequestData::ReadDscrIn(req) => {
+            check_quantity(req.get_quantity(), MAX_READ_BITS)?;
+            check_range(req.get_address(), req.get_quantity())?;
+            let inputs = store.read_dscr_in(req.get_address(), req.get_quantity())?;
+            ResponseData::ReadDscrIn(ReadDscrInResponse::new(&inputs))
+        }
+        RequestData::ReadHldReg(req) => {
+            check_quantity(req.get_quantity(), MAX_READ_REGS)?;
+            check_range(req.get_address(), req.get_quantity())?;
+            let registers = store.read_hld_reg(req.get_address(), req.get_quantity())?;
+            ResponseData::ReadHldReg(ReadHldRegResponse::new(&registers))
+        }
+        RequestData::ReadInReg(req) => {
+            check_quantity(req.get_quantity(), MAX_READ_REGS)?;
+            check_range(req.get_address(), req.get_quantity())?;
+            let registers = store.read_in_reg(req.get_address(), req.get_quantity())?;
+            ResponseData::ReadInReg(ReadInRegResponse::new(&registers))
+        }
+        RequestData::WriteSingleCoil(req) => {
+            store.remote_write_coils(req.get_address(), &[req.get_value()])?;
+            ResponseData::WriteSingleCoil(req)
+        }
+        RequestData::WriteSingleReg(req) => {
+            store.remote_write_hld_reg(req.get_address(), &[req.get_value()])?;
+            ResponseData::WriteSingleReg(req)
+        }
+        RequestData::WriteMultiReg(req) => {
+            check_range(req.get_address(), req.get_values().len() as u16)?;
+            store.remote_write_hld_reg(req.get_address(), req.get_values())?;
+            ResponseData::WriteMultiReg(WriteMultiRegResponse::new(req.get_address(), req.get_values().len() as u16))
+        }
+        RequestData::MaskWriteReg(req) => {
+            store.remote_update_hld_reg(req.get_address(), |current| req.apply(current))?;
+            ResponseData::MaskWriteReg(req)
+        }
+    };
+
+    Ok(rsp)
+}
+
+/// Execute a request PDU on the data store and create a response PDU.
+///
+/// Errors which shall be reported to the master are converted to exception responses.
+/// Requests with function codes not included in `functions` are not executed, the other ones
+/// are executed through the `middlewares` chain.
+fn dispatch(store: &mut DataStore, functions: &[FunctionCode], middlewares: &mut [Box<dyn Middleware>], unit_id: u8,
+            req_pdu: &[u8]) -> Result<Vec<u8>, Error> {
+    if req_pdu.is_empty() {
+        return Err(Error::InvalidDataLength);
+    }
+
+    let function_code = req_pdu[0];
+    if !functions.iter().any(|function| *function as u8 == function_code) {
+        return Ok(encode_exc_rsp(function_code, ExceptionCode::IllegalFunction));
+    }
+
+    let req = match decode_req(req_pdu) {
+        Ok(req) => req,
+        Err(_) => return Ok(encode_exc_rsp(function_code, ExceptionCode::IllegalDataValue)),
+    };
+
+    let rsp_pdu = middleware::run(middlewares, unit_id, req, &mut |req| execute(store, req))
+        .and_then(|rsp| rsp.encode().map_err(|_| ExceptionCode::ServerDeviceFailure));
+    match rsp_pdu {
+        Ok(rsp_pdu) => Ok(rsp_pdu),
+        Err(exc_code) => Ok(encode_exc_rsp(function_code, exc_code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+
+    fn create_store() -> DataStore {
+        DataStore::new()
+            .with_coils(0x0000..=0x000f)
+            .with_hld_reg(0x0100..=0x01ff)
+    }
+
+    #[test]
+    fn test_serve_not_started() {
+        let mut server = Server::new(MockTransport::new(), create_store());
+
+        match server.serve_forever() {
+            Err(Error::InvalidValue) => {}
+            result => panic!("Expected InvalidValue, but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_serve_until_cancelled() {
+        let mut server = Server::new(MockTransport::new(), create_store());
+        server.start(1).unwrap();
+        server.get_transport_mut().push_req(1, &[0x03, 0x01, 0x00, 0x00, 0x01]);
+
+        let token = CancelToken::new();
+        let canceller = token.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+
+        // The transport keeps failing with no more requests queued
+        server.serve_until(&token).unwrap();
+        handle.join().unwrap();
+        assert_eq!(server.get_transport_mut().get_rsps(), &[(1, vec![0x03, 0x02, 0x00, 0x00])]);
+    }
+
+    #[test]
+    fn test_add_duplicated_unit() {
+        let mut server = Server::new(MockTransport::new(), create_store());
+        server.add_unit(11, DataStore::new()).unwrap();
+
+        assert!(server.add_unit(11, DataStore::new()).is_err());
+        assert!(server.start(11).is_err());
+        assert!(server.get_unit_store(11).is_some());
+        assert!(server.get_unit_store(12).is_none());
+    }
+
+    #[test]
+    fn test_unit_id_filter() {
+        let mut server = Server::new(MockTransport::new(), create_store());
+        server.add_unit(2, DataStore::new().with_hld_reg(0x0100..=0x0100)).unwrap();
+        server.get_store_mut().write_hld_reg(0x0100, &[0x0001]).unwrap();
+        server.get_unit_store_mut(2).unwrap().write_hld_reg(0x0100, &[0x0002]).unwrap();
+        server.start(1).unwrap();
+        assert!(matches!(server.execute_req(7, &[0x03, 0x01, 0x00, 0x00, 0x01]), Err(Error::MissingReqHandler)));
+
+        let mut server = Server::new(MockTransport::new(), server.store);
+        server.add_unit(2, DataStore::new().with_hld_reg(0x0100..=0x0100)).unwrap();
+        server.set_unit_id_filter(UnitIdFilter::Any).unwrap();
+        server.start(1).unwrap();
+        assert_eq!(server.execute_req(7, &[0x03, 0x01, 0x00, 0x00, 0x01]).unwrap(), vec![0x03, 0x02, 0x00, 0x01]);
+        assert_eq!(server.execute_req(2, &[0x03, 0x01, 0x00, 0x00, 0x01]).unwrap(), vec![0x03, 0x02, 0x00, 0x00]);
+        assert!(server.set_unit_id_filter(UnitIdFilter::Strict).is_err());
+    }
+
+    #[test]
+    fn test_supported_functions() {
+        let mut server = Server::new(MockTransport::new(), create_store());
+        assert_eq!(server.get_supported_functions(), &SERVED_FUNCTIONS);
+        assert!(server.set_supported_functions(&[FunctionCode::ReadFileRecord]).is_err());
+
+        server.set_supported_functions(&[FunctionCode::ReadHldReg]).unwrap();
+        server.start(1).unwrap();
+        assert_eq!(server.execute_req(1, &[0x03, 0x01, 0x00, 0x00, 0x01]).unwrap(), vec![0x03, 0x02, 0x00, 0x00]);
+        assert_eq!(server.execute_req(1, &[0x06, 0x01, 0x00, 0x12, 0x34]).unwrap(),
+                   vec![0x86, ExceptionCode::IllegalFunction as u8]);
+        assert_eq!(server.get_store().read_hld_reg(0x0100, 1), Ok(vec![0x0000]));
+        assert!(server.set_supported_functions(&SERVED_FUNCTIONS).is_err());
+    }
+
+    #[test]
+    fn test_acl() {
+        let peer = |last: u8| Some(SocketAddr::from(([10, 0, 0, last], 50000)));
+        let mut server = Server::new(MockTransport::new(), create_store());
+        server.set_acl(Some(Acl::new()
+            .allow_subnet(acl::Subnet::new(peer(0).unwrap().ip(), 24).unwrap())
+            .read_only(acl::Subnet::host(peer(2).unwrap().ip()))
+            .with_exception(ExceptionCode::GatewayPathUnavailable)));
+        server.start(1).unwrap();
+
+        assert_eq!(server.execute_peer_req(peer(1), 1, &[0x06, 0x01, 0x00, 0x12, 0x34]).unwrap(), vec![0x06, 0x01, 0x00, 0x12, 0x34]);
+        assert_eq!(server.execute_peer_req(peer(2), 1, &[0x06, 0x01, 0x00, 0x43, 0x21]).unwrap(),
+                   vec![0x86, ExceptionCode::GatewayPathUnavailable as u8]);
+        assert_eq!(server.execute_peer_req(peer(2), 1, &[0x03, 0x01, 0x00, 0x00, 0x01]).unwrap(), vec![0x03, 0x02, 0x12, 0x34]);
+        assert_eq!(server.execute_req(1, &[0x03, 0x01, 0x00, 0x00, 0x01]).unwrap(),
+                   vec![0x83, ExceptionCode::GatewayPathUnavailable as u8]);
+    }
+
+    #[test]
+    fn test_audit_sink() {
+        let (sink, records) = std::sync::mpsc::channel();
+        let mut server = Server::new(MockTransport::new(), create_store());
+        server.set_audit_sink(Some(Box::new(sink)));
+        server.start(1).unwrap();
+
+        server.execute_req(1, &[0x03, 0x01, 0x00, 0x00, 0x01]).unwrap();
+        server.execute_req(1, &[0x06, 0x01, 0x00, 0x12, 0x34]).unwrap();
+        server.execute_req(1, &[0x06, 0x00, 0x00, 0x12, 0x34]).unwrap();
+
+        let records: Vec<WriteRecord> = records.try_iter().collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].get_address(), records[0].get_values(), records[0].get_result()), (0x0100, &[0x1234][..], Ok(())));
+        assert_eq!(records[1].get_result(), Err(ExceptionCode::IllegalDataAddress));
+    }
+
+    #[test]
+    fn test_middleware() {
+        let mut server = Server::new(MockTransport::new(), create_store());
+        server.add_middleware(Box::new(|unit_id: u8, req: RequestData, next: middleware::Next| match &req {
+            RequestData::WriteSingleReg(_) if unit_id != 1 => Err(ExceptionCode::IllegalFunction),
+            _ => next(req),
+        }));
+        server.add_middleware(Box::new(|_unit_id: u8, req: RequestData, next: middleware::Next| match next(req)? {
+            ResponseData::ReadHldReg(rsp) => {
+                let registers: Vec<u16> = rsp.get_registers().iter().map(|value| !value).collect();
+                Ok(ResponseData::ReadHldReg(ReadHldRegResponse::new(&registers)))
+            }
+            rsp => Ok(rsp),
+        }));
+        server.add_unit(2, create_store()).unwrap();
+        server.start(1).unwrap();
+
+        assert_eq!(server.execute_req(2, &[0x06, 0x01, 0x00, 0x12, 0x34]).unwrap(),
+                   vec![0x86, ExceptionCode::IllegalFunction as u8]);
+        assert_eq!(server.execute_req(1, &[0x06, 0x01, 0x00, 0x12, 0x34]).unwrap(), vec![0x06, 0x01, 0x00, 0x12, 0x34]);
+        assert_eq!(server.execute_req(1, &[0x03, 0x01, 0x00, 0x00, 0x01]).unwrap(), vec![0x03, 0x02, 0xed, 0xcb]);
+        assert_eq!(server.get_store().read_hld_reg(0x0100, 1), Ok(vec![0x1234]));
+    }
+
+    #[test]
+    fn test_autosave() {
+        let path = std::env::temp_dir().join(format!("modbus_autosave_{}.bin", std::process::id()));
+        let mut server = Server::new(MockTransport::new(), create_store());
+        server.get_store_mut().write_hld_reg(0x0100, &[0xbeef]).unwrap();
+
+        server.set_autosave(&path, Duration::from_secs(0));
+        server.autosave_if_due().unwrap();
+
+        let mut restored = create_store();
+        restored.load_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.read_hld_reg(0x0100, 1), Ok(vec![0xbeef]));
+    }
+
+    #[test]
+    fn test_dispatch_read_hld_reg() {
+        let mut store = create_store();
+        store.write_hld_reg(0x0100, &[0xcafe]).unwrap();
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x03, 0x01, 0x00, 0x00, 0x01]).unwrap();
+        assert_eq!(rsp, vec![0x03, 0x02, 0xca, 0xfe]);
+    }
+
+    #[test]
+    fn test_dispatch_write_single_coil() {
+        let mut store = create_store();
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x05, 0x00, 0x03, 0xff, 0x00]).unwrap();
+        assert_eq!(rsp, vec![0x05, 0x00, 0x03, 0xff, 0x00]);
+        assert_eq!(store.read_coils(0x0003, 1), Ok(vec![true]));
+    }
+
+    #[test]
+    fn test_dispatch_mask_write_reg() {
+        let mut store = create_store();
+        store.write_hld_reg(0x0104, &[0x0012]).unwrap();
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x16, 0x01, 0x04, 0x00, 0xf2, 0x00, 0x25]).unwrap();
+        assert_eq!(rsp, vec![0x16, 0x01, 0x04, 0x00, 0xf2, 0x00, 0x25]);
+        assert_eq!(store.read_hld_reg(0x0104, 1), Ok(vec![0x0017]));
+    }
+
+    #[test]
+    fn test_dispatch_write_read_only() {
+        let mut store = create_store().with_read_only_hld_reg(0x0100..=0x0100);
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x06, 0x01, 0x00, 0x12, 0x34]).unwrap();
+        assert_eq!(rsp, vec![0x86, ExceptionCode::IllegalDataAddress as u8]);
+        assert_eq!(store.read_hld_reg(0x0100, 1), Ok(vec![0x0000]));
+    }
+
+    #[test]
+    fn test_dispatch_unsupported_function() {
+        let mut store = create_store();
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x2b, 0x0e, 0x01, 0x00]).unwrap();
+        assert_eq!(rsp, vec![0xab, ExceptionCode::IllegalFunction as u8]);
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x14, 0x07, 0x06, 0x00, 0x04, 0x00, 0x01, 0x00, 0x02]).unwrap();
+        assert_eq!(rsp, vec![0x94, ExceptionCode::IllegalFunction as u8]);
+    }
+
+    #[test]
+    fn test_dispatch_illegal_address() {
+        let mut store = create_store();
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x03, 0x01, 0xff, 0x00, 0x02]).unwrap();
+        assert_eq!(rsp, vec![0x83, ExceptionCode::IllegalDataAddress as u8]);
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x04, 0x00, 0x00, 0x00, 0x01]).unwrap();
+        assert_eq!(rsp, vec![0x84, ExceptionCode::IllegalDataAddress as u8]);
+    }
+
+    #[test]
+    fn test_dispatch_address_overflow() {
+        let mut store = create_store();
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x01, 0xff, 0xff, 0x07, 0xd0]).unwrap();
+        assert_eq!(rsp, vec![0x81, ExceptionCode::IllegalDataAddress as u8]);
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x10, 0xff, 0xff, 0x00, 0x02, 0x04, 0x00, 0x01, 0x00, 0x02]).unwrap();
+        assert_eq!(rsp, vec![0x90, ExceptionCode::IllegalDataAddress as u8]);
+    }
+
+    #[test]
+    fn test_dispatch_illegal_quantity() {
+        let mut store = create_store();
+
+        let rsp = dispatch(&mut store, &SERVED_FUNCTIONS, &mut [], 1, &[0x01, 0x00, 0x00, 0x00, 0x00]).unwrap();
+        assert_eq!(rsp, vec![0x81, ExceptionCode::IllegalDataValue as u8]);
+    }
+}
@@ -0,0 +1,1021 @@
+//! In-memory data model of a Modbus slave
+
+use crate::error::Error;
+use crate::pdu::ExceptionCode;
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+
+const PERSIST_MAGIC: &[u8; 4] = b"MBDS";
+const PERSIST_VERSION: u8 = 1;
+
+/// Callback fired after the master writes a value: `(address, old value, new value)`
+pub type WriteHook<T> = Box<dyn FnMut(u16, T, T) + Send>;
+
+/// List of write hooks registered for a table
+struct Hooks<T> {
+    hooks: Vec<WriteHook<T>>,
+}
+
+impl<T: Copy> Hooks<T> {
+    fn fire(&mut self, address: u16, old_values: &[T], new_values: &[T]) {
+        for hook in self.hooks.iter_mut() {
+            for (i, (old, new)) in old_values.iter().zip(new_values).enumerate() {
+                hook(address.wrapping_add(i as u16), *old, *new);
+            }
+        }
+    }
+}
+
+impl<T> Default for Hooks<T> {
+    fn default() -> Self {
+        Self {hooks: Vec::new()}
+    }
+}
+
+impl<T> fmt::Debug for Hooks<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} hooks", self.hooks.len())
+    }
+}
+
+/// Continuous window of addresses in a table
+#[derive(Debug)]
+struct Window<T> {
+    start: u16,
+    values: Vec<T>,
+}
+
+impl<T> Window<T> {
+    fn contains(&self, address: u16, quantity: usize) -> bool {
+        address >= self.start && (address - self.start) as usize + quantity <= self.values.len()
+    }
+
+    fn overlaps(&self, range: &RangeInclusive<u16>) -> bool {
+        let end = self.start as usize + self.values.len();
+        (*range.start() as usize) < end && (*range.end() as usize) >= self.start as usize
+    }
+}
+
+/// Reset of values which the master stopped writing
+#[derive(Debug)]
+struct Watchdog<T> {
+    range: RangeInclusive<u16>,
+    timeout: Duration,
+    default: T,
+    refreshed: Vec<Option<Instant>>,
+}
+
+/// Single table of the Modbus data model
+///
+/// A table consists of windows of valid addresses. Every access must fit
+/// entirely in one of the windows.
+#[derive(Debug)]
+struct Table<T> {
+    windows: Vec<Window<T>>,
+    read_only: Vec<RangeInclusive<u16>>,
+    watchdogs: Vec<Watchdog<T>>,
+}
+
+impl<T: Clone + Default> Table<T> {
+    fn add_window(&mut self, range: RangeInclusive<u16>) {
+        if range.is_empty() {
+            return;
+        }
+
+        assert!(!self.windows.iter().any(|w| w.overlaps(&range)), "Overlapping address windows");
+
+        let len = (*range.end() - *range.start()) as usize + 1;
+        self.windows.push(Window {start: *range.start(), values: vec![T::default(); len]});
+    }
+
+    fn get_window(&self, address: u16, quantity: usize) -> Result<(usize, usize), ExceptionCode> {
+        self.windows.iter()
+            .position(|w| w.contains(address, quantity))
+            .map(|i| (i, (address - self.windows[i].start) as usize))
+            .ok_or(ExceptionCode::IllegalDataAddress)
+    }
+
+    fn add_read_only(&mut self, range: RangeInclusive<u16>) {
+        self.read_only.push(range);
+    }
+
+    fn is_read_only(&self, address: u16, quantity: usize) -> bool {
+        if quantity == 0 {
+            return false;
+        }
+
+        let last = address as usize + quantity - 1;
+        self.read_only.iter().any(|r| (*r.start() as usize) <= last && (*r.end() as usize) >= address as usize)
+    }
+
+    fn add_watchdog(&mut self, range: RangeInclusive<u16>, timeout: Duration, default: T) {
+        let len = if range.is_empty() { 0 } else { (*range.end() - *range.start()) as usize + 1 };
+        self.watchdogs.push(Watchdog {range, timeout, default, refreshed: vec![None; len]});
+    }
+
+    /// Restart watchdogs of given values written by the master
+    fn refresh(&mut self, address: u16, quantity: usize) {
+        let now = Instant::now();
+        for watchdog in self.watchdogs.iter_mut() {
+            let start = *watchdog.range.start() as usize;
+            for (i, refreshed) in watchdog.refreshed.iter_mut().enumerate() {
+                let watched = start + i;
+                if (address as usize..address as usize + quantity).contains(&watched) {
+                    *refreshed = Some(now);
+                }
+            }
+        }
+    }
+
+    /// Reset values whose watchdogs expired to their defaults, without firing write hooks
+    fn expire(&mut self) {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        for watchdog in self.watchdogs.iter_mut() {
+            let (start, timeout) = (*watchdog.range.start(), watchdog.timeout);
+            for (i, refreshed) in watchdog.refreshed.iter_mut().enumerate() {
+                if refreshed.is_some_and(|refreshed| now.duration_since(refreshed) >= timeout) {
+                    *refreshed = None;
+                    expired.push((start + i as u16, watchdog.default.clone()));
+                }
+            }
+        }
+
+        for (address, default) in expired {
+            // Watched addresses out of the windows are never written, so they never expire
+            let _ = self.write(address, &[default]);
+        }
+    }
+
+    fn read(&self, address: u16, quantity: u16) -> Result<Vec<T>, ExceptionCode> {
+        let (window, idx) = self.get_window(address, quantity as usize)?;
+        Ok(self.windows[window].values[idx..idx + quantity as usize].to_vec())
+    }
+
+    fn write(&mut self, address: u16, values: &[T]) -> Result<(), ExceptionCode> {
+        self.replace(address, values).map(|_| ())
+    }
+
+    fn replace(&mut self, address: u16, values: &[T]) -> Result<Vec<T>, ExceptionCode> {
+        let (window, idx) = self.get_window(address, values.len())?;
+        let slice = &mut self.windows[window].values[idx..idx + values.len()];
+        let old_values = slice.to_vec();
+        slice.clone_from_slice(values);
+        Ok(old_values)
+    }
+
+    fn snapshot(&self) -> Vec<(u16, Vec<T>)> {
+        self.windows.iter().map(|w| (w.start, w.values.clone())).collect()
+    }
+}
+
+impl<T: Clone + PartialEq> Table<T> {
+    /// Get addresses and current values differing from given snapshot of the table
+    ///
+    /// Windows missing in the snapshot are reported as changed entirely.
+    fn diff(&self, snapshot: &[(u16, Vec<T>)]) -> Vec<(u16, T)> {
+        let mut changes = Vec::new();
+
+        for window in &self.windows {
+            let old_values = snapshot.iter()
+                .find(|(start, values)| *start == window.start && values.len() == window.values.len())
+                .map(|(_, values)| values);
+
+            for (i, value) in window.values.iter().enumerate() {
+                if old_values.is_none_or(|old_values| old_values[i] != *value) {
+                    changes.push((window.start + i as u16, value.clone()));
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+/// Value which can be persisted in the binary data store file
+trait Persist: Sized {
+    const SIZE: usize;
+
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(data: &[u8]) -> Result<Self, Error>;
+}
+
+impl Persist for bool {
+    const SIZE: usize = 1;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, Error> {
+        match data[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::InvalidData),
+        }
+    }
+}
+
+impl Persist for u16 {
+    const SIZE: usize = 2;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, Error> {
+        Ok(u16::from_be_bytes(data[0..2].try_into().unwrap()))
+    }
+}
+
+fn read_exact<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>, Error> {
+    let mut data = vec![0; len];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+impl<T: Clone + Default + Persist> Table<T> {
+    fn save(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.windows.len() as u32).to_be_bytes());
+
+        for window in &self.windows {
+            out.extend_from_slice(&window.start.to_be_bytes());
+            out.extend_from_slice(&(window.values.len() as u32).to_be_bytes());
+            for value in &window.values {
+                value.encode(out);
+            }
+        }
+    }
+
+    /// Read saved windows, verifying that each of them fits in a window of this table
+    fn load<R: Read>(&self, reader: &mut R) -> Result<Vec<(u16, Vec<T>)>, Error> {
+        let window_cnt = u32::from_be_bytes(read_exact(reader, 4)?[..].try_into().unwrap());
+        let mut windows = Vec::new();
+
+        for _ in 0..window_cnt {
+            let start = u16::from_be_bytes(read_exact(reader, 2)?[..].try_into().unwrap());
+            let len = u32::from_be_bytes(read_exact(reader, 4)?[..].try_into().unwrap()) as usize;
+            if len > 0x10000 {
+                return Err(Error::InvalidDataLength);
+            }
+
+            let data = read_exact(reader, len * T::SIZE)?;
+            let values = data.chunks(T::SIZE).map(T::decode).collect::<Result<Vec<T>, Error>>()?;
+            self.get_window(start, values.len()).map_err(|_| Error::InvalidData)?;
+            windows.push((start, values));
+        }
+
+        Ok(windows)
+    }
+
+    /// Write windows read by [Table::load]
+    fn restore(&mut self, windows: Vec<(u16, Vec<T>)>) {
+        for (start, values) in windows {
+            // Windows are fixed when the store is built, so the ones verified by load still fit
+            let _ = self.write(start, &values);
+        }
+    }
+}
+
+impl<T> Default for Table<T> {
+    fn default() -> Self {
+        Self {windows: Vec::new(), read_only: Vec::new(), watchdogs: Vec::new()}
+    }
+}
+
+/// Table guarded by its own lock
+///
+/// Each access holds the lock for its whole duration, so concurrent readers never observe
+/// a partially applied multi-value write.
+#[derive(Debug, Default)]
+struct Locked<T> {
+    table: RwLock<Table<T>>,
+}
+
+impl<T: Clone + Default> Locked<T> {
+    /// Lock the table for reading, resetting values with expired watchdogs first
+    fn read(&self) -> RwLockReadGuard<'_, Table<T>> {
+        let table = self.table.read().unwrap_or_else(PoisonError::into_inner);
+        if table.watchdogs.is_empty() {
+            return table;
+        }
+
+        drop(table);
+        self.write().expire();
+        self.table.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Table<T>> {
+        self.table.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Tables and hooks shared by all handles of a [DataStore]
+#[derive(Debug, Default)]
+struct Tables {
+    coils: Locked<bool>,
+    dscr_in: Locked<bool>,
+    hld_reg: Locked<u16>,
+    in_reg: Locked<u16>,
+
+    coil_hooks: Mutex<Hooks<bool>>,
+    hld_reg_hooks: Mutex<Hooks<u16>>,
+}
+
+fn lock_hooks<T>(hooks: &Mutex<Hooks<T>>) -> MutexGuard<'_, Hooks<T>> {
+    hooks.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Copy of values of all tables of a [DataStore]
+///
+/// It is taken with [DataStore::snapshot] and compared to the current values with [DataStore::diff].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Snapshot {
+    coils: Vec<(u16, Vec<bool>)>,
+    dscr_in: Vec<(u16, Vec<bool>)>,
+    hld_reg: Vec<(u16, Vec<u16>)>,
+    in_reg: Vec<(u16, Vec<u16>)>,
+}
+
+/// Values of a [DataStore] changed since a [Snapshot] was taken
+///
+/// Each table lists addresses of changed values with their current values, in the order of addresses within windows.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Diff {
+    coils: Vec<(u16, bool)>,
+    dscr_in: Vec<(u16, bool)>,
+    hld_reg: Vec<(u16, u16)>,
+    in_reg: Vec<(u16, u16)>,
+}
+
+impl Diff {
+    /// Get changed coils
+    pub fn get_coils(&self) -> &[(u16, bool)] {
+        &self.coils
+    }
+
+    /// Get changed discrete inputs
+    pub fn get_dscr_in(&self) -> &[(u16, bool)] {
+        &self.dscr_in
+    }
+
+    /// Get changed holding registers
+    pub fn get_hld_reg(&self) -> &[(u16, u16)] {
+        &self.hld_reg
+    }
+
+    /// Get changed input registers
+    pub fn get_in_reg(&self) -> &[(u16, u16)] {
+        &self.in_reg
+    }
+
+    /// Check if no value changed
+    pub fn is_empty(&self) -> bool {
+        self.coils.is_empty() && self.dscr_in.is_empty() && self.hld_reg.is_empty() && self.in_reg.is_empty()
+    }
+}
+
+/// Data store of a Modbus slave
+///
+/// The data store keeps coils, discrete inputs, holding registers and input registers.
+/// Each table covers configured windows of addresses. Accessing addresses missing
+/// the windows or crossing a window boundary results in [ExceptionCode::IllegalDataAddress].
+///
+/// Coils and holding registers can be marked as read-only. The master cannot
+/// modify them, while the application still can using `write_*` methods.
+///
+/// Cloning a data store creates another handle to the same tables, which can be used by
+/// another thread or another server. Each table is locked for the duration of every access,
+/// so a request writing multiple values is applied atomically with respect to concurrent reads.
+///
+/// # Examples
+/// ```
+/// let store = modbus::server::DataStore::new().with_hld_reg(0x0000..=0x000f);
+/// let mut handle = store.clone();
+///
+/// std::thread::spawn(move || handle.write_hld_reg(0x0000, &[0x1234, 0x5678]).unwrap()).join().unwrap();
+/// assert_eq!(store.read_hld_reg(0x0000, 2), Ok(vec![0x1234, 0x5678]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct DataStore {
+    tables: Arc<Tables>,
+    read_only_exc: ExceptionCode,
+}
+
+impl Default for DataStore {
+    fn default() -> Self {
+        Self {tables: Arc::default(), read_only_exc: ExceptionCode::IllegalDataAddress}
+    }
+}
+
+impl DataStore {
+    /// Create a new data store with all tables empty
+    ///
+    /// # Examples
+    /// ```
+    /// let store = modbus::server::DataStore::new()
+    ///     .with_coils(0x0000..=0x00ff)
+    ///     .with_hld_reg(0x0000..=0x01ff)
+    ///     .with_hld_reg(0x1000..=0x10ff);
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a window of valid addresses to the coils table
+    ///
+    /// # Panics
+    /// Panics if the window overlaps a window already added to the table.
+    pub fn with_coils(self, range: RangeInclusive<u16>) -> Self {
+        self.tables.coils.write().add_window(range);
+        self
+    }
+
+    /// Add a window of valid addresses to the discrete inputs table
+    ///
+    /// # Panics
+    /// Panics if the window overlaps a window already added to the table.
+    pub fn with_dscr_in(self, range: RangeInclusive<u16>) -> Self {
+        self.tables.dscr_in.write().add_window(range);
+        self
+    }
+
+    /// Add a window of valid addresses to the holding registers table
+    ///
+    /// # Panics
+    /// Panics if the window overlaps a window already added to the table.
+    pub fn with_hld_reg(self, range: RangeInclusive<u16>) -> Self {
+        self.tables.hld_reg.write().add_window(range);
+        self
+    }
+
+    /// Add a window of valid addresses to the input registers table
+    ///
+    /// # Panics
+    /// Panics if the window overlaps a window already added to the table.
+    pub fn with_in_reg(self, range: RangeInclusive<u16>) -> Self {
+        self.tables.in_reg.write().add_window(range);
+        self
+    }
+
+    /// Mark coils in given range as read-only for the master
+    ///
+    /// # Examples
+    /// ```
+    /// let store = modbus::server::DataStore::new()
+    ///     .with_coils(0x0000..=0x000f)
+    ///     .with_read_only_coils(0x0003..=0x0003);
+    /// ```
+    pub fn with_read_only_coils(self, range: RangeInclusive<u16>) -> Self {
+        self.tables.coils.write().add_read_only(range);
+        self
+    }
+
+    /// Mark holding registers in given range as read-only for the master
+    pub fn with_read_only_hld_reg(self, range: RangeInclusive<u16>) -> Self {
+        self.tables.hld_reg.write().add_read_only(range);
+        self
+    }
+
+    /// Reset coils in given range to `default` if the master does not write them for `timeout`
+    ///
+    /// Each coil is watched separately, starting from its first write by the master. Writes of
+    /// the application with [DataStore::write_coils] do not restart the watchdog. It is a common
+    /// dead-man switch of outputs controlled remotely, which are switched off when the master
+    /// stops refreshing them.
+    ///
+    /// Expired coils are reset lazily, on the next access to the coils, and silently: the reset
+    /// is not a write of the master, so it does not fire hooks registered with
+    /// [DataStore::add_coil_write_hook]. An application driving outputs from the hooks shall
+    /// read the watched coils periodically instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let store = modbus::server::DataStore::new()
+    ///     .with_coils(0x0000..=0x000f)
+    ///     .with_coil_watchdog(0x0000..=0x0003, Duration::from_secs(1), false);
+    /// ```
+    pub fn with_coil_watchdog(self, range: RangeInclusive<u16>, timeout: Duration, default: bool) -> Self {
+        self.tables.coils.write().add_watchdog(range, timeout, default);
+        self
+    }
+
+    /// Reset holding registers in given range to `default` if the master does not write them for `timeout`
+    ///
+    /// See [DataStore::with_coil_watchdog]. Like there, the reset is lazy and does not fire hooks
+    /// registered with [DataStore::add_hld_reg_write_hook].
+    pub fn with_hld_reg_watchdog(self, range: RangeInclusive<u16>, timeout: Duration, default: u16) -> Self {
+        self.tables.hld_reg.write().add_watchdog(range, timeout, default);
+        self
+    }
+
+    /// Select exception reported to the master writing read-only data
+    ///
+    /// By default it is [ExceptionCode::IllegalDataAddress].
+    pub fn with_read_only_exception(mut self, exc_code: ExceptionCode) -> Self {
+        self.read_only_exc = exc_code;
+        self
+    }
+
+    /// Register a hook fired after the master successfully writes a coil
+    ///
+    /// The hook is called for every written coil with its address, old value and new value.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut store = modbus::server::DataStore::new().with_coils(0x0000..=0x000f);
+    /// store.add_coil_write_hook(Box::new(|address, old, new| {
+    ///     println!("Coil {} changed from {} to {}", address, old, new);
+    /// }));
+    /// ```
+    pub fn add_coil_write_hook(&mut self, hook: WriteHook<bool>) {
+        lock_hooks(&self.tables.coil_hooks).hooks.push(hook);
+    }
+
+    /// Register a hook fired after the master successfully writes a holding register
+    ///
+    /// The hook is called for every written register with its address, old value and new value.
+    pub fn add_hld_reg_write_hook(&mut self, hook: WriteHook<u16>) {
+        lock_hooks(&self.tables.hld_reg_hooks).hooks.push(hook);
+    }
+
+    /// Read values of coils
+    ///
+    /// # Examples
+    /// ```
+    /// let store = modbus::server::DataStore::new().with_coils(0x0010..=0x001f);
+    /// assert_eq!(store.read_coils(0x0010, 2), Ok(vec![false, false]));
+    /// assert!(store.read_coils(0x001f, 2).is_err());
+    /// ```
+    pub fn read_coils(&self, address: u16, quantity: u16) -> Result<Vec<bool>, ExceptionCode> {
+        self.tables.coils.read().read(address, quantity)
+    }
+
+    /// Read values of discrete inputs
+    pub fn read_dscr_in(&self, address: u16, quantity: u16) -> Result<Vec<bool>, ExceptionCode> {
+        self.tables.dscr_in.read().read(address, quantity)
+    }
+
+    /// Read values of holding registers
+    pub fn read_hld_reg(&self, address: u16, quantity: u16) -> Result<Vec<u16>, ExceptionCode> {
+        self.tables.hld_reg.read().read(address, quantity)
+    }
+
+    /// Read values of input registers
+    pub fn read_in_reg(&self, address: u16, quantity: u16) -> Result<Vec<u16>, ExceptionCode> {
+        self.tables.in_reg.read().read(address, quantity)
+    }
+
+    /// Write values of coils starting from given address
+    ///
+    /// # Examples
+    /// ```
+    /// let mut store = modbus::server::DataStore::new().with_coils(0x0000..=0x000f);
+    /// store.write_coils(0x0001, &[true]).unwrap();
+    /// assert_eq!(store.read_coils(0x0000, 2), Ok(vec![false, true]));
+    /// ```
+    pub fn write_coils(&mut self, address: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+        self.tables.coils.write().write(address, values)
+    }
+
+    /// Write values of discrete inputs starting from given address
+    pub fn write_dscr_in(&mut self, address: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+        self.tables.dscr_in.write().write(address, values)
+    }
+
+    /// Write values of holding registers starting from given address
+    pub fn write_hld_reg(&mut self, address: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+        self.tables.hld_reg.write().write(address, values)
+    }
+
+    /// Write values of input registers starting from given address
+    pub fn write_in_reg(&mut self, address: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+        self.tables.in_reg.write().write(address, values)
+    }
+
+    /// Save values of all tables
+    ///
+    /// Values are stored in a compact binary format which can be restored with [DataStore::load].
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let mut data = Vec::new();
+        data.extend_from_slice(PERSIST_MAGIC);
+        data.push(PERSIST_VERSION);
+
+        self.tables.coils.read().save(&mut data);
+        self.tables.dscr_in.read().save(&mut data);
+        self.tables.hld_reg.read().save(&mut data);
+        self.tables.in_reg.read().save(&mut data);
+
+        writer.write_all(&data)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Restore values of all tables saved with [DataStore::save]
+    ///
+    /// Address windows are not restored. Each saved window must fit in a window
+    /// configured in this data store, otherwise [Error::InvalidData] is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::server::DataStore;
+    ///
+    /// let mut store = DataStore::new().with_hld_reg(0x0000..=0x000f);
+    /// store.write_hld_reg(0x0001, &[0xcafe]).unwrap();
+    ///
+    /// let mut data = Vec::new();
+    /// store.save(&mut data).unwrap();
+    ///
+    /// let mut restored = DataStore::new().with_hld_reg(0x0000..=0x000f);
+    /// restored.load(&data[..]).unwrap();
+    /// assert_eq!(restored.read_hld_reg(0x0001, 1), Ok(vec![0xcafe]));
+    /// ```
+    pub fn load<R: Read>(&mut self, mut reader: R) -> Result<(), Error> {
+        let header = read_exact(&mut reader, PERSIST_MAGIC.len() + 1)?;
+        if &header[..PERSIST_MAGIC.len()] != PERSIST_MAGIC || header[PERSIST_MAGIC.len()] != PERSIST_VERSION {
+            return Err(Error::InvalidData);
+        }
+
+        // Decode all tables before modifying any of them, so invalid data leaves the store intact
+        let coils = self.tables.coils.read().load(&mut reader)?;
+        let dscr_in = self.tables.dscr_in.read().load(&mut reader)?;
+        let hld_reg = self.tables.hld_reg.read().load(&mut reader)?;
+        let in_reg = self.tables.in_reg.read().load(&mut reader)?;
+
+        let (mut coils_table, mut dscr_in_table) = (self.tables.coils.write(), self.tables.dscr_in.write());
+        let (mut hld_reg_table, mut in_reg_table) = (self.tables.hld_reg.write(), self.tables.in_reg.write());
+        coils_table.restore(coils);
+        dscr_in_table.restore(dscr_in);
+        hld_reg_table.restore(hld_reg);
+        in_reg_table.restore(in_reg);
+        Ok(())
+    }
+
+    /// Save values of all tables to given file
+    ///
+    /// The values are written to a temporary file in the same directory first, which then
+    /// replaces given file. A failure never leaves the file partially written.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut tmp_name = path.file_name().ok_or(Error::InvalidValue)?.to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let result = self.save_to_new_file(&tmp_path).and_then(|()| Ok(fs::rename(&tmp_path, path)?));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    fn save_to_new_file(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        self.save(BufWriter::new(&mut file))?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Restore values of all tables from given file
+    pub fn load_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        self.load(BufReader::new(File::open(path)?))
+    }
+
+    /// Take a copy of values of all tables
+    ///
+    /// # Examples
+    /// ```
+    /// let mut store = modbus::server::DataStore::new().with_hld_reg(0x0000..=0x000f);
+    /// let snapshot = store.snapshot();
+    ///
+    /// store.write_hld_reg(0x0003, &[0x1234]).unwrap();
+    /// assert_eq!(store.diff(&snapshot).get_hld_reg(), &[(0x0003, 0x1234)]);
+    /// ```
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            coils: self.tables.coils.read().snapshot(),
+            dscr_in: self.tables.dscr_in.read().snapshot(),
+            hld_reg: self.tables.hld_reg.read().snapshot(),
+            in_reg: self.tables.in_reg.read().snapshot(),
+        }
+    }
+
+    /// Get values changed since given snapshot was taken
+    ///
+    /// Values written with unchanged values are not reported. Windows missing in the snapshot,
+    /// e.g. if it was taken from a data store with other windows, are reported as changed entirely.
+    pub fn diff(&self, snapshot: &Snapshot) -> Diff {
+        Diff {
+            coils: self.tables.coils.read().diff(&snapshot.coils),
+            dscr_in: self.tables.dscr_in.read().diff(&snapshot.dscr_in),
+            hld_reg: self.tables.hld_reg.read().diff(&snapshot.hld_reg),
+            in_reg: self.tables.in_reg.read().diff(&snapshot.in_reg),
+        }
+    }
+
+    /// Write values of coils on request of the master
+    pub(crate) fn remote_write_coils(&mut self, address: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+        let old_values = self.remote_replace(&self.tables.coils, address, values.len(), |_| values.to_vec())?;
+        lock_hooks(&self.tables.coil_hooks).fire(address, &old_values, values);
+        Ok(())
+    }
+
+    /// Write values of holding registers on request of the master
+    pub(crate) fn remote_write_hld_reg(&mut self, address: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+        let old_values = self.remote_replace(&self.tables.hld_reg, address, values.len(), |_| values.to_vec())?;
+        lock_hooks(&self.tables.hld_reg_hooks).fire(address, &old_values, values);
+        Ok(())
+    }
+
+    /// Modify a holding register on request of the master, without other accesses in between
+    pub(crate) fn remote_update_hld_reg<F: FnOnce(u16) -> u16>(&mut self, address: u16, update: F) -> Result<(), ExceptionCode> {
+        let mut new_value = 0;
+        let old_values = self.remote_replace(&self.tables.hld_reg, address, 1, |old_values| {
+            new_value = update(old_values[0]);
+            vec![new_value]
+        })?;
+        lock_hooks(&self.tables.hld_reg_hooks).fire(address, &old_values, &[new_value]);
+        Ok(())
+    }
+
+    /// Replace values of a table with values created from the current ones, holding the lock of the table
+    ///
+    /// Hooks shall be fired by the caller after the lock is released, so that they can access the data store.
+    fn remote_replace<T, F>(&self, table: &Locked<T>, address: u16, quantity: usize, values: F) -> Result<Vec<T>, ExceptionCode>
+        where T: Clone + Default, F: FnOnce(&[T]) -> Vec<T>
+    {
+        let mut table = table.write();
+        let (window, idx) = table.get_window(address, quantity)?;
+        if table.is_read_only(address, quantity) {
+            return Err(self.read_only_exc);
+        }
+
+        let values = values(&table.windows[window].values[idx..idx + quantity]);
+        let old_values = table.replace(address, &values)?;
+        table.refresh(address, quantity);
+        Ok(old_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_read_write() {
+        let mut store = DataStore::new().with_hld_reg(0x0100..=0x01ff);
+        store.write_hld_reg(0x01fe, &[0xcafe, 0xface]).unwrap();
+
+        assert_eq!(store.read_hld_reg(0x01fd, 3), Ok(vec![0x0000, 0xcafe, 0xface]));
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let mut store = DataStore::new()
+            .with_coils(0x0000..=0x000f)
+            .with_hld_reg(0x0100..=0x01ff)
+            .with_hld_reg(0x1000..=0x1001);
+        store.write_hld_reg(0x0100, &[0x0001]).unwrap();
+        let snapshot = store.snapshot();
+        assert!(store.diff(&snapshot).is_empty());
+
+        store.write_hld_reg(0x0100, &[0x0001, 0x0002]).unwrap();
+        store.remote_write_hld_reg(0x1001, &[0xcafe]).unwrap();
+        store.write_coils(0x000f, &[true]).unwrap();
+
+        let diff = store.diff(&snapshot);
+        assert_eq!(diff.get_hld_reg(), &[(0x0101, 0x0002), (0x1001, 0xcafe)]);
+        assert_eq!(diff.get_coils(), &[(0x000f, true)]);
+        assert!(diff.get_dscr_in().is_empty());
+        assert!(store.diff(&store.snapshot()).is_empty());
+
+        let other = DataStore::new().with_in_reg(0x0000..=0x0001);
+        assert_eq!(other.diff(&snapshot).get_in_reg(), &[(0x0000, 0x0000), (0x0001, 0x0000)]);
+    }
+
+    #[test]
+    fn test_atomic_multi_register_write() {
+        let store = DataStore::new().with_hld_reg(0x0000..=0x0003);
+        let mut writer = store.clone();
+
+        let handle = std::thread::spawn(move || {
+            for value in 0..2000u16 {
+                writer.remote_write_hld_reg(0x0001, &[value, value, value]).unwrap();
+            }
+        });
+        while !handle.is_finished() {
+            let values = store.read_hld_reg(0x0001, 3).unwrap();
+            assert!(values.iter().all(|value| *value == values[0]), "Torn write: {:?}", values);
+        }
+        handle.join().unwrap();
+        assert_eq!(store.read_hld_reg(0x0001, 3), Ok(vec![1999; 3]));
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let mut store = DataStore::new()
+            .with_coils(0x0000..=0x000f)
+            .with_hld_reg(0x0000..=0x000f)
+            .with_coil_watchdog(0x0001..=0x0002, Duration::from_millis(500), false)
+            .with_hld_reg_watchdog(0x0004..=0x0004, Duration::from_millis(500), 0xffff);
+
+        store.write_coils(0x0002, &[true]).unwrap();
+        store.remote_write_coils(0x0000, &[true, true]).unwrap();
+        store.remote_write_hld_reg(0x0003, &[0x0001, 0x0002]).unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        store.remote_write_coils(0x0001, &[true]).unwrap();
+        assert_eq!(store.read_coils(0x0000, 3), Ok(vec![true, true, true]));
+
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(store.read_coils(0x0000, 3), Ok(vec![true, true, true]));
+        assert_eq!(store.read_hld_reg(0x0003, 2), Ok(vec![0x0001, 0xffff]));
+
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(store.read_coils(0x0000, 3), Ok(vec![true, false, true]));
+    }
+
+    #[test]
+    fn test_read_outside_range() {
+        let store = DataStore::new().with_in_reg(0x0100..=0x01ff);
+
+        assert_eq!(store.read_in_reg(0x00ff, 1), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(store.read_in_reg(0x01ff, 2), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(store.read_dscr_in(0x0000, 1), Err(ExceptionCode::IllegalDataAddress));
+    }
+
+    #[test]
+    fn test_write_outside_range() {
+        let mut store = DataStore::new().with_coils(0x0000..=0x0007);
+
+        assert_eq!(store.write_coils(0x0007, &[true, true]), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(store.read_coils(0x0007, 1), Ok(vec![false]));
+    }
+
+    #[test]
+    fn test_multiple_windows() {
+        let mut store = DataStore::new()
+            .with_hld_reg(0x0000..=0x01ff)
+            .with_hld_reg(0x1000..=0x10ff);
+
+        store.write_hld_reg(0x1000, &[0xabcd]).unwrap();
+        assert_eq!(store.read_hld_reg(0x1000, 1), Ok(vec![0xabcd]));
+        assert_eq!(store.read_hld_reg(0x0000, 1), Ok(vec![0x0000]));
+        assert_eq!(store.read_hld_reg(0x0200, 1), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(store.read_hld_reg(0x10ff, 2), Err(ExceptionCode::IllegalDataAddress));
+    }
+
+    #[test]
+    fn test_crossing_adjacent_windows() {
+        let mut store = DataStore::new()
+            .with_coils(0x0000..=0x0007)
+            .with_coils(0x0008..=0x000f);
+
+        assert_eq!(store.read_coils(0x0007, 1), Ok(vec![false]));
+        assert_eq!(store.read_coils(0x0008, 1), Ok(vec![false]));
+        assert_eq!(store.read_coils(0x0007, 2), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(store.write_coils(0x0006, &[true, true, true]), Err(ExceptionCode::IllegalDataAddress));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_overlapping_windows() {
+        DataStore::new()
+            .with_in_reg(0x0000..=0x0010)
+            .with_in_reg(0x0010..=0x0020);
+    }
+
+    #[test]
+    fn test_read_only_hld_reg() {
+        let mut store = DataStore::new()
+            .with_hld_reg(0x0000..=0x00ff)
+            .with_read_only_hld_reg(0x0010..=0x001f);
+
+        assert_eq!(store.remote_write_hld_reg(0x000f, &[0x0001, 0x0002]), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(store.remote_write_hld_reg(0x0020, &[0x0003]), Ok(()));
+        assert_eq!(store.read_hld_reg(0x000f, 2), Ok(vec![0x0000, 0x0000]));
+
+        store.write_hld_reg(0x0010, &[0x1234]).unwrap();
+        assert_eq!(store.read_hld_reg(0x0010, 1), Ok(vec![0x1234]));
+    }
+
+    #[test]
+    fn test_read_only_coil_exception() {
+        let mut store = DataStore::new()
+            .with_coils(0x0000..=0x000f)
+            .with_read_only_coils(0x0003..=0x0003)
+            .with_read_only_exception(ExceptionCode::IllegalDataValue);
+
+        assert_eq!(store.remote_write_coils(0x0003, &[true]), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(store.remote_write_coils(0x0020, &[true]), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(store.remote_write_coils(0x0002, &[true]), Ok(()));
+    }
+
+    #[test]
+    fn test_write_hooks() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let mut store = DataStore::new()
+            .with_hld_reg(0x0000..=0x00ff)
+            .with_read_only_hld_reg(0x0000..=0x0000);
+        store.write_hld_reg(0x0001, &[0x0011, 0x0022]).unwrap();
+
+        let hook_changes = changes.clone();
+        store.add_hld_reg_write_hook(Box::new(move |address, old, new| {
+            hook_changes.lock().unwrap().push((address, old, new));
+        }));
+
+        store.remote_write_hld_reg(0x0001, &[0x0111, 0x0222]).unwrap();
+        store.remote_write_hld_reg(0x0000, &[0x0333]).err().unwrap();
+        store.write_hld_reg(0x0003, &[0x0444]).unwrap();
+
+        assert_eq!(*changes.lock().unwrap(), vec![(0x0001, 0x0011, 0x0111), (0x0002, 0x0022, 0x0222)]);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut store = DataStore::new()
+            .with_coils(0x0000..=0x000f)
+            .with_hld_reg(0x0000..=0x00ff)
+            .with_hld_reg(0x1000..=0x10ff);
+        store.write_coils(0x0002, &[true, true]).unwrap();
+        store.write_hld_reg(0x10fe, &[0x1234, 0x5678]).unwrap();
+
+        let mut data = Vec::new();
+        store.save(&mut data).unwrap();
+
+        let mut restored = DataStore::new()
+            .with_coils(0x0000..=0x000f)
+            .with_hld_reg(0x0000..=0x00ff)
+            .with_hld_reg(0x1000..=0x10ff);
+        restored.load(&data[..]).unwrap();
+
+        assert_eq!(restored.read_coils(0x0001, 3), Ok(vec![false, true, true]));
+        assert_eq!(restored.read_hld_reg(0x10fe, 2), Ok(vec![0x1234, 0x5678]));
+    }
+
+    #[test]
+    fn test_load_mismatched_windows() {
+        let store = DataStore::new().with_in_reg(0x0000..=0x00ff);
+        let mut data = Vec::new();
+        store.save(&mut data).unwrap();
+
+        let mut restored = DataStore::new().with_in_reg(0x0000..=0x000f);
+        match restored.load(&data[..]) {
+            Err(Error::InvalidData) => {}
+            result => panic!("Expected InvalidData, but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_load_keeps_values_on_error() {
+        let mut store = DataStore::new()
+            .with_coils(0x0000..=0x000f)
+            .with_in_reg(0x0000..=0x00ff);
+        store.write_coils(0x0000, &[true]).unwrap();
+        let mut data = Vec::new();
+        store.save(&mut data).unwrap();
+
+        let mut restored = DataStore::new()
+            .with_coils(0x0000..=0x000f)
+            .with_in_reg(0x0000..=0x000f);
+        assert!(restored.load(&data[..]).is_err());
+        assert_eq!(restored.read_coils(0x0000, 1), Ok(vec![false]));
+    }
+
+    #[test]
+    fn test_save_to_file() {
+        let path = std::env::temp_dir().join(format!("modbus-store-{}.bin", std::process::id()));
+        let mut store = DataStore::new().with_hld_reg(0x0000..=0x000f);
+        store.write_hld_reg(0x0001, &[0xcafe]).unwrap();
+        store.save_to_file(&path).unwrap();
+        store.write_hld_reg(0x0001, &[0xbeef]).unwrap();
+        store.save_to_file(&path).unwrap();
+
+        let mut restored = DataStore::new().with_hld_reg(0x0000..=0x000f);
+        restored.load_from_file(&path).unwrap();
+        assert_eq!(restored.read_hld_reg(0x0001, 1), Ok(vec![0xbeef]));
+        assert!(!path.with_extension("bin.tmp").exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_invalid_header() {
+        let mut store = DataStore::new();
+        assert!(store.load(&b"MBDX\x01"[..]).is_err());
+    }
+
+    #[test]
+    fn test_full_address_space() {
+        let mut store = DataStore::new().with_hld_reg(0x0000..=0xffff);
+        store.write_hld_reg(0xffff, &[0x1234]).unwrap();
+
+        assert_eq!(store.read_hld_reg(0xffff, 1), Ok(vec![0x1234]));
+    }
+}
@@ -0,0 +1,299 @@
+//! Modbus slave device simulator
+//!
+//! The simulator is a [Server] with data preloaded from a [Pattern]. It can be
+//! configured to misbehave: delay responses, drop responses or answer with
+//! exceptions. Values can be driven by [Signal]s changing in time. It is intended
+//! for integration testing of Modbus masters.
+
+pub mod signal;
+
+pub use signal::Signal;
+
+use crate::address::Table;
+use crate::cancel::CancelToken;
+use crate::error::Error;
+use crate::pdu::{encode_exc_rsp, ExceptionCode};
+use crate::server::{DataStore, Server};
+use crate::transport::Transport;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Pattern of values preloaded to the simulated device
+///
+/// Coils and discrete inputs are set when the corresponding register value is nonzero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern {
+    /// All values are zero
+    Zeros,
+    /// All registers have given value
+    Constant(u16),
+    /// Each register holds its own address
+    Address,
+    /// Registers alternate between 0x5555 and 0xAAAA, bits alternate starting from `false`
+    Alternating,
+}
+
+impl Pattern {
+    /// Get value of the register with given address
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::simulator::Pattern;
+    ///
+    /// assert_eq!(Pattern::Address.get_register(0x1234), 0x1234);
+    /// assert_eq!(Pattern::Alternating.get_register(0x0001), 0xaaaa);
+    /// ```
+    pub fn get_register(&self, address: u16) -> u16 {
+        match self {
+            Pattern::Zeros => 0x0000,
+            Pattern::Constant(value) => *value,
+            Pattern::Address => address,
+            Pattern::Alternating => if address.is_multiple_of(2) { 0x5555 } else { 0xaaaa },
+        }
+    }
+
+    /// Get value of the coil or discrete input with given address
+    pub fn get_bit(&self, address: u16) -> bool {
+        match self {
+            Pattern::Alternating => !address.is_multiple_of(2),
+            _ => self.get_register(address) != 0,
+        }
+    }
+
+    fn create_store(&self) -> DataStore {
+        let mut store = DataStore::new()
+            .with_coils(0x0000..=0xffff)
+            .with_dscr_in(0x0000..=0xffff)
+            .with_hld_reg(0x0000..=0xffff)
+            .with_in_reg(0x0000..=0xffff);
+
+        let bits: Vec<bool> = (0..=0xffff).map(|address| self.get_bit(address)).collect();
+        let registers: Vec<u16> = (0..=0xffff).map(|address| self.get_register(address)).collect();
+
+        store.write_coils(0, &bits).unwrap();
+        store.write_dscr_in(0, &bits).unwrap();
+        store.write_hld_reg(0, &registers).unwrap();
+        store.write_in_reg(0, &registers).unwrap();
+
+        store
+    }
+}
+
+/// Simulated Modbus slave device with configurable faults
+pub struct Simulator<T: Transport> {
+    server: Server<T>,
+
+    delay: Duration,
+    drop_every: Option<u32>,
+    exceptions: Vec<(Option<u8>, ExceptionCode)>,
+    signals: Vec<(Table, u16, Signal)>,
+
+    req_cnt: u32,
+    created: Instant,
+}
+
+impl<T: Transport> Simulator<T> {
+    /// Create a new simulator using given transport with the whole address space preloaded with given pattern
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::simulator::{Pattern, Simulator};
+    /// use std::time::Duration;
+    ///
+    /// let mut sim = Simulator::new(modbus::tcp::Tcp::new(), Pattern::Address)
+    ///     .with_delay(Duration::from_millis(200))
+    ///     .with_dropped_responses(5);
+    /// sim.start(10).unwrap();
+    /// sim.serve_forever().unwrap();
+    /// ```
+    pub fn new(transport: T, pattern: Pattern) -> Self {
+        Self::with_store(transport, pattern.create_store())
+    }
+
+    /// Create a new simulator using given transport and data store
+    pub fn with_store(transport: T, store: DataStore) -> Self {
+        Self {
+            server: Server::new(transport, store),
+            delay: Duration::from_secs(0),
+            drop_every: None,
+            exceptions: Vec::new(),
+            signals: Vec::new(),
+            req_cnt: 0,
+            created: Instant::now(),
+        }
+    }
+
+    /// Delay each response by given time
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Drop every n-th response
+    ///
+    /// # Panics
+    /// Panics if `n` is 0.
+    pub fn with_dropped_responses(mut self, n: u32) -> Self {
+        assert!(n > 0);
+        self.drop_every = Some(n);
+        self
+    }
+
+    /// Answer all requests with given exception
+    pub fn with_exception(mut self, exc_code: ExceptionCode) -> Self {
+        self.exceptions.push((None, exc_code));
+        self
+    }
+
+    /// Answer requests with given function code with given exception
+    pub fn with_function_exception(mut self, function_code: u8, exc_code: ExceptionCode) -> Self {
+        self.exceptions.push((Some(function_code), exc_code));
+        self
+    }
+
+    /// Drive the value at given address of given table with a signal
+    ///
+    /// Signals start when the simulator is created and values are updated before processing each
+    /// request. Signals driving addresses missing in the data store are ignored.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::address::Table;
+    /// use modbus::simulator::{Pattern, Signal, Simulator};
+    /// use std::time::Duration;
+    ///
+    /// let mut sim = Simulator::new(modbus::tcp::Tcp::new(), Pattern::Zeros)
+    ///     .with_signal(Table::InputRegisters, 0x0000, Signal::sine(Duration::from_secs(60), 50.0, 200.0))
+    ///     .with_signal(Table::InputRegisters, 0x0001, Signal::random_walk(3, 0, 100))
+    ///     .with_signal(Table::DiscreteInputs, 0x0000, Signal::square(Duration::from_secs(2), 0, 1));
+    /// sim.start(10).unwrap();
+    /// sim.serve_forever().unwrap();
+    /// ```
+    pub fn with_signal(mut self, table: Table, address: u16, signal: Signal) -> Self {
+        self.signals.push((table, address, signal));
+        self
+    }
+
+    /// Update values driven by signals to the current time
+    pub fn update_signals(&mut self) {
+        let elapsed = self.created.elapsed();
+        let store = self.server.get_store_mut();
+
+        for (table, address, signal) in self.signals.iter_mut() {
+            let value = signal.get_value(elapsed);
+            let _ = match table {
+                Table::Coils => store.write_coils(*address, &[value != 0]),
+                Table::DiscreteInputs => store.write_dscr_in(*address, &[value != 0]),
+                Table::HoldingRegisters => store.write_hld_reg(*address, &[value]),
+                Table::InputRegisters => store.write_in_reg(*address, &[value]),
+            };
+        }
+    }
+
+    /// Start simulating device with given unit id
+    pub fn start(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.server.start(unit_id)
+    }
+
+    /// Get the data store of the simulated device
+    pub fn get_store_mut(&mut self) -> &mut DataStore {
+        self.server.get_store_mut()
+    }
+
+    /// Read a single request and answer it according to the configured faults
+    pub fn process_req(&mut self) -> Result<(), Error> {
+        let (req_pdu, mut stream) = self.server.get_transport_mut().read_req_pdu()?;
+
+        if let Some(rsp_pdu) = self.create_rsp(T::get_unit_id(&stream), &req_pdu)? {
+            sleep(self.delay);
+            self.server.get_transport_mut().write_rsp_pdu(&mut stream, &rsp_pdu)?;
+        }
+
+        Ok(())
+    }
+
+    /// Serve requests until the process is terminated
+    pub fn serve_forever(&mut self) -> Result<(), Error> {
+        self.serve_until(&CancelToken::new())
+    }
+
+    /// Serve requests until given token is cancelled
+    ///
+    /// It works like [Server::serve_until](crate::server::Server::serve_until).
+    pub fn serve_until(&mut self, cancel_token: &CancelToken) -> Result<(), Error> {
+        if !self.server.is_started() {
+            return Err(Error::InvalidValue);
+        }
+
+        crate::cancel::serve_until(cancel_token, || self.process_req())
+    }
+
+    fn create_rsp(&mut self, unit_id: u8, req_pdu: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        if req_pdu.is_empty() {
+            return Err(Error::InvalidDataLength);
+        }
+
+        self.update_signals();
+        self.req_cnt = self.req_cnt.wrapping_add(1);
+        if let Some(n) = self.drop_every {
+            if self.req_cnt.is_multiple_of(n) {
+                return Ok(None);
+            }
+        }
+
+        let function_code = req_pdu[0];
+        let exception = self.exceptions.iter()
+            .find(|(code, _)| code.is_none() || *code == Some(function_code))
+            .map(|(_, exc_code)| *exc_code);
+
+        match exception {
+            Some(exc_code) => Ok(Some(encode_exc_rsp(function_code, exc_code))),
+            None => Ok(Some(self.server.execute_req(unit_id, req_pdu)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+
+    #[test]
+    fn test_pattern_store() {
+        let store = Pattern::Address.create_store();
+
+        assert_eq!(store.read_hld_reg(0xfffe, 2), Ok(vec![0xfffe, 0xffff]));
+        assert_eq!(store.read_coils(0x0000, 2), Ok(vec![false, true]));
+    }
+
+    #[test]
+    fn test_dropped_responses() {
+        let mut sim = Simulator::with_store(MockTransport::new(), DataStore::new())
+            .with_dropped_responses(3)
+            .with_exception(ExceptionCode::Acknowledge);
+        let req_pdu = [0x03, 0x00, 0x00, 0x00, 0x01];
+
+        let rsps: Vec<bool> = (0..6).map(|_| sim.create_rsp(10, &req_pdu).unwrap().is_some()).collect();
+        assert_eq!(rsps, vec![true, true, false, true, true, false]);
+    }
+
+    #[test]
+    fn test_signals() {
+        let mut sim = Simulator::with_store(MockTransport::new(), DataStore::new().with_in_reg(0x0000..=0x000f))
+            .with_signal(Table::InputRegisters, 0x0002, Signal::replay(&[7]).unwrap())
+            .with_signal(Table::Coils, 0x0000, Signal::replay(&[1]).unwrap());
+        sim.start(1).unwrap();
+
+        let rsp = sim.create_rsp(1, &[0x04, 0x00, 0x02, 0x00, 0x01]).unwrap();
+        assert_eq!(rsp, Some(vec![0x04, 0x02, 0x00, 0x07]));
+    }
+
+    #[test]
+    fn test_function_exception() {
+        let mut sim = Simulator::with_store(MockTransport::new(), DataStore::new().with_hld_reg(0x0000..=0x000f))
+            .with_function_exception(0x06, ExceptionCode::ServerDeviceBusy);
+
+        let rsp = sim.create_rsp(0, &[0x06, 0x00, 0x00, 0x00, 0x01]).unwrap();
+        assert_eq!(rsp, Some(vec![0x86, ExceptionCode::ServerDeviceBusy as u8]));
+    }
+}
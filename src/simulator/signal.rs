@@ -0,0 +1,223 @@
+//! Signals driving values of a simulated device
+//!
+//! A [Signal] produces a value of a register changing in time. The value is updated once per
+//! interval, 100 ms by default, so masters polling the simulator see data changing like
+//! readings of a real device.
+
+use crate::error::Error;
+use crate::rng::Rng;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug)]
+enum Waveform {
+    Sine {period: Duration, amplitude: f64, offset: f64},
+    Ramp {period: Duration, min: u16, max: u16},
+    Square {period: Duration, low: u16, high: u16},
+    RandomWalk {step: u16, min: u16, max: u16, value: u16, rng: Rng},
+    Replay {values: Vec<u16>},
+}
+
+/// Generator of values of a simulated register
+///
+/// Values of coils and discrete inputs driven by a signal are set when the value is nonzero.
+///
+/// # Examples
+/// ```
+/// use modbus::simulator::Signal;
+/// use std::time::Duration;
+///
+/// let mut signal = Signal::ramp(Duration::from_secs(10), 0, 1000);
+/// assert_eq!(signal.get_value(Duration::from_secs(0)), 0);
+/// assert_eq!(signal.get_value(Duration::from_secs(5)), 500);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Signal {
+    waveform: Waveform,
+    interval: Duration,
+    tick: u64,
+}
+
+impl Signal {
+    fn new(waveform: Waveform) -> Self {
+        Self {waveform, interval: DEFAULT_INTERVAL, tick: 0}
+    }
+
+    /// Create a sine wave with given period, oscillating by `amplitude` around `offset`
+    pub fn sine(period: Duration, amplitude: f64, offset: f64) -> Self {
+        Self::new(Waveform::Sine {period, amplitude, offset})
+    }
+
+    /// Create a sawtooth wave rising from `min` to `max` during each period
+    pub fn ramp(period: Duration, min: u16, max: u16) -> Self {
+        Self::new(Waveform::Ramp {period, min, max})
+    }
+
+    /// Create a square wave being `high` during the first half of each period and `low` during the second one
+    pub fn square(period: Duration, low: u16, high: u16) -> Self {
+        Self::new(Waveform::Square {period, low, high})
+    }
+
+    /// Create a random walk between `min` and `max`, changing by up to `step` each interval
+    ///
+    /// The walk starts in the middle of the range and is seeded from the current time.
+    pub fn random_walk(step: u16, min: u16, max: u16) -> Self {
+        let value = ((min as u32 + max as u32) / 2) as u16;
+        Self::new(Waveform::RandomWalk {step, min, max, value, rng: Rng::from_time()})
+    }
+
+    /// Create a signal replaying given values in a loop, a value per interval
+    ///
+    /// Returns [Error::InvalidValue] if `values` is empty.
+    pub fn replay(values: &[u16]) -> Result<Self, Error> {
+        if values.is_empty() {
+            return Err(Error::InvalidValue);
+        }
+
+        Ok(Self::new(Waveform::Replay {values: values.to_vec()}))
+    }
+
+    /// Create a signal replaying values from given column of a CSV file, a row per interval
+    ///
+    /// Columns are separated with commas and numbered from 0. A first row which is not a number
+    /// is treated as a header. Values are rounded and limited to the range of a register.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::simulator::Signal;
+    ///
+    /// let signal = Signal::from_csv("recorded_flow.csv", 1).unwrap();
+    /// ```
+    pub fn from_csv<P: AsRef<Path>>(path: P, column: usize) -> Result<Self, Error> {
+        let data = fs::read_to_string(path)?;
+        let mut values = Vec::new();
+
+        for (i, line) in data.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let value = line.split(',').nth(column).and_then(|value| value.trim().parse::<f64>().ok());
+            match value {
+                Some(value) => values.push(to_register(value)),
+                None if i == 0 => continue,
+                None => return Err(Error::InvalidData),
+            }
+        }
+
+        Self::replay(&values)
+    }
+
+    /// Set time between updates of the value, 100 ms by default
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        assert!(!interval.is_zero());
+        self.interval = interval;
+        self
+    }
+
+    /// Set seed of a random walk, making its values reproducible
+    ///
+    /// It has no effect on other signals.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        if let Waveform::RandomWalk {rng, ..} = &mut self.waveform {
+            *rng = Rng::new(seed);
+        }
+        self
+    }
+
+    /// Get value of the signal given time after it started
+    ///
+    /// A random walk does not go back in time, so it keeps its current value if `elapsed` precedes
+    /// the time of a value already generated.
+    pub fn get_value(&mut self, elapsed: Duration) -> u16 {
+        let tick = (elapsed.as_nanos() / self.interval.as_nanos()) as u64;
+        let time = self.interval.as_secs_f64() * tick as f64;
+
+        let value = match &mut self.waveform {
+            Waveform::Sine {period, amplitude, offset} => {
+                to_register(*offset + *amplitude * (2.0 * PI * time / period.as_secs_f64()).sin())
+            }
+            Waveform::Ramp {period, min, max} => {
+                let phase = get_phase(time, *period);
+                to_register(*min as f64 + phase * (*max as f64 - *min as f64))
+            }
+            Waveform::Square {period, low, high} => if get_phase(time, *period) < 0.5 { *high } else { *low },
+            Waveform::RandomWalk {step, min, max, value, rng} => {
+                for _ in self.tick..tick {
+                    let change = rng.below(2 * *step as u64 + 1) as i64 - *step as i64;
+                    *value = (*value as i64 + change).clamp(*min as i64, *max as i64) as u16;
+                }
+                *value
+            }
+            Waveform::Replay {values} => values[(tick % values.len() as u64) as usize],
+        };
+
+        self.tick = self.tick.max(tick);
+        value
+    }
+}
+
+/// Get part of the period elapsed at given time, in range `[0, 1)`
+fn get_phase(time: f64, period: Duration) -> f64 {
+    if period.is_zero() {
+        0.0
+    } else {
+        (time % period.as_secs_f64()) / period.as_secs_f64()
+    }
+}
+
+fn to_register(value: f64) -> u16 {
+    value.round().clamp(0.0, u16::MAX as f64) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waveforms() {
+        let mut sine = Signal::sine(Duration::from_secs(4), 100.0, 1000.0);
+        let values: Vec<u16> = (0..5).map(|s| sine.get_value(Duration::from_secs(s))).collect();
+        assert_eq!(values, vec![1000, 1100, 1000, 900, 1000]);
+
+        let mut square = Signal::square(Duration::from_secs(2), 1, 7).with_interval(Duration::from_secs(1));
+        let values: Vec<u16> = (0..4).map(|s| square.get_value(Duration::from_millis(s * 1000 + 500))).collect();
+        assert_eq!(values, vec![7, 1, 7, 1]);
+
+        let mut ramp = Signal::ramp(Duration::from_secs(1), 10, 20);
+        assert_eq!(ramp.get_value(Duration::from_millis(550)), 15);
+        assert_eq!(ramp.get_value(Duration::from_millis(1000)), 10);
+    }
+
+    #[test]
+    fn test_random_walk() {
+        let mut walk = Signal::random_walk(5, 0, 20).with_seed(1);
+        let values: Vec<u16> = (0..50).map(|i| walk.get_value(DEFAULT_INTERVAL * i)).collect();
+
+        assert_eq!(values[0], 10);
+        assert!(values.windows(2).all(|pair| pair[0].abs_diff(pair[1]) <= 5));
+        assert!(values.iter().all(|value| *value <= 20));
+        assert!(values.iter().any(|value| *value != 10));
+        assert_eq!(walk.get_value(Duration::ZERO), values[49]);
+
+        let mut same = Signal::random_walk(5, 0, 20).with_seed(1);
+        assert_eq!(same.get_value(DEFAULT_INTERVAL * 49), values[49]);
+    }
+
+    #[test]
+    fn test_csv() {
+        let path = std::env::temp_dir().join(format!("modbus_signal_{}.csv", std::process::id()));
+        fs::write(&path, "time,flow\n0,12.4\n1,-3\n\n2,70000\n").unwrap();
+        let mut signal = Signal::from_csv(&path, 1).unwrap();
+        let invalid = Signal::from_csv(&path, 2);
+        std::fs::remove_file(&path).unwrap();
+
+        let values: Vec<u16> = (0..4).map(|i| signal.get_value(DEFAULT_INTERVAL * i)).collect();
+        assert_eq!(values, vec![12, 0, 65535, 12]);
+        assert!(invalid.is_err());
+        assert!(Signal::replay(&[]).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Metrics of Modbus masters and servers
+//!
+//! Metrics are recorded with the [metrics](https://docs.rs/metrics) facade. They are
+//! discarded unless the application installs a recorder, for example the Prometheus
+//! exporter of the `metrics-exporter-prometheus` crate.
+//!
+//! Transactions of masters are labelled with the function code of the request, formatted as
+//! `0x03`. Exceptions are also labelled with the exception code.
+
+use crate::error::Error;
+use crate::pdu::EXC_FUNCTION_CODE_FLAG;
+use std::io::ErrorKind;
+use std::time::Instant;
+
+/// Counter of requests written by masters
+pub const REQUESTS_TOTAL: &str = "modbus_requests_total";
+/// Counter of exception responses received by masters
+pub const EXCEPTIONS_TOTAL: &str = "modbus_exceptions_total";
+/// Counter of requests left without response
+pub const TIMEOUTS_TOTAL: &str = "modbus_timeouts_total";
+/// Histogram of seconds elapsed between writing a request and reading its response
+pub const RESPONSE_DURATION_SECONDS: &str = "modbus_response_duration_seconds";
+/// Counter of RTU frames dropped because of a CRC mismatch
+pub const CRC_ERRORS_TOTAL: &str = "modbus_crc_errors_total";
+/// Counter of requests served by servers
+pub const SERVER_REQUESTS_TOTAL: &str = "modbus_server_requests_total";
+/// Counter of exception responses written by servers
+pub const SERVER_EXCEPTIONS_TOTAL: &str = "modbus_server_exceptions_total";
+/// Counter of requests rejected by the access control lists of servers
+pub const SERVER_REJECTED_TOTAL: &str = "modbus_server_rejected_total";
+
+fn label(code: u8) -> String {
+    format!("0x{:02x}", code)
+}
+
+fn is_timeout(error: &Error) -> bool {
+    match error.get_root() {
+        Error::NoResponse => true,
+        Error::IoError(error) => matches!(error.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock),
+        _ => false,
+    }
+}
+
+pub(crate) fn record_request(function_code: u8) -> Instant {
+    metrics::counter!(REQUESTS_TOTAL, "function_code" => label(function_code)).increment(1);
+    Instant::now()
+}
+
+pub(crate) fn record_response<T>(function_code: u8, start: Instant, rsp: &Result<T, Error>) {
+    match rsp {
+        Ok(_) => {
+            metrics::histogram!(RESPONSE_DURATION_SECONDS, "function_code" => label(function_code))
+                .record(start.elapsed().as_secs_f64());
+        }
+        Err(error) if is_timeout(error) => {
+            metrics::counter!(TIMEOUTS_TOTAL, "function_code" => label(function_code)).increment(1);
+        }
+        Err(_) => {}
+    }
+}
+
+pub(crate) fn record_decoded<T>(function_code: u8, rsp: &Result<T, Error>) {
+    if let Err(Error::ExceptionResponse(exc_code)) = rsp {
+        metrics::counter!(EXCEPTIONS_TOTAL, "function_code" => label(function_code),
+                          "exception_code" => label(*exc_code as u8)).increment(1);
+    }
+}
+
+#[cfg(any(feature = "serial", feature = "tcp", feature = "embedded"))]
+pub(crate) fn record_crc_error() {
+    metrics::counter!(CRC_ERRORS_TOTAL).increment(1);
+}
+
+pub(crate) fn record_rejected(function_code: u8) {
+    metrics::counter!(SERVER_REJECTED_TOTAL, "function_code" => label(function_code)).increment(1);
+}
+
+pub(crate) fn record_served(rsp_pdu: &[u8]) {
+    let function_code = match rsp_pdu.first() {
+        Some(function_code) => function_code & !EXC_FUNCTION_CODE_FLAG,
+        None => return,
+    };
+    metrics::counter!(SERVER_REQUESTS_TOTAL, "function_code" => label(function_code)).increment(1);
+
+    if rsp_pdu[0] & EXC_FUNCTION_CODE_FLAG != 0 {
+        let exc_code = rsp_pdu.get(1).copied().unwrap_or_default();
+        metrics::counter!(SERVER_EXCEPTIONS_TOTAL, "function_code" => label(function_code),
+                          "exception_code" => label(exc_code)).increment(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::transport::Transport;
+    use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct TestRecorder {
+        counters: Mutex<HashMap<String, Arc<AtomicU64>>>,
+    }
+
+    impl TestRecorder {
+        fn get(&self, key: &str) -> u64 {
+            self.counters.lock().unwrap().get(key).map_or(0, |counter| counter.load(Ordering::Relaxed))
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            let labels: Vec<String> = key.labels().map(|label| format!("{}={}", label.key(), label.value())).collect();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            Counter::from_arc(self.counters.lock().unwrap().entry(name).or_default().clone())
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_master_metrics() {
+        let recorder = TestRecorder::default();
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x03, 0x00, 0x00, 0x00, 0x01], &[0x03, 0x02, 0x00, 0x01]);
+        mb.expect(&[0x03, 0x00, 0x00, 0x00, 0x01], &[0x83, 0x02]);
+        mb.expect_no_response(&[0x03, 0x00, 0x00, 0x00, 0x01]);
+
+        metrics::with_local_recorder(&recorder, || {
+            for _ in 0..3 {
+                let _ = mb.write_req_read_rsp(&1, &crate::ReadHldRegRequest::new(0x0000, 1));
+            }
+        });
+
+        assert_eq!(recorder.get("modbus_requests_total{function_code=0x03}"), 3);
+        assert_eq!(recorder.get("modbus_exceptions_total{function_code=0x03,exception_code=0x02}"), 1);
+        assert_eq!(recorder.get("modbus_timeouts_total{function_code=0x03}"), 1);
+    }
+
+    #[test]
+    #[cfg(any(feature = "serial", feature = "tcp", feature = "embedded"))]
+    fn test_crc_error_metrics() {
+        let recorder = TestRecorder::default();
+        metrics::with_local_recorder(&recorder, record_crc_error);
+
+        assert_eq!(recorder.get("modbus_crc_errors_total{}"), 1);
+    }
+
+    #[test]
+    fn test_server_metrics() {
+        let recorder = TestRecorder::default();
+
+        metrics::with_local_recorder(&recorder, || {
+            record_served(&[0x06, 0x00, 0x01, 0x00, 0x03]);
+            record_served(&[0x86, 0x02]);
+        });
+
+        assert_eq!(recorder.get("modbus_server_requests_total{function_code=0x06}"), 2);
+        assert_eq!(recorder.get("modbus_server_exceptions_total{function_code=0x06,exception_code=0x02}"), 1);
+    }
+}
@@ -0,0 +1,160 @@
+//! Transport owned by a worker thread and shared through cloneable handles
+//!
+//! [spawn] moves a transport to a dedicated thread executing commands sent through a channel,
+//! one at a time, and returns a [Handle] to it. Handles are cheap to clone and need only a shared
+//! reference to request a transaction, so a transport can be used from many components without
+//! wrapping it in a mutex. Each command returns its result through a dedicated channel.
+//!
+//! # Examples
+//! ```
+//! use modbus::Transport;
+//! use modbus::mock::MockTransport;
+//!
+//! let mut device = MockTransport::new();
+//! device.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x34]);
+//!
+//! let mb = modbus::actor::spawn(device).unwrap();
+//! let worker = mb.clone();
+//! let rsp = std::thread::spawn(move || worker.write_req_read_rsp(&1, &modbus::ReadHldRegRequest::new(0x0010, 1)))
+//!     .join().unwrap().unwrap().unwrap();
+//! assert_eq!(rsp.get_registers(), &[0x1234]);
+//! assert!(mb.call(|device| device.is_complete()).unwrap());
+//! ```
+
+use crate::error::Error;
+use crate::pdu::{Request, Setter};
+use std::fmt;
+use std::sync::mpsc;
+use std::thread;
+use super::Transport;
+
+type Command<T> = Box<dyn FnOnce(&mut T) + Send>;
+
+/// Move given transport to a worker thread and get a handle to it
+///
+/// The thread stops, dropping the transport, when all the handles are dropped.
+pub fn spawn<T: Transport + Send + 'static>(mut transport: T) -> Result<Handle<T>, Error> {
+    let (commands, rx) = mpsc::channel::<Command<T>>();
+    thread::Builder::new()
+        .name("modbus-actor".to_string())
+        .spawn(move || {
+            for command in rx {
+                command(&mut transport);
+            }
+        })?;
+    Ok(Handle {commands})
+}
+
+/// Handle to a transport owned by a worker thread
+///
+/// Commands sent through all the clones of a handle are executed in the order they are sent.
+pub struct Handle<T> {
+    commands: mpsc::Sender<Command<T>>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {commands: self.commands.clone()}
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Handle").finish_non_exhaustive()
+    }
+}
+
+impl<T: Transport + 'static> Handle<T> {
+    /// Execute given function with the transport in the worker thread and wait for its result
+    ///
+    /// Returns [Error::NoResponse] if the worker thread stopped, e.g. because an earlier command panicked.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use modbus::Transport;
+    ///
+    /// let mb = modbus::actor::spawn(modbus::tcp::Tcp::new()).unwrap();
+    /// mb.call(|tcp| tcp.start_master()).unwrap().unwrap();
+    /// ```
+    pub fn call<R, F>(&self, f: F) -> Result<R, Error>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut T) -> R + Send + 'static,
+    {
+        let (reply, rx) = mpsc::sync_channel(1);
+        self.commands.send(Box::new(move |transport| {
+            // The caller may have stopped waiting in the meantime
+            let _ = reply.send(f(transport));
+        })).map_err(|_| Error::NoResponse)?;
+        rx.recv().map_err(|_| Error::NoResponse)
+    }
+
+    /// Write a request frame and read a response frame like [Transport::write_req_read_rsp]
+    pub fn write_req_read_rsp<Req>(&self, dst: &T::Dst, req: &Req) -> Result<Option<Req::Rsp>, Error>
+    where
+        T::Dst: Clone + Send,
+        Req: Request + Clone + Send + 'static,
+        Req::Rsp: Send,
+    {
+        let (dst, req) = (dst.clone(), req.clone());
+        self.call(move |transport| transport.write_req_read_rsp(&dst, &req))?
+    }
+
+    /// Write a setter request and verify its response like [Transport::write_setter_req]
+    pub fn write_setter_req<Req>(&self, dst: &T::Dst, req: &Req) -> Result<(), Error>
+    where
+        T::Dst: Clone + Send,
+        Req: Setter + Clone + Send + 'static,
+        Req::Rsp: PartialEq,
+    {
+        let (dst, req) = (dst.clone(), req.clone());
+        self.call(move |transport| transport.write_setter_req(&dst, &req))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+
+    #[test]
+    fn test_commands_from_multiple_threads() {
+        let mut device = MockTransport::new();
+        for _ in 0..4 {
+            device.expect_fn(Box::new(|unit_id, _| Some(vec![0x03, 0x02, 0x00, unit_id])));
+        }
+        let mb = spawn(device).unwrap();
+
+        let threads: Vec<_> = (1..=4).map(|unit_id| {
+            let mb = mb.clone();
+            thread::spawn(move || mb.write_req_read_rsp(&unit_id, &crate::ReadHldRegRequest::new(0x0000, 1)))
+        }).collect();
+
+        for (unit_id, thread) in (1..=4).zip(threads) {
+            let rsp = thread.join().unwrap().unwrap().unwrap();
+            assert_eq!(rsp.get_registers(), &[unit_id as u16]);
+        }
+        assert!(mb.call(|device| device.is_complete()).unwrap());
+    }
+
+    #[test]
+    fn test_setter_req() {
+        let mut device = MockTransport::new();
+        device.expect(&[0x05, 0x00, 0x10, 0xff, 0x00], &[0x05, 0x00, 0x10, 0xff, 0x00]);
+        let mb = spawn(device).unwrap();
+
+        mb.write_setter_req(&1, &crate::WriteSingleCoilRequest::new(0x0010, true)).unwrap();
+    }
+
+    #[test]
+    fn test_worker_stopped() {
+        let mb = spawn(MockTransport::new()).unwrap();
+
+        let result: Result<(), Error> = mb.call(|_| panic!("Command failed"));
+        assert!(result.is_err());
+        match mb.call(|device| device.is_complete()).err().unwrap() {
+            Error::NoResponse => {}
+            err => panic!("Expected NoResponse, but got {:?}", err),
+        }
+    }
+}
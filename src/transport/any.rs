@@ -0,0 +1,256 @@
+//! Transport with the link layer selected at runtime
+//!
+//! [Transport] has associated types, so transports of different link layers cannot be
+//! stored in the same variable. [AnyTransport] wraps any of the link layers enabled in the
+//! crate, allowing applications to choose the link layer, e.g. from their configuration.
+//!
+//! # Examples
+//! ```no_run
+//! # #[cfg(feature = "tcp")] {
+//! use modbus::{Target, Transport};
+//! use modbus::any::AnyTransport;
+//! use std::net::{IpAddr, Ipv4Addr};
+//!
+//! # let use_converter = false;
+//! let (mut mb, dst) = if use_converter {
+//!     let converter = modbus::rtu_over_tcp::RtuOverTcp::connect("192.168.0.20:4001").unwrap();
+//!     (AnyTransport::from(converter), Target::new(10))
+//! } else {
+//!     let ip_addr = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 10));
+//!     (AnyTransport::from(modbus::tcp::Tcp::new()), Target::tcp(ip_addr, 10))
+//! };
+//!
+//! mb.start_master().unwrap();
+//! let rsp = mb.write_req_read_rsp(&dst, &modbus::ReadHldRegRequest::new(0x0000, 2)).unwrap();
+//! # }
+//! ```
+
+use crate::error::Error;
+use std::net::SocketAddr;
+#[cfg(feature = "serial")]
+use super::rtu::conn::Rtu;
+#[cfg(feature = "tcp")]
+use super::rtu::tcp_conn::RtuOverTcp;
+#[cfg(feature = "tcp")]
+use super::tcp::conn as tcp;
+use super::Transport;
+use super::target::Target;
+
+/// Stream used by [AnyTransport] to exchange a transaction
+pub enum AnyStream {
+    /// Stream of the TCP/IP transport
+    #[cfg(feature = "tcp")]
+    Tcp(tcp::Stream),
+    /// Unit id of the transaction over RTU transports
+    Unit(u8),
+}
+
+/// Transport wrapping any of the supported link layers
+///
+/// Requests are addressed with [Targets](Target). The TCP/IP transport requires targets with
+/// a network endpoint, while RTU transports require targets without it. Mismatched targets fail
+/// with [Error::InvalidValue].
+pub enum AnyTransport {
+    /// Modbus TCP/IP
+    #[cfg(feature = "tcp")]
+    Tcp(Box<tcp::Tcp>),
+    /// Modbus RTU over a serial line
+    #[cfg(feature = "serial")]
+    Rtu(Rtu),
+    /// Modbus RTU frames tunneled over TCP/IP
+    #[cfg(feature = "tcp")]
+    RtuOverTcp(RtuOverTcp),
+}
+
+#[cfg(feature = "tcp")]
+impl From<tcp::Tcp> for AnyTransport {
+    fn from(transport: tcp::Tcp) -> Self {
+        AnyTransport::Tcp(Box::new(transport))
+    }
+}
+
+#[cfg(feature = "serial")]
+impl From<Rtu> for AnyTransport {
+    fn from(transport: Rtu) -> Self {
+        AnyTransport::Rtu(transport)
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl From<RtuOverTcp> for AnyTransport {
+    fn from(transport: RtuOverTcp) -> Self {
+        AnyTransport::RtuOverTcp(transport)
+    }
+}
+
+/// Call given method of the wrapped transport
+macro_rules! dispatch {
+    ($self:expr, $transport:ident => $call:expr) => {
+        match $self {
+            #[cfg(feature = "tcp")]
+            AnyTransport::Tcp($transport) => $call,
+            #[cfg(feature = "serial")]
+            AnyTransport::Rtu($transport) => $call,
+            #[cfg(feature = "tcp")]
+            AnyTransport::RtuOverTcp($transport) => $call,
+        }
+    };
+}
+
+/// Call given method of a wrapped RTU transport with the unit id of the target or stream
+macro_rules! dispatch_unit {
+    ($self:expr, $unit:expr, $transport:ident, $unit_id:ident => $call:expr) => {
+        match ($self, $unit) {
+            #[cfg(feature = "serial")]
+            (AnyTransport::Rtu($transport), Some($unit_id)) => $call,
+            #[cfg(feature = "tcp")]
+            (AnyTransport::RtuOverTcp($transport), Some($unit_id)) => $call,
+            _ => Err(Error::InvalidValue),
+        }
+    };
+}
+
+/// Get unit id of a target addressable by RTU transports
+fn get_unit(target: &Target) -> Option<u8> {
+    #[cfg(feature = "tcp")]
+    if target.get_endpoint().is_some() {
+        return None;
+    }
+
+    Some(target.get_unit_id())
+}
+
+impl AnyStream {
+    fn get_unit_mut(&mut self) -> Option<&mut u8> {
+        match self {
+            AnyStream::Unit(unit_id) => Some(unit_id),
+            #[cfg(feature = "tcp")]
+            _ => None,
+        }
+    }
+}
+
+impl Transport for AnyTransport {
+    type Dst = Target;
+    type Stream = AnyStream;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        dispatch!(self, transport => transport.start_master())
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        dispatch!(self, transport => transport.start_slave(unit_id))
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        dispatch!(self, transport => transport.start_slave_units(unit_ids))
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        dst.is_broadcast()
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        match stream {
+            #[cfg(feature = "tcp")]
+            AnyStream::Tcp(stream) => tcp::Tcp::get_unit_id(stream),
+            AnyStream::Unit(unit_id) => *unit_id,
+        }
+    }
+
+    fn get_peer_addr(stream: &Self::Stream) -> Option<SocketAddr> {
+        match stream {
+            #[cfg(feature = "tcp")]
+            AnyStream::Tcp(stream) => tcp::Tcp::get_peer_addr(stream),
+            AnyStream::Unit(_) => None,
+        }
+    }
+
+    fn get_ping_pdu(&self) -> &[u8] {
+        dispatch!(self, transport => transport.get_ping_pdu())
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        #[cfg(feature = "tcp")]
+        if let (AnyTransport::Tcp(transport), Some(dst)) = (&mut *self, dst.get_endpoint()) {
+            return transport.write_req_pdu(dst, pdu).map(AnyStream::Tcp);
+        }
+
+        dispatch_unit!(self, get_unit(dst), transport, unit_id => transport.write_req_pdu(&unit_id, pdu).map(AnyStream::Unit))
+    }
+
+    fn read_rsp_pdu(&mut self, stream: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        #[cfg(feature = "tcp")]
+        if let (AnyTransport::Tcp(transport), AnyStream::Tcp(stream), Some(src)) = (&mut *self, &mut *stream, src.get_endpoint()) {
+            return transport.read_rsp_pdu(stream, src);
+        }
+
+        let unit = stream.get_unit_mut().zip(get_unit(src));
+        dispatch_unit!(self, unit, transport, unit => transport.read_rsp_pdu(unit.0, &unit.1))
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        match self {
+            #[cfg(feature = "tcp")]
+            AnyTransport::Tcp(transport) => transport.read_req_pdu().map(|(pdu, stream)| (pdu, AnyStream::Tcp(stream))),
+            #[cfg(feature = "serial")]
+            AnyTransport::Rtu(transport) => transport.read_req_pdu().map(|(pdu, stream)| (pdu, AnyStream::Unit(stream))),
+            #[cfg(feature = "tcp")]
+            AnyTransport::RtuOverTcp(transport) => transport.read_req_pdu().map(|(pdu, stream)| (pdu, AnyStream::Unit(stream))),
+        }
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        #[cfg(feature = "tcp")]
+        if let (AnyTransport::Tcp(transport), AnyStream::Tcp(stream)) = (&mut *self, &mut *stream) {
+            return transport.write_rsp_pdu(stream, pdu);
+        }
+
+        dispatch_unit!(self, stream.get_unit_mut(), transport, unit_id => transport.write_rsp_pdu(unit_id, pdu))
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr, TcpListener};
+    use std::thread;
+
+    #[test]
+    fn test_transaction() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let slave = thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            let mut mb = AnyTransport::from(RtuOverTcp::new(socket).unwrap());
+            mb.start_slave(2).unwrap();
+
+            let (req_pdu, stream) = mb.read_req_pdu().unwrap();
+            assert_eq!(req_pdu, vec![0x03, 0x00, 0x04, 0x00, 0x01]);
+            mb.write_rsp(stream, crate::ReadHldRegResponse::new(&[0x1234])).unwrap();
+        });
+
+        let mut mb = AnyTransport::from(RtuOverTcp::connect(addr).unwrap());
+        let rsp = mb.write_req_read_rsp(&Target::new(2), &crate::ReadHldRegRequest::new(0x0004, 0x0001)).unwrap();
+        slave.join().unwrap();
+
+        assert_eq!(rsp.unwrap().get_registers(), &vec![0x1234]);
+    }
+
+    #[test]
+    fn test_mismatched_dst() {
+        let mut mb = AnyTransport::from(tcp::Tcp::new());
+        mb.start_master().unwrap();
+
+        let err = mb.write_req_pdu(&Target::new(1), &[0x03, 0x00, 0x00, 0x00, 0x01]).err().unwrap();
+        assert!(matches!(err, Error::InvalidValue));
+    }
+
+    #[test]
+    fn test_broadcast() {
+        assert!(AnyTransport::is_broadcast(&Target::new(0)));
+        assert!(!AnyTransport::is_broadcast(&Target::new(1)));
+        assert!(AnyTransport::is_broadcast(&Target::tcp(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)));
+    }
+}
@@ -0,0 +1,50 @@
+//! Capture of raw frames exchanged by transports
+//!
+//! An [Observer] set on a transport receives every frame with its address and checksum
+//! (RTU) or MBAP header (TCP/IP): transmitted frames after encoding and received frames
+//! before decoding. It allows debugging and recording the traffic.
+
+use std::time::SystemTime;
+
+/// Direction of a captured frame
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    /// Frame written by the transport
+    Tx,
+    /// Frame read by the transport
+    Rx,
+}
+
+/// Callback receiving the direction, the timestamp and the raw bytes of captured frames
+pub type Observer = Box<dyn FnMut(Direction, SystemTime, &[u8]) + Send>;
+
+// Only the serial and TCP/IP transports capture frames
+#[cfg_attr(not(any(feature = "serial", feature = "tcp")), allow(dead_code))]
+pub(crate) fn notify(observer: &mut Option<Observer>, direction: Direction, frame_data: &[u8]) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(?direction, frame = %crate::fmt::HexDump::new(frame_data), "frame");
+
+    if let Some(observer) = observer {
+        observer(direction, SystemTime::now(), frame_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_notify() {
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let captured = frames.clone();
+        let mut observer: Option<Observer> = Some(Box::new(move |direction, _, frame_data| {
+            captured.lock().unwrap().push((direction, frame_data.to_vec()));
+        }));
+
+        notify(&mut observer, Direction::Tx, &[0x02, 0x07]);
+        notify(&mut None, Direction::Rx, &[0x02, 0x07]);
+
+        assert_eq!(*frames.lock().unwrap(), vec![(Direction::Tx, vec![0x02, 0x07])]);
+    }
+}
@@ -0,0 +1,254 @@
+//! Injection of latency for testing timeouts and retries
+//!
+//! A [DelayedTransport] wraps a transport and delays writing and reading PDUs by fixed or
+//! random times, the way a slow device or a congested network would. With a response timeout
+//! set, responses delayed longer than the timeout are discarded and reported as missing, so
+//! timeout and retry configurations can be validated deterministically.
+//!
+//! # Examples
+//! ```
+//! use modbus::Transport;
+//! use modbus::delay::{Delay, DelayedTransport};
+//! use modbus::mock::MockTransport;
+//! use std::time::Duration;
+//!
+//! let mut device = MockTransport::new();
+//! device.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x34]);
+//!
+//! let mut mb = DelayedTransport::new(device)
+//!     .with_read_delay(Delay::fixed(Duration::from_millis(20)))
+//!     .with_timeout(Duration::from_millis(10));
+//!
+//! assert!(matches!(mb.write_req_read_rsp(&1, &modbus::ReadHldRegRequest::new(0x0010, 1)),
+//!                  Err(modbus::Error::NoResponse)));
+//! ```
+
+use crate::error::Error;
+use crate::rng::Rng;
+use std::fmt;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::Duration;
+use super::Transport;
+
+/// Time a PDU is delayed by
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Delay {
+    min: Duration,
+    max: Duration,
+}
+
+impl Delay {
+    /// Create a delay of a constant time
+    pub fn fixed(delay: Duration) -> Self {
+        Self {min: delay, max: delay}
+    }
+
+    /// Create a delay uniformly distributed between `min` and `max`
+    ///
+    /// # Panics
+    /// Panics if `min` is greater than `max`.
+    pub fn random(min: Duration, max: Duration) -> Self {
+        assert!(min <= max);
+        Self {min, max}
+    }
+
+    /// Get the shortest time of the delay
+    pub fn get_min(&self) -> Duration {
+        self.min
+    }
+
+    /// Get the longest time of the delay
+    pub fn get_max(&self) -> Duration {
+        self.max
+    }
+
+    fn draw(&self, rng: &mut Rng) -> Duration {
+        let range = (self.max - self.min).as_nanos() as u64;
+        self.min + Duration::from_nanos(rng.below(range.saturating_add(1)))
+    }
+}
+
+/// Transport delaying PDUs written and read through it
+///
+/// The write delay is applied before writing requests and responses, the read delay before
+/// returning received requests and responses. There are no delays by default.
+pub struct DelayedTransport<T: Transport> {
+    transport: T,
+    write_delay: Delay,
+    read_delay: Delay,
+    timeout: Option<Duration>,
+    rng: Rng,
+}
+
+impl<T: Transport> DelayedTransport<T> {
+    /// Create a transport passing PDUs of given transport without delays
+    ///
+    /// Random delays are drawn from a generator seeded from the current time.
+    pub fn new(transport: T) -> Self {
+        Self {transport, write_delay: Delay::default(), read_delay: Delay::default(), timeout: None, rng: Rng::from_time()}
+    }
+
+    /// Set delay of writing requests and responses
+    pub fn with_write_delay(mut self, delay: Delay) -> Self {
+        self.write_delay = delay;
+        self
+    }
+
+    /// Set delay of reading requests and responses
+    pub fn with_read_delay(mut self, delay: Delay) -> Self {
+        self.read_delay = delay;
+        self
+    }
+
+    /// Set response timeout of the master mode
+    ///
+    /// A response with the read delay not shorter than the timeout is discarded after waiting
+    /// for the timeout, and [Error::NoResponse] is returned instead.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set seed of the generator drawing random delays, making them reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// Get mutable reference to the wrapped transport
+    pub fn get_transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Get back the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    fn wait_write(&mut self) {
+        let delay = self.write_delay.draw(&mut self.rng);
+        thread::sleep(delay);
+    }
+}
+
+impl<T: Transport> Transport for DelayedTransport<T> {
+    type Dst = T::Dst;
+    type Stream = T::Stream;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        self.transport.start_master()
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.transport.start_slave(unit_id)
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        self.transport.start_slave_units(unit_ids)
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        T::is_broadcast(dst)
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        T::get_unit_id(stream)
+    }
+
+    fn get_peer_addr(stream: &Self::Stream) -> Option<SocketAddr> {
+        T::get_peer_addr(stream)
+    }
+
+    fn get_ping_pdu(&self) -> &[u8] {
+        self.transport.get_ping_pdu()
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        self.wait_write();
+        self.transport.write_req_pdu(dst, pdu)
+    }
+
+    fn read_rsp_pdu(&mut self, stream: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        let pdu = self.transport.read_rsp_pdu(stream, src)?;
+        let delay = self.read_delay.draw(&mut self.rng);
+
+        match self.timeout {
+            Some(timeout) if delay >= timeout => {
+                thread::sleep(timeout);
+                Err(Error::NoResponse)
+            }
+            _ => {
+                thread::sleep(delay);
+                Ok(pdu)
+            }
+        }
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        let req = self.transport.read_req_pdu()?;
+        thread::sleep(self.read_delay.draw(&mut self.rng));
+        Ok(req)
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        self.wait_write();
+        self.transport.write_rsp_pdu(stream, pdu)
+    }
+}
+
+impl<T: Transport + fmt::Debug> fmt::Debug for DelayedTransport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DelayedTransport")
+            .field("transport", &self.transport)
+            .field("write_delay", &self.write_delay)
+            .field("read_delay", &self.read_delay)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::ReadHldRegRequest;
+    use std::time::Instant;
+
+    #[test]
+    fn test_delays() {
+        let mut device = MockTransport::new();
+        device.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x34]);
+        let mut mb = DelayedTransport::new(device)
+            .with_write_delay(Delay::fixed(Duration::from_millis(20)))
+            .with_read_delay(Delay::random(Duration::from_millis(10), Duration::from_millis(30)))
+            .with_timeout(Duration::from_millis(50));
+
+        let start = Instant::now();
+        let rsp = mb.write_req_read_rsp(&1, &ReadHldRegRequest::new(0x0010, 1)).unwrap().unwrap();
+        assert_eq!(rsp.get_registers(), &[0x1234]);
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_random_delay() {
+        let delay = Delay::random(Duration::from_millis(5), Duration::from_millis(8));
+        let mut rng = Rng::new(1);
+        assert!((0..100).map(|_| delay.draw(&mut rng)).all(|d| d >= delay.get_min() && d <= delay.get_max()));
+        assert_eq!(Delay::fixed(Duration::from_millis(5)).draw(&mut rng), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut device = MockTransport::new();
+        device.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x34]);
+        let mut mb = DelayedTransport::new(device)
+            .with_read_delay(Delay::fixed(Duration::from_millis(100)))
+            .with_timeout(Duration::from_millis(20));
+
+        let start = Instant::now();
+        assert!(matches!(mb.write_req_read_rsp(&1, &ReadHldRegRequest::new(0x0010, 1)), Err(Error::NoResponse)));
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert!(mb.into_inner().is_complete());
+    }
+}
@@ -0,0 +1,248 @@
+//! Injection of communication faults for robustness testing
+//!
+//! A [FaultyTransport] wraps a transport working in the master mode and damages responses
+//! with configured probabilities, the way a noisy line or a misbehaving device would. It lets
+//! test error handling of the master logic and of applications built on top of it without
+//! real faulty hardware.
+//!
+//! # Examples
+//! ```
+//! use modbus::Transport;
+//! use modbus::fault::FaultyTransport;
+//! use modbus::mock::MockTransport;
+//!
+//! let mut device = MockTransport::new();
+//! device.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x34]);
+//!
+//! let mut mb = FaultyTransport::new(device).with_drop(1.0);
+//! assert!(mb.write_req_read_rsp(&1, &modbus::ReadHldRegRequest::new(0x0010, 1)).is_err());
+//! ```
+
+use crate::error::Error;
+use crate::rng::Rng;
+use std::fmt;
+use std::net::SocketAddr;
+use super::Transport;
+
+/// Transport injecting faults into responses read in the master mode
+///
+/// For each response at most one fault is injected. The faults are drawn in the following order:
+/// * drop - the response is discarded and [Error::NoResponse] is returned, as after a timeout,
+/// * CRC corruption - the response is discarded and [Error::InvalidData] is returned, as for a frame with invalid checksum,
+/// * truncation - a random number of bytes is cut from the end of the response,
+/// * duplication - the response is returned, and its copy is returned again by the next transaction
+///   instead of the response to that transaction.
+///
+/// No faults are injected by default. The slave mode is passed to the wrapped transport without faults.
+pub struct FaultyTransport<T: Transport> {
+    transport: T,
+    drop: f64,
+    corrupt_crc: f64,
+    truncate: f64,
+    duplicate: f64,
+    rng: Rng,
+    duplicated: Option<Vec<u8>>,
+}
+
+impl<T: Transport> FaultyTransport<T> {
+    /// Create a transport passing transactions of given transport without faults
+    ///
+    /// The faults are drawn from a generator seeded from the current time.
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            drop: 0.0,
+            corrupt_crc: 0.0,
+            truncate: 0.0,
+            duplicate: 0.0,
+            rng: Rng::from_time(),
+            duplicated: None,
+        }
+    }
+
+    /// Set probability of dropping a response
+    ///
+    /// # Panics
+    /// Panics if `probability` is not in range `[0, 1]`.
+    pub fn with_drop(mut self, probability: f64) -> Self {
+        self.drop = check_probability(probability);
+        self
+    }
+
+    /// Set probability of receiving a response with invalid CRC
+    ///
+    /// # Panics
+    /// Panics if `probability` is not in range `[0, 1]`.
+    pub fn with_corrupt_crc(mut self, probability: f64) -> Self {
+        self.corrupt_crc = check_probability(probability);
+        self
+    }
+
+    /// Set probability of truncating a response
+    ///
+    /// # Panics
+    /// Panics if `probability` is not in range `[0, 1]`.
+    pub fn with_truncate(mut self, probability: f64) -> Self {
+        self.truncate = check_probability(probability);
+        self
+    }
+
+    /// Set probability of duplicating a response
+    ///
+    /// # Panics
+    /// Panics if `probability` is not in range `[0, 1]`.
+    pub fn with_duplicate(mut self, probability: f64) -> Self {
+        self.duplicate = check_probability(probability);
+        self
+    }
+
+    /// Set seed of the generator drawing the faults, making them reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// Get mutable reference to the wrapped transport
+    pub fn get_transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Get back the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+}
+
+fn check_probability(probability: f64) -> f64 {
+    assert!((0.0..=1.0).contains(&probability), "probability out of range [0, 1]");
+    probability
+}
+
+impl<T: Transport> Transport for FaultyTransport<T> {
+    type Dst = T::Dst;
+    type Stream = T::Stream;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        self.transport.start_master()
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.transport.start_slave(unit_id)
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        self.transport.start_slave_units(unit_ids)
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        T::is_broadcast(dst)
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        T::get_unit_id(stream)
+    }
+
+    fn get_peer_addr(stream: &Self::Stream) -> Option<SocketAddr> {
+        T::get_peer_addr(stream)
+    }
+
+    fn get_ping_pdu(&self) -> &[u8] {
+        self.transport.get_ping_pdu()
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        self.transport.write_req_pdu(dst, pdu)
+    }
+
+    fn read_rsp_pdu(&mut self, stream: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        let mut pdu = self.transport.read_rsp_pdu(stream, src)?;
+        if let Some(duplicated) = self.duplicated.take() {
+            return Ok(duplicated);
+        }
+
+        if self.rng.chance(self.drop) {
+            Err(Error::NoResponse)
+        } else if self.rng.chance(self.corrupt_crc) {
+            Err(Error::InvalidData)
+        } else if self.rng.chance(self.truncate) && !pdu.is_empty() {
+            let len = self.rng.below(pdu.len() as u64) as usize;
+            pdu.truncate(len);
+            Ok(pdu)
+        } else {
+            if self.rng.chance(self.duplicate) {
+                self.duplicated = Some(pdu.clone());
+            }
+            Ok(pdu)
+        }
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        self.transport.read_req_pdu()
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        self.transport.write_rsp_pdu(stream, pdu)
+    }
+}
+
+impl<T: Transport + fmt::Debug> fmt::Debug for FaultyTransport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FaultyTransport")
+            .field("transport", &self.transport)
+            .field("drop", &self.drop)
+            .field("corrupt_crc", &self.corrupt_crc)
+            .field("truncate", &self.truncate)
+            .field("duplicate", &self.duplicate)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::ReadHldRegRequest;
+
+    fn device(count: u8) -> MockTransport {
+        let mut device = MockTransport::new();
+        for i in 0..count {
+            device.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x00, i]);
+        }
+        device
+    }
+
+    #[test]
+    fn test_faults() {
+        let req = ReadHldRegRequest::new(0x0010, 1);
+
+        let mut mb = FaultyTransport::new(device(1)).with_drop(1.0).with_corrupt_crc(1.0);
+        assert!(matches!(mb.write_req_read_rsp(&1, &req), Err(Error::NoResponse)));
+        assert!(mb.into_inner().is_complete());
+
+        let mut mb = FaultyTransport::new(device(1)).with_corrupt_crc(1.0);
+        assert!(matches!(mb.write_req_read_rsp(&1, &req).unwrap_err().get_root(), Error::InvalidData));
+
+        let mut mb = FaultyTransport::new(device(1)).with_truncate(1.0).with_seed(3);
+        assert!(mb.write_req_read_rsp(&1, &req).is_err());
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let req = ReadHldRegRequest::new(0x0010, 1);
+        let mut mb = FaultyTransport::new(device(3)).with_duplicate(1.0);
+
+        assert_eq!(mb.write_req_read_rsp(&1, &req).unwrap().unwrap().get_registers(), &[0]);
+        assert_eq!(mb.write_req_read_rsp(&1, &req).unwrap().unwrap().get_registers(), &[0]);
+        assert_eq!(mb.write_req_read_rsp(&1, &req).unwrap().unwrap().get_registers(), &[2]);
+        assert!(mb.into_inner().is_complete());
+    }
+
+    #[test]
+    fn test_no_faults() {
+        let mut mb = FaultyTransport::new(device(10)).with_seed(1);
+        for i in 0..10 {
+            let rsp = mb.write_req_read_rsp(&1, &ReadHldRegRequest::new(0x0010, 1)).unwrap().unwrap();
+            assert_eq!(rsp.get_registers(), &[i]);
+        }
+    }
+}
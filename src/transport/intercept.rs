@@ -0,0 +1,233 @@
+//! Interception of transactions issued by a master
+//!
+//! An [InterceptingTransport] wraps a transport working in the master mode and passes each
+//! request and response PDU through a chain of [Interceptor]s. Interceptors observe or modify
+//! the PDUs and destinations, e.g. to add jitter, rewrite unit ids for a gateway or collect
+//! metrics, and work with any transport and any [Client](crate::client::Client).
+//!
+//! Requests pass the interceptors in the order they were added, responses in the reverse order.
+//!
+//! # Examples
+//! ```
+//! use modbus::client::{Client, LocalClient};
+//! use modbus::intercept::{Interceptor, InterceptingTransport};
+//! use modbus::mock::MockTransport;
+//!
+//! /// Forward requests addressed to unit 1 to unit 11
+//! struct Remap;
+//!
+//! impl Interceptor<u8> for Remap {
+//!     fn on_request(&mut self, dst: &mut u8, _pdu: &mut Vec<u8>) -> Result<(), modbus::Error> {
+//!         if *dst == 1 {
+//!             *dst = 11;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut device = MockTransport::new();
+//! device.expect_fn(Box::new(|dst, _| if dst == 11 { Some(vec![0x03, 0x02, 0x12, 0x34]) } else { None }));
+//!
+//! let client = LocalClient::new(InterceptingTransport::new(device).with_interceptor(Box::new(Remap)));
+//! let rsp = client.write_req_read_rsp(&1, &modbus::ReadHldRegRequest::new(0x0010, 1)).unwrap().unwrap();
+//! assert_eq!(rsp.get_registers(), &[0x1234]);
+//! ```
+
+use crate::error::Error;
+use std::fmt;
+use std::net::SocketAddr;
+use super::Transport;
+
+/// Observer and modifier of transactions issued through an [InterceptingTransport]
+pub trait Interceptor<D>: Send {
+    /// Process a request PDU about to be written to given destination
+    ///
+    /// Both the destination and the PDU may be modified. Returning an error aborts the
+    /// transaction before the request is written.
+    fn on_request(&mut self, _dst: &mut D, _pdu: &mut Vec<u8>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Process a response PDU read from given destination
+    ///
+    /// The PDU may be modified before it is decoded. Returning an error fails the transaction.
+    fn on_response(&mut self, _dst: &D, _pdu: &mut Vec<u8>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Transport passing requests and responses of the master mode through a chain of interceptors
+///
+/// The slave mode is passed to the wrapped transport without interception.
+pub struct InterceptingTransport<T: Transport> {
+    transport: T,
+    interceptors: Vec<Box<dyn Interceptor<T::Dst>>>,
+    pending_dst: Option<T::Dst>,
+}
+
+impl<T: Transport> InterceptingTransport<T>
+where
+    T::Dst: Clone,
+{
+    /// Create a transport passing transactions of given transport through no interceptors
+    pub fn new(transport: T) -> Self {
+        Self {transport, interceptors: Vec::new(), pending_dst: None}
+    }
+
+    /// Add an interceptor at the end of the chain
+    pub fn with_interceptor(mut self, interceptor: Box<dyn Interceptor<T::Dst>>) -> Self {
+        self.add_interceptor(interceptor);
+        self
+    }
+
+    /// Add an interceptor at the end of the chain
+    pub fn add_interceptor(&mut self, interceptor: Box<dyn Interceptor<T::Dst>>) {
+        self.interceptors.push(interceptor);
+    }
+
+    /// Get mutable reference to the wrapped transport
+    pub fn get_transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Get back the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+}
+
+impl<T: Transport> Transport for InterceptingTransport<T>
+where
+    T::Dst: Clone,
+{
+    type Dst = T::Dst;
+    type Stream = T::Stream;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        self.transport.start_master()
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.transport.start_slave(unit_id)
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        self.transport.start_slave_units(unit_ids)
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        T::is_broadcast(dst)
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        T::get_unit_id(stream)
+    }
+
+    fn get_peer_addr(stream: &Self::Stream) -> Option<SocketAddr> {
+        T::get_peer_addr(stream)
+    }
+
+    fn get_ping_pdu(&self) -> &[u8] {
+        self.transport.get_ping_pdu()
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        let mut dst = dst.clone();
+        let mut pdu = pdu.to_vec();
+        for interceptor in self.interceptors.iter_mut() {
+            interceptor.on_request(&mut dst, &mut pdu)?;
+        }
+
+        let stream = self.transport.write_req_pdu(&dst, &pdu)?;
+        self.pending_dst = Some(dst);
+        Ok(stream)
+    }
+
+    fn read_rsp_pdu(&mut self, stream: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        let src = self.pending_dst.take().unwrap_or_else(|| src.clone());
+        let mut pdu = self.transport.read_rsp_pdu(stream, &src)?;
+        for interceptor in self.interceptors.iter_mut().rev() {
+            interceptor.on_response(&src, &mut pdu)?;
+        }
+
+        Ok(pdu)
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        self.transport.read_req_pdu()
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        self.transport.write_rsp_pdu(stream, pdu)
+    }
+}
+
+impl<T: Transport + fmt::Debug> fmt::Debug for InterceptingTransport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InterceptingTransport")
+            .field("transport", &self.transport)
+            .field("interceptors", &self.interceptors.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::ReadHldRegRequest;
+    use std::sync::{Arc, Mutex};
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    struct Logger {
+        name: &'static str,
+        log: Log,
+    }
+
+    impl Interceptor<u8> for Logger {
+        fn on_request(&mut self, dst: &mut u8, _pdu: &mut Vec<u8>) -> Result<(), Error> {
+            self.log.lock().unwrap().push(format!("{} request to {}", self.name, dst));
+            *dst += 1;
+            Ok(())
+        }
+
+        fn on_response(&mut self, dst: &u8, pdu: &mut Vec<u8>) -> Result<(), Error> {
+            self.log.lock().unwrap().push(format!("{} response from {}", self.name, dst));
+            pdu[3] += 1;
+            Ok(())
+        }
+    }
+
+    struct Reject;
+
+    impl Interceptor<u8> for Reject {
+        fn on_request(&mut self, _dst: &mut u8, _pdu: &mut Vec<u8>) -> Result<(), Error> {
+            Err(Error::InvalidValue)
+        }
+    }
+
+    #[test]
+    fn test_chain() {
+        let log = Log::default();
+        let mut device = MockTransport::new();
+        device.expect_fn(Box::new(|dst, _| Some(vec![0x03, 0x02, 0x00, dst])));
+        let mut mb = InterceptingTransport::new(device)
+            .with_interceptor(Box::new(Logger {name: "first", log: log.clone()}))
+            .with_interceptor(Box::new(Logger {name: "second", log: log.clone()}));
+
+        let rsp = mb.write_req_read_rsp(&1, &ReadHldRegRequest::new(0x0010, 1)).unwrap().unwrap();
+
+        assert_eq!(rsp.get_registers(), &[0x0005]);
+        assert_eq!(*log.lock().unwrap(), vec!["first request to 1", "second request to 2",
+                                              "second response from 3", "first response from 3"]);
+    }
+
+    #[test]
+    fn test_reject() {
+        let mut mb = InterceptingTransport::new(MockTransport::new()).with_interceptor(Box::new(Reject));
+
+        assert!(matches!(mb.write_req_read_rsp(&1, &ReadHldRegRequest::new(0x0010, 1)), Err(Error::InvalidValue)));
+        assert!(mb.into_inner().is_complete());
+    }
+}
@@ -0,0 +1,240 @@
+//! Mock transport for testing Modbus masters and slaves without hardware
+//!
+//! In the master mode, the mock verifies written requests against the expected ones
+//! and replies with canned responses. In the slave mode, it delivers enqueued requests
+//! and records written responses.
+
+use crate::error::Error;
+use std::collections::VecDeque;
+use std::fmt;
+use super::Transport;
+
+const BROADCAST_DST: u8 = 0;
+
+/// Handler creating a response PDU from the unit id and the PDU of a request
+pub type Handler = Box<dyn FnMut(u8, &[u8]) -> Option<Vec<u8>> + Send>;
+
+enum Reply {
+    Pdu(Option<Vec<u8>>),
+    Handler(Handler),
+}
+
+struct Expectation {
+    req_pdu: Option<Vec<u8>>,
+    reply: Reply,
+}
+
+/// Mock transport for Modbus commands
+///
+/// This structure implements [Transport trait](Transport) that provides
+/// functions needed to read and write Modbus functions using this transport.
+/// Destinations are plain unit ids.
+///
+/// # Examples
+/// ```
+/// use modbus::Transport;
+/// use modbus::mock::MockTransport;
+///
+/// let mut mb = MockTransport::new();
+/// mb.expect(&[0x01, 0x00, 0x10, 0x00, 0x02], &[0x01, 0x01, 0x02]);
+///
+/// let rsp = mb.write_req_read_rsp(&10, &modbus::ReadCoilsRequest::new(0x0010, 0x0002)).unwrap();
+/// assert_eq!(rsp.unwrap().get_coils().to_vec()[..2], [false, true]);
+/// assert!(mb.is_complete());
+/// ```
+#[derive(Default)]
+pub struct MockTransport {
+    expectations: VecDeque<Expectation>,
+    pending_rsp: Option<Vec<u8>>,
+
+    unit_ids: Vec<u8>,
+    reqs: VecDeque<(u8, Vec<u8>)>,
+    rsps: Vec<(u8, Vec<u8>)>,
+}
+
+impl fmt::Debug for MockTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MockTransport")
+            .field("expectations", &self.expectations.len())
+            .field("unit_ids", &self.unit_ids)
+            .field("reqs", &self.reqs)
+            .field("rsps", &self.rsps)
+            .finish()
+    }
+}
+
+impl MockTransport {
+    /// Create a new mock transport without any expectations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expect given request PDU and reply to it with given response PDU
+    pub fn expect(&mut self, req_pdu: &[u8], rsp_pdu: &[u8]) {
+        self.expectations.push_back(Expectation {
+            req_pdu: Some(req_pdu.to_vec()),
+            reply: Reply::Pdu(Some(rsp_pdu.to_vec())),
+        });
+    }
+
+    /// Expect given request PDU and leave it without response
+    ///
+    /// Reading the response fails with [NoResponse error](Error::NoResponse).
+    pub fn expect_no_response(&mut self, req_pdu: &[u8]) {
+        self.expectations.push_back(Expectation {
+            req_pdu: Some(req_pdu.to_vec()),
+            reply: Reply::Pdu(None),
+        });
+    }
+
+    /// Expect any request and reply to it with a response created by given handler
+    ///
+    /// If the handler returns `None`, the request is left without response.
+    ///
+    /// # Examples
+    /// ```
+    /// use modbus::Transport;
+    /// use modbus::mock::MockTransport;
+    ///
+    /// let mut mb = MockTransport::new();
+    /// mb.expect_fn(Box::new(|_unit_id, req_pdu| Some(req_pdu.to_vec())));
+    ///
+    /// mb.write_setter_req(&10, &modbus::WriteSingleCoilRequest::new(0x0010, true)).unwrap();
+    /// ```
+    pub fn expect_fn(&mut self, handler: Handler) {
+        self.expectations.push_back(Expectation {
+            req_pdu: None,
+            reply: Reply::Handler(handler),
+        });
+    }
+
+    /// Verify if all expected requests were written
+    pub fn is_complete(&self) -> bool {
+        self.expectations.is_empty()
+    }
+
+    /// Enqueue a request PDU addressed to given unit id to be read in the slave mode
+    pub fn push_req(&mut self, unit_id: u8, req_pdu: &[u8]) {
+        self.reqs.push_back((unit_id, req_pdu.to_vec()));
+    }
+
+    /// Get unit ids and PDUs of responses written in the slave mode
+    pub fn get_rsps(&self) -> &[(u8, Vec<u8>)] {
+        &self.rsps
+    }
+}
+
+impl Transport for MockTransport {
+    type Dst = u8;
+    type Stream = u8;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.start_slave_units(&[unit_id])
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        if unit_ids.is_empty() {
+            return Err(Error::InvalidValue);
+        }
+
+        self.unit_ids = unit_ids.to_vec();
+        Ok(())
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        *dst == BROADCAST_DST
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        *stream
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        let expectation = self.expectations.pop_front().ok_or(Error::InvalidRequest)?;
+
+        if let Some(req_pdu) = expectation.req_pdu {
+            if req_pdu != pdu {
+                return Err(Error::InvalidRequest);
+            }
+        }
+
+        self.pending_rsp = match expectation.reply {
+            Reply::Pdu(rsp_pdu) => rsp_pdu,
+            Reply::Handler(mut handler) => handler(*dst, pdu),
+        };
+
+        Ok(*dst)
+    }
+
+    fn read_rsp_pdu(&mut self, _: &mut Self::Stream, _: &Self::Dst) -> Result<Vec<u8>, Error> {
+        self.pending_rsp.take().ok_or(Error::NoResponse)
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        if self.unit_ids.is_empty() {
+            return Err(Error::InvalidValue);
+        }
+
+        while let Some((unit_id, req_pdu)) = self.reqs.pop_front() {
+            if self.unit_ids.contains(&unit_id) {
+                return Ok((req_pdu, unit_id));
+            }
+        }
+
+        Err(Error::NoResponse)
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        self.rsps.push((*stream, pdu.to_vec()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unexpected_request() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x01, 0x00, 0x10, 0x00, 0x02], &[0x01, 0x01, 0x02]);
+
+        let err = mb.write_req_pdu(&10, &[0x01, 0x00, 0x11, 0x00, 0x02]).err().unwrap();
+        match err {
+            Error::InvalidRequest => {}
+            _ => panic!("Expected InvalidRequest, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_no_response() {
+        let mut mb = MockTransport::new();
+        mb.expect_no_response(&[0x07]);
+
+        let mut stream = mb.write_req_pdu(&10, &[0x07]).unwrap();
+        let err = mb.read_rsp_pdu(&mut stream, &10).err().unwrap();
+        match err {
+            Error::NoResponse => {}
+            _ => panic!("Expected NoResponse, but got {:?}", err),
+        }
+        assert!(mb.is_complete());
+    }
+
+    #[test]
+    fn test_slave() {
+        let mut mb = MockTransport::new();
+        mb.start_slave(10).unwrap();
+        mb.push_req(11, &[0x07]);
+        mb.push_req(10, &[0x08]);
+
+        let (req_pdu, mut stream) = mb.read_req_pdu().unwrap();
+        assert_eq!(req_pdu, vec![0x08]);
+
+        mb.write_rsp_pdu(&mut stream, &[0x08, 0x00]).unwrap();
+        assert_eq!(mb.get_rsps(), &[(10, vec![0x08, 0x00])]);
+    }
+}
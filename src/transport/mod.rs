@@ -1,8 +1,34 @@
+#[cfg(any(feature = "serial", feature = "tcp"))]
+pub mod any;
+#[cfg(feature = "std")]
+pub mod actor;
+#[cfg(feature = "std")]
+pub mod capture;
+#[cfg(feature = "std")]
+pub mod delay;
+#[cfg(feature = "std")]
+pub mod fault;
+#[cfg(feature = "std")]
+pub mod intercept;
+#[cfg(feature = "std")]
+pub mod mock;
+#[cfg(feature = "std")]
+pub mod pace;
+#[cfg(feature = "std")]
+pub mod record;
 pub mod rtu;
+pub mod scan;
+pub mod target;
+#[cfg(feature = "tcp")]
 pub mod tcp;
 
-use crate::error::Error;
-use crate::pdu::{Request, Response, Setter, RequestData, decode_req};
+use crate::error::{Error, Stage};
+use alloc::vec::Vec;
+use core::net::SocketAddr;
+use core::ops::RangeInclusive;
+use scan::ScanReport;
+use crate::pdu::{DecodeMode, ExceptionCode, Request, Response, Setter, RequestData, decode_req, encode_exc_rsp};
+use crate::pdu::raw::Decoded;
 
 /// The trait implemented by Modbus protocol link layers 
 pub trait Transport {
@@ -15,10 +41,36 @@ pub trait Transport {
     fn start_master(&mut self) -> Result<(), Error>;
     /// Enable Modbus slave mode for given transport.
     fn start_slave(&mut self, unit_id: u8) -> Result<(), Error>;
+    /// Enable Modbus slave mode serving multiple unit ids with given transport.
+    /// 
+    /// By default only a single unit id is supported.
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        match unit_ids {
+            [unit_id] => self.start_slave(*unit_id),
+            _ => Err(Error::InvalidValue),
+        }
+    }
 
     /// Verify if given destination is broadcast.
     fn is_broadcast(dst: &Self::Dst) -> bool;
 
+    /// Get unit id of the slave addressed by the request read to given stream.
+    fn get_unit_id(stream: &Self::Stream) -> u8;
+
+    /// Get address of the master which sent the request read to given stream.
+    /// 
+    /// By default it is unknown, as for serial lines.
+    fn get_peer_addr(_stream: &Self::Stream) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Get request PDU sent to check health of a link with a slave.
+    /// 
+    /// By default it is [Diagnostics Return Query Data](scan::ECHO_PDU).
+    fn get_ping_pdu(&self) -> &[u8] {
+        &scan::ECHO_PDU
+    }
+
     /// Write PDU of a request frame through given transport.
     /// 
     /// This method shall be used only in master mode.
@@ -53,14 +105,64 @@ pub trait Transport {
     /// let rsp = mb.write_req_read_rsp(&dst, &req);
     /// ```
     fn write_req_read_rsp<Req: Request>(&mut self, dst: &Self::Dst, req: &Req) -> Result<Option<Req::Rsp>, Error> {
+        self.write_req_read_rsp_with_mode(dst, req, DecodeMode::Strict)
+    }
+
+    /// Write a request frame and read a response frame decoded in the given mode.
+    /// 
+    /// The response is also verified to carry the data requested by the request.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// # use modbus::{DecodeMode, Transport};
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// let mut mb = modbus::tcp::Tcp::new();
+    /// let dst = modbus::tcp::Dst::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10);
+    /// let req = modbus::ReadCoilsRequest::new(0x0123, 0x0002);
+    /// let rsp = mb.write_req_read_rsp_with_mode(&dst, &req, DecodeMode::Lenient);
+    /// ```
+    fn write_req_read_rsp_with_mode<Req: Request>(&mut self, dst: &Self::Dst, req: &Req, mode: DecodeMode) -> Result<Option<Req::Rsp>, Error> {
+        Ok(self.write_req_read_rsp_raw(dst, req, mode)?.map(Decoded::into_inner))
+    }
+
+    /// Write a request frame and read a response frame decoded in the given mode, keeping its raw PDU.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// # use modbus::{DecodeMode, Transport};
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// let mut mb = modbus::tcp::Tcp::new();
+    /// let dst = modbus::tcp::Dst::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10);
+    /// let req = modbus::ReadHldRegRequest::new(0x0123, 0x0002);
+    /// if let Some(rsp) = mb.write_req_read_rsp_raw(&dst, &req, DecodeMode::Strict).unwrap() {
+    ///     println!("{:?} received as {:02x?}", rsp.get().get_registers(), rsp.get_pdu());
+    /// }
+    /// ```
+    fn write_req_read_rsp_raw<Req: Request>(&mut self, dst: &Self::Dst, req: &Req, mode: DecodeMode) -> Result<Option<Decoded<Req::Rsp>>, Error> {
         let req_pdu: Vec<u8> = req.encode()?;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("transaction", function_code = req_pdu[0], unit_id = tracing::field::Empty).entered();
         let mut stream = self.write_req_pdu(dst, &req_pdu)?;
+        #[cfg(feature = "metrics")]
+        let start = crate::telemetry::record_request(req_pdu[0]);
 
         if Self::is_broadcast(dst) {
             Ok(None)
         } else {
-            let rsp_pdu = self.read_rsp_pdu(&mut stream, dst)?;
-            Ok(Some(Req::Rsp::decode_response(&rsp_pdu)?))
+            let unit_id = Some(Self::get_unit_id(&stream));
+            #[cfg(feature = "tracing")]
+            _span.record("unit_id", unit_id);
+            let rsp_pdu = self.read_rsp_pdu(&mut stream, dst);
+            #[cfg(feature = "metrics")]
+            crate::telemetry::record_response(req_pdu[0], start, &rsp_pdu);
+            let rsp_pdu = rsp_pdu.map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::ReadResponse))?;
+            let rsp = Req::Rsp::decode_response_with_mode(&rsp_pdu, mode);
+            #[cfg(feature = "metrics")]
+            crate::telemetry::record_decoded(req_pdu[0], &rsp);
+            let rsp = rsp.map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::DecodeResponse))?;
+            req.check_response(&rsp, mode)
+                .map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::VerifyResponse))?;
+            Ok(Some(Decoded::new(rsp, rsp_pdu)))
         }
     }
 
@@ -82,23 +184,73 @@ pub trait Transport {
         where Req::Rsp: PartialEq 
     {
         let req_pdu: Vec<u8> = req.encode()?;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("transaction", function_code = req_pdu[0], unit_id = tracing::field::Empty).entered();
         let mut stream = self.write_req_pdu(dst, &req_pdu)?;
+        #[cfg(feature = "metrics")]
+        let start = crate::telemetry::record_request(req_pdu[0]);
 
         if Self::is_broadcast(dst) {
             Ok(())
         } else {
-            let rsp_pdu = self.read_rsp_pdu(&mut stream, dst)?;
-            let rsp = Req::Rsp::decode_response(&rsp_pdu)?;
+            let unit_id = Some(Self::get_unit_id(&stream));
+            #[cfg(feature = "tracing")]
+            _span.record("unit_id", unit_id);
+            let rsp_pdu = self.read_rsp_pdu(&mut stream, dst);
+            #[cfg(feature = "metrics")]
+            crate::telemetry::record_response(req_pdu[0], start, &rsp_pdu);
+            let rsp_pdu = rsp_pdu.map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::ReadResponse))?;
+            let rsp = Req::Rsp::decode_response(&rsp_pdu);
+            #[cfg(feature = "metrics")]
+            crate::telemetry::record_decoded(req_pdu[0], &rsp);
+            let rsp = rsp.map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::DecodeResponse))?;
             let exp_rsp = req.create_expected_response();
 
             if exp_rsp == rsp {
                 Ok(())
             } else {
-                Err(Error::InvalidData)
+                Err(Error::InvalidData.with_context(req_pdu[0], unit_id, Stage::VerifyResponse))
             }
         }
     }
 
+    /// Probe given range of unit ids and report which of them respond.
+    /// 
+    /// Each unit id is converted to a destination with `dst` and sent `probe_pdu`, for example
+    /// [the default probe](scan::PROBE_PDU). Broadcast destinations are skipped. Any response,
+    /// including an exception response, marks the unit as present.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// # #[cfg(feature = "serial")] {
+    /// use modbus::Transport;
+    /// use modbus::scan::PROBE_PDU;
+    /// 
+    /// let mut mb = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &Default::default()).unwrap();
+    /// let report = mb.scan(1..=247, &PROBE_PDU, |unit_id| unit_id);
+    /// println!("Found units: {:?}", report.get_unit_ids());
+    /// # }
+    /// ```
+    fn scan<F: FnMut(u8) -> Self::Dst>(&mut self, unit_ids: RangeInclusive<u8>, probe_pdu: &[u8], mut dst: F) -> ScanReport {
+        let mut report = ScanReport::default();
+
+        for unit_id in unit_ids {
+            let dst = dst(unit_id);
+            if Self::is_broadcast(&dst) {
+                continue;
+            }
+
+            let rsp_pdu = self.write_req_pdu(&dst, probe_pdu)
+                .and_then(|mut stream| self.read_rsp_pdu(&mut stream, &dst));
+            match rsp_pdu {
+                Ok(rsp_pdu) => report.add_response(unit_id, rsp_pdu),
+                Err(_) => report.add_silent(unit_id),
+            }
+        }
+
+        report
+    }
+
     /// Read a request frame.
     /// 
     /// This method with [Transport::write_rsp] are the main functionality in the Modbus slave mode.
@@ -112,9 +264,26 @@ pub trait Transport {
     /// let (req, stream) = mb.read_req().unwrap();
     /// ```
     fn read_req(&mut self) -> Result<(RequestData, Self::Stream), Error> {
+        let (req, stream) = self.read_req_raw()?;
+        Ok((req.into_inner(), stream))
+    }
+
+    /// Read a request frame, keeping its raw PDU.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::Transport;
+    /// 
+    /// let mut mb = modbus::tcp::Tcp::new();
+    /// mb.start_slave(10).unwrap();
+    /// let (req, stream) = mb.read_req_raw().unwrap();
+    /// println!("{} received as {:02x?}", req.get(), req.get_pdu());
+    /// ```
+    fn read_req_raw(&mut self) -> Result<(Decoded<RequestData>, Self::Stream), Error> {
         let (req_pdu, stream) = self.read_req_pdu()?;
-        let req_data = decode_req(&req_pdu)?;
-        Ok((req_data, stream))
+        let req_data = decode_req(&req_pdu)
+            .map_err(|err| err.with_context(req_pdu.first().copied().unwrap_or_default(), Some(Self::get_unit_id(&stream)), Stage::DecodeRequest))?;
+        Ok((Decoded::new(req_data, req_pdu), stream))
     }
 
     /// Write a response frame.
@@ -136,42 +305,337 @@ pub trait Transport {
     fn write_rsp<Rsp: Response>(&mut self, mut stream: Self::Stream, response: Rsp) -> Result<(), Error> {
         self.write_rsp_pdu(&mut stream, &response.encode()?)
     }
+
+    /// Write an exception response to a request with given function code.
+    /// 
+    /// Call to this method may follow [Transport::read_req] or [Transport::read_req_pdu] in the Modbus slave mode
+    /// to reject a request.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::{ExceptionCode, Transport};
+    /// 
+    /// let mut mb = modbus::tcp::Tcp::new();
+    /// mb.start_slave(10).unwrap();
+    /// let (req_pdu, stream) = mb.read_req_pdu().unwrap();
+    /// mb.reply_exception(stream, req_pdu[0], ExceptionCode::IllegalFunction).unwrap();
+    /// ```
+    fn reply_exception(&mut self, mut stream: Self::Stream, function_code: u8, exc_code: ExceptionCode) -> Result<(), Error> {
+        self.write_rsp_pdu(&mut stream, &encode_exc_rsp(function_code, exc_code))
+    }
 }
 
-#[cfg(test)]
+/// The asynchronous counterpart of the [Transport] trait
+///
+/// Link layers implementing this trait can be used by the asynchronous
+/// master and slave in the same way as the synchronous ones.
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+#[allow(async_fn_in_trait)]
+pub trait AsyncTransport {
+    /// Type describing message destination
+    type Dst;
+    /// Stream used to read or write messages in during data exchange
+    type Stream;
+
+    /// Enable Modbus master mode for given transport.
+    async fn start_master(&mut self) -> Result<(), Error>;
+    /// Enable Modbus slave mode for given transport.
+    async fn start_slave(&mut self, unit_id: u8) -> Result<(), Error>;
+
+    /// Verify if given destination is broadcast.
+    fn is_broadcast(dst: &Self::Dst) -> bool;
+
+    /// Get unit id of the slave addressed by the request written or read to given stream.
+    fn get_unit_id(stream: &Self::Stream) -> u8;
+
+    /// Write PDU of a request frame through given transport.
+    /// 
+    /// This method shall be used only in master mode.
+    /// This method returns Stream that shall be used to read response.
+    async fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error>;
+
+    /// Read PDU of a response frame through given transport.
+    /// 
+    /// This method shall be used only in master mode.
+    async fn read_rsp_pdu(&mut self, stream: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error>;
+
+    /// Read PDU of a request frame through given transport.
+    /// 
+    /// This method shall be used only is the slave mode.
+    async fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error>;
+
+    /// Write PDU of a response frame through given transport.
+    /// 
+    /// This method shall be used only in the slave mode.
+    async fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error>;
+
+    /// Write a request frame and read a response frame.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// # use modbus::AsyncTransport;
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// # async fn poll<T: AsyncTransport<Dst = modbus::tcp::Dst>>(mut mb: T) {
+    /// let dst = modbus::tcp::Dst::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10);
+    /// let req = modbus::ReadCoilsRequest::new(0x0123, 0x0002);
+    /// let rsp = mb.write_req_read_rsp(&dst, &req).await;
+    /// # }
+    /// ```
+    async fn write_req_read_rsp<Req: Request>(&mut self, dst: &Self::Dst, req: &Req) -> Result<Option<Req::Rsp>, Error> {
+        self.write_req_read_rsp_with_mode(dst, req, DecodeMode::Strict).await
+    }
+
+    /// Write a request frame and read a response frame decoded in the given mode.
+    /// 
+    /// The response is also verified to carry the data requested by the request.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// # use modbus::{DecodeMode, AsyncTransport};
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// # async fn poll<T: AsyncTransport<Dst = modbus::tcp::Dst>>(mut mb: T) {
+    /// let dst = modbus::tcp::Dst::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10);
+    /// let req = modbus::ReadCoilsRequest::new(0x0123, 0x0002);
+    /// let rsp = mb.write_req_read_rsp_with_mode(&dst, &req, DecodeMode::Lenient).await;
+    /// # }
+    /// ```
+    async fn write_req_read_rsp_with_mode<Req: Request>(&mut self, dst: &Self::Dst, req: &Req, mode: DecodeMode) -> Result<Option<Req::Rsp>, Error> {
+        Ok(self.write_req_read_rsp_raw(dst, req, mode).await?.map(Decoded::into_inner))
+    }
+
+    /// Write a request frame and read a response frame decoded in the given mode, keeping its raw PDU.
+    async fn write_req_read_rsp_raw<Req: Request>(&mut self, dst: &Self::Dst, req: &Req, mode: DecodeMode) -> Result<Option<Decoded<Req::Rsp>>, Error> {
+        let req_pdu: Vec<u8> = req.encode()?;
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("transaction", function_code = req_pdu[0], unit_id = tracing::field::Empty);
+        let transaction = async {
+            let mut stream = self.write_req_pdu(dst, &req_pdu).await?;
+            #[cfg(feature = "metrics")]
+            let start = crate::telemetry::record_request(req_pdu[0]);
+
+            if Self::is_broadcast(dst) {
+                Ok(None)
+            } else {
+                let unit_id = Some(Self::get_unit_id(&stream));
+                #[cfg(feature = "tracing")]
+                tracing::Span::current().record("unit_id", unit_id);
+                let rsp_pdu = self.read_rsp_pdu(&mut stream, dst).await;
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_response(req_pdu[0], start, &rsp_pdu);
+                let rsp_pdu = rsp_pdu.map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::ReadResponse))?;
+                let rsp = Req::Rsp::decode_response_with_mode(&rsp_pdu, mode);
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_decoded(req_pdu[0], &rsp);
+                let rsp = rsp.map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::DecodeResponse))?;
+                req.check_response(&rsp, mode)
+                    .map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::VerifyResponse))?;
+                Ok(Some(Decoded::new(rsp, rsp_pdu)))
+            }
+        };
+        // The span is entered only while the transaction is polled, not across its suspension points
+        #[cfg(feature = "tracing")]
+        let transaction = tracing::Instrument::instrument(transaction, span);
+        transaction.await
+    }
+
+    /// Write a setter request and read a response frame.
+    /// 
+    /// This function handles unexpected responses
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// # use modbus::AsyncTransport;
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// # async fn set<T: AsyncTransport<Dst = modbus::tcp::Dst>>(mut mb: T) {
+    /// let dst = modbus::tcp::Dst::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10);
+    /// let req = modbus::WriteSingleCoilRequest::new(0x0123, true);
+    /// mb.write_setter_req(&dst, &req).await.unwrap();
+    /// # }
+    /// ```
+    async fn write_setter_req<Req: Setter>(&mut self, dst: &Self::Dst, req: &Req) -> Result<(), Error> 
+        where Req::Rsp: PartialEq 
+    {
+        let req_pdu: Vec<u8> = req.encode()?;
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("transaction", function_code = req_pdu[0], unit_id = tracing::field::Empty);
+        let transaction = async {
+            let mut stream = self.write_req_pdu(dst, &req_pdu).await?;
+            #[cfg(feature = "metrics")]
+            let start = crate::telemetry::record_request(req_pdu[0]);
+
+            if Self::is_broadcast(dst) {
+                Ok(())
+            } else {
+                let unit_id = Some(Self::get_unit_id(&stream));
+                #[cfg(feature = "tracing")]
+                tracing::Span::current().record("unit_id", unit_id);
+                let rsp_pdu = self.read_rsp_pdu(&mut stream, dst).await;
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_response(req_pdu[0], start, &rsp_pdu);
+                let rsp_pdu = rsp_pdu.map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::ReadResponse))?;
+                let rsp = Req::Rsp::decode_response(&rsp_pdu);
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_decoded(req_pdu[0], &rsp);
+                let rsp = rsp.map_err(|err| err.with_context(req_pdu[0], unit_id, Stage::DecodeResponse))?;
+                let exp_rsp = req.create_expected_response();
+
+                if exp_rsp == rsp {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidData.with_context(req_pdu[0], unit_id, Stage::VerifyResponse))
+                }
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let transaction = tracing::Instrument::instrument(transaction, span);
+        transaction.await
+    }
+
+    /// Read a request frame.
+    /// 
+    /// This method with [AsyncTransport::write_rsp] are the main functionality in the asynchronous Modbus slave mode.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::AsyncTransport;
+    /// 
+    /// # async fn serve<T: AsyncTransport>(mut mb: T) {
+    /// mb.start_slave(10).await.unwrap();
+    /// let (req, stream) = mb.read_req().await.unwrap();
+    /// # }
+    /// ```
+    async fn read_req(&mut self) -> Result<(RequestData, Self::Stream), Error> {
+        let (req, stream) = self.read_req_raw().await?;
+        Ok((req.into_inner(), stream))
+    }
+
+    /// Read a request frame, keeping its raw PDU.
+    async fn read_req_raw(&mut self) -> Result<(Decoded<RequestData>, Self::Stream), Error> {
+        let (req_pdu, stream) = self.read_req_pdu().await?;
+        let req_data = decode_req(&req_pdu)
+            .map_err(|err| err.with_context(req_pdu.first().copied().unwrap_or_default(), Some(Self::get_unit_id(&stream)), Stage::DecodeRequest))?;
+        Ok((Decoded::new(req_data, req_pdu), stream))
+    }
+
+    /// Write a response frame.
+    /// 
+    /// Call to this method shall follow [AsyncTransport::read_req] in the asynchronous Modbus slave mode.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::AsyncTransport;
+    /// 
+    /// # async fn serve<T: AsyncTransport>(mut mb: T) {
+    /// mb.start_slave(10).await.unwrap();
+    /// let (req, stream) = mb.read_req().await.unwrap();
+    /// 
+    /// if let modbus::RequestData::ReadCoils(request) = req {
+    ///     let result = mb.write_rsp(stream, modbus::ReadCoilsResponse::new(&[true, false])).await;
+    /// }
+    /// # }
+    /// ```
+    async fn write_rsp<Rsp: Response>(&mut self, mut stream: Self::Stream, response: Rsp) -> Result<(), Error> {
+        self.write_rsp_pdu(&mut stream, &response.encode()?).await
+    }
+
+    /// Write an exception response to a request with given function code.
+    /// 
+    /// Call to this method may follow [AsyncTransport::read_req] or [AsyncTransport::read_req_pdu] in the asynchronous
+    /// Modbus slave mode to reject a request.
+    async fn reply_exception(&mut self, mut stream: Self::Stream, function_code: u8, exc_code: ExceptionCode) -> Result<(), Error> {
+        self.write_rsp_pdu(&mut stream, &encode_exc_rsp(function_code, exc_code)).await
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
     use crate::ReadCoilsResponse;
 
-    /*
-    use crate::ReadCoilsRequest;
-    use std::net::{IpAddr, Ipv4Addr};
+    use crate::{ReadCoilsRequest, WriteSingleCoilRequest};
+    use mock::MockTransport;
 
     #[test]
-    fn test_tcp_master() {
-        let mut mb = tcp::conn::Tcp::new();
-        let dst = tcp::conn::Dst::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10);
+    fn test_master() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x01, 0x01, 0x23, 0x00, 0x02], &[0x01, 0x01, 0x02]);
         let req = ReadCoilsRequest::new(0x0123, 0x0002);
 
-        let rsp = mb.write_req_read_rsp(&dst, &req);
-        println!("{:?}", rsp);
-        assert_eq!(false, true);
+        let rsp = mb.write_req_read_rsp(&10, &req).unwrap().unwrap();
+        assert_eq!(rsp.get_coils().to_vec()[..2], [false, true]);
+        assert!(mb.is_complete());
+    }
+
+    #[test]
+    fn test_master_broadcast() {
+        let mut mb = MockTransport::new();
+        mb.expect_no_response(&[0x05, 0x01, 0x23, 0xFF, 0x00]);
+        let req = WriteSingleCoilRequest::new(0x0123, true);
+
+        mb.write_setter_req(&0, &req).unwrap();
+        assert!(mb.is_complete());
+    }
+
+    #[test]
+    fn test_master_unexpected_setter_response() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x05, 0x01, 0x23, 0xFF, 0x00], &[0x05, 0x01, 0x23, 0x00, 0x00]);
+        let req = WriteSingleCoilRequest::new(0x0123, true);
+
+        let err = mb.write_setter_req(&10, &req).err().unwrap();
+        match err.get_root() {
+            Error::InvalidData => {}
+            _ => panic!("Expected InvalidData, but got {:?}", err),
+        }
+
+        let context = err.get_context().unwrap();
+        assert_eq!(context.get_function_code(), 0x05);
+        assert_eq!(context.get_unit_id(), Some(10));
+        assert_eq!(context.get_stage(), Stage::VerifyResponse);
     }
 
     #[test]
-    fn test_tcp_slave() {
-        let mut mb = tcp::conn::Tcp::new();
+    fn test_slave() {
+        let mut mb = MockTransport::new();
         mb.start_slave(10).unwrap();
-        let (req, stream) = mb.read_req().unwrap();
-        let res = mb.write_rsp(stream, ReadCoilsResponse::new(&[true, false]));
+        mb.push_req(10, &[0x01, 0x01, 0x23, 0x00, 0x02]);
 
-        if let Err(error) = res {
-            panic!("Error during writing response: {:?}", error);
+        let (req, stream) = mb.read_req().unwrap();
+        match req {
+            RequestData::ReadCoils(req) => assert_eq!(req.get_address(), 0x0123),
+            _ => panic!("Expected ReadCoils request, but got {:?}", req),
         }
-        println!("{:?}", req);
-        assert_eq!(false, true);
+
+        mb.write_rsp(stream, ReadCoilsResponse::new(&[true, false])).unwrap();
+        assert_eq!(mb.get_rsps(), &[(10, vec![0x01, 0x01, 0x01])]);
+    }
+
+    #[test]
+    fn test_raw_pdu() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x01, 0x01, 0x23, 0x00, 0x02], &[0x01, 0x01, 0x02]);
+        mb.start_slave(10).unwrap();
+        mb.push_req(10, &[0x06, 0x00, 0x01, 0x00, 0x03]);
+
+        let rsp = mb.write_req_read_rsp_raw(&10, &ReadCoilsRequest::new(0x0123, 0x0002), DecodeMode::Strict).unwrap().unwrap();
+        assert!(rsp.get()[1]);
+        assert_eq!(rsp.get_pdu(), &[0x01, 0x01, 0x02]);
+
+        let (req, _) = mb.read_req_raw().unwrap();
+        assert!(matches!(req.get(), RequestData::WriteSingleReg(_)));
+        assert_eq!(req.into_parts().1, vec![0x06, 0x00, 0x01, 0x00, 0x03]);
+    }
+
+    #[test]
+    fn test_reply_exception() {
+        let mut mb = MockTransport::new();
+        mb.start_slave(10).unwrap();
+        mb.push_req(10, &[0x2b, 0x0e, 0x01, 0x00]);
+
+        let (req_pdu, stream) = mb.read_req_pdu().unwrap();
+        mb.reply_exception(stream, req_pdu[0], ExceptionCode::IllegalFunction).unwrap();
+        assert_eq!(mb.get_rsps(), &[(10, vec![0xab, 0x01])]);
     }
-    */
 
     #[test]
     fn test_reading_coils() {
@@ -189,4 +653,35 @@ mod tests {
             panic!("Expected error, but got Ok result");
         }
     }
+
+    #[test]
+    fn test_master_response_padding() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x01, 0x00, 0x10, 0x00, 0x02], &[0x01, 0x01, 0x07]);
+        mb.expect(&[0x01, 0x00, 0x10, 0x00, 0x02], &[0x01, 0x01, 0x07]);
+        let req = ReadCoilsRequest::new(0x0010, 0x0002);
+
+        let err = mb.write_req_read_rsp(&10, &req).err().unwrap();
+        match err.get_root() {
+            Error::InvalidResponse => {}
+            _ => panic!("Expected InvalidResponse, but got {:?}", err),
+        }
+        assert_eq!(err.get_context().unwrap().get_stage(), Stage::VerifyResponse);
+
+        let rsp = mb.write_req_read_rsp_with_mode(&10, &req, DecodeMode::Lenient).unwrap().unwrap();
+        assert_eq!(rsp.get_coils().to_vec()[..2], [true, true]);
+    }
+
+    #[test]
+    fn test_master_unexpected_function_code() {
+        let mut mb = MockTransport::new();
+        mb.expect(&[0x01, 0x00, 0x10, 0x00, 0x02], &[0x02, 0x01, 0x02]);
+
+        let err = mb.write_req_read_rsp(&10, &ReadCoilsRequest::new(0x0010, 0x0002)).err().unwrap();
+        match err.get_root() {
+            Error::UnexpectedFunctionCode {expected: 0x01, received: 0x02, pdu} => assert_eq!(pdu, &vec![0x02, 0x01, 0x02]),
+            _ => panic!("Expected UnexpectedFunctionCode, but got {:?}", err),
+        }
+        assert_eq!(err.get_context().unwrap().get_stage(), Stage::DecodeResponse);
+    }
 }
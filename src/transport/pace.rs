@@ -0,0 +1,225 @@
+//! Pacing of transactions for slaves requiring a minimum gap between them
+//!
+//! A [PacedTransport] wraps a transport working in the master mode and delays requests,
+//! so that a configured time passes between the end of a transaction and the next request
+//! to the same destination. Requests to other destinations are not delayed.
+//!
+//! # Examples
+//! ```
+//! use modbus::Transport;
+//! use modbus::mock::MockTransport;
+//! use modbus::pace::PacedTransport;
+//! use std::time::Duration;
+//!
+//! let mut device = MockTransport::new();
+//! device.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x34]);
+//! device.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x35]);
+//!
+//! let mut mb = PacedTransport::new(device)
+//!     .with_delay(Duration::from_millis(10))
+//!     .with_dst_delay(1, Duration::from_millis(50));
+//!
+//! // The second request is written at least 50 ms after the first response is read
+//! mb.write_req_read_rsp(&1, &modbus::ReadHldRegRequest::new(0x0010, 1)).unwrap();
+//! mb.write_req_read_rsp(&1, &modbus::ReadHldRegRequest::new(0x0010, 1)).unwrap();
+//! ```
+
+use crate::error::Error;
+use std::fmt;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+use super::Transport;
+
+/// Transport keeping a minimum gap between transactions with each destination
+///
+/// The gap is measured from reading the response, or from writing the request if no response
+/// is read, e.g. for broadcasts. The slave mode is passed to the wrapped transport without pacing.
+pub struct PacedTransport<T: Transport> {
+    transport: T,
+    delay: Duration,
+    dst_delays: Vec<(T::Dst, Duration)>,
+    last_transactions: Vec<(T::Dst, Instant)>,
+    pending_dst: Option<T::Dst>,
+}
+
+impl<T: Transport> PacedTransport<T>
+where
+    T::Dst: Clone + PartialEq,
+{
+    /// Create a transport pacing transactions of given transport, without any delay by default
+    pub fn new(transport: T) -> Self {
+        Self {transport, delay: Duration::ZERO, dst_delays: Vec::new(), last_transactions: Vec::new(), pending_dst: None}
+    }
+
+    /// Set the gap kept between transactions with destinations without a dedicated one
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Set the gap kept between transactions with given destination
+    pub fn with_dst_delay(mut self, dst: T::Dst, delay: Duration) -> Self {
+        self.set_dst_delay(dst, delay);
+        self
+    }
+
+    /// Set the gap kept between transactions with given destination
+    pub fn set_dst_delay(&mut self, dst: T::Dst, delay: Duration) {
+        match self.dst_delays.iter_mut().find(|(known_dst, _)| *known_dst == dst) {
+            Some((_, known_delay)) => *known_delay = delay,
+            None => self.dst_delays.push((dst, delay)),
+        }
+    }
+
+    /// Get the gap kept between transactions with given destination
+    pub fn get_delay(&self, dst: &T::Dst) -> Duration {
+        self.dst_delays.iter()
+            .find(|(known_dst, _)| known_dst == dst)
+            .map_or(self.delay, |(_, delay)| *delay)
+    }
+
+    /// Get mutable reference to the wrapped transport
+    pub fn get_transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Get back the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    fn wait_for_gap(&self, dst: &T::Dst) {
+        let Some((_, last_transaction)) = self.last_transactions.iter().find(|(known_dst, _)| known_dst == dst) else {
+            return;
+        };
+
+        if let Some(remaining) = self.get_delay(dst).checked_sub(last_transaction.elapsed()) {
+            thread::sleep(remaining);
+        }
+    }
+
+    fn mark_transaction(&mut self, dst: &T::Dst) {
+        let now = Instant::now();
+        match self.last_transactions.iter_mut().find(|(known_dst, _)| known_dst == dst) {
+            Some((_, last_transaction)) => *last_transaction = now,
+            None => self.last_transactions.push((dst.clone(), now)),
+        }
+    }
+}
+
+impl<T: Transport> Transport for PacedTransport<T>
+where
+    T::Dst: Clone + PartialEq,
+{
+    type Dst = T::Dst;
+    type Stream = T::Stream;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        self.transport.start_master()
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.transport.start_slave(unit_id)
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        self.transport.start_slave_units(unit_ids)
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        T::is_broadcast(dst)
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        T::get_unit_id(stream)
+    }
+
+    fn get_peer_addr(stream: &Self::Stream) -> Option<SocketAddr> {
+        T::get_peer_addr(stream)
+    }
+
+    fn get_ping_pdu(&self) -> &[u8] {
+        self.transport.get_ping_pdu()
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        self.wait_for_gap(dst);
+        let stream = self.transport.write_req_pdu(dst, pdu);
+
+        self.mark_transaction(dst);
+        self.pending_dst = Some(dst.clone());
+        stream
+    }
+
+    fn read_rsp_pdu(&mut self, stream: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        let rsp_pdu = self.transport.read_rsp_pdu(stream, src);
+
+        if let Some(dst) = self.pending_dst.take() {
+            self.mark_transaction(&dst);
+        }
+
+        rsp_pdu
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        self.transport.read_req_pdu()
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        self.transport.write_rsp_pdu(stream, pdu)
+    }
+}
+
+impl<T: Transport + fmt::Debug> fmt::Debug for PacedTransport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PacedTransport")
+            .field("transport", &self.transport)
+            .field("delay", &self.delay)
+            .field("dst_delays", &self.dst_delays.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::ReadHldRegRequest;
+
+    const REQ_PDU: [u8; 5] = [0x03, 0x00, 0x10, 0x00, 0x01];
+    const RSP_PDU: [u8; 4] = [0x03, 0x02, 0x12, 0x34];
+
+    #[test]
+    fn test_delay() {
+        let mut device = MockTransport::new();
+        for _ in 0..4 {
+            device.expect(&REQ_PDU, &RSP_PDU);
+        }
+        let mut mb = PacedTransport::new(device)
+            .with_delay(Duration::from_millis(100))
+            .with_dst_delay(2, Duration::ZERO);
+        let req = ReadHldRegRequest::new(0x0010, 1);
+
+        // The first transaction with a destination and transactions with other destinations are not delayed
+        let start = Instant::now();
+        mb.write_req_read_rsp(&1, &req).unwrap();
+        mb.write_req_read_rsp(&2, &req).unwrap();
+        mb.write_req_read_rsp(&2, &req).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        mb.write_req_read_rsp(&1, &req).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(mb.into_inner().is_complete());
+    }
+
+    #[test]
+    fn test_get_delay() {
+        let mut mb = PacedTransport::new(MockTransport::new()).with_delay(Duration::from_millis(10));
+        mb.set_dst_delay(3, Duration::from_millis(30));
+        mb.set_dst_delay(3, Duration::from_millis(40));
+
+        assert_eq!(mb.get_delay(&1), Duration::from_millis(10));
+        assert_eq!(mb.get_delay(&3), Duration::from_millis(40));
+    }
+}
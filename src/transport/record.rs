@@ -0,0 +1,323 @@
+//! Recording of traffic exchanged with real devices and replaying it offline
+//!
+//! A [RecordingTransport] wraps a transport working in the master mode and writes every
+//! transaction to a recording. A [ReplayTransport] loaded from the recording serves the recorded
+//! responses back, so that applications can be tested without the devices.
+//!
+//! The recording is a text file with a transaction per line: the unit id, the request PDU and
+//! the response PDU separated with tabs. PDUs are hex dumps. Requests left without response
+//! have the response PDU replaced with `-`. Empty lines and lines starting with `#` are ignored.
+//!
+//! # Examples
+//! ```
+//! use modbus::Transport;
+//! use modbus::mock::MockTransport;
+//! use modbus::record::{RecordingTransport, ReplayTransport};
+//!
+//! let mut device = MockTransport::new();
+//! device.expect(&[0x03, 0x00, 0x10, 0x00, 0x01], &[0x03, 0x02, 0x12, 0x34]);
+//!
+//! let mut mb = RecordingTransport::new(device, Vec::new());
+//! mb.write_req_read_rsp(&1, &modbus::ReadHldRegRequest::new(0x0010, 1)).unwrap();
+//! let (_, recording) = mb.into_inner();
+//! assert_eq!(String::from_utf8(recording.clone()).unwrap(), "1\t03 00 10 00 01\t03 02 12 34\n");
+//!
+//! let mut mb = ReplayTransport::from_reader(&recording[..]).unwrap();
+//! let rsp = mb.write_req_read_rsp(&1, &modbus::ReadHldRegRequest::new(0x0010, 1)).unwrap();
+//! assert_eq!(rsp.unwrap().get_registers(), &[0x1234]);
+//! ```
+
+use crate::error::Error;
+use crate::fmt::HexDump;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use super::Transport;
+
+const BROADCAST_DST: u8 = 0;
+const NO_RESPONSE: &str = "-";
+
+/// Transport writing transactions of the wrapped transport to a recording
+///
+/// Only the master mode is recorded. The slave mode is passed to the wrapped transport
+/// without recording. Requests which could not be written are not recorded.
+#[derive(Debug)]
+pub struct RecordingTransport<T: Transport, W: Write> {
+    transport: T,
+    writer: W,
+    pending_req: Option<(u8, Vec<u8>)>,
+}
+
+impl<T: Transport, W: Write> RecordingTransport<T, W> {
+    /// Create a transport recording transactions of given transport to given writer
+    pub fn new(transport: T, writer: W) -> Self {
+        Self {transport, writer, pending_req: None}
+    }
+
+    /// Get mutable reference to the wrapped transport
+    pub fn get_transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Get back the wrapped transport and the writer
+    pub fn into_inner(self) -> (T, W) {
+        (self.transport, self.writer)
+    }
+
+    fn record(&mut self, unit_id: u8, req_pdu: &[u8], rsp_pdu: Option<&[u8]>) -> Result<(), Error> {
+        match rsp_pdu {
+            Some(rsp_pdu) => writeln!(self.writer, "{}\t{}\t{}", unit_id, HexDump::new(req_pdu), HexDump::new(rsp_pdu))?,
+            None => writeln!(self.writer, "{}\t{}\t{}", unit_id, HexDump::new(req_pdu), NO_RESPONSE)?,
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<T: Transport> RecordingTransport<T, BufWriter<File>> {
+    /// Create a transport recording transactions of given transport to a file at given path
+    ///
+    /// An existing file is truncated.
+    pub fn create<P: AsRef<Path>>(transport: T, path: P) -> Result<Self, Error> {
+        Ok(Self::new(transport, BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<T: Transport, W: Write> Transport for RecordingTransport<T, W> {
+    type Dst = T::Dst;
+    type Stream = T::Stream;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        self.transport.start_master()
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.transport.start_slave(unit_id)
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        self.transport.start_slave_units(unit_ids)
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        T::is_broadcast(dst)
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        T::get_unit_id(stream)
+    }
+
+    fn get_peer_addr(stream: &Self::Stream) -> Option<SocketAddr> {
+        T::get_peer_addr(stream)
+    }
+
+    fn get_ping_pdu(&self) -> &[u8] {
+        self.transport.get_ping_pdu()
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        let stream = self.transport.write_req_pdu(dst, pdu)?;
+        let unit_id = T::get_unit_id(&stream);
+
+        if T::is_broadcast(dst) {
+            self.record(unit_id, pdu, None)?;
+        } else {
+            self.pending_req = Some((unit_id, pdu.to_vec()));
+        }
+
+        Ok(stream)
+    }
+
+    fn read_rsp_pdu(&mut self, stream: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        let rsp_pdu = self.transport.read_rsp_pdu(stream, src);
+
+        if let Some((unit_id, req_pdu)) = self.pending_req.take() {
+            self.record(unit_id, &req_pdu, rsp_pdu.as_deref().ok())?;
+        }
+
+        rsp_pdu
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        self.transport.read_req_pdu()
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        self.transport.write_rsp_pdu(stream, pdu)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Transaction {
+    unit_id: u8,
+    req_pdu: Vec<u8>,
+    rsp_pdu: Option<Vec<u8>>,
+}
+
+fn parse_pdu(field: &str) -> Result<Vec<u8>, Error> {
+    field.split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).map_err(|_| Error::InvalidData))
+        .collect()
+}
+
+fn parse_transaction(line: &str) -> Result<Transaction, Error> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [unit_id, req_pdu, rsp_pdu] = fields[..] else {
+        return Err(Error::InvalidData);
+    };
+
+    Ok(Transaction {
+        unit_id: unit_id.trim().parse().map_err(|_| Error::InvalidData)?,
+        req_pdu: parse_pdu(req_pdu)?,
+        rsp_pdu: match rsp_pdu.trim() {
+            NO_RESPONSE => None,
+            rsp_pdu => Some(parse_pdu(rsp_pdu)?),
+        },
+    })
+}
+
+/// Transport serving responses from a recording
+///
+/// Destinations are plain unit ids. A written request is matched with the first not yet served
+/// transaction of the recording with the same unit id and request PDU, so that requests to
+/// different devices can be interleaved differently than during the recording. Requests missing
+/// from the recording fail with [InvalidRequest error](Error::InvalidRequest).
+///
+/// Only the master mode is supported.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    transactions: Vec<Transaction>,
+    pending_rsp: Option<Vec<u8>>,
+}
+
+impl ReplayTransport {
+    /// Load a recording from given reader
+    ///
+    /// Malformed lines fail with [InvalidData error](Error::InvalidData).
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut transactions = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            transactions.push(parse_transaction(&line)?);
+        }
+
+        Ok(Self {transactions, pending_rsp: None})
+    }
+
+    /// Load a recording from a file at given path
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Get number of recorded transactions not served yet
+    pub fn get_remaining(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Verify if all recorded transactions were served
+    pub fn is_complete(&self) -> bool {
+        self.transactions.is_empty()
+    }
+}
+
+impl Transport for ReplayTransport {
+    type Dst = u8;
+    type Stream = u8;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn start_slave(&mut self, _: u8) -> Result<(), Error> {
+        Err(Error::InvalidValue)
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        *dst == BROADCAST_DST
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        *stream
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        let index = self.transactions.iter()
+            .position(|transaction| transaction.unit_id == *dst && transaction.req_pdu == pdu)
+            .ok_or(Error::InvalidRequest)?;
+
+        self.pending_rsp = self.transactions.remove(index).rsp_pdu;
+        Ok(*dst)
+    }
+
+    fn read_rsp_pdu(&mut self, _: &mut Self::Stream, _: &Self::Dst) -> Result<Vec<u8>, Error> {
+        self.pending_rsp.take().ok_or(Error::NoResponse)
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        Err(Error::InvalidValue)
+    }
+
+    fn write_rsp_pdu(&mut self, _: &mut Self::Stream, _: &[u8]) -> Result<(), Error> {
+        Err(Error::InvalidValue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use crate::{ReadCoilsRequest, WriteSingleRegRequest};
+
+    #[test]
+    fn test_record() {
+        let mut device = MockTransport::new();
+        device.expect(&[0x01, 0x00, 0x00, 0x00, 0x03], &[0x81, 0x02]);
+        device.expect_no_response(&[0x06, 0x00, 0x01, 0x00, 0x05]);
+        device.expect(&[0x06, 0x00, 0x01, 0x00, 0x07], &[0x06, 0x00, 0x01, 0x00, 0x07]);
+
+        let mut mb = RecordingTransport::new(device, Vec::new());
+        assert!(mb.write_req_read_rsp(&2, &ReadCoilsRequest::new(0x0000, 3)).is_err());
+        assert!(mb.write_setter_req(&2, &WriteSingleRegRequest::new(0x0001, 5)).is_err());
+        mb.write_setter_req(&0, &WriteSingleRegRequest::new(0x0001, 7)).unwrap();
+
+        let (device, recording) = mb.into_inner();
+        assert!(device.is_complete());
+        assert_eq!(String::from_utf8(recording).unwrap(),
+                   "2\t01 00 00 00 03\t81 02\n\
+                    2\t06 00 01 00 05\t-\n\
+                    0\t06 00 01 00 07\t-\n");
+    }
+
+    #[test]
+    fn test_replay() {
+        let recording = "# coils of two devices\n\
+                         1\t01 00 00 00 03\t01 01 05\n\
+                         \n\
+                         2\t01 00 00 00 03\t-\n\
+                         1\t01 00 00 00 03\t81 02\n";
+        let mut mb = ReplayTransport::from_reader(recording.as_bytes()).unwrap();
+        assert_eq!(mb.get_remaining(), 3);
+
+        assert!(matches!(mb.write_req_read_rsp(&2, &ReadCoilsRequest::new(0x0000, 3)).err().unwrap().get_root(),
+                         Error::NoResponse));
+        let rsp = mb.write_req_read_rsp(&1, &ReadCoilsRequest::new(0x0000, 3)).unwrap().unwrap();
+        assert_eq!(rsp.get_coils().to_vec()[..3], [true, false, true]);
+        assert!(matches!(mb.write_req_read_rsp(&1, &ReadCoilsRequest::new(0x0000, 3)).err().unwrap().get_root(),
+                         Error::ExceptionResponse(_)));
+        assert!(mb.is_complete());
+
+        assert!(matches!(mb.write_req_read_rsp(&1, &ReadCoilsRequest::new(0x0000, 3)), Err(Error::InvalidRequest)));
+    }
+
+    #[test]
+    fn test_malformed_recording() {
+        assert!(matches!(ReplayTransport::from_reader("1\t01 00 00 00 03\n".as_bytes()), Err(Error::InvalidData)));
+        assert!(matches!(ReplayTransport::from_reader("1\t01 0g\t-\n".as_bytes()), Err(Error::InvalidData)));
+        assert!(matches!(ReplayTransport::from_reader("256\t01\t-\n".as_bytes()), Err(Error::InvalidData)));
+    }
+}
@@ -0,0 +1,125 @@
+//! Sharing of a single RTU bus between threads
+//!
+//! A [Bus] moves the transport of a serial line to a dedicated thread, which performs the
+//! transactions requested through any number of handles one at a time. As only this thread
+//! touches the serial port, frames of different application components never interleave and
+//! the silent interval the transport keeps before each frame is preserved. The thread is an
+//! [actor](crate::actor) started in the master mode.
+//!
+//! # Examples
+//! ```no_run
+//! use modbus::rtu_bus::Bus;
+//! use std::thread;
+//!
+//! let rtu = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &Default::default()).unwrap();
+//! let bus = Bus::spawn(rtu).unwrap();
+//!
+//! let meter_bus = bus.clone();
+//! let meter = thread::spawn(move || meter_bus.write_req_read_rsp(1, &modbus::ReadInRegRequest::new(0x0000, 4)));
+//! let relay = bus.write_req_read_rsp(2, &modbus::WriteSingleCoilRequest::new(0x0000, true));
+//! let energy = meter.join().unwrap();
+//! ```
+
+use crate::error::Error;
+use crate::pdu::Request;
+use std::fmt;
+use super::super::actor::{self, Handle};
+use super::super::Transport;
+
+/// Handle to a serial bus owned by a dedicated thread
+///
+/// It may be cloned to request transactions from multiple threads. Transactions are performed in
+/// the order they are requested. The thread stops when all the handles are dropped.
+pub struct Bus<T> {
+    handle: Handle<T>,
+}
+
+impl<T> Clone for Bus<T> {
+    fn clone(&self) -> Self {
+        Self {handle: self.handle.clone()}
+    }
+}
+
+impl<T> fmt::Debug for Bus<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Bus").finish_non_exhaustive()
+    }
+}
+
+impl<T: Transport<Dst = u8> + Send + 'static> Bus<T> {
+    /// Start the master mode of given transport and move it to a thread serving the bus
+    pub fn spawn(mut transport: T) -> Result<Self, Error> {
+        transport.start_master()?;
+        Ok(Self {handle: actor::spawn(transport)?})
+    }
+
+    /// Write a request PDU to given unit id and read the response PDU, `None` for broadcasts
+    ///
+    /// It blocks until the transactions requested earlier from other handles are completed.
+    pub fn write_req_pdu_read_rsp(&self, unit_id: u8, req_pdu: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let req_pdu = req_pdu.to_vec();
+        self.handle.call(move |transport| transact(transport, unit_id, &req_pdu))?
+    }
+
+    /// Write a request to given unit id and read the response like [Transport::write_req_read_rsp]
+    pub fn write_req_read_rsp<Req>(&self, unit_id: u8, req: &Req) -> Result<Option<Req::Rsp>, Error>
+    where
+        Req: Request + Clone + Send + 'static,
+        Req::Rsp: Send,
+    {
+        self.handle.write_req_read_rsp(&unit_id, req)
+    }
+}
+
+fn transact<T: Transport<Dst = u8>>(transport: &mut T, unit_id: u8, req_pdu: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+    let mut stream = transport.write_req_pdu(&unit_id, req_pdu)?;
+
+    if T::is_broadcast(&unit_id) {
+        Ok(None)
+    } else {
+        transport.read_rsp_pdu(&mut stream, &unit_id).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+    use std::thread;
+
+    #[test]
+    fn test_transactions_from_multiple_threads() {
+        let mut transport = MockTransport::new();
+        for _ in 0..4 {
+            transport.expect_fn(Box::new(|unit_id, _| Some(vec![0x03, 0x02, 0x00, unit_id])));
+        }
+        let bus = Bus::spawn(transport).unwrap();
+
+        let threads: Vec<_> = (1..=4).map(|unit_id| {
+            let bus = bus.clone();
+            thread::spawn(move || bus.write_req_read_rsp(unit_id, &crate::ReadHldRegRequest::new(0x0000, 1)))
+        }).collect();
+
+        for (unit_id, thread) in (1..=4).zip(threads) {
+            let rsp = thread.join().unwrap().unwrap().unwrap();
+            assert_eq!(rsp.get_registers(), &[unit_id as u16]);
+        }
+    }
+
+    #[test]
+    fn test_broadcast() {
+        let mut transport = MockTransport::new();
+        transport.expect_no_response(&[0x06, 0x00, 0x01, 0x00, 0x02]);
+        let bus = Bus::spawn(transport).unwrap();
+
+        assert_eq!(bus.write_req_pdu_read_rsp(0, &[0x06, 0x00, 0x01, 0x00, 0x02]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_transport_error() {
+        let bus = Bus::spawn(MockTransport::new()).unwrap();
+
+        assert!(bus.write_req_pdu_read_rsp(1, &[0x03, 0x00, 0x00, 0x00, 0x01]).is_err());
+        assert!(bus.write_req_pdu_read_rsp(1, &[0x03, 0x00, 0x00, 0x00, 0x01]).is_err());
+    }
+}
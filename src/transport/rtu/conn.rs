@@ -1,20 +1,37 @@
 //! Modbus RTU over serial interface
  
+use crate::cancel::CancelToken;
 use crate::error::Error;
-use serialport::{SerialPort, SerialPortSettings, open_with_settings};
-use std::convert::TryInto;
+use serialport::{SerialPort, SerialPortInfo, SerialPortSettings, SerialPortType, available_ports, open_with_settings};
 use std::ffi::OsStr;
+use std::io::{ErrorKind, Read};
 use std::time::{Duration, Instant};
 use std::thread::sleep;
-use super::frame::Frame;
+use super::super::capture::{notify, Direction, Observer};
+use super::frame::{Frame, MAX_FRAME_LEN};
+pub use super::counters::Counters;
+use super::timing::char_timeouts;
 use super::super::Transport;
 
-const BROADCAST_DST: u8 = 0;
+pub(super) const BROADCAST_DST: u8 = 0;
+pub(super) const RSP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Round given duration up to whole milliseconds, which is the resolution of serial port timeouts
+pub(super) fn ceil_millis(duration: Duration) -> Duration {
+    Duration::from_millis(duration.as_nanos().div_ceil(1_000_000) as u64)
+}
+
+fn is_usb_port(info: &SerialPortInfo, vid: u16, pid: Option<u16>) -> bool {
+    match &info.port_type {
+        SerialPortType::UsbPort(usb) => usb.vid == vid && pid.is_none_or(|pid| usb.pid == pid),
+        _ => false,
+    }
+}
  
 #[derive(PartialEq)]
 enum Role {
     Master,
-    Slave(u8),
+    Slave(Vec<u8>),
 }
 
 /// RTU transport for Modbus commands
@@ -25,7 +42,18 @@ pub struct Rtu {
     serial: Box<dyn SerialPort>,
     role: Role,
 
+    rsp_timeout: Duration,
+    inter_frame_timeout: Duration,
     last_baud_timestamp: Instant,
+
+    rts_control: Option<(Duration, Duration)>,
+
+    crc_retries: u8,
+    last_req_pdu: Vec<u8>,
+
+    observer: Option<Observer>,
+    cancel_token: Option<CancelToken>,
+    counters: Counters,
 }
 
 impl Rtu {
@@ -33,6 +61,13 @@ impl Rtu {
     /// 
     /// This function opens serial port with [this](serialport::open_with_settings) function.
     /// 
+    /// The timeout in `settings` is ignored. Frames are delimited with the 3.5 character
+    /// interval derived from the baud rate as required by the specification: a gap longer
+    /// than 3.5 characters ends a frame and the line is kept silent for 3.5 characters
+    /// before a frame is written. Above 19200 baud the fixed values of
+    /// 750 µs and 1.75 ms are used. The read timeout of the serial port is rounded up to
+    /// whole milliseconds.
+    /// 
     /// # Examples
     /// ```
     /// use serialport::{SerialPortSettings, DataBits, FlowControl, Parity, StopBits};
@@ -49,15 +84,163 @@ impl Rtu {
     /// let modbus = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &s);
     /// ```
     pub fn conn<T: AsRef<OsStr> + ?Sized>(port: &T, settings: &SerialPortSettings) -> Result<Self, Error> {
-        Ok(Rtu{serial:              open_with_settings(port, settings)?, 
+        Self::with_serial(open_with_settings(port, settings)?, settings.baud_rate)
+    }
+
+    fn with_serial(mut serial: Box<dyn SerialPort>, baud_rate: u32) -> Result<Self, Error> {
+        let (_, t3_5) = char_timeouts(baud_rate);
+        serial.set_timeout(ceil_millis(t3_5))?;
+
+        Ok(Rtu{serial, 
                role:                Role::Master, 
-               last_baud_timestamp: Instant::now()})
-        // TODO: select timeout based on spec (1.5 baud)
+               rsp_timeout:         RSP_TIMEOUT,
+               inter_frame_timeout: t3_5,
+               last_baud_timestamp: Instant::now(),
+               rts_control: None,
+               crc_retries: 0,
+               last_req_pdu: Vec::new(),
+               observer: None,
+               cancel_token: None,
+               counters: Counters::default()})
+    }
+
+    /// List serial ports available in the system
+    /// 
+    /// Names of the listed ports can be passed to [Rtu::conn].
+    pub fn available_ports() -> Result<Vec<SerialPortInfo>, Error> {
+        Ok(available_ports()?)
+    }
+
+    /// List USB serial ports with given vendor id and, optionally, product id
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::rtu::Rtu;
+    /// 
+    /// let ports = Rtu::usb_ports(0x0403, Some(0x6001)).unwrap();
+    /// let port = ports.first().expect("No FTDI adapter found");
+    /// let modbus = Rtu::conn(&port.port_name, &Default::default()).unwrap();
+    /// ```
+    pub fn usb_ports(vid: u16, pid: Option<u16>) -> Result<Vec<SerialPortInfo>, Error> {
+        Ok(available_ports()?.into_iter().filter(|info| is_usb_port(info, vid, pid)).collect())
+    }
+
+    /// Set how long the master waits for the first byte of a response, 1 second by default
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.rsp_timeout = timeout;
+    }
+
+    /// Set the silent interval that ends a frame and precedes each transmitted frame
+    /// 
+    /// By default it is the 3.5 character time derived from the baud rate.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use serialport::SerialPortSettings;
+    /// use std::time::Duration;
+    /// 
+    /// let s = SerialPortSettings::default();
+    /// let mut modbus = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &s).unwrap();
+    /// modbus.set_response_timeout(Duration::from_millis(500));
+    /// modbus.set_inter_frame_timeout(Duration::from_millis(5)).unwrap();
+    /// ```
+    pub fn set_inter_frame_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.serial.set_timeout(ceil_millis(timeout))?;
+        self.inter_frame_timeout = timeout;
+        Ok(())
+    }
+
+    /// Set how many times the master repeats a request whose response has an invalid CRC
+    /// 
+    /// The corrupted response is discarded and the request is sent again. If all the
+    /// retries fail, [InvalidData error](Error::InvalidData) is returned. By default
+    /// requests are not repeated.
+    pub fn set_crc_retries(&mut self, retries: u8) {
+        self.crc_retries = retries;
+    }
+
+    /// Control RS-485 transceiver direction with the RTS signal
+    /// 
+    /// RTS is asserted before each frame is transmitted and deasserted after the frame is
+    /// flushed. `pre_delay` is the time between asserting RTS and the transmission start
+    /// and `post_delay` is the time between the transmission end and deasserting RTS.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use serialport::SerialPortSettings;
+    /// use std::time::Duration;
+    /// 
+    /// let s = SerialPortSettings::default();
+    /// let modbus = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &s).unwrap()
+    ///     .with_rts_control(Duration::from_micros(100), Duration::from_micros(100));
+    /// ```
+    pub fn with_rts_control(mut self, pre_delay: Duration, post_delay: Duration) -> Self {
+        self.rts_control = Some((pre_delay, post_delay));
+        self
+    }
+
+    /// Set observer receiving every transmitted and received frame, or remove it with `None`
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use serialport::SerialPortSettings;
+    /// 
+    /// let s = SerialPortSettings::default();
+    /// let mut modbus = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &s).unwrap();
+    /// modbus.set_observer(Some(Box::new(|direction, _timestamp, frame_data| {
+    ///     println!("{:?} {:02X?}", direction, frame_data);
+    /// })));
+    /// ```
+    pub fn set_observer(&mut self, observer: Option<Observer>) {
+        self.observer = observer;
+    }
+
+    /// Set token aborting blocking reads when cancelled, or remove it with `None`
+    /// 
+    /// A cancelled read fails with [Error::Cancelled]. The token stays cancelled, so a new token
+    /// shall be set to use the transport again.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::{CancelToken, Transport};
+    /// 
+    /// let mut modbus = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &Default::default()).unwrap();
+    /// let token = CancelToken::new();
+    /// modbus.set_cancel_token(Some(token.clone()));
+    /// modbus.start_slave(10).unwrap();
+    /// 
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(std::time::Duration::from_secs(10));
+    ///     token.cancel();
+    /// });
+    /// assert!(matches!(modbus.read_req_pdu(), Err(modbus::Error::Cancelled)));
+    /// ```
+    pub fn set_cancel_token(&mut self, cancel_token: Option<CancelToken>) {
+        self.cancel_token = cancel_token;
+    }
+
+    /// Get diagnostic counters of the serial line
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::Transport;
+    /// 
+    /// let mut modbus = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &Default::default()).unwrap();
+    /// modbus.start_slave(10).unwrap();
+    /// let (req_pdu, mut stream) = modbus.read_req_pdu().unwrap();
+    /// println!("CRC errors: {}", modbus.get_counters().get_bus_comm_error_count());
+    /// ```
+    pub fn get_counters(&self) -> Counters {
+        self.counters
+    }
+
+    /// Reset all diagnostic counters of the serial line to zero
+    pub fn reset_counters(&mut self) {
+        self.counters = Counters::default();
     }
 
     fn sleep_before_write(&self) {
-        // TODO: select sleep time based on spec (3.5 baud)
-        let min_delay = Duration::new(0, 100000000);
+        let min_delay = self.inter_frame_timeout;
         let curr_delay = Instant::now().duration_since(self.last_baud_timestamp);
 
         if curr_delay < min_delay {
@@ -69,54 +252,147 @@ impl Rtu {
         self.sleep_before_write();
 
         let frame = Frame::new(unit_id, pdu);
-        self.serial.write_all(&frame.encode()?)?;
+        let frame_data = frame.encode()?;
+
+        if let Some((pre_delay, _)) = self.rts_control {
+            self.serial.write_request_to_send(true)?;
+            sleep(pre_delay);
+        }
+
+        let result = self.serial.write_all(&frame_data).and_then(|_| self.serial.flush());
+
+        if let Some((_, post_delay)) = self.rts_control {
+            sleep(post_delay);
+            self.serial.write_request_to_send(false)?;
+        }
 
-        self.serial.flush()?;
+        result?;
         self.last_baud_timestamp = Instant::now();
+        notify(&mut self.observer, Direction::Tx, &frame_data);
 
         Ok(())
     }
 
-    fn read_pdu(&mut self, expected_unit_id: u8, infinitely: bool) -> Result<Vec<u8>, Error> {
-        let mut rsp_frame = Vec::new();
-        let mut rsp_byte: [u8; 1] = [0];
+    pub(super) fn read_frame<R: Read + ?Sized>(reader: &mut R, rsp_timeout: Option<Duration>,
+                                               cancel_token: Option<&CancelToken>) -> Result<Vec<u8>, Error> {
+        let mut frame_data = Vec::new();
+        let mut buf = [0; MAX_FRAME_LEN];
+        let mut overflow = false;
+        let start = Instant::now();
 
         loop {
-            match self.serial.read(&mut rsp_byte) {
-                Ok(num_bytes) => {
-                    assert_eq!(num_bytes, 1);
-                    rsp_frame.push(rsp_byte[0]);
-
-                    self.last_baud_timestamp = Instant::now();
+            match reader.read(&mut buf) {
+                // A serial port signals silence with a timeout, so no data means the end of a stream
+                Ok(0) if frame_data.is_empty() || overflow => return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into()),
+                Ok(0) => return Ok(frame_data),
+                Ok(_) if overflow => {}
+                Ok(num_bytes) if frame_data.len() + num_bytes > MAX_FRAME_LEN => {
+                    // Discard the data until the line is silent to resynchronize with the next frame
+                    overflow = true;
+                    frame_data.clear();
                 }
+                Ok(num_bytes) => frame_data.extend_from_slice(&buf[..num_bytes]),
                 Err(err) => {
                     match err.kind() {
-                        std::io::ErrorKind::TimedOut => {
-                            if infinitely && rsp_frame.len() == 0 {
-                                continue;
+                        ErrorKind::TimedOut => {
+                            if overflow {
+                                return Err(Error::FrameTooLong);
+                            }
+                            if !frame_data.is_empty() {
+                                return Ok(frame_data);
+                            }
+                            if cancel_token.is_some_and(CancelToken::is_cancelled) {
+                                return Err(Error::Cancelled);
                             }
 
-                            let frame = Frame::decode(&rsp_frame)?;
-                            
-                            if frame.is_address(expected_unit_id) {
-                                return Ok(frame.get_pdu());
-                            } else {
-                                return Err(Error::InvalidData);
+                            match rsp_timeout {
+                                Some(timeout) if start.elapsed() >= timeout => return Err(Error::NoResponse),
+                                _ => continue,
                             }
                         }
                         _ => { 
-                            return Err(err.try_into().unwrap()); 
+                            return Err(err.into()); 
                         }
                     }
                 }
             }
         }
     }
+
+    /// Read a frame, counting frames too long to be received
+    fn read_counted_frame(&mut self, rsp_timeout: Option<Duration>) -> Result<Vec<u8>, Error> {
+        let frame_data = Self::read_frame(&mut self.serial, rsp_timeout, self.cancel_token.as_ref()).inspect_err(|err| {
+            if let Error::FrameTooLong = err {
+                self.counters.count_char_overrun();
+            }
+        })?;
+        self.last_baud_timestamp = Instant::now();
+        notify(&mut self.observer, Direction::Rx, &frame_data);
+
+        Ok(frame_data)
+    }
+
+    /// Read PDU of a request frame, waiting for it at most given time
+    /// 
+    /// It works like [Transport::read_req_pdu], but returns `None` if no request addressed to
+    /// this slave arrived before the timeout expired. It allows slave loops to perform other
+    /// tasks between requests.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::Transport;
+    /// use std::time::Duration;
+    /// 
+    /// let mut modbus = modbus::rtu::Rtu::conn("/dev/ttyUSB0", &Default::default()).unwrap();
+    /// modbus.start_slave(10).unwrap();
+    /// loop {
+    ///     match modbus.read_req_timeout(Duration::from_millis(500)).unwrap() {
+    ///         Some((req_pdu, stream)) => println!("Request {:02X?} to unit {}", req_pdu, stream),
+    ///         None => println!("Idle"),
+    ///     }
+    /// }
+    /// ```
+    pub fn read_req_timeout(&mut self, timeout: Duration) -> Result<Option<(Vec<u8>, u8)>, Error> {
+        self.read_req_until(Some(Instant::now() + timeout))
+    }
+
+    fn read_req_until(&mut self, deadline: Option<Instant>) -> Result<Option<(Vec<u8>, u8)>, Error> {
+        let unit_ids = match &self.role {
+            Role::Slave(unit_ids) => unit_ids.clone(),
+            Role::Master => return Err(Error::InvalidValue),
+        };
+
+        loop {
+            let timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let frame_data = match self.read_counted_frame(timeout) {
+                Err(Error::NoResponse) => return Ok(None),
+                result => result?,
+            };
+            self.counters.count_frame(Frame::decode_complete(&frame_data).is_ok());
+
+            if let Some((unit_id, pdu)) = Self::accept_req_frame(&frame_data, &unit_ids) {
+                self.counters.count_slave_message();
+                return Ok(Some((pdu, unit_id)));
+            }
+        }
+    }
+
+    /// Get unit id and PDU of a request frame addressed to one of given unit ids
+    /// 
+    /// Corrupted frames and frames addressed to other slaves are discarded, as a slave
+    /// must not respond to them.
+    fn accept_req_frame(frame_data: &[u8], unit_ids: &[u8]) -> Option<(u8, Vec<u8>)> {
+        let frame = Frame::decode(frame_data).ok()?;
+
+        unit_ids.iter()
+            .find(|unit_id| frame.is_address(**unit_id))
+            .map(|unit_id| (*unit_id, frame.get_pdu()))
+    }
 }
 
 impl Transport for Rtu {
     type Dst = u8;
-    type Stream = ();
+    type Stream = u8;
 
     fn start_master(&mut self) -> Result<(), Error> {
         self.role = Role::Master;
@@ -124,47 +400,304 @@ impl Transport for Rtu {
     }
 
     fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
-        match unit_id {
-            1..=247 => {
-                self.role = Role::Slave(unit_id);
-                Ok(())
-            }
-            _ => Err(Error::InvalidValue)
+        self.start_slave_units(&[unit_id])
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        if unit_ids.is_empty() || unit_ids.iter().any(|unit_id| !(1..=247).contains(unit_id)) {
+            return Err(Error::InvalidValue);
         }
+
+        self.role = Role::Slave(unit_ids.to_vec());
+        Ok(())
     }
 
     fn is_broadcast(dst: &Self::Dst) -> bool {
         *dst == BROADCAST_DST
     }
 
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        *stream
+    }
+
     fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
         self.write_pdu(*dst, pdu)?;
-        Ok(())
+        self.last_req_pdu = pdu.to_vec();
+        Ok(*dst)
     }
 
     fn read_rsp_pdu(&mut self, _: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
-        self.read_pdu(*src, false)
-    }
+        let mut retries = self.crc_retries;
 
-    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
-        if let Role::Slave(unit_id) = self.role {
-            loop {
-                let result = self.read_pdu(unit_id, true);
+        loop {
+            let frame_data = self.read_counted_frame(Some(self.rsp_timeout))?;
+            let frame = Frame::decode_complete(&frame_data);
+            self.counters.count_frame(frame.is_ok());
 
-                if let Ok(result) = result {
-                    return Ok((result, ()));
+            match frame {
+                Ok(frame) if frame.is_address(*src) => return Ok(frame.get_pdu()),
+                Ok(_) => return Err(Error::InvalidData),
+                Err(Error::InvalidData) if retries > 0 && !self.last_req_pdu.is_empty() => {
+                    retries -= 1;
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(retries_left = retries, "Retrying request after corrupted response");
+                    let req_pdu = self.last_req_pdu.clone();
+                    self.write_pdu(*src, &req_pdu)?;
                 }
+                Err(err) => return Err(err),
             }
-        } else {
-            Err(Error::InvalidValue)
         }
     }
 
-    fn write_rsp_pdu(&mut self, _: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
-        if let Role::Slave(unit_id) = self.role {
-            self.write_pdu(unit_id, pdu)
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        self.read_req_until(None)?.ok_or(Error::NoResponse)
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        if let Role::Slave(_) = self.role {
+            self.counters.count_slave_response(pdu);
+            self.write_pdu(*stream, pdu)
         } else {
             Err(Error::InvalidValue)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialport::{ClearBuffer, DataBits, FlowControl, Parity, Result as SerialResult, StopBits};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_is_usb_port() {
+        let usb = serialport::UsbPortInfo {vid: 0x0403, pid: 0x6001, serial_number: None, manufacturer: None, product: None};
+        let info = SerialPortInfo {port_name: "/dev/ttyUSB0".to_string(), port_type: SerialPortType::UsbPort(usb)};
+
+        assert!(is_usb_port(&info, 0x0403, None));
+        assert!(is_usb_port(&info, 0x0403, Some(0x6001)));
+        assert!(!is_usb_port(&info, 0x0403, Some(0x6015)));
+        assert!(!is_usb_port(&info, 0x10C4, None));
+
+        let info = SerialPortInfo {port_name: "/dev/ttyS0".to_string(), port_type: SerialPortType::PciPort};
+        assert!(!is_usb_port(&info, 0x0403, None));
+    }
+
+    #[test]
+    fn test_ceil_millis() {
+        assert_eq!(ceil_millis(Duration::from_micros(750)), Duration::from_millis(1));
+        assert_eq!(ceil_millis(Duration::from_millis(2)), Duration::from_millis(2));
+    }
+
+    struct ChunkReader {
+        chunks: Vec<Vec<u8>>,
+    }
+
+    impl Read for ChunkReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.chunks.is_empty() {
+                return Err(ErrorKind::TimedOut.into());
+            }
+
+            let chunk = self.chunks.remove(0);
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_read_frame_chunks() {
+        let mut reader = ChunkReader {chunks: vec![vec![0x02, 0x07], vec![0x41, 0x12]]};
+        let frame_data = Rtu::read_frame(&mut reader, Some(RSP_TIMEOUT), None).unwrap();
+
+        assert_eq!(frame_data, vec![0x02, 0x07, 0x41, 0x12]);
+    }
+
+    #[test]
+    fn test_read_frame_no_response() {
+        let mut reader = ChunkReader {chunks: Vec::new()};
+        let err = Rtu::read_frame(&mut reader, Some(Duration::from_millis(10)), None).err().unwrap();
+
+        match err {
+            Error::NoResponse => {}
+            _ => panic!("Expected NoResponse, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_read_frame_max_len() {
+        let mut reader = ChunkReader {chunks: vec![vec![0x02; 200], vec![0x03; 56]]};
+        let frame_data = Rtu::read_frame(&mut reader, None, None).unwrap();
+
+        assert_eq!(frame_data.len(), MAX_FRAME_LEN);
+    }
+
+    #[test]
+    fn test_read_frame_overflow() {
+        let mut reader = ChunkReader {chunks: vec![vec![0x02; 200], vec![0x03; 100], vec![0x04; 10]]};
+        let err = Rtu::read_frame(&mut reader, None, None).err().unwrap();
+
+        match err {
+            Error::FrameTooLong => {}
+            _ => panic!("Expected FrameTooLong, but got {:?}", err),
+        }
+        assert!(reader.chunks.is_empty());
+    }
+
+    #[test]
+    fn test_read_frame_eof() {
+        let mut reader: &[u8] = &[0x02, 0x07, 0x41, 0x12];
+        assert_eq!(Rtu::read_frame(&mut reader, None, None).unwrap(), vec![0x02, 0x07, 0x41, 0x12]);
+
+        match Rtu::read_frame(&mut reader, None, None).err().unwrap() {
+            Error::IoError(err) if err.kind() == ErrorKind::UnexpectedEof => {}
+            err => panic!("Expected UnexpectedEof, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_read_frame_cancelled() {
+        let mut reader = ChunkReader {chunks: Vec::new()};
+        let token = CancelToken::new();
+        token.cancel();
+        let err = Rtu::read_frame(&mut reader, None, Some(&token)).err().unwrap();
+
+        match err {
+            Error::Cancelled => {}
+            _ => panic!("Expected Cancelled, but got {:?}", err),
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Rts(bool),
+        Write(Vec<u8>),
+    }
+
+    /// Serial port reading given chunks, `None` for a silent interval, and recording writes
+    struct MockSerial {
+        rx: Vec<Option<Vec<u8>>>,
+        events: Arc<Mutex<Vec<Event>>>,
+    }
+
+    impl Read for MockSerial {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match if self.rx.is_empty() { None } else { self.rx.remove(0) } {
+                Some(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                None => Err(ErrorKind::TimedOut.into()),
+            }
+        }
+    }
+
+    impl Write for MockSerial {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.events.lock().unwrap().push(Event::Write(buf.to_vec()));
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SerialPort for MockSerial {
+        fn name(&self) -> Option<String> { None }
+        fn settings(&self) -> SerialPortSettings { SerialPortSettings::default() }
+        fn baud_rate(&self) -> SerialResult<u32> { Ok(115200) }
+        fn data_bits(&self) -> SerialResult<DataBits> { Ok(DataBits::Eight) }
+        fn flow_control(&self) -> SerialResult<FlowControl> { Ok(FlowControl::None) }
+        fn parity(&self) -> SerialResult<Parity> { Ok(Parity::None) }
+        fn stop_bits(&self) -> SerialResult<StopBits> { Ok(StopBits::One) }
+        fn timeout(&self) -> Duration { Duration::from_millis(1) }
+        fn set_all(&mut self, _: &SerialPortSettings) -> SerialResult<()> { Ok(()) }
+        fn set_baud_rate(&mut self, _: u32) -> SerialResult<()> { Ok(()) }
+        fn set_data_bits(&mut self, _: DataBits) -> SerialResult<()> { Ok(()) }
+        fn set_flow_control(&mut self, _: FlowControl) -> SerialResult<()> { Ok(()) }
+        fn set_parity(&mut self, _: Parity) -> SerialResult<()> { Ok(()) }
+        fn set_stop_bits(&mut self, _: StopBits) -> SerialResult<()> { Ok(()) }
+        fn set_timeout(&mut self, _: Duration) -> SerialResult<()> { Ok(()) }
+
+        fn write_request_to_send(&mut self, level: bool) -> SerialResult<()> {
+            self.events.lock().unwrap().push(Event::Rts(level));
+            Ok(())
+        }
+
+        fn write_data_terminal_ready(&mut self, _: bool) -> SerialResult<()> { Ok(()) }
+        fn read_clear_to_send(&mut self) -> SerialResult<bool> { Ok(true) }
+        fn read_data_set_ready(&mut self) -> SerialResult<bool> { Ok(true) }
+        fn read_ring_indicator(&mut self) -> SerialResult<bool> { Ok(false) }
+        fn read_carrier_detect(&mut self) -> SerialResult<bool> { Ok(false) }
+        fn bytes_to_read(&self) -> SerialResult<u32> { Ok(0) }
+        fn bytes_to_write(&self) -> SerialResult<u32> { Ok(0) }
+        fn clear(&self, _: ClearBuffer) -> SerialResult<()> { Ok(()) }
+        fn try_clone(&self) -> SerialResult<Box<dyn SerialPort>> {
+            Err(serialport::Error::new(serialport::ErrorKind::Unknown, "Mock serial port cannot be cloned"))
+        }
+    }
+
+    fn create_rtu(rx: Vec<Option<Vec<u8>>>) -> (Rtu, Arc<Mutex<Vec<Event>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let rtu = Rtu::with_serial(Box::new(MockSerial {rx, events: events.clone()}), 115200).unwrap();
+        (rtu, events)
+    }
+
+    #[test]
+    fn test_crc_retry() {
+        let rsp_frame = Frame::new(0x0A, &[0x03, 0x02, 0x12, 0x34]).encode().unwrap();
+        let mut corrupted = rsp_frame.clone();
+        corrupted[3] ^= 0x01;
+        let (mut rtu, events) = create_rtu(vec![Some(corrupted), None, Some(rsp_frame)]);
+        rtu.set_crc_retries(1);
+
+        let rsp = rtu.write_req_read_rsp(&0x0A, &crate::ReadHldRegRequest::new(0x0010, 1)).unwrap().unwrap();
+        assert_eq!(rsp.get_registers(), &[0x1234]);
+
+        let req_frame = Frame::new(0x0A, &[0x03, 0x00, 0x10, 0x00, 0x01]).encode().unwrap();
+        assert_eq!(*events.lock().unwrap(), vec![Event::Write(req_frame.clone()), Event::Write(req_frame)]);
+        assert_eq!(rtu.get_counters().get_bus_comm_error_count(), 1);
+    }
+
+    #[test]
+    fn test_crc_retries_exhausted() {
+        let mut corrupted = Frame::new(0x0A, &[0x03, 0x02, 0x12, 0x34]).encode().unwrap();
+        corrupted[3] ^= 0x01;
+        let (mut rtu, events) = create_rtu(vec![Some(corrupted.clone()), None, Some(corrupted)]);
+        rtu.set_crc_retries(1);
+
+        let mut stream = rtu.write_req_pdu(&0x0A, &[0x03, 0x00, 0x10, 0x00, 0x01]).unwrap();
+        let err = rtu.read_rsp_pdu(&mut stream, &0x0A).err().unwrap();
+        match err {
+            Error::InvalidData => {}
+            _ => panic!("Expected InvalidData, but got {:?}", err),
+        }
+        assert_eq!(events.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_rts_control() {
+        let (rtu, events) = create_rtu(Vec::new());
+        let mut rtu = rtu.with_rts_control(Duration::from_micros(10), Duration::from_micros(10));
+
+        rtu.write_req_pdu(&0x0A, &[0x07]).unwrap();
+        let frame = Frame::new(0x0A, &[0x07]).encode().unwrap();
+        assert_eq!(*events.lock().unwrap(), vec![Event::Rts(true), Event::Write(frame), Event::Rts(false)]);
+    }
+
+    #[test]
+    fn test_accept_req_frame() {
+        let frame_data = [0x02, 0x07, 0x41, 0x12];
+
+        assert_eq!(Rtu::accept_req_frame(&frame_data, &[1, 2]), Some((2, vec![0x07])));
+        assert_eq!(Rtu::accept_req_frame(&frame_data, &[1, 3]), None);
+    }
+
+    #[test]
+    fn test_accept_req_frame_corrupted() {
+        assert_eq!(Rtu::accept_req_frame(&[0x02, 0x07, 0x41, 0x00], &[2]), None);
+        assert_eq!(Rtu::accept_req_frame(&[0x02, 0x07], &[2]), None);
+    }
+}
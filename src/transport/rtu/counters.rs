@@ -0,0 +1,116 @@
+//! Diagnostic counters of a Modbus serial line
+
+use crate::pdu::EXC_FUNCTION_CODE_FLAG;
+
+const EXC_SERVER_DEVICE_BUSY: u8 = 0x06;
+const EXC_NEGATIVE_ACKNOWLEDGE: u8 = 0x07;
+
+/// Diagnostic counters defined by the Modbus over serial line specification
+///
+/// Counters are 16-bit and wrap around on overflow, as they are reported in responses
+/// to the Diagnostics (0x08) function. They count since the transport was created or the
+/// counters were reset.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Counters {
+    bus_message: u16,
+    bus_comm_error: u16,
+    bus_char_overrun: u16,
+    slave_message: u16,
+    slave_exception_error: u16,
+    slave_nak: u16,
+    slave_busy: u16,
+}
+
+impl Counters {
+    /// Get number of frames with a valid CRC detected on the bus
+    pub fn get_bus_message_count(&self) -> u16 {
+        self.bus_message
+    }
+
+    /// Get number of frames with an invalid CRC or too short to be a frame
+    pub fn get_bus_comm_error_count(&self) -> u16 {
+        self.bus_comm_error
+    }
+
+    /// Get number of frames too long to be received
+    pub fn get_bus_char_overrun_count(&self) -> u16 {
+        self.bus_char_overrun
+    }
+
+    /// Get number of requests addressed to this slave
+    pub fn get_slave_message_count(&self) -> u16 {
+        self.slave_message
+    }
+
+    /// Get number of exception responses written by this slave
+    pub fn get_slave_exception_error_count(&self) -> u16 {
+        self.slave_exception_error
+    }
+
+    /// Get number of Negative Acknowledge exception responses written by this slave
+    pub fn get_slave_nak_count(&self) -> u16 {
+        self.slave_nak
+    }
+
+    /// Get number of Server Device Busy exception responses written by this slave
+    pub fn get_slave_busy_count(&self) -> u16 {
+        self.slave_busy
+    }
+
+    pub(crate) fn count_frame(&mut self, valid: bool) {
+        if valid {
+            self.bus_message = self.bus_message.wrapping_add(1);
+        } else {
+            self.bus_comm_error = self.bus_comm_error.wrapping_add(1);
+        }
+    }
+
+    pub(crate) fn count_char_overrun(&mut self) {
+        self.bus_char_overrun = self.bus_char_overrun.wrapping_add(1);
+    }
+
+    pub(crate) fn count_slave_message(&mut self) {
+        self.slave_message = self.slave_message.wrapping_add(1);
+    }
+
+    pub(crate) fn count_slave_response(&mut self, rsp_pdu: &[u8]) {
+        if rsp_pdu.first().is_none_or(|function_code| function_code & EXC_FUNCTION_CODE_FLAG == 0) {
+            return;
+        }
+
+        self.slave_exception_error = self.slave_exception_error.wrapping_add(1);
+        match rsp_pdu.get(1) {
+            Some(&EXC_NEGATIVE_ACKNOWLEDGE) => self.slave_nak = self.slave_nak.wrapping_add(1),
+            Some(&EXC_SERVER_DEVICE_BUSY) => self.slave_busy = self.slave_busy.wrapping_add(1),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_slave_response() {
+        let mut counters = Counters::default();
+        counters.count_slave_response(&[0x03, 0x02, 0x00, 0x01]);
+        counters.count_slave_response(&[0x83, 0x02]);
+        counters.count_slave_response(&[0x83, 0x06]);
+        counters.count_slave_response(&[0x90, 0x07]);
+
+        assert_eq!(counters.get_slave_exception_error_count(), 3);
+        assert_eq!(counters.get_slave_busy_count(), 1);
+        assert_eq!(counters.get_slave_nak_count(), 1);
+    }
+
+    #[test]
+    fn test_wrap_around() {
+        let mut counters = Counters {bus_message: u16::MAX, ..Default::default()};
+        counters.count_frame(true);
+        counters.count_frame(false);
+
+        assert_eq!(counters.get_bus_message_count(), 0);
+        assert_eq!(counters.get_bus_comm_error_count(), 1);
+    }
+}
@@ -0,0 +1,364 @@
+//! Modbus RTU over embedded-hal serial interface
+//!
+//! This module is available with the `embedded` feature. It runs the RTU framing and
+//! timing on top of the non-blocking `embedded-hal` serial traits, so that Modbus
+//! slaves and masters can be implemented in microcontroller firmware.
+
+use crate::error::Error;
+use alloc::vec::Vec;
+use core::time::Duration;
+use embedded_hal_nb::nb;
+use embedded_hal_nb::serial::{Error as _, Read, Write};
+use super::frame::{Frame, MAX_FRAME_LEN};
+use super::timing::char_timeouts;
+use super::super::Transport;
+
+const BROADCAST_DST: u8 = 0;
+const RSP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Source of monotonic time used to measure the RTU character intervals
+pub trait Clock {
+    /// Get time elapsed since an arbitrary fixed point in the past
+    fn now(&self) -> Duration;
+}
+
+/// Detector of RTU frame boundaries based on the silent interval between frames
+///
+/// Data longer than the longest frame is discarded until the line is silent, so that noise
+/// or a talker without gaps cannot grow the buffer.
+struct Framer {
+    inter_frame_timeout: Duration,
+    data: Vec<u8>,
+    overflow: bool,
+    last_rx: Duration,
+}
+
+impl Framer {
+    fn new(inter_frame_timeout: Duration) -> Self {
+        Self {inter_frame_timeout, data: Vec::new(), overflow: false, last_rx: Duration::from_secs(0)}
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty() && !self.overflow
+    }
+
+    fn push(&mut self, byte: u8, now: Duration) {
+        if self.data.len() >= MAX_FRAME_LEN {
+            self.overflow = true;
+            self.data.clear();
+        }
+        if !self.overflow {
+            self.data.push(byte);
+        }
+        self.last_rx = now;
+    }
+
+    fn poll(&mut self, now: Duration) -> Option<Result<Vec<u8>, Error>> {
+        if self.is_empty() || now.saturating_sub(self.last_rx) < self.inter_frame_timeout {
+            return None;
+        }
+
+        if self.overflow {
+            self.overflow = false;
+            Some(Err(Error::FrameTooLong))
+        } else {
+            Some(Ok(core::mem::take(&mut self.data)))
+        }
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.overflow = false;
+    }
+}
+
+#[derive(PartialEq)]
+enum Role {
+    Master,
+    Slave(Vec<u8>),
+}
+
+/// RTU transport for Modbus commands over an embedded-hal serial interface
+///
+/// This structure implements [Transport trait](Transport) that provides
+/// functions needed to read and write Modbus functions using this transport.
+/// Frames are delimited with the 3.5 character interval measured with given [Clock].
+pub struct EmbeddedRtu<S, C> {
+    serial: S,
+    clock: C,
+    role: Role,
+
+    rsp_timeout: Duration,
+    framer: Framer,
+    last_activity: Duration,
+}
+
+impl<S: Read + Write, C: Clock> EmbeddedRtu<S, C> {
+    /// Create a new RTU transport using given serial interface configured to given baud rate
+    pub fn new(serial: S, clock: C, baud_rate: u32) -> Self {
+        let (_, t3_5) = char_timeouts(baud_rate);
+        let last_activity = clock.now();
+
+        Self {serial, clock, role: Role::Master, rsp_timeout: RSP_TIMEOUT, framer: Framer::new(t3_5), last_activity}
+    }
+
+    /// Set how long the master waits for the first byte of a response, 1 second by default
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.rsp_timeout = timeout;
+    }
+
+    /// Release the serial interface and the clock
+    pub fn release(self) -> (S, C) {
+        (self.serial, self.clock)
+    }
+
+    fn wait_before_write(&self) {
+        while self.clock.now().saturating_sub(self.last_activity) < self.framer.inter_frame_timeout {}
+    }
+
+    fn write_pdu(&mut self, unit_id: u8, pdu: &[u8]) -> Result<(), Error> {
+        self.wait_before_write();
+
+        let frame = Frame::new(unit_id, pdu);
+        for byte in frame.encode()? {
+            nb::block!(self.serial.write(byte)).map_err(|err| Error::SerialHalError(err.kind()))?;
+        }
+        nb::block!(self.serial.flush()).map_err(|err| Error::SerialHalError(err.kind()))?;
+
+        self.last_activity = self.clock.now();
+        Ok(())
+    }
+
+    fn read_frame(&mut self, rsp_timeout: Option<Duration>) -> Result<Vec<u8>, Error> {
+        let start = self.clock.now();
+
+        loop {
+            match self.serial.read() {
+                Ok(byte) => {
+                    self.last_activity = self.clock.now();
+                    self.framer.push(byte, self.last_activity);
+                }
+                Err(nb::Error::WouldBlock) => {
+                    let now = self.clock.now();
+
+                    if let Some(frame_data) = self.framer.poll(now) {
+                        return frame_data;
+                    }
+                    if let Some(timeout) = rsp_timeout {
+                        if self.framer.is_empty() && now.saturating_sub(start) >= timeout {
+                            return Err(Error::NoResponse);
+                        }
+                    }
+                }
+                Err(nb::Error::Other(err)) => {
+                    self.framer.clear();
+                    return Err(Error::SerialHalError(err.kind()));
+                }
+            }
+        }
+    }
+}
+
+impl<S: Read + Write, C: Clock> Transport for EmbeddedRtu<S, C> {
+    type Dst = u8;
+    type Stream = u8;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        self.role = Role::Master;
+        Ok(())
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.start_slave_units(&[unit_id])
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        if unit_ids.is_empty() || unit_ids.iter().any(|unit_id| !(1..=247).contains(unit_id)) {
+            return Err(Error::InvalidValue);
+        }
+
+        self.role = Role::Slave(unit_ids.to_vec());
+        Ok(())
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        *dst == BROADCAST_DST
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        *stream
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        self.write_pdu(*dst, pdu)?;
+        Ok(*dst)
+    }
+
+    fn read_rsp_pdu(&mut self, _: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        let frame_data = self.read_frame(Some(self.rsp_timeout))?;
+        let frame = Frame::decode_complete(&frame_data)?;
+
+        if frame.is_address(*src) {
+            Ok(frame.get_pdu())
+        } else {
+            Err(Error::InvalidData)
+        }
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        if let Role::Slave(unit_ids) = &self.role {
+            let unit_ids = unit_ids.clone();
+
+            loop {
+                let frame_data = match self.read_frame(None) {
+                    Err(Error::FrameTooLong) => continue,
+                    result => result?,
+                };
+
+                if let Ok(frame) = Frame::decode_complete(&frame_data) {
+                    if let Some(unit_id) = unit_ids.iter().find(|unit_id| frame.is_address(**unit_id)) {
+                        return Ok((frame.get_pdu(), *unit_id));
+                    }
+                }
+            }
+        } else {
+            Err(Error::InvalidValue)
+        }
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        if let Role::Slave(_) = self.role {
+            self.write_pdu(*stream, pdu)
+        } else {
+            Err(Error::InvalidValue)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal_nb::serial::{ErrorKind, ErrorType};
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+
+    struct MockSerial {
+        rx: VecDeque<Option<u8>>,
+        tx: Vec<u8>,
+    }
+
+    impl ErrorType for MockSerial {
+        type Error = ErrorKind;
+    }
+
+    impl Read for MockSerial {
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            self.rx.pop_front().flatten().ok_or(nb::Error::WouldBlock)
+        }
+    }
+
+    impl Write for MockSerial {
+        fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            self.tx.push(word);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    struct MockClock {
+        now: Cell<Duration>,
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Duration {
+            let now = self.now.get();
+            self.now.set(now + Duration::from_micros(100));
+            now
+        }
+    }
+
+    fn create_rtu(rx: &[u8]) -> EmbeddedRtu<MockSerial, MockClock> {
+        let serial = MockSerial {rx: rx.iter().map(|byte| Some(*byte)).collect(), tx: Vec::new()};
+        let clock = MockClock {now: Cell::new(Duration::from_secs(0))};
+
+        EmbeddedRtu::new(serial, clock, 115200)
+    }
+
+    #[test]
+    fn test_framer() {
+        let mut framer = Framer::new(Duration::from_millis(2));
+        framer.push(0x02, Duration::from_millis(10));
+        framer.push(0x07, Duration::from_millis(11));
+
+        assert!(framer.poll(Duration::from_millis(12)).is_none());
+        assert_eq!(framer.poll(Duration::from_millis(13)).unwrap().unwrap(), vec![0x02, 0x07]);
+        assert!(framer.is_empty());
+    }
+
+    #[test]
+    fn test_framer_overflow() {
+        let mut framer = Framer::new(Duration::from_millis(2));
+        for _ in 0..MAX_FRAME_LEN + 100 {
+            framer.push(0x55, Duration::from_millis(10));
+        }
+        assert!(framer.data.len() <= MAX_FRAME_LEN);
+
+        match framer.poll(Duration::from_millis(12)).unwrap() {
+            Err(Error::FrameTooLong) => {}
+            result => panic!("Expected FrameTooLong, but got {:?}", result),
+        }
+        assert!(framer.is_empty());
+    }
+
+    #[test]
+    fn test_slave_discards_too_long_data() {
+        let mut mb = create_rtu(&[0x55; MAX_FRAME_LEN + 100]);
+        mb.serial.rx.extend([None; 20].iter());
+        mb.serial.rx.extend([0x02, 0x07, 0x41, 0x12].iter().map(|byte| Some(*byte)));
+        mb.start_slave(2).unwrap();
+
+        let (req_pdu, _) = mb.read_req_pdu().unwrap();
+        assert_eq!(req_pdu, vec![0x07]);
+    }
+
+    #[test]
+    fn test_master() {
+        let mut mb = create_rtu(&[0x02, 0x07, 0x41, 0x12]);
+
+        let mut stream = mb.write_req_pdu(&2, &[0x07]).unwrap();
+        let rsp_pdu = mb.read_rsp_pdu(&mut stream, &2).unwrap();
+
+        assert_eq!(rsp_pdu, vec![0x07]);
+        assert_eq!(mb.release().0.tx, vec![0x02, 0x07, 0x41, 0x12]);
+    }
+
+    #[test]
+    fn test_master_no_response() {
+        let mut mb = create_rtu(&[]);
+        mb.set_response_timeout(Duration::from_millis(10));
+
+        let mut stream = mb.write_req_pdu(&2, &[0x07]).unwrap();
+        let err = mb.read_rsp_pdu(&mut stream, &2).err().unwrap();
+        match err {
+            Error::NoResponse => {}
+            _ => panic!("Expected NoResponse, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_slave_discards_corrupted_frame() {
+        let mut mb = create_rtu(&[0x02, 0x07, 0x41, 0x00]);
+        mb.serial.rx.extend([None; 20].iter());
+        mb.serial.rx.extend([0x02, 0x07, 0x41, 0x12].iter().map(|byte| Some(*byte)));
+        mb.start_slave(2).unwrap();
+
+        let (req_pdu, mut stream) = mb.read_req_pdu().unwrap();
+        assert_eq!(req_pdu, vec![0x07]);
+
+        mb.write_rsp_pdu(&mut stream, &[0x07]).unwrap();
+        assert_eq!(mb.release().0.tx, vec![0x02, 0x07, 0x41, 0x12]);
+    }
+}
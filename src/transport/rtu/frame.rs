@@ -1,9 +1,15 @@
-use std::convert::TryInto;
-use std::vec::Vec;
+use core::convert::TryInto;
+use alloc::vec::Vec;
 
 use crc16;
 
 use crate::error::Error;
+use crate::pdu::check_size;
+
+/// Length of the shortest frame: address, function code and CRC
+pub const MIN_FRAME_LEN: usize = 4;
+/// Length of the longest frame: address, the longest PDU and CRC
+pub const MAX_FRAME_LEN: usize = 256;
 
 pub struct Frame<'a> {
     address: u8,
@@ -36,9 +42,10 @@ impl<'a> Frame<'a> {
 
     pub fn decode(data: &'a [u8]) -> Result<Self, Error> {
         let len = data.len();
-        if len < 4 {
+        if len < MIN_FRAME_LEN {
             return Err(Error::InvalidDataLength);
         }
+        check_size(len - 3)?;
 
         let expected_crc = crc16::State::<crc16::MODBUS>::calculate(&data[0..len-2]);
         let crc = u16::from_le_bytes(data[len-2..len].try_into().unwrap());
@@ -49,6 +56,22 @@ impl<'a> Frame<'a> {
 
         Ok(Self{address: data[0], pdu: &data[1..len-2]})
     }
+
+    /// Decode data known to make a whole frame, reporting a CRC mismatch
+    ///
+    /// [Frame::decode] is also used to probe incomplete data for the end of a frame,
+    /// so it does not report mismatches itself.
+    #[cfg(any(feature = "serial", feature = "embedded"))]
+    pub fn decode_complete(data: &'a [u8]) -> Result<Self, Error> {
+        let frame = Self::decode(data);
+        if let Err(Error::InvalidData) = frame {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(len = data.len(), "RTU frame CRC mismatch");
+            #[cfg(feature = "metrics")]
+            crate::telemetry::record_crc_error();
+        }
+        frame
+    }
 }
 
 #[cfg(test)]
@@ -71,6 +94,19 @@ mod tests {
         assert_eq!(frame.pdu, &frame_data[1..=1]);
     }
 
+    #[test]
+    fn test_decode_too_long() {
+        let mut frame_data = vec![0x02; 257];
+        let crc = crc16::State::<crc16::MODBUS>::calculate(&frame_data[..255]);
+        frame_data[255..].copy_from_slice(&crc.to_le_bytes());
+
+        let err = Frame::decode(&frame_data).err().unwrap();
+        match err {
+            Error::InvalidDataLength => {}
+            _ => panic!("Expected InvalidDataLength, but got {:?}", err),
+        }
+    }
+
     #[test]
     fn test_decode_invalid_crc() {
         let frame_data = [0x02, 0x07, 0x41, 0x00];
@@ -78,7 +114,7 @@ mod tests {
 
         match err {
             Error::InvalidData => {}
-            _ => panic!(format!("Expected InvalidData, but got {:?}", err)),
+            _ => panic!("Expected InvalidData, but got {:?}", err),
         }
     }
 }
\ No newline at end of file
@@ -1,2 +1,16 @@
+#[cfg(feature = "serial")]
+pub mod bus;
+#[cfg(feature = "serial")]
 pub mod conn;
-mod frame;
\ No newline at end of file
+#[cfg(feature = "serial")]
+mod counters;
+#[cfg(feature = "embedded")]
+pub mod embedded;
+#[cfg(feature = "serial")]
+pub mod monitor;
+#[cfg(feature = "tcp")]
+pub mod tcp_conn;
+#[cfg(any(feature = "serial", feature = "tcp", feature = "embedded"))]
+pub(crate) mod frame;
+#[cfg(any(feature = "serial", feature = "embedded"))]
+mod timing;
@@ -0,0 +1,278 @@
+//! Passive monitor of Modbus RTU traffic
+//!
+//! The monitor never transmits. It reconstructs frames exchanged on a shared serial bus
+//! and pairs requests with responses, which helps troubleshooting installations of
+//! third-party devices.
+
+use crate::error::Error;
+use crate::fmt::Pdu;
+use crate::pdu::EXC_FUNCTION_CODE_FLAG;
+use serialport::{SerialPort, SerialPortSettings, open_with_settings};
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::Read;
+use std::time::Duration;
+use super::conn::{ceil_millis, Rtu, BROADCAST_DST, RSP_TIMEOUT};
+use super::frame::Frame;
+use super::timing::char_timeouts;
+
+/// Request observed on the bus together with its response
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Transaction {
+    unit_id: u8,
+    req_pdu: Vec<u8>,
+    rsp_pdu: Option<Vec<u8>>,
+}
+
+impl Transaction {
+    /// Get unit id the request was addressed to
+    pub fn get_unit_id(&self) -> u8 {
+        self.unit_id
+    }
+
+    /// Get function code of the request
+    pub fn get_function_code(&self) -> u8 {
+        self.req_pdu[0]
+    }
+
+    /// Get PDU of the request
+    pub fn get_req_pdu(&self) -> &[u8] {
+        &self.req_pdu
+    }
+
+    /// Get PDU of the response, `None` for broadcasts and requests left without response
+    pub fn get_rsp_pdu(&self) -> Option<&[u8]> {
+        self.rsp_pdu.as_deref()
+    }
+
+    /// Check if the slave replied with an exception response
+    pub fn is_exception(&self) -> bool {
+        matches!(&self.rsp_pdu, Some(rsp_pdu) if rsp_pdu[0] & EXC_FUNCTION_CODE_FLAG != 0)
+    }
+
+    fn is_rsp(&self, unit_id: u8, pdu: &[u8]) -> bool {
+        self.unit_id != BROADCAST_DST && unit_id == self.unit_id && (pdu[0] & !EXC_FUNCTION_CODE_FLAG) == self.get_function_code()
+    }
+}
+
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unit {}: {} -> ", self.unit_id, Pdu::new(&self.req_pdu))?;
+        match &self.rsp_pdu {
+            Some(rsp_pdu) => write!(f, "{}", Pdu::new(rsp_pdu)),
+            None => f.write_str("no response"),
+        }
+    }
+}
+
+/// Traffic observed on the bus
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Traffic {
+    /// Request with its response, if any
+    Transaction(Transaction),
+    /// Data that does not form a valid RTU frame
+    Corrupted(Vec<u8>),
+}
+
+/// Passive monitor of an RTU bus
+///
+/// A frame following a request is treated as its response if it comes from the addressed
+/// unit with the same function code within the response timeout. Otherwise it is treated
+/// as the next request.
+///
+/// # Examples
+/// ```no_run
+/// use serialport::SerialPortSettings;
+///
+/// let s = SerialPortSettings::default();
+/// let monitor = modbus::rtu_monitor::Monitor::conn("/dev/ttyUSB0", &s).unwrap();
+/// for traffic in monitor {
+///     println!("{:?}", traffic.unwrap());
+/// }
+/// ```
+pub struct Monitor<R: Read = Box<dyn SerialPort>> {
+    reader: R,
+    rsp_timeout: Duration,
+    pending_req: Option<Transaction>,
+}
+
+impl Monitor {
+    /// Open serial port to monitor
+    ///
+    /// The timeout in `settings` is ignored. Frames are delimited with the 3.5 character
+    /// interval derived from the baud rate, as in the [RTU transport](super::conn::Rtu::conn).
+    pub fn conn<T: AsRef<OsStr> + ?Sized>(port: &T, settings: &SerialPortSettings) -> Result<Self, Error> {
+        let (_, t3_5) = char_timeouts(settings.baud_rate);
+        let mut serial = open_with_settings(port, settings)?;
+        serial.set_timeout(ceil_millis(t3_5))?;
+
+        Ok(Self::new(serial))
+    }
+}
+
+impl<R: Read> Monitor<R> {
+    /// Create a monitor of frames read from given reader
+    ///
+    /// The reader shall fail with [TimedOut](std::io::ErrorKind::TimedOut) error after
+    /// the 3.5 character silent interval, which ends a frame.
+    pub fn new(reader: R) -> Self {
+        Self {reader, rsp_timeout: RSP_TIMEOUT, pending_req: None}
+    }
+
+    /// Set how long the monitor waits for a response to an observed request, 1 second by default
+    pub fn set_response_timeout(&mut self, timeout: Duration) {
+        self.rsp_timeout = timeout;
+    }
+
+    /// Wait for the next transaction or corrupted frame observed on the bus
+    pub fn read(&mut self) -> Result<Traffic, Error> {
+        loop {
+            let rsp_timeout = self.pending_req.as_ref().map(|_| self.rsp_timeout);
+            let frame_data = match Rtu::read_frame(&mut self.reader, rsp_timeout, None) {
+                Ok(frame_data) => frame_data,
+                Err(Error::NoResponse) => return Ok(Traffic::Transaction(self.pending_req.take().unwrap())),
+                Err(err) => return Err(err),
+            };
+
+            let pdu = match Frame::decode_complete(&frame_data) {
+                Ok(frame) if !frame.get_pdu().is_empty() => frame.get_pdu(),
+                _ => return Ok(Traffic::Corrupted(frame_data)),
+            };
+            let unit_id = frame_data[0];
+
+            match self.pending_req.take() {
+                Some(mut req) if req.is_rsp(unit_id, &pdu) => {
+                    req.rsp_pdu = Some(pdu);
+                    return Ok(Traffic::Transaction(req));
+                }
+                pending_req => {
+                    let req = Transaction {unit_id, req_pdu: pdu, rsp_pdu: None};
+
+                    match pending_req {
+                        Some(pending_req) => {
+                            self.pending_req = Some(req);
+                            return Ok(Traffic::Transaction(pending_req));
+                        }
+                        None if unit_id == BROADCAST_DST => return Ok(Traffic::Transaction(req)),
+                        None => self.pending_req = Some(req),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Monitor<R> {
+    type Item = Result<Traffic, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.read())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    struct BusReader {
+        frames: Vec<Vec<u8>>,
+    }
+
+    impl Read for BusReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.frames.first_mut() {
+                Some(frame) if !frame.is_empty() => {
+                    let len = frame.len();
+                    buf[..len].copy_from_slice(frame);
+                    frame.clear();
+                    Ok(len)
+                }
+                Some(_) => {
+                    self.frames.remove(0);
+                    Err(ErrorKind::TimedOut.into())
+                }
+                None => Err(ErrorKind::TimedOut.into()),
+            }
+        }
+    }
+
+    fn create_monitor(frames: &[&[u8]]) -> Monitor<BusReader> {
+        let mut monitor = Monitor::new(BusReader {frames: frames.iter().map(|frame| frame.to_vec()).collect()});
+        monitor.set_response_timeout(Duration::from_millis(0));
+        monitor
+    }
+
+    fn encode(address: u8, pdu: &[u8]) -> Vec<u8> {
+        Frame::new(address, pdu).encode().unwrap()
+    }
+
+    #[test]
+    fn test_transaction() {
+        let req = encode(0x02, &[0x03, 0x00, 0x04, 0x00, 0x01]);
+        let rsp = encode(0x02, &[0x03, 0x02, 0x12, 0x34]);
+        let mut monitor = create_monitor(&[&req, &rsp]);
+
+        match monitor.read().unwrap() {
+            Traffic::Transaction(transaction) => {
+                assert_eq!(transaction.get_unit_id(), 0x02);
+                assert_eq!(transaction.get_function_code(), 0x03);
+                assert_eq!(transaction.get_req_pdu(), &[0x03, 0x00, 0x04, 0x00, 0x01]);
+                assert_eq!(transaction.get_rsp_pdu(), Some(&[0x03, 0x02, 0x12, 0x34][..]));
+                assert!(!transaction.is_exception());
+            }
+            traffic => panic!("Expected Transaction, but got {:?}", traffic),
+        }
+    }
+
+    #[test]
+    fn test_exception_response() {
+        let req = encode(0x02, &[0x03, 0x00, 0x04, 0x00, 0x01]);
+        let rsp = encode(0x02, &[0x83, 0x02]);
+        let mut monitor = create_monitor(&[&req, &rsp]);
+
+        match monitor.read().unwrap() {
+            Traffic::Transaction(transaction) => assert!(transaction.is_exception()),
+            traffic => panic!("Expected Transaction, but got {:?}", traffic),
+        }
+    }
+
+    #[test]
+    fn test_unanswered_requests() {
+        let req_a = encode(0x02, &[0x03, 0x00, 0x04, 0x00, 0x01]);
+        let req_b = encode(0x00, &[0x06, 0x00, 0x01, 0x00, 0x02]);
+        let mut monitor = create_monitor(&[&req_a, &req_b]);
+
+        let expected_a = Transaction {unit_id: 0x02, req_pdu: vec![0x03, 0x00, 0x04, 0x00, 0x01], rsp_pdu: None};
+        let expected_b = Transaction {unit_id: 0x00, req_pdu: vec![0x06, 0x00, 0x01, 0x00, 0x02], rsp_pdu: None};
+        assert_eq!(monitor.next().unwrap().unwrap(), Traffic::Transaction(expected_a));
+        assert_eq!(monitor.next().unwrap().unwrap(), Traffic::Transaction(expected_b));
+    }
+
+    #[test]
+    fn test_display_transaction() {
+        let transaction = Transaction {unit_id: 0x02, req_pdu: vec![0x03, 0x00, 0x04, 0x00, 0x01], rsp_pdu: Some(vec![0x83, 0x02])};
+        assert_eq!(transaction.to_string(),
+                   "unit 2: Read Holding Registers: 03 00 04 00 01 -> Read Holding Registers exception: 83 02");
+
+        let transaction = Transaction {unit_id: 0x00, req_pdu: vec![0x06, 0x00, 0x01, 0x00, 0x02], rsp_pdu: None};
+        assert_eq!(transaction.to_string(), "unit 0: Write Single Register: 06 00 01 00 02 -> no response");
+    }
+
+    #[test]
+    fn test_reader_at_eof() {
+        let mut monitor = Monitor::new(std::io::empty());
+
+        match monitor.read().err().unwrap() {
+            Error::IoError(err) if err.kind() == ErrorKind::UnexpectedEof => {}
+            err => panic!("Expected UnexpectedEof, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_corrupted_frame() {
+        let mut monitor = create_monitor(&[&[0x02, 0x07, 0x41, 0x00]]);
+
+        assert_eq!(monitor.read().unwrap(), Traffic::Corrupted(vec![0x02, 0x07, 0x41, 0x00]));
+    }
+}
@@ -0,0 +1,253 @@
+//! Modbus RTU tunneled over TCP/IP
+//!
+//! Serial-to-Ethernet converters usually forward raw RTU frames (address, PDU and CRC)
+//! through a plain TCP connection. This transport exchanges such frames instead of the
+//! Modbus TCP/IP ones.
+
+use crate::error::Error;
+use std::io::{prelude::*, ErrorKind};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use super::frame::{Frame, MAX_FRAME_LEN, MIN_FRAME_LEN};
+use super::super::Transport;
+
+const BROADCAST_DST: u8 = 0;
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(PartialEq)]
+enum Role {
+    Master,
+    Slave(Vec<u8>),
+}
+
+/// RTU over TCP/IP transport for Modbus commands
+///
+/// This structure implements [Transport trait](Transport) that provides
+/// functions needed to read and write Modbus functions using this transport.
+///
+/// As RTU frames carry no length, the end of a frame is detected by a valid CRC.
+pub struct RtuOverTcp {
+    socket: TcpStream,
+    role: Role,
+    rx: Vec<u8>,
+}
+
+impl RtuOverTcp {
+    /// Connect to a serial-to-Ethernet converter at given address
+    ///
+    /// # Examples
+    /// ```no_run
+    /// let modbus = modbus::rtu_over_tcp::RtuOverTcp::connect("192.168.0.10:4001").unwrap();
+    /// ```
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, Error> {
+        Self::new(TcpStream::connect(addr)?)
+    }
+
+    /// Create RTU over TCP/IP transport using an already established connection
+    pub fn new(socket: TcpStream) -> Result<Self, Error> {
+        socket.set_read_timeout(Some(READ_TIMEOUT))?;
+        socket.set_nodelay(true)?;
+
+        Ok(Self {socket, role: Role::Master, rx: Vec::new()})
+    }
+
+    fn write_pdu(&mut self, unit_id: u8, pdu: &[u8]) -> Result<(), Error> {
+        let frame = Frame::new(unit_id, pdu);
+        self.socket.write_all(&frame.encode()?)?;
+        Ok(())
+    }
+
+    /// Read a frame, keeping data received after it in given buffer for the next frame
+    ///
+    /// Data not making a valid frame within the longest frame length is discarded.
+    fn read_frame<S: Read>(stream: &mut S, rx: &mut Vec<u8>, infinitely: bool) -> Result<Vec<u8>, Error> {
+        let mut buf = [0; MAX_FRAME_LEN];
+        let mut checked_len = MIN_FRAME_LEN - 1;
+
+        loop {
+            if let Some(len) = (checked_len + 1..=rx.len()).find(|len| Frame::decode(&rx[..*len]).is_ok()) {
+                return Ok(rx.drain(..len).collect());
+            }
+            checked_len = checked_len.max(rx.len());
+            if rx.len() >= MAX_FRAME_LEN {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(len = rx.len(), "Discarding RTU data without a valid CRC");
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_crc_error();
+                rx.clear();
+                return Err(Error::FrameTooLong);
+            }
+
+            match stream.read(&mut buf[..MAX_FRAME_LEN - rx.len()]) {
+                Ok(0) => return Err(Error::InvalidDataLength),
+                Ok(num_bytes) => rx.extend_from_slice(&buf[..num_bytes]),
+                Err(err) => {
+                    match err.kind() {
+                        ErrorKind::TimedOut | ErrorKind::WouldBlock if infinitely && rx.is_empty() => continue,
+                        ErrorKind::TimedOut | ErrorKind::WouldBlock => {
+                            if !rx.is_empty() {
+                                #[cfg(feature = "tracing")]
+                                tracing::warn!(len = rx.len(), "Discarding RTU data without a valid CRC");
+                                #[cfg(feature = "metrics")]
+                                crate::telemetry::record_crc_error();
+                            }
+                            rx.clear();
+                            return Err(Error::TooShortData);
+                        }
+                        _ => return Err(err.into()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Transport for RtuOverTcp {
+    type Dst = u8;
+    type Stream = u8;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        self.role = Role::Master;
+        Ok(())
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.start_slave_units(&[unit_id])
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        if unit_ids.is_empty() || unit_ids.iter().any(|unit_id| !(1..=247).contains(unit_id)) {
+            return Err(Error::InvalidValue);
+        }
+
+        self.role = Role::Slave(unit_ids.to_vec());
+        Ok(())
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        *dst == BROADCAST_DST
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        *stream
+    }
+
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        self.write_pdu(*dst, pdu)?;
+        Ok(*dst)
+    }
+
+    fn read_rsp_pdu(&mut self, _: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        let frame_data = Self::read_frame(&mut self.socket, &mut self.rx, false)?;
+        let frame = Frame::decode(&frame_data)?;
+
+        if frame.is_address(*src) {
+            Ok(frame.get_pdu())
+        } else {
+            Err(Error::InvalidData)
+        }
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        if let Role::Slave(unit_ids) = &self.role {
+            let unit_ids = unit_ids.clone();
+
+            loop {
+                let frame_data = match Self::read_frame(&mut self.socket, &mut self.rx, true) {
+                    Ok(frame_data) => frame_data,
+                    Err(Error::TooShortData) | Err(Error::FrameTooLong) => continue,
+                    Err(err) => return Err(err),
+                };
+                let frame = Frame::decode(&frame_data)?;
+
+                if let Some(unit_id) = unit_ids.iter().find(|unit_id| frame.is_address(**unit_id)) {
+                    return Ok((frame.get_pdu(), *unit_id));
+                }
+            }
+        } else {
+            Err(Error::InvalidValue)
+        }
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        if let Role::Slave(_) = self.role {
+            self.write_pdu(*stream, pdu)
+        } else {
+            Err(Error::InvalidValue)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_read_frame() {
+        let data = [0x02, 0x07, 0x41, 0x12, 0xFF];
+        let mut rx = Vec::new();
+        let frame_data = RtuOverTcp::read_frame(&mut &data[..], &mut rx, false).unwrap();
+
+        assert_eq!(frame_data, vec![0x02, 0x07, 0x41, 0x12]);
+        assert_eq!(rx, vec![0xFF]);
+    }
+
+    #[test]
+    fn test_read_consecutive_frames() {
+        let data = [0x02, 0x07, 0x41, 0x12, 0x02, 0x07, 0x41, 0x12];
+        let mut reader = &data[..];
+        let mut rx = Vec::new();
+
+        assert_eq!(RtuOverTcp::read_frame(&mut reader, &mut rx, false).unwrap(), vec![0x02, 0x07, 0x41, 0x12]);
+        assert_eq!(RtuOverTcp::read_frame(&mut reader, &mut rx, false).unwrap(), vec![0x02, 0x07, 0x41, 0x12]);
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_read_frame_closed() {
+        let data = [0x02, 0x07, 0x41];
+        let err = RtuOverTcp::read_frame(&mut &data[..], &mut Vec::new(), false).err().unwrap();
+
+        match err {
+            Error::InvalidDataLength => {}
+            _ => panic!("Expected InvalidDataLength, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_read_frame_too_long() {
+        let data = [0x02; MAX_FRAME_LEN + 4];
+        let mut rx = Vec::new();
+        let err = RtuOverTcp::read_frame(&mut &data[..], &mut rx, false).err().unwrap();
+
+        match err {
+            Error::FrameTooLong => {}
+            _ => panic!("Expected FrameTooLong, but got {:?}", err),
+        }
+        assert!(rx.is_empty());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let slave = thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            let mut mb = RtuOverTcp::new(socket).unwrap();
+            mb.start_slave(2).unwrap();
+
+            let (req_pdu, mut stream) = mb.read_req_pdu().unwrap();
+            assert_eq!(req_pdu, vec![0x03, 0x00, 0x04, 0x00, 0x01]);
+            mb.write_rsp_pdu(&mut stream, &[0x03, 0x02, 0x12, 0x34]).unwrap();
+        });
+
+        let mut mb = RtuOverTcp::connect(addr).unwrap();
+        let rsp = mb.write_req_read_rsp(&2, &crate::ReadHldRegRequest::new(0x0004, 0x0001)).unwrap();
+        slave.join().unwrap();
+
+        assert_eq!(rsp.unwrap().get_registers(), &vec![0x1234]);
+    }
+}
@@ -0,0 +1,39 @@
+//! RTU character timing derived from the baud rate
+
+use core::time::Duration;
+
+const BITS_PER_CHAR: u64 = 11;
+const HIGH_BAUD_RATE: u32 = 19200;
+const HIGH_BAUD_T1_5: Duration = Duration::from_micros(750);
+const HIGH_BAUD_T3_5: Duration = Duration::from_micros(1750);
+
+/// Get the maximal inter-character gap (t1.5) and the minimal inter-frame gap (t3.5)
+///
+/// Above 19200 baud the fixed values of 750 µs and 1.75 ms are used.
+pub fn char_timeouts(baud_rate: u32) -> (Duration, Duration) {
+    if baud_rate > HIGH_BAUD_RATE || baud_rate == 0 {
+        return (HIGH_BAUD_T1_5, HIGH_BAUD_T3_5);
+    }
+
+    let char_time_ns = BITS_PER_CHAR * 1_000_000_000 / baud_rate as u64;
+    (Duration::from_nanos(char_time_ns * 3 / 2), Duration::from_nanos(char_time_ns * 7 / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_timeouts_low_baud_rate() {
+        let (t1_5, t3_5) = char_timeouts(9600);
+
+        assert_eq!(t1_5, Duration::from_nanos(1_718_749));
+        assert_eq!(t3_5, Duration::from_nanos(4_010_415));
+    }
+
+    #[test]
+    fn test_char_timeouts_high_baud_rate() {
+        assert_eq!(char_timeouts(19200), (Duration::from_nanos(859_374), Duration::from_nanos(2_005_206)));
+        assert_eq!(char_timeouts(115200), (HIGH_BAUD_T1_5, HIGH_BAUD_T3_5));
+    }
+}
@@ -0,0 +1,79 @@
+//! Scanning of unit ids responding on a bus
+//!
+//! See [Transport::scan](super::Transport::scan).
+
+use alloc::vec::Vec;
+use crate::pdu::EXC_FUNCTION_CODE_FLAG;
+
+/// Cheap probe request reading the holding register at address 0
+///
+/// Devices without this register still reveal themselves with an exception response.
+pub const PROBE_PDU: [u8; 5] = [0x03, 0x00, 0x00, 0x00, 0x01];
+
+/// Diagnostics Return Query Data request, answered with its echo
+///
+/// It does not access any data of the device, so it is the default [ping](crate::client::Client::ping) request.
+pub const ECHO_PDU: [u8; 5] = [0x08, 0x00, 0x00, 0x00, 0x00];
+
+/// Report of a bus scan
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScanReport {
+    responses: Vec<(u8, Vec<u8>)>,
+    silent_unit_ids: Vec<u8>,
+}
+
+impl ScanReport {
+    pub(super) fn add_response(&mut self, unit_id: u8, rsp_pdu: Vec<u8>) {
+        self.responses.push((unit_id, rsp_pdu));
+    }
+
+    pub(super) fn add_silent(&mut self, unit_id: u8) {
+        self.silent_unit_ids.push(unit_id);
+    }
+
+    /// Get unit ids and response PDUs of the units that responded to the probe
+    pub fn get_responses(&self) -> &[(u8, Vec<u8>)] {
+        &self.responses
+    }
+
+    /// Get unit ids that responded to the probe, including with an exception response
+    pub fn get_unit_ids(&self) -> Vec<u8> {
+        self.responses.iter().map(|(unit_id, _)| *unit_id).collect()
+    }
+
+    /// Get unit ids that did not respond to the probe or responded with invalid data
+    pub fn get_silent_unit_ids(&self) -> &[u8] {
+        &self.silent_unit_ids
+    }
+
+    /// Get unit ids that responded to the probe with an exception response
+    pub fn get_exception_unit_ids(&self) -> Vec<u8> {
+        self.responses.iter()
+            .filter(|(_, rsp_pdu)| rsp_pdu.first().is_some_and(|code| code & EXC_FUNCTION_CODE_FLAG != 0))
+            .map(|(unit_id, _)| *unit_id)
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use super::super::Transport;
+    use super::super::mock::MockTransport;
+
+    #[test]
+    fn test_scan() {
+        let mut mb = MockTransport::new();
+        mb.expect(&PROBE_PDU, &[0x03, 0x02, 0x12, 0x34]);
+        mb.expect_no_response(&PROBE_PDU);
+        mb.expect(&PROBE_PDU, &[0x83, 0x02]);
+
+        let report = mb.scan(0..=3, &PROBE_PDU, |unit_id| unit_id);
+
+        assert!(mb.is_complete());
+        assert_eq!(report.get_unit_ids(), vec![1, 3]);
+        assert_eq!(report.get_responses()[0], (1, vec![0x03, 0x02, 0x12, 0x34]));
+        assert_eq!(report.get_silent_unit_ids(), &[2]);
+        assert_eq!(report.get_exception_unit_ids(), vec![3]);
+    }
+}
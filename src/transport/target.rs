@@ -0,0 +1,79 @@
+#[cfg(feature = "tcp")]
+use super::tcp::conn as tcp;
+#[cfg(feature = "tcp")]
+use std::net::IpAddr;
+
+const BROADCAST_UNIT_ID: u8 = 0;
+
+/// Destination of Modbus requests independent of the link layer
+///
+/// A target carries the unit id of the addressed device and, for devices on a TCP/IP network,
+/// its network endpoint. It allows writing polling code that works with any transport
+/// selected at runtime, like [AnyTransport](crate::any::AnyTransport).
+///
+/// # Examples
+/// ```
+/// # #[cfg(feature = "tcp")] {
+/// use modbus::Target;
+/// use std::net::{IpAddr, Ipv4Addr};
+///
+/// let serial = Target::new(10);
+/// assert!(serial.get_endpoint().is_none());
+///
+/// let network = Target::tcp(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 10)), 10);
+/// assert_eq!(network.get_unit_id(), 10);
+/// assert!(network.get_endpoint().is_some());
+/// # }
+/// ```
+#[derive(Clone, PartialEq)]
+pub struct Target {
+    unit_id: u8,
+    #[cfg(feature = "tcp")]
+    endpoint: Option<tcp::Dst>,
+}
+
+impl Target {
+    /// Create a target addressed only with a unit id, e.g. a device on a serial line
+    pub fn new(unit_id: u8) -> Self {
+        Self {
+            unit_id,
+            #[cfg(feature = "tcp")]
+            endpoint: None,
+        }
+    }
+
+    /// Create a target of a device with given IP address on a TCP/IP network
+    #[cfg(feature = "tcp")]
+    pub fn tcp(ip_addr: IpAddr, unit_id: u8) -> Self {
+        tcp::Dst::new(ip_addr, unit_id).into()
+    }
+
+    /// Get unit id of the addressed device
+    pub fn get_unit_id(&self) -> u8 {
+        self.unit_id
+    }
+
+    /// Get TCP/IP destination of the addressed device, if it is on a TCP/IP network
+    #[cfg(feature = "tcp")]
+    pub fn get_endpoint(&self) -> Option<&tcp::Dst> {
+        self.endpoint.as_ref()
+    }
+
+    /// Verify if the target addresses all devices
+    pub fn is_broadcast(&self) -> bool {
+        self.unit_id == BROADCAST_UNIT_ID
+    }
+}
+
+impl From<u8> for Target {
+    fn from(unit_id: u8) -> Self {
+        Self::new(unit_id)
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl From<tcp::Dst> for Target {
+    fn from(dst: tcp::Dst) -> Self {
+        Self {unit_id: dst.get_unit_id(), endpoint: Some(dst)}
+    }
+}
@@ -0,0 +1,269 @@
+//! Asynchronous Modbus over TCP/IP independent of the runtime
+//!
+//! [AsyncTcp] implements the framing and the transactions once, performing I/O through a
+//! [Runtime]. The `async_tcp` and `async_std_tcp` modules provide it with the tokio and
+//! the async-std runtimes.
+
+use crate::error::Error;
+use crate::pdu::check_size;
+use std::convert::TryInto;
+use std::future::Future;
+use std::io::{Error as IoError, ErrorKind};
+use std::net::SocketAddr;
+use std::time::Duration;
+use super::conn::{Dst, BROADCAST_UNIT_ID, TCP_PORT};
+use super::frame::{Frame, HEADER_LEN};
+use super::super::AsyncTransport;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn timed_out() -> Error {
+    IoError::new(ErrorKind::TimedOut, "Modbus TCP operation timed out").into()
+}
+
+/// Asynchronous runtime performing I/O of an [AsyncTcp] transport
+#[allow(async_fn_in_trait)]
+pub trait Runtime {
+    /// Connected TCP socket
+    type Socket;
+    /// TCP socket listening for connections
+    type Listener;
+
+    /// Run a blocking function without blocking the runtime
+    async fn unblock<T, F>(f: F) -> Result<T, IoError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static;
+    /// Await given future up to given time, `None` if it did not complete in time
+    async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output>;
+    /// Connect to given address
+    async fn connect(addr: SocketAddr) -> Result<Self::Socket, IoError>;
+    /// Listen for connections on given address
+    async fn bind(addr: SocketAddr) -> Result<Self::Listener, IoError>;
+    /// Accept a connection
+    async fn accept(listener: &Self::Listener) -> Result<Self::Socket, IoError>;
+    /// Read exactly the number of bytes needed to fill given buffer
+    async fn read_exact(socket: &mut Self::Socket, buf: &mut [u8]) -> Result<(), IoError>;
+    /// Write whole given buffer
+    async fn write_all(socket: &mut Self::Socket, buf: &[u8]) -> Result<(), IoError>;
+}
+
+/// Connection used to exchange an asynchronous Modbus transaction over TCP/IP
+pub struct Stream<R: Runtime> {
+    socket: R::Socket,
+    transaction_id: u16,
+    unit_id: u8,
+}
+
+/// Asynchronous TCP/IP transport for the Modbus commands
+///
+/// This structure implements [AsyncTransport trait](AsyncTransport) that provides
+/// functions needed to read and write Modbus functions using this transport.
+/// Each transaction is a future, so a single runtime can poll many devices
+/// concurrently.
+pub struct AsyncTcp<R: Runtime> {
+    listener: Option<R::Listener>,
+    slave_port: u16,
+    unit_id: u8,
+    strict_mbap: bool,
+}
+
+impl<R: Runtime> Default for AsyncTcp<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Runtime> AsyncTcp<R> {
+    /// Create a new instance of the asynchronous Modbus transport
+    ///
+    /// # Examples
+    /// ```
+    /// # #[cfg(feature = "tokio")] {
+    /// let modbus = modbus::async_tcp::AsyncTcp::new();
+    /// # }
+    /// ```
+    pub fn new() -> Self {
+        Self {listener: None, slave_port: TCP_PORT, unit_id: 255, strict_mbap: true}
+    }
+
+    /// Set TCP port the slave listens on instead of the default Modbus port 502
+    ///
+    /// This method shall be called before the slave mode is started.
+    pub fn set_slave_port(&mut self, port: u16) {
+        self.slave_port = port;
+    }
+
+    /// Verify transaction and protocol identifiers of received responses, enabled by default
+    ///
+    /// It works like [TcpBuilder::strict_mbap](super::conn::TcpBuilder::strict_mbap) of the
+    /// synchronous transport.
+    pub fn set_strict_mbap(&mut self, strict: bool) {
+        self.strict_mbap = strict;
+    }
+
+    async fn resolve(dst: &Dst) -> Result<Vec<SocketAddr>, Error> {
+        if dst.is_resolved() {
+            return Ok(dst.resolve()?);
+        }
+
+        let dst = dst.clone();
+        match R::timeout(DNS_TIMEOUT, R::unblock(move || dst.resolve())).await {
+            Some(result) => Ok(result??),
+            None => Err(timed_out()),
+        }
+    }
+
+    async fn connect(dst: &Dst) -> Result<R::Socket, Error> {
+        let mut last_err = None;
+
+        for addr in Self::resolve(dst).await? {
+            match R::timeout(CONNECT_TIMEOUT, R::connect(addr)).await {
+                Some(Ok(socket)) => return Ok(socket),
+                Some(Err(err)) => last_err = Some(err.into()),
+                None => last_err = Some(timed_out()),
+            }
+        }
+
+        Err(last_err.unwrap())
+    }
+
+    pub(super) async fn read_frame(socket: &mut R::Socket) -> Result<Vec<u8>, Error> {
+        let mut frame_data = vec![0; HEADER_LEN];
+        R::read_exact(socket, &mut frame_data).await?;
+
+        let len = u16::from_be_bytes(frame_data[4..=5].try_into().unwrap()) as usize;
+        if len < 2 {
+            return Err(Error::InvalidDataLength);
+        }
+        check_size(len - 1)?;
+        frame_data.resize(HEADER_LEN + len - 1, 0);
+        R::read_exact(socket, &mut frame_data[HEADER_LEN..]).await?;
+
+        Ok(frame_data)
+    }
+
+    pub(super) async fn write_frame(socket: &mut R::Socket, frame: &Frame<'_>) -> Result<(), Error> {
+        R::write_all(socket, &frame.encode()?).await?;
+        Ok(())
+    }
+}
+
+fn check_rsp_frame(frame: &Frame, transaction_id: u16, src: &Dst, strict_mbap: bool) -> Result<(), Error> {
+    if strict_mbap && (frame.get_transaction_id() != transaction_id || !frame.is_modbus_protocol()) {
+        return Err(Error::TransactionMismatch);
+    }
+    if !src.matches_rsp_unit_id(frame.get_unit_id()) {
+        return Err(Error::InvalidData);
+    }
+    Ok(())
+}
+
+impl<R: Runtime> AsyncTransport for AsyncTcp<R> {
+    type Dst = Dst;
+    type Stream = Stream<R>;
+
+    async fn start_master(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.unit_id = unit_id;
+        self.listener = Some(R::bind(SocketAddr::from(([127, 0, 0, 1], self.slave_port))).await?);
+        Ok(())
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        dst.unit_id == BROADCAST_UNIT_ID
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        stream.unit_id
+    }
+
+    async fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        let mut socket = Self::connect(dst).await?;
+        let frame = Frame::new(dst.unit_id, pdu);
+
+        Self::write_frame(&mut socket, &frame).await?;
+        Ok(Stream {socket, transaction_id: frame.get_transaction_id(), unit_id: dst.unit_id})
+    }
+
+    async fn read_rsp_pdu(&mut self, stream: &mut Self::Stream, src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        let frame_data = match R::timeout(READ_TIMEOUT, Self::read_frame(&mut stream.socket)).await {
+            Some(result) => result?,
+            None => return Err(timed_out()),
+        };
+        let frame = Frame::decode(&frame_data)?;
+
+        check_rsp_frame(&frame, stream.transaction_id, src, self.strict_mbap)?;
+        Ok(frame.get_pdu())
+    }
+
+    async fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        if let Some(listener) = &self.listener {
+            let mut socket = R::accept(listener).await?;
+
+            let frame_data = Self::read_frame(&mut socket).await?;
+            let frame = Frame::decode(&frame_data)?;
+
+            if !frame.is_modbus_protocol() || frame.get_unit_id() != self.unit_id {
+                return Err(Error::InvalidData);
+            }
+
+            Ok((frame.get_pdu(), Stream {socket, transaction_id: frame.get_transaction_id(), unit_id: frame.get_unit_id()}))
+        }
+        else {
+            Err(Error::InvalidValue)
+        }
+    }
+
+    async fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        let frame = Frame::with_transaction_id(stream.transaction_id, self.unit_id, pdu);
+        Self::write_frame(&mut stream.socket, &frame).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn create_dst(unit_id: u8) -> Dst {
+        Dst::new(IpAddr::V4(Ipv4Addr::LOCALHOST), unit_id)
+    }
+
+    #[test]
+    fn test_check_rsp_frame_invalid_unit_id() {
+        let frame_data = [0x15, 0x01, 0x00, 0x00, 0x00, 0x02, 0x0A, 0x07];
+        let frame = Frame::decode(&frame_data).unwrap();
+
+        let err = check_rsp_frame(&frame, 0x1501, &create_dst(0x0B), true).err().unwrap();
+        match err {
+            Error::InvalidData => {}
+            _ => panic!("Expected InvalidData, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_check_rsp_frame_transaction_mismatch() {
+        let frame_data = [0x15, 0x01, 0x00, 0x00, 0x00, 0x02, 0x0A, 0x07];
+        let frame = Frame::decode(&frame_data).unwrap();
+
+        let err = check_rsp_frame(&frame, 0x1502, &create_dst(0x0A), true).err().unwrap();
+        match err {
+            Error::TransactionMismatch => {}
+            _ => panic!("Expected TransactionMismatch, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_check_rsp_frame_lenient_mbap() {
+        let frame_data = [0x15, 0x01, 0x00, 0x01, 0x00, 0x02, 0x0A, 0x07];
+        let frame = Frame::decode(&frame_data).unwrap();
+
+        assert!(check_rsp_frame(&frame, 0x1502, &create_dst(0x0A), false).is_ok());
+    }
+}
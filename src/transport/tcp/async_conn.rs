@@ -0,0 +1,149 @@
+//! Asynchronous Modbus over TCP/IP
+//!
+//! This module is available with the `tokio` feature.
+
+use std::future::Future;
+use std::io::Error as IoError;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::spawn_blocking;
+use tokio::time::timeout;
+
+pub use super::async_base::Runtime;
+
+/// The tokio runtime performing I/O of [AsyncTcp]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tokio;
+
+impl Runtime for Tokio {
+    type Socket = TcpStream;
+    type Listener = TcpListener;
+
+    async fn unblock<T, F>(f: F) -> Result<T, IoError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        spawn_blocking(f).await.map_err(IoError::other)
+    }
+
+    async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+        timeout(duration, future).await.ok()
+    }
+
+    async fn connect(addr: SocketAddr) -> Result<TcpStream, IoError> {
+        TcpStream::connect(addr).await
+    }
+
+    async fn bind(addr: SocketAddr) -> Result<TcpListener, IoError> {
+        TcpListener::bind(addr).await
+    }
+
+    async fn accept(listener: &TcpListener) -> Result<TcpStream, IoError> {
+        Ok(listener.accept().await?.0)
+    }
+
+    async fn read_exact(socket: &mut TcpStream, buf: &mut [u8]) -> Result<(), IoError> {
+        socket.read_exact(buf).await?;
+        Ok(())
+    }
+
+    async fn write_all(socket: &mut TcpStream, buf: &[u8]) -> Result<(), IoError> {
+        socket.write_all(buf).await
+    }
+}
+
+/// Asynchronous TCP/IP transport for the Modbus commands running on tokio
+pub type AsyncTcp = super::async_base::AsyncTcp<Tokio>;
+
+/// Connection used to exchange an asynchronous Modbus transaction over TCP/IP
+pub type Stream = super::async_base::Stream<Tokio>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, Stage};
+    use crate::AsyncTransport;
+    use super::super::conn::Dst;
+    use super::super::frame::Frame;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    async fn connect() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn test_read_frame() {
+        let (mut client, mut server) = connect().await;
+        server.write_all(&[0x15, 0x01, 0x00, 0x00, 0x00, 0x06, 0xFF, 0x03, 0x00, 0x04, 0x00, 0x01]).await.unwrap();
+
+        let frame_data = AsyncTcp::read_frame(&mut client).await.unwrap();
+        let frame = Frame::decode(&frame_data).unwrap();
+        assert_eq!(frame.get_pdu(), vec![0x03, 0x00, 0x04, 0x00, 0x01]);
+    }
+
+    #[tokio::test]
+    async fn test_read_oversized_frame() {
+        let (mut client, mut server) = connect().await;
+        server.write_all(&[0x15, 0x01, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x03]).await.unwrap();
+
+        match AsyncTcp::read_frame(&mut client).await.err().unwrap() {
+            Error::InvalidDataLength => {}
+            err => panic!("Expected InvalidDataLength, but got {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_frame() {
+        let (mut client, mut server) = connect().await;
+        AsyncTcp::write_frame(&mut client, &Frame::with_transaction_id(0x1501, 0x0A, &[0x07])).await.unwrap();
+
+        let mut frame = [0; 8];
+        server.read_exact(&mut frame).await.unwrap();
+        assert_eq!(frame, [0x15, 0x01, 0x00, 0x00, 0x00, 0x02, 0x0A, 0x07]);
+    }
+
+    #[tokio::test]
+    async fn test_transaction() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let dst = Dst::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0x0A).with_port(listener.local_addr().unwrap().port());
+
+        let master = tokio::spawn(async move {
+            AsyncTcp::new().write_req_read_rsp(&dst, &crate::ReadHldRegRequest::new(0x0010, 1)).await
+        });
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let frame_data = AsyncTcp::read_frame(&mut socket).await.unwrap();
+        let req = Frame::decode(&frame_data).unwrap();
+        assert_eq!(req.get_pdu(), vec![0x03, 0x00, 0x10, 0x00, 0x01]);
+        let rsp = Frame::with_transaction_id(req.get_transaction_id(), 0x0A, &[0x03, 0x02, 0x12, 0x34]);
+        AsyncTcp::write_frame(&mut socket, &rsp).await.unwrap();
+
+        let rsp = master.await.unwrap().unwrap().unwrap();
+        assert_eq!(rsp.get_registers(), &[0x1234]);
+    }
+
+    #[tokio::test]
+    async fn test_error_context() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let dst = Dst::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0x0A).with_port(listener.local_addr().unwrap().port());
+
+        let master = tokio::spawn(async move {
+            AsyncTcp::new().write_req_read_rsp(&dst, &crate::ReadHldRegRequest::new(0x0010, 1)).await
+        });
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let transaction_id = Frame::decode(&AsyncTcp::read_frame(&mut socket).await.unwrap()).unwrap().get_transaction_id();
+        AsyncTcp::write_frame(&mut socket, &Frame::with_transaction_id(transaction_id, 0x0A, &[0x03, 0x04, 0x12, 0x34])).await.unwrap();
+
+        let err = master.await.unwrap().err().unwrap();
+        let context = err.get_context().unwrap();
+        assert_eq!(context.get_unit_id(), Some(0x0A));
+        assert_eq!(context.get_stage(), Stage::DecodeResponse);
+    }
+}
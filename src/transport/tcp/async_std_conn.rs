@@ -0,0 +1,115 @@
+//! Asynchronous Modbus over TCP/IP for the async-std runtime
+//!
+//! This module is available with the `async-std` feature. It provides the transport of
+//! the tokio based `async_tcp` module running on async-std.
+
+use std::future::Future;
+use std::io::Error as IoError;
+use std::net::SocketAddr;
+use std::time::Duration;
+use async_std::future::timeout;
+use async_std::io::{ReadExt, WriteExt};
+use async_std::net::{TcpListener, TcpStream};
+use async_std::task::spawn_blocking;
+
+pub use super::async_base::Runtime;
+
+/// The async-std runtime performing I/O of [AsyncTcp]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsyncStd;
+
+impl Runtime for AsyncStd {
+    type Socket = TcpStream;
+    type Listener = TcpListener;
+
+    async fn unblock<T, F>(f: F) -> Result<T, IoError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        Ok(spawn_blocking(f).await)
+    }
+
+    async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+        timeout(duration, future).await.ok()
+    }
+
+    async fn connect(addr: SocketAddr) -> Result<TcpStream, IoError> {
+        TcpStream::connect(addr).await
+    }
+
+    async fn bind(addr: SocketAddr) -> Result<TcpListener, IoError> {
+        TcpListener::bind(addr).await
+    }
+
+    async fn accept(listener: &TcpListener) -> Result<TcpStream, IoError> {
+        Ok(listener.accept().await?.0)
+    }
+
+    async fn read_exact(socket: &mut TcpStream, buf: &mut [u8]) -> Result<(), IoError> {
+        socket.read_exact(buf).await
+    }
+
+    async fn write_all(socket: &mut TcpStream, buf: &[u8]) -> Result<(), IoError> {
+        socket.write_all(buf).await
+    }
+}
+
+/// Asynchronous TCP/IP transport for the Modbus commands running on async-std
+pub type AsyncTcp = super::async_base::AsyncTcp<AsyncStd>;
+
+/// Connection used to exchange an asynchronous Modbus transaction over TCP/IP
+pub type Stream = super::async_base::Stream<AsyncStd>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use super::super::frame::Frame;
+    use async_std::task::block_on;
+    use std::net::Ipv4Addr;
+
+    async fn connect() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn test_read_frame() {
+        block_on(async {
+            let (mut client, mut server) = connect().await;
+            server.write_all(&[0x15, 0x01, 0x00, 0x00, 0x00, 0x06, 0xFF, 0x03, 0x00, 0x04, 0x00, 0x01]).await.unwrap();
+
+            let frame_data = AsyncTcp::read_frame(&mut client).await.unwrap();
+            let frame = Frame::decode(&frame_data).unwrap();
+            assert_eq!(frame.get_pdu(), vec![0x03, 0x00, 0x04, 0x00, 0x01]);
+        });
+    }
+
+    #[test]
+    fn test_read_oversized_frame() {
+        block_on(async {
+            let (mut client, mut server) = connect().await;
+            server.write_all(&[0x15, 0x01, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x03]).await.unwrap();
+
+            match AsyncTcp::read_frame(&mut client).await.err().unwrap() {
+                Error::InvalidDataLength => {}
+                err => panic!("Expected InvalidDataLength, but got {:?}", err),
+            }
+        });
+    }
+
+    #[test]
+    fn test_write_frame() {
+        block_on(async {
+            let (mut client, mut server) = connect().await;
+            AsyncTcp::write_frame(&mut client, &Frame::with_transaction_id(0x1501, 0x0A, &[0x07])).await.unwrap();
+
+            let mut frame = [0; 8];
+            server.read_exact(&mut frame).await.unwrap();
+            assert_eq!(frame, [0x15, 0x01, 0x00, 0x00, 0x00, 0x02, 0x0A, 0x07]);
+        });
+    }
+}
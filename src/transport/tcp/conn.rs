@@ -1,20 +1,64 @@
 //! Modbus over TCP/IP
  
+use crate::cancel::CancelToken;
 use crate::error::Error;
+use crate::pdu::check_size;
+use std::io::{prelude::*, Error as IoError, ErrorKind};
+use socket2::{Domain, SockRef, Socket, TcpKeepalive, Type};
+use std::collections::HashMap;
 use std::convert::TryInto;
-use std::io::prelude::*;
-use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
-use std::time::Duration;
-use super::frame::Frame;
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{mpsc, Arc, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+use super::super::capture::{notify, Direction, Observer};
+use super::super::scan::ECHO_PDU;
+use super::frame::{Frame, HEADER_LEN};
+use super::limit::TokenBucket;
 use super::super::Transport;
 
-const TCP_PORT: u16 = 502;
-const BROADCAST_UNIT_ID: u8 = 0;
+pub use super::limit::{RateLimit, RateLimitAction};
+pub use super::split::{split, Pending, RecvHalf, SendHalf};
+
+pub(super) const TCP_PORT: u16 = 502;
+pub(super) const BROADCAST_UNIT_ID: u8 = 0;
+/// Unit id conventionally used to address a device connected directly to the TCP/IP network
+pub const DIRECT_UNIT_ID: u8 = 0xFF;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+const LISTEN_BACKLOG: u32 = 128;
+const RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(5);
+/// Interval of checking the cancellation token and deadlines while waiting for a frame or a connection
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+type Resolver = Arc<dyn Fn() -> std::io::Result<Vec<SocketAddr>> + Send + Sync>;
+
+#[derive(Clone)]
+enum Host {
+    Ip(IpAddr),
+    Addr(Resolver),
+}
+
+impl PartialEq for Host {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Host::Ip(ip_addr), Host::Ip(other_ip_addr)) => ip_addr == other_ip_addr,
+            // Resolvers are opaque, only clones of the same destination are equal
+            (Host::Addr(resolver), Host::Addr(other_resolver)) => Arc::ptr_eq(resolver, other_resolver),
+            _ => false,
+        }
+    }
+}
 
 /// Structure describing destination node for TCP/IP Modbus functions
+#[derive(Clone, PartialEq)]
 pub struct Dst {
-    ip_addr: IpAddr,
-    unit_id: u8,
+    host: Host,
+    port: Option<u16>,
+    pub(super) unit_id: u8,
+    pub(super) ignore_direct_unit_id: bool,
 }
 
 impl Dst {
@@ -26,7 +70,239 @@ impl Dst {
     /// let dst = modbus::tcp::Dst::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10);
     /// ```
     pub fn new(ip_addr: IpAddr, unit_id: u8) -> Self {
-        Self {ip_addr, unit_id}
+        Self {host: Host::Ip(ip_addr), port: None, unit_id, ignore_direct_unit_id: false}
+    }
+
+    /// Create a new TCP/IP destination description from any socket address representation
+    /// 
+    /// It accepts host names, `host:port` strings, IPv6 addresses with scope ids and any
+    /// other type implementing [ToSocketAddrs]. The address is resolved each time a request
+    /// is sent. If the address resolves to multiple socket addresses, they are tried in order
+    /// until a connection is established.
+    /// 
+    /// # Examples
+    /// ```
+    /// let dst = modbus::tcp::Dst::from_addr("localhost:502", 10);
+    /// let dst = modbus::tcp::Dst::from_addr(("plc.local", 502), 10);
+    /// ```
+    pub fn from_addr<A: ToSocketAddrs + Send + Sync + 'static>(addr: A, unit_id: u8) -> Self {
+        let resolver: Resolver = Arc::new(move || Ok(addr.to_socket_addrs()?.collect()));
+        Self {host: Host::Addr(resolver), port: None, unit_id, ignore_direct_unit_id: false}
+    }
+
+    /// Create a new destination description of a device connected directly to the TCP/IP network
+    /// 
+    /// The device is addressed with the [direct connection unit id](DIRECT_UNIT_ID) and
+    /// the unit id of its responses is not verified.
+    /// 
+    /// # Examples
+    /// ```
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// let dst = modbus::tcp::Dst::direct(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+    /// ```
+    pub fn direct(ip_addr: IpAddr) -> Self {
+        Self::new(ip_addr, DIRECT_UNIT_ID).with_ignored_direct_unit_id(true)
+    }
+
+    /// Treat unit id 0xFF as "don't care" when validating responses
+    /// 
+    /// When enabled, a response is accepted regardless of its unit id if the request was
+    /// addressed to unit id 0xFF, and a response with unit id 0xFF is accepted for any request.
+    pub fn with_ignored_direct_unit_id(mut self, ignore: bool) -> Self {
+        self.ignore_direct_unit_id = ignore;
+        self
+    }
+
+    /// Use given TCP port instead of the default Modbus port 502
+    /// 
+    /// For destinations created with [from_addr](Dst::from_addr), it overrides the port of
+    /// the resolved addresses.
+    /// 
+    /// # Examples
+    /// ```
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// let dst = modbus::tcp::Dst::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 10).with_port(5020);
+    /// ```
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Get unit id of the destination device
+    pub fn get_unit_id(&self) -> u8 {
+        self.unit_id
+    }
+
+    pub(super) fn is_resolved(&self) -> bool {
+        matches!(self.host, Host::Ip(_))
+    }
+
+    pub(super) fn resolve(&self) -> std::io::Result<Vec<SocketAddr>> {
+        let mut addrs = match &self.host {
+            Host::Ip(ip_addr) => vec![SocketAddr::new(*ip_addr, TCP_PORT)],
+            Host::Addr(resolver) => resolver()?,
+        };
+
+        if let Some(port) = self.port {
+            addrs.iter_mut().for_each(|addr| addr.set_port(port));
+        }
+        if addrs.is_empty() {
+            return Err(IoError::new(ErrorKind::NotFound, "Address resolved to no socket address"));
+        }
+
+        Ok(addrs)
+    }
+
+    pub(super) fn matches_rsp_unit_id(&self, unit_id: u8) -> bool {
+        unit_id == self.unit_id ||
+            (self.ignore_direct_unit_id && (unit_id == DIRECT_UNIT_ID || self.unit_id == DIRECT_UNIT_ID))
+    }
+}
+
+/// Policy of re-establishing connections of the TCP/IP master
+/// 
+/// A failed connection attempt is repeated up to the configured number of times.
+/// The delay before the first repetition doubles with each next one, up to the maximum delay.
+/// 
+/// With the replay enabled, a request whose connection was lost before the response
+/// arrived is sent again over a new connection. Enable it only for requests that are
+/// safe to be executed more than once by the slave.
+/// 
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use modbus::tcp::ReconnectPolicy;
+/// 
+/// let policy = ReconnectPolicy::new(3)
+///     .with_delay(Duration::from_millis(50), Duration::from_secs(1))
+///     .with_replay(true);
+/// let modbus = modbus::tcp::Tcp::builder().reconnect_policy(policy).build();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReconnectPolicy {
+    max_attempts: u32,
+    delay: Duration,
+    max_delay: Duration,
+    replay: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl ReconnectPolicy {
+    /// Create a policy repeating failed connection attempts up to given number of times
+    /// 
+    /// By default, the first repetition is delayed by 100 milliseconds, the delay is limited
+    /// to 5 seconds and lost requests are not replayed.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {max_attempts, delay: RECONNECT_DELAY, max_delay: RECONNECT_MAX_DELAY, replay: false}
+    }
+
+    /// Set delay before the first repetition and the limit of the growing delay
+    pub fn with_delay(mut self, delay: Duration, max_delay: Duration) -> Self {
+        self.delay = delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Send again requests whose connection was lost before the response arrived
+    pub fn with_replay(mut self, replay: bool) -> Self {
+        self.replay = replay;
+        self
+    }
+
+    /// Get the maximal number of repeated connection attempts
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Get delay before given repetition of a connection attempt, counted from 0
+    pub fn get_delay(&self, attempt: u32) -> Duration {
+        self.delay.checked_mul(1 << attempt.min(31)).unwrap_or(self.max_delay).min(self.max_delay)
+    }
+
+    /// Check if requests whose connection was lost are sent again
+    pub fn is_replay(&self) -> bool {
+        self.replay
+    }
+}
+
+/// Behavior of the slave when the [maximal number of connections](TcpBuilder::max_connections) is reached
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LimitPolicy {
+    /// Accept and immediately close connections over the limit
+    #[default]
+    Reject,
+    /// Leave connections over the limit in the listener backlog until a connection is closed
+    Queue,
+}
+
+/// Connection used to exchange a Modbus transaction over TCP/IP
+pub struct Stream {
+    socket: TcpStream,
+    peer_addr: Option<SocketAddr>,
+    unit_id: u8,
+    transaction_id: u16,
+    req_pdu: Vec<u8>,
+}
+
+struct Connection {
+    socket: TcpStream,
+    unit_id: u8,
+    last_used: Instant,
+}
+
+/// Connection accepted by the slave, kept open until the master closes it
+struct SlaveConnection {
+    socket: TcpStream,
+    /// Alive as long as the thread reading requests from the connection
+    reader: Weak<()>,
+}
+
+impl SlaveConnection {
+    fn is_open(&self) -> bool {
+        self.reader.strong_count() > 0
+    }
+}
+
+/// Request frame read from a slave connection, with the socket used to respond to it
+type SlaveFrame = (Vec<u8>, TcpStream);
+
+/// Socket reader waking up periodically to check the cancellation token
+/// 
+/// Each read waits for data at most for the read timeout of the transport, like a plain socket.
+struct CancellableSocket<'a> {
+    socket: &'a TcpStream,
+    token: &'a CancelToken,
+    timeout: Option<Duration>,
+}
+
+impl Read for CancellableSocket<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let start = Instant::now();
+
+        loop {
+            if self.token.is_cancelled() {
+                return Err(IoError::other("Operation cancelled"));
+            }
+
+            let poll_interval = match self.timeout {
+                Some(timeout) => match timeout.checked_sub(start.elapsed()) {
+                    Some(remaining) if !remaining.is_zero() => remaining.min(POLL_INTERVAL),
+                    _ => return Err(ErrorKind::TimedOut.into()),
+                },
+                None => POLL_INTERVAL,
+            };
+            self.socket.set_read_timeout(Some(poll_interval))?;
+
+            match (&mut self.socket).read(buf) {
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+                result => return result,
+            }
+        }
     }
 }
 
@@ -34,58 +310,719 @@ impl Dst {
 /// 
 /// This structure implements [Transport trait](Transport) that provides
 /// functions needed to read and write Modbus functions using this transport.
+/// 
+/// In the slave mode, connections accepted from masters are kept open until the masters
+/// close them, and requests are read from all open connections.
 pub struct Tcp {
     listener: Option<TcpListener>,
-    unit_id: u8,
+    slave_port: u16,
+    unit_ids: Vec<u8>,
+
+    connect_timeout: Duration,
+    read_timeout: Option<Duration>,
+    dns_timeout: Duration,
+    nodelay: bool,
+    strict_mbap: bool,
+    persistent: bool,
+    reconnect_policy: ReconnectPolicy,
+    keepalive: Option<Duration>,
+    idle_ping: Option<Duration>,
+    idle_ping_pdu: Vec<u8>,
+    connections: HashMap<SocketAddr, Connection>,
+
+    listen_backlog: u32,
+    max_connections: Option<usize>,
+    limit_policy: LimitPolicy,
+    rate_limit: Option<RateLimit>,
+    global_rate_limit: Option<TokenBucket>,
+    slave_connections: Vec<SlaveConnection>,
+    frame_tx: mpsc::Sender<SlaveFrame>,
+    frame_rx: mpsc::Receiver<SlaveFrame>,
+
+    observer: Option<Observer>,
+    cancel_token: Option<CancelToken>,
+}
+
+/// Builder of the [TCP/IP transport](Tcp) with custom connection options
+#[derive(Clone, Debug)]
+pub struct TcpBuilder {
+    connect_timeout: Duration,
+    read_timeout: Option<Duration>,
+    dns_timeout: Duration,
+    nodelay: bool,
+    strict_mbap: bool,
+    persistent: bool,
+    reconnect_policy: ReconnectPolicy,
+    keepalive: Option<Duration>,
+    idle_ping: Option<Duration>,
+    idle_ping_pdu: Vec<u8>,
+    listen_backlog: u32,
+    max_connections: Option<usize>,
+    limit_policy: LimitPolicy,
+    rate_limit: Option<RateLimit>,
+    global_rate_limit: Option<RateLimit>,
+}
+
+impl TcpBuilder {
+    /// Set timeout of establishing the connection with the slave, 1 second by default
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set timeout of reading data from the connection, 1 second by default
+    /// 
+    /// `None` blocks the read operations indefinitely.
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Set timeout of resolving destination addresses given by host names, 5 seconds by default
+    pub fn dns_timeout(mut self, timeout: Duration) -> Self {
+        self.dns_timeout = timeout;
+        self
+    }
+
+    /// Set the `TCP_NODELAY` option on the created connections, disabled by default
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Verify transaction and protocol identifiers of received responses, enabled by default
+    /// 
+    /// With the verification enabled, a response carrying other transaction identifier than
+    /// the request or a non-Modbus protocol identifier is rejected with
+    /// [TransactionMismatch error](Error::TransactionMismatch). Disable it to tolerate
+    /// gateways that do not echo the MBAP header correctly.
+    pub fn strict_mbap(mut self, strict: bool) -> Self {
+        self.strict_mbap = strict;
+        self
+    }
+
+    /// Keep connections to slaves open between transactions, disabled by default
+    /// 
+    /// A connection is reused by subsequent requests to the same socket address. It is
+    /// closed if a transaction over it fails.
+    pub fn persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+
+    /// Set the [policy](ReconnectPolicy) of re-establishing connections, no reconnection by default
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    /// Enable the `SO_KEEPALIVE` option with given idle time on the created connections, disabled by default
+    /// 
+    /// The operating system probes a connection that has been idle for the given time,
+    /// which keeps NAT and firewall mappings of [persistent](TcpBuilder::persistent)
+    /// connections alive and detects peers that disappeared.
+    pub fn keepalive(mut self, idle_time: Option<Duration>) -> Self {
+        self.keepalive = idle_time;
+        self
+    }
+
+    /// Set interval after which [idle persistent connections are pinged](Tcp::ping_idle_connections), disabled by default
+    pub fn idle_ping(mut self, interval: Option<Duration>) -> Self {
+        self.idle_ping = interval;
+        self
+    }
+
+    /// Set request PDU sent as the idle ping, Diagnostics Return Query Data by default
+    /// 
+    /// Any response to this request, including an exception response, proves the connection is alive.
+    /// The same request is sent by [Client::ping](crate::client::Client::ping), so devices not supporting
+    /// diagnostics can be checked with a cheap read, like [PROBE_PDU](crate::scan::PROBE_PDU).
+    pub fn idle_ping_pdu(mut self, pdu: &[u8]) -> Self {
+        self.idle_ping_pdu = pdu.to_vec();
+        self
+    }
+
+    /// Set length of the queue of pending connections of the slave listener, 128 by default
+    pub fn listen_backlog(mut self, backlog: u32) -> Self {
+        self.listen_backlog = backlog;
+        self
+    }
+
+    /// Limit the number of simultaneous connections served by the slave, unlimited by default
+    /// 
+    /// A connection is counted from its acceptance until the master closes it.
+    /// Connections over the limit are handled according to the [policy](TcpBuilder::limit_policy).
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::Transport;
+    /// use modbus::tcp::LimitPolicy;
+    /// 
+    /// let mut mb = modbus::tcp::Tcp::builder()
+    ///     .listen_backlog(8)
+    ///     .max_connections(Some(4))
+    ///     .limit_policy(LimitPolicy::Queue)
+    ///     .build();
+    /// mb.start_slave(10).unwrap();
+    /// ```
+    pub fn max_connections(mut self, max_connections: Option<usize>) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Set behavior of the slave when the maximal number of connections is reached, [LimitPolicy::Reject] by default
+    pub fn limit_policy(mut self, policy: LimitPolicy) -> Self {
+        self.limit_policy = policy;
+        self
+    }
+
+    /// Limit the rate of requests read by the slave from each connection, unlimited by default
+    /// 
+    /// Requests over the limit are handled according to its [action](RateLimit::with_action),
+    /// so that a misbehaving master cannot starve the other ones.
+    pub fn rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.rate_limit = limit;
+        self
+    }
+
+    /// Limit the rate of requests read by the slave from all connections together, unlimited by default
+    pub fn global_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.global_rate_limit = limit;
+        self
+    }
+
+    /// Create the transport with configured options
+    pub fn build(self) -> Tcp {
+        let (frame_tx, frame_rx) = mpsc::channel();
+
+        Tcp {
+            listener: None,
+            slave_port: TCP_PORT,
+            unit_ids: Vec::new(),
+            connect_timeout: self.connect_timeout,
+            read_timeout: self.read_timeout,
+            dns_timeout: self.dns_timeout,
+            nodelay: self.nodelay,
+            strict_mbap: self.strict_mbap,
+            persistent: self.persistent,
+            reconnect_policy: self.reconnect_policy,
+            keepalive: self.keepalive,
+            idle_ping: self.idle_ping,
+            idle_ping_pdu: self.idle_ping_pdu,
+            connections: HashMap::new(),
+            listen_backlog: self.listen_backlog,
+            max_connections: self.max_connections,
+            limit_policy: self.limit_policy,
+            rate_limit: self.rate_limit,
+            global_rate_limit: self.global_rate_limit.map(TokenBucket::new),
+            slave_connections: Vec::new(),
+            frame_tx,
+            frame_rx,
+            observer: None,
+            cancel_token: None,
+        }
+    }
+}
+
+impl Default for Tcp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Tcp {
+    fn drop(&mut self) {
+        // Wake up threads reading requests from the slave connections
+        for conn in &self.slave_connections {
+            let _ = conn.socket.shutdown(Shutdown::Both);
+        }
+    }
 }
 
 impl Tcp {
-    /// Create a new instance of the Modbus transport
+    /// Create a new instance of the Modbus transport with default connection options
+    /// 
+    /// It is equivalent to `Tcp::builder().build()`.
     /// 
     /// # Examples
     /// ```
     /// let modbus = modbus::tcp::Tcp::new();
     /// ```
     pub fn new() -> Self {
-        Self {listener: None, unit_id: 255}
+        Self::builder().build()
     }
 
-    fn connect(addr: &SocketAddr) -> Result<TcpStream, Error> {
-        let stream = TcpStream::connect_timeout(addr, Duration::from_secs(1))?;
-        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
-        Ok(stream)
+    /// Create a builder of the Modbus transport with custom connection options
+    /// 
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// 
+    /// let modbus = modbus::tcp::Tcp::builder()
+    ///     .connect_timeout(Duration::from_millis(500))
+    ///     .read_timeout(Some(Duration::from_secs(2)))
+    ///     .nodelay(true)
+    ///     .build();
+    /// ```
+    pub fn builder() -> TcpBuilder {
+        TcpBuilder {
+            connect_timeout: CONNECT_TIMEOUT,
+            read_timeout: Some(READ_TIMEOUT),
+            dns_timeout: DNS_TIMEOUT,
+            nodelay: false,
+            strict_mbap: true,
+            persistent: false,
+            reconnect_policy: ReconnectPolicy::default(),
+            keepalive: None,
+            idle_ping: None,
+            idle_ping_pdu: ECHO_PDU.to_vec(),
+            listen_backlog: LISTEN_BACKLOG,
+            max_connections: None,
+            limit_policy: LimitPolicy::default(),
+            rate_limit: None,
+            global_rate_limit: None,
+        }
+    }
+
+    /// Set TCP port the slave listens on instead of the default Modbus port 502
+    /// 
+    /// This method shall be called before the slave mode is started.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::Transport;
+    /// 
+    /// let mut mb = modbus::tcp::Tcp::new();
+    /// mb.set_slave_port(5020);
+    /// mb.start_slave(10).unwrap();
+    /// ```
+    pub fn set_slave_port(&mut self, port: u16) {
+        self.slave_port = port;
     }
 
-    fn read_pdu(stream: &mut TcpStream, expected_unit_id: u8) -> Result<Vec<u8>, Error> {
-        let mut frame_pdu = Vec::new();
-        let mut byte: [u8; 1] = [0];
+    /// Ping persistent connections that have been idle longer than the [configured interval](TcpBuilder::idle_ping)
+    /// 
+    /// This function shall be called periodically by a master keeping long-lived connections,
+    /// so that they are not silently closed by gateways, NATs or firewalls. A connection that
+    /// does not respond to the ping is closed and the last error is returned.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// 
+    /// let mut mb = modbus::tcp::Tcp::builder()
+    ///     .persistent(true)
+    ///     .idle_ping(Some(Duration::from_secs(30)))
+    ///     .build();
+    /// loop {
+    ///     // Poll the slaves
+    ///     mb.ping_idle_connections().unwrap();
+    ///     std::thread::sleep(Duration::from_secs(1));
+    /// }
+    /// ```
+    pub fn ping_idle_connections(&mut self) -> Result<(), Error> {
+        let interval = match self.idle_ping {
+            Some(interval) => interval,
+            None => return Ok(()),
+        };
+        let idle_addrs: Vec<SocketAddr> = self.connections.iter()
+            .filter(|(_, conn)| conn.last_used.elapsed() >= interval)
+            .map(|(addr, _)| *addr)
+            .collect();
+        let mut result = Ok(());
 
-        loop {
-            match stream.read(&mut byte) {
-                Ok(0) => return Err(Error::InvalidDataLength),
-                Ok(1) => frame_pdu.push(byte[0]),
-                Ok(_) => panic!("Invalid number of bytes received"),
+        for addr in idle_addrs {
+            let conn = self.connections.get_mut(&addr).unwrap();
+            match Self::ping(conn, &self.idle_ping_pdu, &mut self.observer) {
+                Ok(()) => conn.last_used = Instant::now(),
                 Err(err) => {
-                    return Err(err.try_into().unwrap()); 
+                    self.connections.remove(&addr);
+                    result = Err(err);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Set observer receiving every transmitted and received frame, or remove it with `None`
+    /// 
+    /// # Examples
+    /// ```
+    /// let mut mb = modbus::tcp::Tcp::new();
+    /// mb.set_observer(Some(Box::new(|direction, _timestamp, frame_data| {
+    ///     println!("{:?} {:02X?}", direction, frame_data);
+    /// })));
+    /// ```
+    pub fn set_observer(&mut self, observer: Option<Observer>) {
+        self.observer = observer;
+    }
+
+    /// Set token aborting blocking reads and waiting for connections when cancelled, or remove it with `None`
+    /// 
+    /// A cancelled operation fails with [Error::Cancelled]. The token stays cancelled, so a new token
+    /// shall be set to use the transport again.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::{CancelToken, Transport};
+    /// 
+    /// let mut mb = modbus::tcp::Tcp::new();
+    /// let token = CancelToken::new();
+    /// mb.set_cancel_token(Some(token.clone()));
+    /// mb.start_slave(10).unwrap();
+    /// 
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(std::time::Duration::from_secs(10));
+    ///     token.cancel();
+    /// });
+    /// assert!(matches!(mb.read_req_pdu(), Err(modbus::Error::Cancelled)));
+    /// ```
+    pub fn set_cancel_token(&mut self, cancel_token: Option<CancelToken>) {
+        self.cancel_token = cancel_token;
+    }
+
+    /// Read PDU of a request frame, waiting for it at most given time
+    /// 
+    /// It works like [Transport::read_req_pdu], but returns `None` if no request arrived before
+    /// the timeout expired. It allows slave loops to perform other tasks between requests.
+    /// 
+    /// # Examples
+    /// ```no_run
+    /// use modbus::Transport;
+    /// use std::time::Duration;
+    /// 
+    /// let mut mb = modbus::tcp::Tcp::new();
+    /// mb.start_slave(10).unwrap();
+    /// loop {
+    ///     match mb.read_req_timeout(Duration::from_millis(500)).unwrap() {
+    ///         Some((req_pdu, mut stream)) => mb.write_rsp_pdu(&mut stream, &req_pdu).unwrap(),
+    ///         None => println!("Idle"),
+    ///     }
+    /// }
+    /// ```
+    pub fn read_req_timeout(&mut self, timeout: Duration) -> Result<Option<(Vec<u8>, Stream)>, Error> {
+        self.read_req_until(Some(Instant::now() + timeout))
+    }
+
+    fn read_req_until(&mut self, deadline: Option<Instant>) -> Result<Option<(Vec<u8>, Stream)>, Error> {
+        if self.listener.is_none() {
+            return Err(Error::InvalidValue);
+        }
+
+        loop {
+            if let Ok((frame_data, socket)) = self.frame_rx.try_recv() {
+                if self.admit(&frame_data, &socket) {
+                    return self.accept_req_frame(&frame_data, socket).map(Some);
+                }
+                continue;
+            }
+            self.slave_connections.retain(SlaveConnection::is_open);
+
+            // Wait for a connection only if there is no open one to receive a request from
+            let accept_deadline = if self.slave_connections.is_empty() { deadline } else { Some(Instant::now()) };
+            if let Some(socket) = self.accept(self.listener.as_ref().unwrap(), accept_deadline)? {
+                self.keep_connection(socket)?;
+                continue;
+            }
+
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if remaining.is_some_and(|remaining| remaining.is_zero()) {
+                return Ok(None);
+            }
+            if let Ok((frame_data, socket)) = self.frame_rx.recv_timeout(remaining.map_or(POLL_INTERVAL, |remaining| remaining.min(POLL_INTERVAL))) {
+                if self.admit(&frame_data, &socket) {
+                    return self.accept_req_frame(&frame_data, socket).map(Some);
+                }
+            }
+        }
+    }
+
+    fn keep_connection(&mut self, socket: TcpStream) -> Result<(), Error> {
+        self.configure(&socket)?;
+        // The master decides when the next request is sent
+        socket.set_read_timeout(None)?;
+
+        let reader = Arc::new(());
+        self.slave_connections.push(SlaveConnection {socket: socket.try_clone()?, reader: Arc::downgrade(&reader)});
+        let frame_tx = self.frame_tx.clone();
+        let rate_limit = self.rate_limit.map(TokenBucket::new);
+        thread::spawn(move || Self::read_connection(socket, frame_tx, reader, rate_limit));
+
+        Ok(())
+    }
+
+    /// Forward request frames of a slave connection allowed by its rate limit until it is closed
+    fn read_connection(mut socket: TcpStream, frame_tx: mpsc::Sender<SlaveFrame>, _reader: Arc<()>,
+                       mut rate_limit: Option<TokenBucket>) {
+        while let Ok(frame) = Self::read_frame(&mut socket).and_then(|frame_data| Ok((frame_data, socket.try_clone()?))) {
+            if rate_limit.as_mut().is_some_and(|limit| !limit.admit(&frame.0, &socket)) {
+                continue;
+            }
+            if frame_tx.send(frame).is_err() {
+                break;
+            }
+        }
+
+        let _ = socket.shutdown(Shutdown::Both);
+    }
+
+    /// Apply the global rate limit to a request frame, checking if it shall be served
+    fn admit(&mut self, frame_data: &[u8], socket: &TcpStream) -> bool {
+        self.global_rate_limit.as_mut().is_none_or(|limit| limit.admit(frame_data, socket))
+    }
+
+    fn accept_req_frame(&mut self, frame_data: &[u8], socket: TcpStream) -> Result<(Vec<u8>, Stream), Error> {
+        notify(&mut self.observer, Direction::Rx, frame_data);
+        let frame = Frame::decode(frame_data)?;
+
+        if !frame.is_modbus_protocol() || !self.unit_ids.contains(&frame.get_unit_id()) {
+            return Err(Error::InvalidData);
+        }
+
+        let peer_addr = socket.peer_addr().ok();
+        let stream = Stream {socket, peer_addr, unit_id: frame.get_unit_id(), transaction_id: frame.get_transaction_id(), req_pdu: Vec::new()};
+        Ok((frame.get_pdu(), stream))
+    }
+
+    fn ping(conn: &mut Connection, pdu: &[u8], observer: &mut Option<Observer>) -> Result<(), Error> {
+        let frame = Frame::new(conn.unit_id, pdu);
+        Self::write_frame(&mut conn.socket, &frame)?;
+        notify(observer, Direction::Tx, &frame.encode()?);
+
+        let frame_data = Self::read_frame(&mut conn.socket)?;
+        notify(observer, Direction::Rx, &frame_data);
+        if Frame::decode(&frame_data)?.get_transaction_id() != frame.get_transaction_id() {
+            return Err(Error::TransactionMismatch);
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, dst: &Dst) -> Result<Vec<SocketAddr>, Error> {
+        if dst.is_resolved() {
+            return Ok(dst.resolve()?);
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let dst = dst.clone();
+        thread::spawn(move || {
+            let _ = tx.send(dst.resolve());
+        });
+
+        match rx.recv_timeout(self.dns_timeout) {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(IoError::new(ErrorKind::TimedOut, "Address resolution timed out").into()),
+        }
+    }
+
+    fn connect(&self, dst: &Dst) -> Result<TcpStream, Error> {
+        let mut last_err = None;
+
+        for addr in self.resolve(dst)? {
+            match TcpStream::connect_timeout(&addr, self.connect_timeout) {
+                Ok(stream) => {
+                    self.configure(&stream)?;
+                    return Ok(stream);
+                }
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        Err(last_err.unwrap().into())
+    }
+
+    fn connect_with_policy(&self, dst: &Dst) -> Result<TcpStream, Error> {
+        let mut attempt = 0;
+
+        loop {
+            match self.connect(dst) {
+                Ok(socket) => return Ok(socket),
+                Err(_err) if attempt < self.reconnect_policy.get_max_attempts() => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(attempt, error = %_err, "Retrying connection");
+                    thread::sleep(self.reconnect_policy.get_delay(attempt));
+                    attempt += 1;
                 }
+                Err(err) => return Err(err),
             }
+        }
+    }
 
-            match Frame::decode(&frame_pdu) {
-                Err(Error::TooShortData) => {},
-                Ok(frame) => {
-                    if frame.get_unit_id() == expected_unit_id {
-                        return Ok(Vec::from(frame.get_pdu()));
-                    } else {
-                        return Err(Error::InvalidData);
+    fn open(&mut self, dst: &Dst) -> Result<(TcpStream, Option<SocketAddr>), Error> {
+        if self.persistent {
+            for addr in self.resolve(dst)? {
+                if let Some(conn) = self.connections.get_mut(&addr) {
+                    conn.unit_id = dst.unit_id;
+                    conn.last_used = Instant::now();
+                    return Ok((conn.socket.try_clone()?, Some(addr)));
+                }
+            }
+        }
+
+        let socket = self.connect_with_policy(dst)?;
+        let peer_addr = socket.peer_addr().ok();
+
+        if let (true, Some(addr)) = (self.persistent, peer_addr) {
+            let conn = Connection {socket: socket.try_clone()?, unit_id: dst.unit_id, last_used: Instant::now()};
+            self.connections.insert(addr, conn);
+        }
+        Ok((socket, peer_addr))
+    }
+
+    fn close(&mut self, stream: &Stream) {
+        if let Some(addr) = stream.peer_addr {
+            self.connections.remove(&addr);
+        }
+    }
+
+    fn is_connection_lost(err: &Error) -> bool {
+        match err {
+            Error::IoError(err) => matches!(err.kind(),
+                ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted |
+                ErrorKind::BrokenPipe | ErrorKind::UnexpectedEof | ErrorKind::NotConnected),
+            _ => false,
+        }
+    }
+
+    fn send_req(&mut self, dst: &Dst, pdu: &[u8]) -> Result<Stream, Error> {
+        let (socket, peer_addr) = self.open(dst)?;
+        let frame = Frame::new(dst.unit_id, pdu);
+        let mut stream = Stream {socket, peer_addr, unit_id: dst.unit_id, transaction_id: frame.get_transaction_id(), req_pdu: pdu.to_vec()};
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?peer_addr, transaction_id = stream.transaction_id, "Sending request");
+
+        match Self::write_frame(&mut stream.socket, &frame) {
+            Ok(()) => {
+                notify(&mut self.observer, Direction::Tx, &frame.encode()?);
+                Ok(stream)
+            }
+            Err(err) => {
+                self.close(&stream);
+                Err(err)
+            }
+        }
+    }
+
+    fn receive_rsp(&mut self, stream: &mut Stream, src: &Dst) -> Result<Vec<u8>, Error> {
+        let frame_data = self.read_socket_frame(&mut stream.socket)?;
+        notify(&mut self.observer, Direction::Rx, &frame_data);
+        let frame = Frame::decode(&frame_data)?;
+
+        self.check_rsp_frame(&frame, stream.transaction_id, src)?;
+        Ok(frame.get_pdu())
+    }
+
+    fn configure(&self, stream: &TcpStream) -> Result<(), Error> {
+        stream.set_read_timeout(self.read_timeout)?;
+        stream.set_nodelay(self.nodelay)?;
+        if let Some(idle_time) = self.keepalive {
+            SockRef::from(stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(idle_time))?;
+        }
+        Ok(())
+    }
+
+    fn listen(&self) -> Result<TcpListener, Error> {
+        let socket = Socket::new(Domain::IPV4, Type::STREAM, None)?;
+        #[cfg(not(windows))]
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddr::from(([127, 0, 0, 1], self.slave_port)).into())?;
+        socket.listen(self.listen_backlog.min(i32::MAX as u32) as i32)?;
+        Ok(socket.into())
+    }
+
+    fn is_connection_limit_reached(&self) -> bool {
+        self.max_connections.is_some_and(|max| {
+            self.slave_connections.iter().filter(|conn| conn.is_open()).count() >= max
+        })
+    }
+
+    fn accept(&self, listener: &TcpListener, deadline: Option<Instant>) -> Result<Option<TcpStream>, Error> {
+        if self.cancel_token.is_none() && deadline.is_none() && self.max_connections.is_none() {
+            return Ok(Some(listener.accept()?.0));
+        }
+
+        listener.set_nonblocking(true)?;
+        let result = self.poll_accept(listener, deadline);
+        listener.set_nonblocking(false)?;
+
+        result
+    }
+
+    fn poll_accept(&self, listener: &TcpListener, deadline: Option<Instant>) -> Result<Option<TcpStream>, Error> {
+        loop {
+            if self.cancel_token.as_ref().is_some_and(CancelToken::is_cancelled) {
+                return Err(Error::Cancelled);
+            }
+
+            let accepted = if self.limit_policy == LimitPolicy::Queue && self.is_connection_limit_reached() {
+                Err(ErrorKind::WouldBlock.into())
+            } else {
+                listener.accept()
+            };
+
+            match accepted {
+                Ok((socket, _addr)) if self.is_connection_limit_reached() => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(addr = ?_addr, "Rejecting connection over the limit");
+                    drop(socket);
+                }
+                Ok((socket, _addr)) => {
+                    socket.set_nonblocking(false)?;
+                    return Ok(Some(socket));
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    let remaining = deadline.map_or(POLL_INTERVAL, |deadline| deadline.saturating_duration_since(Instant::now()));
+                    if remaining.is_zero() {
+                        return Ok(None);
                     }
+                    thread::sleep(remaining.min(POLL_INTERVAL));
                 }
-                Err(err) => panic!("Unexpected parsing error: {:?}", err),
+                Err(err) => return Err(err.into()),
             }
         }
     }
 
-    fn write_pdu(stream: &mut TcpStream, pdu: &[u8], unit_id: u8) -> Result<(), Error> {
-        let frame = Frame::new(unit_id, pdu);
+    fn read_socket_frame(&self, socket: &mut TcpStream) -> Result<Vec<u8>, Error> {
+        let token = match &self.cancel_token {
+            Some(token) => token,
+            None => return Self::read_frame(socket),
+        };
+
+        let result = Self::read_frame(&mut CancellableSocket {socket, token, timeout: self.read_timeout});
+        socket.set_read_timeout(self.read_timeout)?;
+
+        match result {
+            Err(_) if token.is_cancelled() => Err(Error::Cancelled),
+            result => result,
+        }
+    }
+
+    pub(super) fn read_frame<S: Read>(stream: &mut S) -> Result<Vec<u8>, Error> {
+        let mut frame_data = vec![0; HEADER_LEN];
+        stream.read_exact(&mut frame_data)?;
+
+        let len = u16::from_be_bytes(frame_data[4..=5].try_into().unwrap()) as usize;
+        if len < 2 {
+            return Err(Error::InvalidDataLength);
+        }
+        check_size(len - 1)?;
+        frame_data.resize(HEADER_LEN + len - 1, 0);
+        stream.read_exact(&mut frame_data[HEADER_LEN..])?;
+
+        Ok(frame_data)
+    }
+
+    fn check_rsp_frame(&self, frame: &Frame, transaction_id: u16, src: &Dst) -> Result<(), Error> {
+        if self.strict_mbap && (frame.get_transaction_id() != transaction_id || !frame.is_modbus_protocol()) {
+            return Err(Error::TransactionMismatch);
+        }
+        if !src.matches_rsp_unit_id(frame.get_unit_id()) {
+            return Err(Error::InvalidData);
+        }
+        Ok(())
+    }
+
+    pub(super) fn write_frame(stream: &mut TcpStream, frame: &Frame) -> Result<(), Error> {
         stream.write_all(&frame.encode()?)?;
         Ok(())
     }
@@ -93,15 +1030,23 @@ impl Tcp {
 
 impl Transport for Tcp {
     type Dst = Dst;
-    type Stream = TcpStream;
+    type Stream = Stream;
 
     fn start_master(&mut self) -> Result<(), Error> {
         Ok(())
     }
 
     fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
-        self.unit_id = unit_id;
-        self.listener = Some(TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], TCP_PORT)))?);
+        self.start_slave_units(&[unit_id])
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        if unit_ids.is_empty() {
+            return Err(Error::InvalidValue);
+        }
+
+        self.unit_ids = unit_ids.to_vec();
+        self.listener = Some(self.listen()?);
         Ok(())
     }
 
@@ -109,51 +1054,519 @@ impl Transport for Tcp {
         dst.unit_id == BROADCAST_UNIT_ID
     }
 
-    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
-        let peer_addr = SocketAddr::from((dst.ip_addr, TCP_PORT));
-        let mut stream = Self::connect(&peer_addr)?;
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        stream.unit_id
+    }
+
+    fn get_peer_addr(stream: &Self::Stream) -> Option<SocketAddr> {
+        stream.peer_addr
+    }
+
+    fn get_ping_pdu(&self) -> &[u8] {
+        &self.idle_ping_pdu
+    }
 
-        Self::write_pdu(&mut stream, pdu, dst.unit_id)?;
-        Ok(stream)
+    fn write_req_pdu(&mut self, dst: &Self::Dst, pdu: &[u8]) -> Result<Self::Stream, Error> {
+        match self.send_req(dst, pdu) {
+            // A kept connection may have been closed by the slave while idle
+            Err(ref err) if self.persistent && Self::is_connection_lost(err) => self.send_req(dst, pdu),
+            result => result,
+        }
     }
 
     fn read_rsp_pdu(&mut self, stream: &mut Self::Stream, src: &self::Dst) -> Result<Vec<u8>, Error>
     {
-        // TODO: Timeout
-        Self::read_pdu(stream, src.unit_id)
-    }
+        let mut replays = 0;
 
-    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
-        if let Some(listener) = &self.listener {
-            let (mut socket, _addr) = listener.accept()?;
+        loop {
+            match self.receive_rsp(stream, src) {
+                Ok(pdu) => {
+                    if let Some(conn) = stream.peer_addr.and_then(|addr| self.connections.get_mut(&addr)) {
+                        conn.last_used = Instant::now();
+                    }
+                    return Ok(pdu);
+                }
+                Err(err) => {
+                    self.close(stream);
 
-            Ok((Self::read_pdu(&mut socket, self.unit_id)?, socket))
-        }
-        else {
-            Err(Error::InvalidValue)
+                    if !Self::is_connection_lost(&err) || !self.reconnect_policy.is_replay() ||
+                        replays >= self.reconnect_policy.get_max_attempts() {
+                        return Err(err);
+                    }
+
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(attempt = replays, error = %err, "Replaying request after lost connection");
+                    thread::sleep(self.reconnect_policy.get_delay(replays));
+                    replays += 1;
+                    let req_pdu = core::mem::take(&mut stream.req_pdu);
+                    *stream = self.send_req(src, &req_pdu)?;
+                }
+            }
         }
     }
 
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        self.read_req_until(None)?.ok_or(Error::NoResponse)
+    }
+
     fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
-        Self::write_pdu(stream, pdu, self.unit_id)
+        let frame = Frame::with_transaction_id(stream.transaction_id, stream.unit_id, pdu);
+        Self::write_frame(&mut stream.socket, &frame)?;
+        notify(&mut self.observer, Direction::Tx, &frame.encode()?);
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    //use crate::ReadCoilsResponse;
-    //use super::*;
-    //use std::net::{IpAddr, Ipv4Addr};
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn serve_rsp(socket: &mut TcpStream, rsp_pdu: &[u8]) {
+        let frame_data = Tcp::read_frame(socket).unwrap();
+        let frame = Frame::decode(&frame_data).unwrap();
+        let rsp = Frame::with_transaction_id(frame.get_transaction_id(), frame.get_unit_id(), rsp_pdu);
+        Tcp::write_frame(socket, &rsp).unwrap();
+    }
+
+    fn create_dst(unit_id: u8) -> Dst {
+        Dst::new(IpAddr::V4(Ipv4Addr::LOCALHOST), unit_id)
+    }
+
+    #[test]
+    fn test_builder_defaults() {
+        let tcp = Tcp::new();
+
+        assert_eq!(tcp.connect_timeout, CONNECT_TIMEOUT);
+        assert_eq!(tcp.read_timeout, Some(READ_TIMEOUT));
+        assert!(!tcp.nodelay);
+    }
+
+    #[test]
+    fn test_builder_options() {
+        let tcp = Tcp::builder()
+            .connect_timeout(Duration::from_millis(300))
+            .read_timeout(None)
+            .nodelay(true)
+            .build();
+
+        assert_eq!(tcp.connect_timeout, Duration::from_millis(300));
+        assert_eq!(tcp.read_timeout, None);
+        assert!(tcp.nodelay);
+    }
+
+    #[test]
+    fn test_read_frame() {
+        let data = [0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x0A, 0x03, 0x01, 0x00, 0xff, 0xff];
+        let frame_data = Tcp::read_frame(&mut &data[..]).unwrap();
+        assert_eq!(frame_data, data[..10].to_vec());
+    }
+
+    #[test]
+    fn test_read_truncated_frame() {
+        let data = [0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x0A, 0x03];
+        match Tcp::read_frame(&mut &data[..]).err().unwrap() {
+            Error::IoError(err) => assert_eq!(err.kind(), ErrorKind::UnexpectedEof),
+            err => panic!("Expected IoError, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_read_oversized_frame() {
+        let data = [0x00, 0x01, 0x00, 0x00, 0xff, 0xff, 0x0A, 0x03];
+        match Tcp::read_frame(&mut &data[..]).err().unwrap() {
+            Error::InvalidDataLength => {}
+            err => panic!("Expected InvalidDataLength, but got {:?}", err),
+        }
+    }
 
-    /*
     #[test]
-    fn test_tcp_listener() {
+    fn test_check_rsp_frame() {
+        let tcp = Tcp::new();
+        let frame_data = [0x15, 0x01, 0x00, 0x00, 0x00, 0x02, 0x0A, 0x07];
+        let frame = Frame::decode(&frame_data).unwrap();
+
+        assert!(tcp.check_rsp_frame(&frame, 0x1501, &create_dst(0x0A)).is_ok());
+
+        let err = tcp.check_rsp_frame(&frame, 0x1502, &create_dst(0x0A)).err().unwrap();
+        match err {
+            Error::TransactionMismatch => {}
+            _ => panic!("Expected TransactionMismatch, but got {:?}", err),
+        }
+
+        let err = tcp.check_rsp_frame(&frame, 0x1501, &create_dst(0x0B)).err().unwrap();
+        match err {
+            Error::InvalidData => {}
+            _ => panic!("Expected InvalidData, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_check_rsp_frame_other_protocol() {
+        let tcp = Tcp::new();
+        let frame_data = [0x15, 0x01, 0x00, 0x01, 0x00, 0x02, 0x0A, 0x07];
+        let frame = Frame::decode(&frame_data).unwrap();
+
+        let err = tcp.check_rsp_frame(&frame, 0x1501, &create_dst(0x0A)).err().unwrap();
+        match err {
+            Error::TransactionMismatch => {}
+            _ => panic!("Expected TransactionMismatch, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_check_rsp_frame_tolerant() {
+        let tcp = Tcp::builder().strict_mbap(false).build();
+        let frame_data = [0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x0A, 0x07];
+        let frame = Frame::decode(&frame_data).unwrap();
+
+        assert!(tcp.check_rsp_frame(&frame, 0x1501, &create_dst(0x0A)).is_ok());
+    }
+
+    #[test]
+    fn test_matches_rsp_unit_id() {
+        assert!(create_dst(0x0A).matches_rsp_unit_id(0x0A));
+        assert!(!create_dst(0x0A).matches_rsp_unit_id(DIRECT_UNIT_ID));
+        assert!(!create_dst(DIRECT_UNIT_ID).matches_rsp_unit_id(0x0A));
+
+        let dst = create_dst(0x0A).with_ignored_direct_unit_id(true);
+        assert!(dst.matches_rsp_unit_id(DIRECT_UNIT_ID));
+        assert!(!dst.matches_rsp_unit_id(0x0B));
+
+        let dst = Dst::direct(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(dst.unit_id, DIRECT_UNIT_ID);
+        assert!(dst.matches_rsp_unit_id(0x01));
+    }
+
+    #[test]
+    fn test_resolve_ip() {
+        let dst = create_dst(0x0A);
+        assert_eq!(dst.resolve().unwrap(), vec![SocketAddr::from(([127, 0, 0, 1], TCP_PORT))]);
+
+        let dst = create_dst(0x0A).with_port(5020);
+        assert_eq!(dst.resolve().unwrap(), vec![SocketAddr::from(([127, 0, 0, 1], 5020))]);
+    }
+
+    #[test]
+    fn test_resolve_addr() {
+        let dst = Dst::from_addr("[::1]:5020", 0x0A);
+        assert!(!dst.is_resolved());
+        assert_eq!(dst.resolve().unwrap(), vec!["[::1]:5020".parse().unwrap()]);
+
+        let dst = Dst::from_addr(("127.0.0.1", 5020), 0x0A).with_port(5021);
+        assert_eq!(Tcp::new().resolve(&dst).unwrap(), vec![SocketAddr::from(([127, 0, 0, 1], 5021))]);
+    }
+
+    #[test]
+    fn test_resolve_invalid_addr() {
+        let dst = Dst::from_addr("invalid address", 0x0A);
+
+        let err = Tcp::new().resolve(&dst).err().unwrap();
+        match err {
+            Error::IoError(_) => {}
+            _ => panic!("Expected IoError, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_reconnect_policy_delay() {
+        let policy = ReconnectPolicy::new(5).with_delay(Duration::from_millis(100), Duration::from_millis(300));
+
+        assert_eq!(policy.get_delay(0), Duration::from_millis(100));
+        assert_eq!(policy.get_delay(1), Duration::from_millis(200));
+        assert_eq!(policy.get_delay(2), Duration::from_millis(300));
+        assert_eq!(policy.get_delay(40), Duration::from_millis(300));
+        assert!(!policy.is_replay());
+    }
+
+    #[test]
+    fn test_persistent_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let slave = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            serve_rsp(&mut socket, &[0x06, 0x00, 0x01, 0x00, 0x02]);
+            serve_rsp(&mut socket, &[0x06, 0x00, 0x01, 0x00, 0x03]);
+        });
+
+        let mut tcp = Tcp::builder().persistent(true).build();
+        let dst = create_dst(0x0A).with_port(port);
+        for value in 2..=3 {
+            let mut stream = tcp.write_req_pdu(&dst, &[0x06, 0x00, 0x01, 0x00, value]).unwrap();
+            assert_eq!(tcp.read_rsp_pdu(&mut stream, &dst).unwrap(), vec![0x06, 0x00, 0x01, 0x00, value]);
+        }
+        slave.join().unwrap();
+    }
+
+    #[test]
+    fn test_replay_after_lost_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let slave = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            Tcp::read_frame(&mut socket).unwrap();
+            drop(socket);
+
+            let (mut socket, _) = listener.accept().unwrap();
+            serve_rsp(&mut socket, &[0x03, 0x02, 0x12, 0x34]);
+        });
+
+        let policy = ReconnectPolicy::new(1).with_delay(Duration::from_millis(10), Duration::from_millis(10)).with_replay(true);
+        let mut tcp = Tcp::builder().reconnect_policy(policy).build();
+        let dst = create_dst(0x0A).with_port(port);
+        let mut stream = tcp.write_req_pdu(&dst, &[0x03, 0x00, 0x04, 0x00, 0x01]).unwrap();
+        assert_eq!(tcp.read_rsp_pdu(&mut stream, &dst).unwrap(), vec![0x03, 0x02, 0x12, 0x34]);
+        slave.join().unwrap();
+    }
+
+    #[test]
+    fn test_is_connection_lost() {
+        assert!(Tcp::is_connection_lost(&IoError::from(ErrorKind::UnexpectedEof).into()));
+        assert!(Tcp::is_connection_lost(&IoError::from(ErrorKind::ConnectionReset).into()));
+        assert!(!Tcp::is_connection_lost(&IoError::from(ErrorKind::TimedOut).into()));
+        assert!(!Tcp::is_connection_lost(&Error::InvalidDataLength));
+    }
+
+    #[test]
+    fn test_no_replay_by_default() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let slave = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            Tcp::read_frame(&mut socket).unwrap();
+        });
+
         let mut tcp = Tcp::new();
-        let rsp = tcp.start_slave(10);
-        println!("{:?}", rsp);
-        let req = tcp.read_unsolicited_pdu()
-        println!("{:?}", req);
-        assert_eq!(true, false);
+        let dst = create_dst(0x0A).with_port(port);
+        let mut stream = tcp.write_req_pdu(&dst, &[0x03, 0x00, 0x04, 0x00, 0x01]).unwrap();
+        slave.join().unwrap();
+
+        assert!(Tcp::is_connection_lost(&tcp.read_rsp_pdu(&mut stream, &dst).err().unwrap()));
+    }
+
+    #[test]
+    fn test_keepalive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let dst = create_dst(0x0A).with_port(listener.local_addr().unwrap().port());
+
+        let socket = Tcp::new().connect(&dst).unwrap();
+        assert!(!SockRef::from(&socket).keepalive().unwrap());
+
+        let socket = Tcp::builder().keepalive(Some(Duration::from_secs(60))).build().connect(&dst).unwrap();
+        assert!(SockRef::from(&socket).keepalive().unwrap());
+    }
+
+    #[test]
+    fn test_ping_idle_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let slave = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            serve_rsp(&mut socket, &[0x06, 0x00, 0x01, 0x00, 0x02]);
+            serve_rsp(&mut socket, &ECHO_PDU);
+        });
+
+        let mut tcp = Tcp::builder().persistent(true).idle_ping(Some(Duration::from_millis(0))).build();
+        let dst = create_dst(0x0A).with_port(port);
+        let mut stream = tcp.write_req_pdu(&dst, &[0x06, 0x00, 0x01, 0x00, 0x02]).unwrap();
+        tcp.read_rsp_pdu(&mut stream, &dst).unwrap();
+
+        tcp.ping_idle_connections().unwrap();
+        assert_eq!(tcp.connections.len(), 1);
+        slave.join().unwrap();
+
+        assert!(tcp.ping_idle_connections().is_err());
+        assert!(tcp.connections.is_empty());
+    }
+
+    #[test]
+    fn test_observer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let slave = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            serve_rsp(&mut socket, &[0x07, 0x00]);
+        });
+
+        let frames = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let captured = frames.clone();
+        let mut tcp = Tcp::new();
+        tcp.set_observer(Some(Box::new(move |direction, _, frame_data| {
+            captured.lock().unwrap().push((direction, frame_data[6..].to_vec()));
+        })));
+
+        let dst = create_dst(0x0A).with_port(port);
+        let mut stream = tcp.write_req_pdu(&dst, &[0x07]).unwrap();
+        tcp.read_rsp_pdu(&mut stream, &dst).unwrap();
+        slave.join().unwrap();
+
+        assert_eq!(*frames.lock().unwrap(), vec![
+            (Direction::Tx, vec![0x0A, 0x07]),
+            (Direction::Rx, vec![0x0A, 0x07, 0x00]),
+        ]);
+    }
+
+    #[test]
+    fn test_cancel_read_rsp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let slave = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            Tcp::read_frame(&mut socket).unwrap();
+            thread::sleep(Duration::from_millis(500));
+        });
+
+        let token = CancelToken::new();
+        let mut tcp = Tcp::builder().read_timeout(None).build();
+        tcp.set_cancel_token(Some(token.clone()));
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        });
+
+        let dst = create_dst(0x0A).with_port(port);
+        let mut stream = tcp.write_req_pdu(&dst, &[0x03, 0x00, 0x04, 0x00, 0x01]).unwrap();
+        let err = tcp.read_rsp_pdu(&mut stream, &dst).err().unwrap();
+        canceller.join().unwrap();
+        slave.join().unwrap();
+
+        assert!(matches!(err, Error::Cancelled));
+    }
+
+    #[test]
+    fn test_cancel_read_req() {
+        let token = CancelToken::new();
+        let mut tcp = Tcp::new();
+        tcp.set_slave_port(0);
+        tcp.set_cancel_token(Some(token.clone()));
+        tcp.start_slave(0x0A).unwrap();
+
+        token.cancel();
+        assert!(matches!(tcp.read_req_pdu().err().unwrap(), Error::Cancelled));
+    }
+
+    #[test]
+    fn test_read_req_timeout() {
+        let mut tcp = Tcp::new();
+        tcp.set_slave_port(0);
+        tcp.start_slave(0x0A).unwrap();
+        assert!(tcp.read_req_timeout(Duration::from_millis(50)).unwrap().is_none());
+
+        let port = tcp.listener.as_ref().unwrap().local_addr().unwrap().port();
+        let master = thread::spawn(move || {
+            let mut socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            Tcp::write_frame(&mut socket, &Frame::new(0x0A, &[0x07])).unwrap();
+            Tcp::read_frame(&mut socket).unwrap()
+        });
+
+        let (req_pdu, mut stream) = tcp.read_req_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!(req_pdu, vec![0x07]);
+        tcp.write_rsp_pdu(&mut stream, &[0x07, 0x00]).unwrap();
+        assert_eq!(&master.join().unwrap()[6..], &[0x0A, 0x07, 0x00]);
+    }
+
+    #[test]
+    fn test_rate_limit_busy() {
+        let mut tcp = Tcp::builder().rate_limit(Some(RateLimit::new(1, Duration::from_secs(60)).unwrap())).build();
+        tcp.set_slave_port(0);
+        tcp.start_slave(0x0A).unwrap();
+        let port = tcp.listener.as_ref().unwrap().local_addr().unwrap().port();
+
+        let master = thread::spawn(move || {
+            let mut socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            (0..2).map(|_| {
+                Tcp::write_frame(&mut socket, &Frame::new(0x0A, &[0x07])).unwrap();
+                Tcp::read_frame(&mut socket).unwrap()[7..].to_vec()
+            }).collect::<Vec<_>>()
+        });
+
+        let (_, mut stream) = tcp.read_req_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        tcp.write_rsp_pdu(&mut stream, &[0x07, 0x00]).unwrap();
+        assert_eq!(master.join().unwrap(), vec![vec![0x07, 0x00], vec![0x87, crate::pdu::ExceptionCode::ServerDeviceBusy as u8]]);
+        assert!(tcp.read_req_timeout(Duration::from_millis(50)).unwrap().is_none());
+    }
+
+    fn send_req(port: u16) -> thread::JoinHandle<Option<Vec<u8>>> {
+        thread::spawn(move || {
+            let mut socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            Tcp::write_frame(&mut socket, &Frame::new(0x0A, &[0x07])).unwrap();
+            Tcp::read_frame(&mut socket).ok()
+        })
+    }
+
+    #[test]
+    fn test_connection_limit_reject() {
+        let mut tcp = Tcp::builder().max_connections(Some(1)).build();
+        tcp.set_slave_port(0);
+        tcp.start_slave(0x0A).unwrap();
+        let port = tcp.listener.as_ref().unwrap().local_addr().unwrap().port();
+
+        let first = send_req(port);
+        let (_, mut stream) = tcp.read_req_pdu().unwrap();
+
+        let rejected = send_req(port);
+        assert!(tcp.read_req_timeout(Duration::from_millis(200)).unwrap().is_none());
+        assert!(rejected.join().unwrap().is_none());
+
+        tcp.write_rsp_pdu(&mut stream, &[0x07, 0x00]).unwrap();
+        drop(stream);
+        assert!(first.join().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_connection_limit_queue() {
+        let mut tcp = Tcp::builder().max_connections(Some(1)).limit_policy(LimitPolicy::Queue).build();
+        tcp.set_slave_port(0);
+        tcp.start_slave(0x0A).unwrap();
+        let port = tcp.listener.as_ref().unwrap().local_addr().unwrap().port();
+
+        let first = send_req(port);
+        let (_, mut stream) = tcp.read_req_pdu().unwrap();
+
+        let queued = send_req(port);
+        assert!(tcp.read_req_timeout(Duration::from_millis(100)).unwrap().is_none());
+
+        tcp.write_rsp_pdu(&mut stream, &[0x07, 0x00]).unwrap();
+        drop(stream);
+        assert!(first.join().unwrap().is_some());
+
+        let (_, mut stream) = tcp.read_req_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        tcp.write_rsp_pdu(&mut stream, &[0x07, 0x00]).unwrap();
+        assert!(queued.join().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_multiple_transactions_per_connection() {
+        let mut tcp = Tcp::new();
+        tcp.set_slave_port(0);
+        tcp.start_slave(0x0A).unwrap();
+        let port = tcp.listener.as_ref().unwrap().local_addr().unwrap().port();
+
+        let master = thread::spawn(move || {
+            let mut socket = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            (0..3).map(|value| {
+                Tcp::write_frame(&mut socket, &Frame::new(0x0A, &[0x06, 0x00, 0x01, 0x00, value])).unwrap();
+                Tcp::read_frame(&mut socket).unwrap()[7..].to_vec()
+            }).collect::<Vec<_>>()
+        });
+
+        for _ in 0..3 {
+            let (req_pdu, mut stream) = tcp.read_req_pdu().unwrap();
+            tcp.write_rsp_pdu(&mut stream, &req_pdu).unwrap();
+        }
+        assert_eq!(tcp.slave_connections.len(), 1);
+
+        let rsp_pdus = master.join().unwrap();
+        assert_eq!(rsp_pdus, (0..3).map(|value| vec![0x06, 0x00, 0x01, 0x00, value]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_cancellable_read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let token = CancelToken::new();
+
+        let err = Tcp::read_frame(&mut CancellableSocket {socket: &socket, token: &token, timeout: Some(Duration::from_millis(50))}).err().unwrap();
+        assert!(matches!(err, Error::IoError(ref err) if err.kind() == ErrorKind::TimedOut));
     }
-    */
 }
@@ -1,8 +1,10 @@
 use crate::error::Error;
+use crate::pdu::check_size;
 use std::convert::TryInto;
 use std::sync::atomic::{AtomicU16, Ordering};
 
 const MODBUS_ID: u16 = 0;
+pub const HEADER_LEN: usize = 7;
 static TRANSACTION_ID: AtomicU16 = AtomicU16::new(0);
 
 fn get_transaction_id() -> u16 {
@@ -11,13 +13,26 @@ fn get_transaction_id() -> u16 {
 
 pub struct Frame<'a> {
     transaction_id: u16,
+    protocol_id: u16,
     unit_id: u8,
     pdu: &'a [u8],
 }
 
 impl<'a> Frame<'a> {
     pub fn new(unit_id: u8, pdu: &'a [u8]) -> Self {
-        Self{transaction_id: get_transaction_id(), unit_id, pdu}
+        Self::with_transaction_id(get_transaction_id(), unit_id, pdu)
+    }
+
+    pub fn with_transaction_id(transaction_id: u16, unit_id: u8, pdu: &'a [u8]) -> Self {
+        Self{transaction_id, protocol_id: MODBUS_ID, unit_id, pdu}
+    }
+
+    pub fn get_transaction_id(&self) -> u16 {
+        self.transaction_id
+    }
+
+    pub fn is_modbus_protocol(&self) -> bool {
+        self.protocol_id == MODBUS_ID
     }
 
     pub fn get_unit_id(&self) -> u8 {
@@ -31,7 +46,7 @@ impl<'a> Frame<'a> {
     pub fn encode(&self) -> Result<Vec<u8>, Error> {
         let mut result = Vec::new();
         result.append(&mut self.transaction_id.to_be_bytes().to_vec());
-        result.append(&mut MODBUS_ID.to_be_bytes().to_vec());
+        result.append(&mut self.protocol_id.to_be_bytes().to_vec());
         result.append(&mut ((self.pdu.len() + 1) as u16).to_be_bytes().to_vec());
         result.push(self.unit_id);
         result.append(&mut self.pdu.to_vec());
@@ -41,14 +56,11 @@ impl<'a> Frame<'a> {
 
     pub fn decode(data: &'a [u8]) -> Result<Self, Error> {
         let len = data.len();
-        if len < 8 {
+        if len <= HEADER_LEN {
             return Err(Error::TooShortData);
         }
-        if u16::from_be_bytes(data[2..=3].try_into().unwrap()) != MODBUS_ID {
-            return Err(Error::InvalidData);
-        }
-
-        let expected_len = (u16::from_be_bytes(data[4..=5].try_into().unwrap()) + 6) as usize;
+        let expected_len = u16::from_be_bytes(data[4..=5].try_into().unwrap()) as usize + 6;
+        check_size(expected_len.saturating_sub(HEADER_LEN))?;
         if len < expected_len {
             return Err(Error::TooShortData);
         }
@@ -57,8 +69,9 @@ impl<'a> Frame<'a> {
         }
 
         Ok(Self{transaction_id: u16::from_be_bytes(data[0..=1].try_into().unwrap()), 
+                protocol_id: u16::from_be_bytes(data[2..=3].try_into().unwrap()),
                 unit_id: data[6],
-                pdu: &data[7..]})
+                pdu: &data[HEADER_LEN..]})
     }
 }
 
@@ -81,7 +94,33 @@ mod tests {
         let frame = Frame::decode(&frame_data).unwrap();
 
         assert_eq!(frame.transaction_id, 0x1501);
+        assert!(frame.is_modbus_protocol());
         assert_eq!(frame.unit_id, 0xFF);
         assert_eq!(frame.pdu, &frame_data[7..]);
     }
+
+    #[test]
+    fn test_decode_other_protocol() {
+        let frame_data = vec![0x15, 0x01, 0x00, 0x01, 0x00, 0x02, 0xFF, 0x07];
+        let frame = Frame::decode(&frame_data).unwrap();
+
+        assert!(!frame.is_modbus_protocol());
+    }
+
+    #[test]
+    fn test_decode_too_long() {
+        let frame_data = [0x15, 0x01, 0x00, 0x00, 0x00, 0xFF, 0x0A, 0x03];
+        let err = Frame::decode(&frame_data).err().unwrap();
+
+        match err {
+            Error::InvalidDataLength => {}
+            _ => panic!("Expected InvalidDataLength, but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_encode_with_transaction_id() {
+        let frame = Frame::with_transaction_id(0xABCD, 0x0A, &[0x07]).encode().unwrap();
+        assert_eq!(frame, vec![0xAB, 0xCD, 0x00, 0x00, 0x00, 0x02, 0x0A, 0x07]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,181 @@
+//! Limits of the rate of requests read by a Modbus TCP slave
+//!
+//! Limits are token buckets: a bucket holds up to as many requests as allowed in a period and
+//! is refilled evenly over that period, so short bursts are served while the long-term rate is
+//! kept. They are set per connection with [TcpBuilder::rate_limit](super::conn::TcpBuilder::rate_limit)
+//! and for all connections together with
+//! [TcpBuilder::global_rate_limit](super::conn::TcpBuilder::global_rate_limit).
+
+use crate::error::Error;
+use crate::pdu::{encode_exc_rsp, ExceptionCode};
+use std::io::Write;
+use std::net::{Shutdown, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+use super::frame::Frame;
+
+/// Handling of requests over a [RateLimit]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RateLimitAction {
+    /// Serve the request once the rate allows it
+    ///
+    /// A connection over its own limit is not read in the meantime. A request over the global
+    /// limit delays reading requests from all connections.
+    Delay,
+    /// Answer the request with [ExceptionCode::ServerDeviceBusy]
+    #[default]
+    Busy,
+    /// Close the connection the request was received from without answering it
+    Disconnect,
+}
+
+/// Maximal rate of requests read by a Modbus TCP slave
+///
+/// # Examples
+/// ```no_run
+/// use modbus::Transport;
+/// use modbus::tcp::{RateLimit, RateLimitAction};
+/// use std::time::Duration;
+///
+/// let mut mb = modbus::tcp::Tcp::builder()
+///     .rate_limit(Some(RateLimit::new(10, Duration::from_secs(1)).unwrap().with_action(RateLimitAction::Disconnect)))
+///     .global_rate_limit(Some(RateLimit::new(100, Duration::from_secs(1)).unwrap()))
+///     .build();
+/// mb.start_slave(10).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    requests: u32,
+    period: Duration,
+    action: RateLimitAction,
+}
+
+impl RateLimit {
+    /// Allow given number of requests in each period, answering the other ones with [RateLimitAction::Busy]
+    ///
+    /// Returns [Error::InvalidValue] if no request or a zero period is given.
+    pub fn new(requests: u32, period: Duration) -> Result<Self, Error> {
+        if requests == 0 || period.is_zero() {
+            return Err(Error::InvalidValue);
+        }
+
+        Ok(Self {requests, period, action: RateLimitAction::default()})
+    }
+
+    /// Set handling of requests over the limit
+    pub fn with_action(mut self, action: RateLimitAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Get handling of requests over the limit
+    pub fn get_action(&self) -> RateLimitAction {
+        self.action
+    }
+
+    fn get_rate(&self) -> f64 {
+        self.requests as f64 / self.period.as_secs_f64()
+    }
+}
+
+/// Requests allowed by a [RateLimit] at the moment
+#[derive(Debug)]
+pub(super) struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub(super) fn new(limit: RateLimit) -> Self {
+        Self {limit, tokens: limit.requests as f64, last_refill: Instant::now()}
+    }
+
+    /// Take a token for a request, getting how long the request is over the limit if none is available
+    ///
+    /// With [RateLimitAction::Delay] the token is taken in advance, so that delayed requests are served in turn.
+    fn take(&mut self, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.get_rate()).min(self.limit.requests as f64);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return None;
+        }
+
+        let wait = Duration::from_secs_f64((1.0 - self.tokens) / self.limit.get_rate());
+        if self.limit.action == RateLimitAction::Delay {
+            self.tokens -= 1.0;
+        }
+        Some(wait)
+    }
+
+    /// Apply the limit to a request frame received from given socket, checking if it shall be served
+    pub(super) fn admit(&mut self, frame_data: &[u8], socket: &TcpStream) -> bool {
+        let wait = match self.take(Instant::now()) {
+            Some(wait) => wait,
+            None => return true,
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(peer = ?socket.peer_addr().ok(), action = ?self.limit.action, "Request over the rate limit");
+        match self.limit.action {
+            RateLimitAction::Delay => {
+                thread::sleep(wait);
+                true
+            }
+            RateLimitAction::Busy => {
+                // A master which cannot be answered is going to be disconnected by the failing writes
+                let _ = write_busy_rsp(socket, frame_data);
+                false
+            }
+            RateLimitAction::Disconnect => {
+                let _ = socket.shutdown(Shutdown::Both);
+                false
+            }
+        }
+    }
+}
+
+fn write_busy_rsp(mut socket: &TcpStream, frame_data: &[u8]) -> Result<(), Error> {
+    let frame = Frame::decode(frame_data)?;
+    let function_code = *frame.get_pdu().first().ok_or(Error::InvalidDataLength)?;
+    let rsp_pdu = encode_exc_rsp(function_code, ExceptionCode::ServerDeviceBusy);
+    socket.write_all(&Frame::with_transaction_id(frame.get_transaction_id(), frame.get_unit_id(), &rsp_pdu).encode()?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_limit() {
+        assert!(matches!(RateLimit::new(0, Duration::from_secs(1)), Err(Error::InvalidValue)));
+        assert!(matches!(RateLimit::new(10, Duration::ZERO), Err(Error::InvalidValue)));
+    }
+
+    #[test]
+    fn test_burst_and_refill() {
+        let mut bucket = TokenBucket::new(RateLimit::new(2, Duration::from_secs(1)).unwrap());
+        let start = bucket.last_refill;
+
+        assert_eq!(bucket.take(start), None);
+        assert_eq!(bucket.take(start), None);
+        assert_eq!(bucket.take(start), Some(Duration::from_millis(500)));
+        assert_eq!(bucket.take(start + Duration::from_millis(250)), Some(Duration::from_millis(250)));
+        assert_eq!(bucket.take(start + Duration::from_millis(500)), None);
+    }
+
+    #[test]
+    fn test_delay_reserves_tokens() {
+        let limit = RateLimit::new(1, Duration::from_secs(1)).unwrap().with_action(RateLimitAction::Delay);
+        let mut bucket = TokenBucket::new(limit);
+        let start = bucket.last_refill;
+
+        assert_eq!(bucket.take(start), None);
+        assert_eq!(bucket.take(start), Some(Duration::from_secs(1)));
+        assert_eq!(bucket.take(start), Some(Duration::from_secs(2)));
+    }
+}
@@ -0,0 +1,501 @@
+//! Modbus over TCP/IP served from a single-threaded event loop
+//!
+//! This module is available with the `mio` feature.
+//!
+//! [PolledTcp] is a slave-only transport multiplexing all the connections accepted from masters
+//! in the thread reading requests, without spawning a thread per connection or running an
+//! asynchronous runtime. It suits constrained gateways serving hundreds of clients and plugs into
+//! the [Server](crate::server::Server) like any other transport.
+//!
+//! A connection is read only while fewer than [MAX_QUEUED_FRAMES] of its requests wait to be
+//! served. A master sending requests faster than they are served is slowed down by the TCP flow
+//! control instead of growing the buffers of the slave.
+//!
+//! # Examples
+//! ```no_run
+//! use modbus::mio_tcp::PolledTcp;
+//! use modbus::server::{DataStore, Server};
+//!
+//! let mut tcp = PolledTcp::new();
+//! tcp.set_max_connections(Some(500));
+//!
+//! let store = DataStore::new().with_hld_reg(0x0000..=0x00ff);
+//! let mut server = Server::new(tcp, store);
+//! server.start(10).unwrap();
+//! server.serve_forever().unwrap();
+//! ```
+
+use crate::error::Error;
+use crate::pdu::check_size;
+use mio::net::{TcpListener, TcpStream};
+use mio::{Events, Interest, Poll, Token};
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryInto;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use super::conn::TCP_PORT;
+use super::frame::{Frame, HEADER_LEN};
+use super::super::Transport;
+
+const LISTENER: Token = Token(0);
+const EVENTS_CAPACITY: usize = 256;
+const READ_CHUNK_LEN: usize = 1024;
+
+/// Maximal number of requests read from a single connection and waiting to be served
+pub const MAX_QUEUED_FRAMES: usize = 4;
+
+/// Connection used to respond to a request read by the [PolledTcp] transport
+pub struct Stream {
+    token: Token,
+    peer_addr: SocketAddr,
+    unit_id: u8,
+    transaction_id: u16,
+}
+
+/// Connection accepted from a master, with data not yet parsed or not yet written
+struct Connection {
+    socket: TcpStream,
+    peer_addr: SocketAddr,
+    rx: Vec<u8>,
+    tx: Vec<u8>,
+    queued: usize,
+}
+
+impl Connection {
+    /// Read data available in the socket and queue complete frames, returning `false` if the master closed the connection
+    ///
+    /// Reading stops when [MAX_QUEUED_FRAMES] frames of this connection are queued, leaving
+    /// the remaining data in the socket.
+    fn read(&mut self, token: Token, frames: &mut VecDeque<(Token, Vec<u8>)>) -> Result<bool, Error> {
+        let mut buf = [0; READ_CHUNK_LEN];
+
+        while self.queued < MAX_QUEUED_FRAMES {
+            if let Some(frame_data) = take_frame(&mut self.rx)? {
+                frames.push_back((token, frame_data));
+                self.queued += 1;
+                continue;
+            }
+
+            match self.socket.read(&mut buf) {
+                Ok(0) => return Ok(false),
+                Ok(num_bytes) => self.rx.extend_from_slice(&buf[..num_bytes]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Write as much of the pending data as the socket accepts
+    fn flush(&mut self) -> Result<(), Error> {
+        while !self.tx.is_empty() {
+            match self.socket.write(&self.tx) {
+                Ok(0) => return Err(std::io::Error::from(ErrorKind::WriteZero).into()),
+                Ok(num_bytes) => { self.tx.drain(..num_bytes); }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn interest(&self) -> Interest {
+        if self.tx.is_empty() { Interest::READABLE } else { Interest::READABLE | Interest::WRITABLE }
+    }
+}
+
+/// Remove a complete frame from the beginning of given buffer, if it was already received
+fn take_frame(rx: &mut Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+    if rx.len() < HEADER_LEN {
+        return Ok(None);
+    }
+
+    let len = u16::from_be_bytes(rx[4..=5].try_into().unwrap()) as usize;
+    if len < 2 {
+        return Err(Error::InvalidDataLength);
+    }
+    check_size(len - 1)?;
+
+    let frame_len = HEADER_LEN + len - 1;
+    if rx.len() < frame_len {
+        return Ok(None);
+    }
+    Ok(Some(rx.drain(..frame_len).collect()))
+}
+
+/// TCP/IP slave transport serving all connections from a single-threaded event loop
+///
+/// This structure implements [Transport trait](Transport) only in the slave mode. Starting the
+/// master mode fails with [Error::InvalidValue].
+pub struct PolledTcp {
+    poll: Option<Poll>,
+    events: Events,
+    listener: Option<TcpListener>,
+    slave_addr: IpAddr,
+    slave_port: u16,
+    unit_ids: Vec<u8>,
+
+    max_connections: Option<usize>,
+    connections: HashMap<Token, Connection>,
+    next_token: usize,
+    frames: VecDeque<(Token, Vec<u8>)>,
+}
+
+impl Default for PolledTcp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PolledTcp {
+    /// Create a new instance of the polled Modbus transport
+    ///
+    /// # Examples
+    /// ```
+    /// let modbus = modbus::mio_tcp::PolledTcp::new();
+    /// ```
+    pub fn new() -> Self {
+        Self {poll: None, events: Events::with_capacity(EVENTS_CAPACITY), listener: None,
+              slave_addr: IpAddr::V4(Ipv4Addr::LOCALHOST), slave_port: TCP_PORT,
+              unit_ids: Vec::new(), max_connections: None, connections: HashMap::new(), next_token: LISTENER.0 + 1,
+              frames: VecDeque::new()}
+    }
+
+    /// Set IP address the slave listens on instead of the default loopback address 127.0.0.1
+    ///
+    /// Use the unspecified address `0.0.0.0` or `::` to accept masters on all interfaces.
+    /// This method shall be called before the slave mode is started.
+    pub fn set_slave_addr(&mut self, addr: IpAddr) {
+        self.slave_addr = addr;
+    }
+
+    /// Set TCP port the slave listens on instead of the default Modbus port 502
+    ///
+    /// This method shall be called before the slave mode is started.
+    pub fn set_slave_port(&mut self, port: u16) {
+        self.slave_port = port;
+    }
+
+    /// Set maximal number of connections kept open at once, `None` for no limit (default)
+    ///
+    /// Connections over the limit are accepted and immediately closed.
+    pub fn set_max_connections(&mut self, max_connections: Option<usize>) {
+        self.max_connections = max_connections;
+    }
+
+    /// Get number of connections currently open
+    pub fn get_connection_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Get address the slave listens on, `None` before the slave mode is started
+    pub fn get_local_addr(&self) -> Option<SocketAddr> {
+        self.listener.as_ref().and_then(|listener| listener.local_addr().ok())
+    }
+
+    /// Read PDU of a request frame, waiting for it at most given time
+    ///
+    /// It works like [Transport::read_req_pdu], but returns `None` if no request arrived
+    /// before the timeout expired.
+    pub fn read_req_timeout(&mut self, timeout: Duration) -> Result<Option<(Vec<u8>, Stream)>, Error> {
+        self.read_req_until(Some(Instant::now() + timeout))
+    }
+
+    fn read_req_until(&mut self, deadline: Option<Instant>) -> Result<Option<(Vec<u8>, Stream)>, Error> {
+        if self.listener.is_none() {
+            return Err(Error::InvalidValue);
+        }
+
+        loop {
+            if let Some((token, frame_data)) = self.frames.pop_front() {
+                let req = self.accept_req_frame(token, &frame_data);
+                self.dequeued(token);
+                return req.map(Some);
+            }
+
+            let timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if timeout.is_some_and(|timeout| timeout.is_zero()) {
+                return Ok(None);
+            }
+            self.poll_events(timeout)?;
+        }
+    }
+
+    fn poll_events(&mut self, timeout: Option<Duration>) -> Result<(), Error> {
+        let poll = self.poll.as_mut().ok_or(Error::InvalidValue)?;
+        match poll.poll(&mut self.events, timeout) {
+            Err(err) if err.kind() == ErrorKind::Interrupted => return Ok(()),
+            result => result?,
+        }
+
+        let ready: Vec<(Token, bool, bool)> = self.events.iter()
+            .map(|event| (event.token(), event.is_readable() || event.is_read_closed(), event.is_writable()))
+            .collect();
+        for (token, readable, writable) in ready {
+            if token == LISTENER {
+                self.accept_connections()?;
+            } else if let Err(_err) = self.handle_connection(token, readable, writable) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(error = %_err, "Closing connection");
+                self.close(token);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn accept_connections(&mut self) -> Result<(), Error> {
+        loop {
+            let (mut socket, peer_addr) = match self.listener.as_ref().ok_or(Error::InvalidValue)?.accept() {
+                Ok(accepted) => accepted,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            if self.max_connections.is_some_and(|max| self.connections.len() >= max) {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(addr = ?peer_addr, "Rejecting connection over the limit");
+                continue;
+            }
+
+            let token = Token(self.next_token);
+            self.next_token = self.next_token.wrapping_add(1).max(LISTENER.0 + 1);
+            self.poll.as_ref().ok_or(Error::InvalidValue)?.registry().register(&mut socket, token, Interest::READABLE)?;
+            self.connections.insert(token, Connection {socket, peer_addr, rx: Vec::new(), tx: Vec::new(), queued: 0});
+        }
+    }
+
+    fn handle_connection(&mut self, token: Token, readable: bool, writable: bool) -> Result<(), Error> {
+        let conn = match self.connections.get_mut(&token) {
+            Some(conn) => conn,
+            None => return Ok(()),
+        };
+
+        if writable {
+            conn.flush()?;
+        }
+        if readable && !conn.read(token, &mut self.frames)? {
+            return Err(std::io::Error::from(ErrorKind::ConnectionAborted).into());
+        }
+
+        self.update_interest(token)
+    }
+
+    /// Account a frame taken from the queue, resuming reading of a connection which filled the queue
+    ///
+    /// The socket is edge-triggered, so data left in it is not signaled again and must be read now.
+    fn dequeued(&mut self, token: Token) {
+        let resume = match self.connections.get_mut(&token) {
+            Some(conn) => {
+                conn.queued -= 1;
+                conn.queued == MAX_QUEUED_FRAMES - 1
+            }
+            None => false,
+        };
+
+        if resume {
+            if let Err(_err) = self.handle_connection(token, true, false) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(error = %_err, "Closing connection");
+                self.close(token);
+            }
+        }
+    }
+
+    fn update_interest(&mut self, token: Token) -> Result<(), Error> {
+        if let (Some(poll), Some(conn)) = (&self.poll, self.connections.get_mut(&token)) {
+            let interest = conn.interest();
+            poll.registry().reregister(&mut conn.socket, token, interest)?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, token: Token) {
+        if let Some(mut conn) = self.connections.remove(&token) {
+            if let Some(poll) = &self.poll {
+                let _ = poll.registry().deregister(&mut conn.socket);
+            }
+        }
+    }
+
+    fn accept_req_frame(&mut self, token: Token, frame_data: &[u8]) -> Result<(Vec<u8>, Stream), Error> {
+        let frame = Frame::decode(frame_data)?;
+        let peer_addr = self.connections.get(&token).ok_or_else(|| std::io::Error::from(ErrorKind::NotConnected))?.peer_addr;
+
+        if !frame.is_modbus_protocol() || !self.unit_ids.contains(&frame.get_unit_id()) {
+            return Err(Error::InvalidData);
+        }
+
+        let stream = Stream {token, peer_addr, unit_id: frame.get_unit_id(), transaction_id: frame.get_transaction_id()};
+        Ok((frame.get_pdu(), stream))
+    }
+}
+
+impl Transport for PolledTcp {
+    type Dst = u8;
+    type Stream = Stream;
+
+    fn start_master(&mut self) -> Result<(), Error> {
+        Err(Error::InvalidValue)
+    }
+
+    fn start_slave(&mut self, unit_id: u8) -> Result<(), Error> {
+        self.start_slave_units(&[unit_id])
+    }
+
+    fn start_slave_units(&mut self, unit_ids: &[u8]) -> Result<(), Error> {
+        if unit_ids.is_empty() {
+            return Err(Error::InvalidValue);
+        }
+
+        let poll = Poll::new()?;
+        let mut listener = TcpListener::bind(SocketAddr::new(self.slave_addr, self.slave_port))?;
+        poll.registry().register(&mut listener, LISTENER, Interest::READABLE)?;
+
+        self.unit_ids = unit_ids.to_vec();
+        self.connections.clear();
+        self.frames.clear();
+        self.listener = Some(listener);
+        self.poll = Some(poll);
+        Ok(())
+    }
+
+    fn is_broadcast(dst: &Self::Dst) -> bool {
+        *dst == super::conn::BROADCAST_UNIT_ID
+    }
+
+    fn get_unit_id(stream: &Self::Stream) -> u8 {
+        stream.unit_id
+    }
+
+    fn get_peer_addr(stream: &Self::Stream) -> Option<SocketAddr> {
+        Some(stream.peer_addr)
+    }
+
+    fn write_req_pdu(&mut self, _dst: &Self::Dst, _pdu: &[u8]) -> Result<Self::Stream, Error> {
+        Err(Error::InvalidValue)
+    }
+
+    fn read_rsp_pdu(&mut self, _stream: &mut Self::Stream, _src: &Self::Dst) -> Result<Vec<u8>, Error> {
+        Err(Error::InvalidValue)
+    }
+
+    fn read_req_pdu(&mut self) -> Result<(Vec<u8>, Self::Stream), Error> {
+        self.read_req_until(None)?.ok_or(Error::NoResponse)
+    }
+
+    fn write_rsp_pdu(&mut self, stream: &mut Self::Stream, pdu: &[u8]) -> Result<(), Error> {
+        let frame = Frame::with_transaction_id(stream.transaction_id, stream.unit_id, pdu);
+        let conn = self.connections.get_mut(&stream.token).ok_or_else(|| std::io::Error::from(ErrorKind::NotConnected))?;
+        conn.tx.extend_from_slice(&frame.encode()?);
+
+        // Data the socket does not accept now is written by the event loop when it becomes writable
+
+        match conn.flush() {
+            Ok(()) => self.update_interest(stream.token),
+            Err(err) => {
+                self.close(stream.token);
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::conn::Tcp;
+    use std::thread;
+
+    #[test]
+    fn test_take_frame() {
+        let mut rx = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x0A, 0x07, 0x00, 0x02, 0x00];
+        assert_eq!(take_frame(&mut rx).unwrap(), Some(vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x0A, 0x07]));
+        assert_eq!(take_frame(&mut rx).unwrap(), None);
+        assert_eq!(rx, vec![0x00, 0x02, 0x00]);
+    }
+
+    #[test]
+    fn test_take_frame_invalid_length() {
+        let mut rx = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x0A];
+        assert!(matches!(take_frame(&mut rx), Err(Error::InvalidDataLength)));
+    }
+
+    #[test]
+    fn test_multiple_connections() {
+        let mut tcp = PolledTcp::new();
+        tcp.set_slave_port(0);
+        tcp.start_slave(0x0A).unwrap();
+        let port = tcp.get_local_addr().unwrap().port();
+
+        let masters: Vec<_> = (0..8).map(|value| thread::spawn(move || {
+            let mut socket = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+            Tcp::write_frame(&mut socket, &Frame::new(0x0A, &[0x06, 0x00, 0x01, 0x00, value])).unwrap();
+            Tcp::read_frame(&mut socket).unwrap()[7..].to_vec()
+        })).collect();
+
+        for _ in 0..masters.len() {
+            let (req_pdu, mut stream) = tcp.read_req_timeout(Duration::from_secs(5)).unwrap().unwrap();
+            tcp.write_rsp_pdu(&mut stream, &req_pdu).unwrap();
+        }
+
+        for (value, master) in (0..8).zip(masters) {
+            assert_eq!(master.join().unwrap(), vec![0x06, 0x00, 0x01, 0x00, value]);
+        }
+    }
+
+    #[test]
+    fn test_queued_frames_limit() {
+        let mut tcp = PolledTcp::new();
+        tcp.set_slave_port(0);
+        tcp.start_slave(0x0A).unwrap();
+        let port = tcp.get_local_addr().unwrap().port();
+
+        let mut socket = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let reqs: Vec<u8> = (0..10).flat_map(|value| Frame::new(0x0A, &[0x06, 0x00, 0x01, 0x00, value]).encode().unwrap()).collect();
+        socket.write_all(&reqs).unwrap();
+
+        for value in 0..10 {
+            let (req_pdu, mut stream) = tcp.read_req_timeout(Duration::from_secs(5)).unwrap().unwrap();
+            assert!(tcp.frames.len() <= MAX_QUEUED_FRAMES);
+            assert_eq!(req_pdu, vec![0x06, 0x00, 0x01, 0x00, value]);
+            tcp.write_rsp_pdu(&mut stream, &req_pdu).unwrap();
+        }
+        for value in 0..10 {
+            assert_eq!(Tcp::read_frame(&mut socket).unwrap()[7..].to_vec(), vec![0x06, 0x00, 0x01, 0x00, value]);
+        }
+    }
+
+    #[test]
+    fn test_slave_addr() {
+        let mut tcp = PolledTcp::new();
+        tcp.set_slave_addr(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        tcp.set_slave_port(0);
+        tcp.start_slave(0x0A).unwrap();
+
+        assert_eq!(tcp.get_local_addr().unwrap().ip(), IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    }
+
+    #[test]
+    fn test_connection_limit() {
+        let mut tcp = PolledTcp::new();
+        tcp.set_slave_port(0);
+        tcp.set_max_connections(Some(1));
+        tcp.start_slave(0x0A).unwrap();
+        let port = tcp.get_local_addr().unwrap().port();
+
+        let _first = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        assert!(tcp.read_req_timeout(Duration::from_millis(100)).unwrap().is_none());
+        let mut rejected = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        assert!(tcp.read_req_timeout(Duration::from_millis(100)).unwrap().is_none());
+
+        assert_eq!(tcp.get_connection_count(), 1);
+        assert!(Tcp::read_frame(&mut rejected).is_err());
+    }
+}
@@ -1,2 +1,12 @@
 pub mod conn;
-mod frame;
\ No newline at end of file
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+mod async_base;
+#[cfg(feature = "tokio")]
+pub mod async_conn;
+#[cfg(feature = "async-std")]
+pub mod async_std_conn;
+#[cfg(feature = "mio")]
+pub mod mio_conn;
+pub(crate) mod frame;
+mod limit;
+mod split;
\ No newline at end of file
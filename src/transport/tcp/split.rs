@@ -0,0 +1,316 @@
+//! Full-duplex use of a persistent Modbus TCP connection
+//!
+//! [split] divides a connection into a [SendHalf] writing requests and a [RecvHalf] reading
+//! responses. Each request gets a unique transaction id registered in a correlator shared by
+//! both halves, so the receiving half delivers every response to the [Pending] transaction
+//! waiting for it, regardless of the order the responses arrive in. This lets a dedicated reader
+//! thread run while any number of senders keep multiple requests in flight.
+
+use crate::error::Error;
+use crate::pdu::{DecodeMode, Request, Response};
+use std::collections::HashMap;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use super::conn::Tcp;
+use super::frame::Frame;
+
+type Reply = mpsc::Sender<Result<Vec<u8>, Error>>;
+type Decode<R> = Box<dyn FnOnce(Vec<u8>) -> Result<R, Error> + Send>;
+
+#[derive(Default)]
+struct State {
+    next_transaction_id: u16,
+    pending: HashMap<u16, (u8, Reply)>,
+    closed: bool,
+}
+
+/// Map of transactions in flight to the receivers of their responses
+#[derive(Default)]
+struct Correlator {
+    state: Mutex<State>,
+}
+
+impl Correlator {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Reserve a transaction id unused by other transactions in flight
+    fn register(&self, unit_id: u8, reply: Reply) -> Result<u16, Error> {
+        let mut state = self.lock();
+        if state.closed {
+            return Err(Error::NoResponse);
+        }
+        if state.pending.len() > u16::MAX as usize {
+            return Err(Error::TransactionMismatch);
+        }
+
+        let mut transaction_id = state.next_transaction_id;
+        while state.pending.contains_key(&transaction_id) {
+            transaction_id = transaction_id.wrapping_add(1);
+        }
+        state.next_transaction_id = transaction_id.wrapping_add(1);
+        state.pending.insert(transaction_id, (unit_id, reply));
+        Ok(transaction_id)
+    }
+
+    fn remove(&self, transaction_id: u16) {
+        self.lock().pending.remove(&transaction_id);
+    }
+
+    /// Deliver a received frame, ignoring frames not matching any transaction in flight
+    fn deliver(&self, frame: &Frame) {
+        if !frame.is_modbus_protocol() {
+            return;
+        }
+
+        if let Some((unit_id, reply)) = self.lock().pending.remove(&frame.get_transaction_id()) {
+            let rsp = if frame.get_unit_id() == unit_id { Ok(frame.get_pdu()) } else { Err(Error::InvalidData) };
+            // The transaction may have been abandoned in the meantime
+            let _ = reply.send(rsp);
+        }
+    }
+
+    /// Fail all transactions in flight and reject new ones
+    fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        state.pending.clear();
+    }
+}
+
+/// Split a connection to a Modbus TCP server into the sending and the receiving halves
+///
+/// # Examples
+/// ```no_run
+/// use std::net::TcpStream;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let (sender, receiver) = modbus::tcp::split(TcpStream::connect("192.168.0.10:502").unwrap()).unwrap();
+/// thread::spawn(move || receiver.run());
+///
+/// let first = sender.send_req(1, &modbus::ReadHldRegRequest::new(0x0000, 10)).unwrap();
+/// let second = sender.send_req(1, &modbus::ReadInRegRequest::new(0x0000, 10)).unwrap();
+/// let hld_reg = first.wait(Duration::from_secs(1)).unwrap();
+/// let in_reg = second.wait(Duration::from_secs(1)).unwrap();
+/// ```
+pub fn split(socket: TcpStream) -> Result<(SendHalf, RecvHalf), Error> {
+    socket.set_read_timeout(None)?;
+    let correlator = Arc::new(Correlator::default());
+    let sender = SendHalf {socket: Arc::new(Mutex::new(socket.try_clone()?)), correlator: correlator.clone()};
+    Ok((sender, RecvHalf {socket, correlator}))
+}
+
+/// Half of a split connection writing requests
+///
+/// It may be cloned to send requests from multiple threads.
+#[derive(Clone)]
+pub struct SendHalf {
+    socket: Arc<Mutex<TcpStream>>,
+    correlator: Arc<Correlator>,
+}
+
+impl SendHalf {
+    /// Write a request PDU addressed to given unit id
+    ///
+    /// Returns [Error::NoResponse] if the receiving half stopped, as no response could be read.
+    pub fn send(&self, unit_id: u8, pdu: &[u8]) -> Result<Pending<Vec<u8>>, Error> {
+        self.send_with(unit_id, pdu, Box::new(Ok))
+    }
+
+    /// Write a request addressed to given unit id, with the response decoded and verified when it arrives
+    pub fn send_req<Req: Request + Clone + Send + 'static>(&self, unit_id: u8, req: &Req) -> Result<Pending<Req::Rsp>, Error> {
+        let checked_req = req.clone();
+        self.send_with(unit_id, &req.encode()?, Box::new(move |pdu| {
+            let rsp = Req::Rsp::decode_response_with_mode(&pdu, DecodeMode::Strict)?;
+            checked_req.check_response(&rsp, DecodeMode::Strict)?;
+            Ok(rsp)
+        }))
+    }
+
+    fn send_with<R>(&self, unit_id: u8, pdu: &[u8], decode: Decode<R>) -> Result<Pending<R>, Error> {
+        let (reply, rx) = mpsc::channel();
+        let transaction_id = self.correlator.register(unit_id, reply)?;
+        let pending = Pending {transaction_id, rx, correlator: self.correlator.clone(), decode: Some(decode)};
+
+        let mut socket = self.socket.lock().unwrap_or_else(|err| err.into_inner());
+        Tcp::write_frame(&mut socket, &Frame::with_transaction_id(transaction_id, unit_id, pdu))?;
+        Ok(pending)
+    }
+
+    /// Shut down the connection, stopping also the receiving half
+    pub fn shutdown(&self) -> Result<(), Error> {
+        self.socket.lock().unwrap_or_else(|err| err.into_inner()).shutdown(Shutdown::Both)?;
+        Ok(())
+    }
+}
+
+/// Half of a split connection reading responses and delivering them to the pending transactions
+///
+/// Transactions in flight fail when the receiving half is dropped.
+pub struct RecvHalf {
+    socket: TcpStream,
+    correlator: Arc<Correlator>,
+}
+
+impl RecvHalf {
+    /// Read a single frame and deliver it to the transaction it responds to
+    ///
+    /// Frames not matching any transaction in flight, e.g. late responses to abandoned ones, are dropped.
+    pub fn recv(&mut self) -> Result<(), Error> {
+        let frame_data = Tcp::read_frame(&mut self.socket)?;
+        self.correlator.deliver(&Frame::decode(&frame_data)?);
+        Ok(())
+    }
+
+    /// Deliver responses until the connection fails or is closed, returning the error which stopped it
+    pub fn run(mut self) -> Error {
+        loop {
+            if let Err(err) = self.recv() {
+                return err;
+            }
+        }
+    }
+}
+
+impl Drop for RecvHalf {
+    fn drop(&mut self) {
+        self.correlator.close();
+    }
+}
+
+/// Transaction waiting for its response
+///
+/// Dropping it abandons the transaction, its response is ignored if it arrives.
+pub struct Pending<R> {
+    transaction_id: u16,
+    rx: mpsc::Receiver<Result<Vec<u8>, Error>>,
+    correlator: Arc<Correlator>,
+    decode: Option<Decode<R>>,
+}
+
+impl<R> Pending<R> {
+    /// Get transaction id assigned to the request
+    pub fn get_transaction_id(&self) -> u16 {
+        self.transaction_id
+    }
+
+    /// Wait for the response up to given time
+    ///
+    /// Returns [Error::NoResponse] if the response did not arrive in time or the receiving half stopped.
+    pub fn wait(mut self, timeout: Duration) -> Result<R, Error> {
+        let pdu = self.rx.recv_timeout(timeout).map_err(|_| Error::NoResponse)??;
+        let decode = self.decode.take().ok_or(Error::NoResponse)?;
+        decode(pdu)
+    }
+}
+
+impl<R> Drop for Pending<R> {
+    fn drop(&mut self) {
+        self.correlator.remove(self.transaction_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::thread;
+
+    fn connect() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    fn read_req(socket: &mut TcpStream) -> (u16, u8, Vec<u8>) {
+        let frame_data = Tcp::read_frame(socket).unwrap();
+        let frame = Frame::decode(&frame_data).unwrap();
+        (frame.get_transaction_id(), frame.get_unit_id(), frame.get_pdu())
+    }
+
+    #[test]
+    fn test_out_of_order_responses() {
+        let (client, mut server) = connect();
+        let (sender, receiver) = split(client).unwrap();
+        let reader = thread::spawn(move || receiver.run());
+
+        let first = sender.send(1, &[0x03, 0x00, 0x00, 0x00, 0x01]).unwrap();
+        let second = sender.send(2, &[0x04, 0x00, 0x00, 0x00, 0x01]).unwrap();
+        assert_ne!(first.get_transaction_id(), second.get_transaction_id());
+
+        let (first_id, first_unit, _) = read_req(&mut server);
+        let (second_id, second_unit, _) = read_req(&mut server);
+        Tcp::write_frame(&mut server, &Frame::with_transaction_id(second_id, second_unit, &[0x04, 0x02, 0x00, 0x02])).unwrap();
+        Tcp::write_frame(&mut server, &Frame::with_transaction_id(first_id, first_unit, &[0x03, 0x02, 0x00, 0x01])).unwrap();
+
+        assert_eq!(second.wait(Duration::from_secs(1)).unwrap(), vec![0x04, 0x02, 0x00, 0x02]);
+        assert_eq!(first.wait(Duration::from_secs(1)).unwrap(), vec![0x03, 0x02, 0x00, 0x01]);
+
+        sender.shutdown().unwrap();
+        reader.join().unwrap();
+    }
+
+    #[test]
+    fn test_send_req() {
+        let (client, mut server) = connect();
+        let (sender, receiver) = split(client).unwrap();
+        let reader = thread::spawn(move || receiver.run());
+
+        let valid = sender.send_req(1, &crate::ReadHldRegRequest::new(0x0010, 2)).unwrap();
+        let invalid = sender.send_req(1, &crate::ReadHldRegRequest::new(0x0020, 2)).unwrap();
+
+        let (valid_id, unit_id, pdu) = read_req(&mut server);
+        assert_eq!(pdu, vec![0x03, 0x00, 0x10, 0x00, 0x02]);
+        let (invalid_id, _, _) = read_req(&mut server);
+        Tcp::write_frame(&mut server, &Frame::with_transaction_id(valid_id, unit_id, &[0x03, 0x04, 0x12, 0x34, 0x56, 0x78])).unwrap();
+        Tcp::write_frame(&mut server, &Frame::with_transaction_id(invalid_id, unit_id, &[0x03, 0x02, 0x12, 0x34])).unwrap();
+
+        assert_eq!(valid.wait(Duration::from_secs(1)).unwrap().get_registers(), &[0x1234, 0x5678]);
+        assert!(invalid.wait(Duration::from_secs(1)).is_err());
+
+        sender.shutdown().unwrap();
+        reader.join().unwrap();
+    }
+
+    #[test]
+    fn test_unit_id_mismatch() {
+        let (client, mut server) = connect();
+        let (sender, receiver) = split(client).unwrap();
+        let reader = thread::spawn(move || receiver.run());
+
+        let pending = sender.send(1, &[0x03, 0x00, 0x00, 0x00, 0x01]).unwrap();
+        let (transaction_id, _, _) = read_req(&mut server);
+        Tcp::write_frame(&mut server, &Frame::with_transaction_id(transaction_id, 2, &[0x03, 0x02, 0x00, 0x01])).unwrap();
+
+        match pending.wait(Duration::from_secs(1)).err().unwrap() {
+            Error::InvalidData => {}
+            err => panic!("Expected InvalidData, but got {:?}", err),
+        }
+
+        sender.shutdown().unwrap();
+        reader.join().unwrap();
+    }
+
+    #[test]
+    fn test_send_after_receiver_stopped() {
+        let (client, server) = connect();
+        let (sender, receiver) = split(client).unwrap();
+        let pending = sender.send(1, &[0x03, 0x00, 0x00, 0x00, 0x01]).unwrap();
+
+        drop(server);
+        receiver.run();
+
+        match pending.wait(Duration::from_secs(1)).err().unwrap() {
+            Error::NoResponse => {}
+            err => panic!("Expected NoResponse, but got {:?}", err),
+        }
+        match sender.send(1, &[0x03, 0x00, 0x00, 0x00, 0x01]).err().unwrap() {
+            Error::NoResponse => {}
+            err => panic!("Expected NoResponse, but got {:?}", err),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Typed values kept in consecutive 16-bit registers
+//!
+//! Modbus defines only 16-bit registers. Wider values span multiple consecutive registers,
+//! and devices differ in the order of the words: [WordOrder] selects it. Each register keeps
+//! its word with the most significant byte first, as defined by the protocol.
+//!
+//! With the `derive` feature, `#[derive(ModbusLayout)]` implements [RegisterValue] for structs
+//! whose fields are kept in consecutive registers, so a whole data block of a device can be read
+//! or written in a single transaction, e.g. with `Client::read_value`.
+//!
+//! # Examples
+//! ```
+//! use modbus::value::{RegisterValue, WordOrder};
+//!
+//! let registers = 1.5f32.to_registers(WordOrder::HighWordFirst);
+//! assert_eq!(registers, vec![0x3fc0, 0x0000]);
+//! assert_eq!(f32::from_registers(&[0x0000, 0x3fc0], WordOrder::LowWordFirst).unwrap(), 1.5);
+//! ```
+
+use alloc::vec::Vec;
+use crate::error::Error;
+
+#[cfg(feature = "derive")]
+pub use modbus_derive::ModbusLayout;
+
+/// Order of the registers keeping a value wider than a single register
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum WordOrder {
+    /// The most significant word is kept in the first register
+    #[default]
+    HighWordFirst,
+    /// The least significant word is kept in the first register
+    LowWordFirst,
+}
+
+/// Value kept in a fixed number of consecutive registers
+pub trait RegisterValue: Sized {
+    /// Number of registers keeping the value
+    const SIZE: u16;
+
+    /// Decode the value from registers arranged in given word order
+    ///
+    /// It fails with [Error::InvalidDataLength] if the number of registers is other than [RegisterValue::SIZE].
+    fn from_registers(registers: &[u16], word_order: WordOrder) -> Result<Self, Error>;
+
+    /// Encode the value into registers arranged in given word order
+    fn to_registers(&self, word_order: WordOrder) -> Vec<u16>;
+}
+
+/// Join registers arranged in given word order into an unsigned integer
+fn join(registers: &[u16], size: u16, word_order: WordOrder) -> Result<u64, Error> {
+    if registers.len() != size as usize {
+        return Err(Error::InvalidDataLength);
+    }
+
+    let push = |value: u64, word: &u16| value << 16 | *word as u64;
+    Ok(match word_order {
+        WordOrder::HighWordFirst => registers.iter().fold(0, push),
+        WordOrder::LowWordFirst => registers.iter().rev().fold(0, push),
+    })
+}
+
+/// Split an unsigned integer into registers arranged in given word order
+fn split(value: u64, size: u16, word_order: WordOrder) -> Vec<u16> {
+    let mut registers: Vec<u16> = (0..size).rev().map(|i| (value >> (16 * i)) as u16).collect();
+    if word_order == WordOrder::LowWordFirst {
+        registers.reverse();
+    }
+    registers
+}
+
+/// Implement [RegisterValue] for an integer type kept in given number of registers
+macro_rules! impl_integer {
+    ($type:ty, $unsigned:ty, $size:expr) => {
+        impl RegisterValue for $type {
+            const SIZE: u16 = $size;
+
+            fn from_registers(registers: &[u16], word_order: WordOrder) -> Result<Self, Error> {
+                Ok(join(registers, Self::SIZE, word_order)? as $unsigned as $type)
+            }
+
+            fn to_registers(&self, word_order: WordOrder) -> Vec<u16> {
+                split(*self as $unsigned as u64, Self::SIZE, word_order)
+            }
+        }
+    };
+}
+
+impl_integer!(u16, u16, 1);
+impl_integer!(i16, u16, 1);
+impl_integer!(u32, u32, 2);
+impl_integer!(i32, u32, 2);
+impl_integer!(u64, u64, 4);
+impl_integer!(i64, u64, 4);
+
+impl RegisterValue for f32 {
+    const SIZE: u16 = 2;
+
+    fn from_registers(registers: &[u16], word_order: WordOrder) -> Result<Self, Error> {
+        Ok(f32::from_bits(join(registers, Self::SIZE, word_order)? as u32))
+    }
+
+    fn to_registers(&self, word_order: WordOrder) -> Vec<u16> {
+        split(self.to_bits() as u64, Self::SIZE, word_order)
+    }
+}
+
+/// Convert a packed BCD number into binary
+///
+/// It fails with [Error::InvalidData] if any of the nibbles is not a decimal digit.
+///
+/// # Examples
+/// ```
+/// assert_eq!(modbus::value::from_bcd(0x1234).unwrap(), 1234);
+/// assert!(modbus::value::from_bcd(0x12a4).is_err());
+/// ```
+pub fn from_bcd(bcd: u64) -> Result<u64, Error> {
+    let mut value = 0;
+
+    for shift in (0..u64::BITS).step_by(4).rev() {
+        let digit = (bcd >> shift) & 0xf;
+        if digit > 9 {
+            return Err(Error::InvalidData);
+        }
+        value = value * 10 + digit;
+    }
+
+    Ok(value)
+}
+
+/// Convert a binary number into packed BCD
+///
+/// It fails with [Error::InvalidValue] if the number has more than 16 decimal digits.
+///
+/// # Examples
+/// ```
+/// assert_eq!(modbus::value::to_bcd(1234).unwrap(), 0x1234);
+/// ```
+pub fn to_bcd(value: u64) -> Result<u64, Error> {
+    let mut rest = value;
+    let mut bcd = 0;
+
+    for shift in (0..u64::BITS).step_by(4) {
+        bcd |= (rest % 10) << shift;
+        rest /= 10;
+    }
+
+    if rest == 0 {
+        Ok(bcd)
+    } else {
+        Err(Error::InvalidValue)
+    }
+}
+
+/// Unsigned integer kept in packed BCD, four decimal digits per register
+///
+/// # Examples
+/// ```
+/// use modbus::value::{Bcd, RegisterValue, WordOrder};
+///
+/// let energy = Bcd::<u32>::from_registers(&[0x0012, 0x3456], WordOrder::HighWordFirst).unwrap();
+/// assert_eq!(energy.get(), 123456);
+/// assert_eq!(Bcd::<u16>::new(42).unwrap().to_registers(WordOrder::HighWordFirst), vec![0x0042]);
+/// assert!(Bcd::<u16>::new(10000).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Bcd<T> {
+    value: T,
+}
+
+impl<T: Copy> Bcd<T> {
+    /// Get the binary value
+    pub fn get(&self) -> T {
+        self.value
+    }
+}
+
+/// Implement [RegisterValue] for BCD numbers of given type kept in given number of registers
+macro_rules! impl_bcd {
+    ($type:ty, $size:expr) => {
+        impl Bcd<$type> {
+            /// Create a BCD number of given binary value
+            ///
+            /// It fails with [Error::InvalidValue] if the value has more decimal digits than fit in the registers.
+            pub fn new(value: $type) -> Result<Self, Error> {
+                if (value as u64) < 10u64.pow(4 * $size) {
+                    Ok(Self {value})
+                } else {
+                    Err(Error::InvalidValue)
+                }
+            }
+        }
+
+        impl RegisterValue for Bcd<$type> {
+            const SIZE: u16 = $size;
+
+            fn from_registers(registers: &[u16], word_order: WordOrder) -> Result<Self, Error> {
+                Ok(Self {value: from_bcd(join(registers, Self::SIZE, word_order)?)? as $type})
+            }
+
+            fn to_registers(&self, word_order: WordOrder) -> Vec<u16> {
+                // The value was verified to fit in the registers when the number was created
+                split(to_bcd(self.value as u64).unwrap_or_default(), Self::SIZE, word_order)
+            }
+        }
+    };
+}
+
+impl_bcd!(u16, 1);
+impl_bcd!(u32, 2);
+impl_bcd!(u64, 4);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_f32_word_order() {
+        assert_eq!((-2.25f32).to_registers(WordOrder::HighWordFirst), vec![0xc010, 0x0000]);
+        assert_eq!((-2.25f32).to_registers(WordOrder::LowWordFirst), vec![0x0000, 0xc010]);
+        assert_eq!(f32::from_registers(&[0xc010, 0x0000], WordOrder::HighWordFirst).unwrap(), -2.25);
+        assert_eq!(f32::from_registers(&[0x0000, 0xc010], WordOrder::LowWordFirst).unwrap(), -2.25);
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(0x12345678u32.to_registers(WordOrder::HighWordFirst), vec![0x1234, 0x5678]);
+        assert_eq!((-2i32).to_registers(WordOrder::LowWordFirst), vec![0xfffe, 0xffff]);
+        assert_eq!(0x0102030405060708u64.to_registers(WordOrder::HighWordFirst), vec![0x0102, 0x0304, 0x0506, 0x0708]);
+        assert_eq!((-2i64).to_registers(WordOrder::LowWordFirst), vec![0xfffe, 0xffff, 0xffff, 0xffff]);
+
+        assert_eq!(u32::from_registers(&[0x5678, 0x1234], WordOrder::LowWordFirst).unwrap(), 0x12345678);
+        assert_eq!(i32::from_registers(&[0xffff, 0xfffe], WordOrder::HighWordFirst).unwrap(), -2);
+        assert_eq!(u64::from_registers(&[0x0708, 0x0506, 0x0304, 0x0102], WordOrder::LowWordFirst).unwrap(), 0x0102030405060708);
+        assert_eq!(i64::from_registers(&[0x8000, 0x0000, 0x0000, 0x0000], WordOrder::HighWordFirst).unwrap(), i64::MIN);
+    }
+
+    #[test]
+    fn test_bcd() {
+        assert_eq!(from_bcd(0x9876_5432_1098_7654).unwrap(), 9876_5432_1098_7654);
+        assert!(matches!(from_bcd(0x0000_000f), Err(Error::InvalidData)));
+        assert_eq!(to_bcd(9999_9999_9999_9999).unwrap(), 0x9999_9999_9999_9999);
+        assert!(matches!(to_bcd(1_0000_0000_0000_0000), Err(Error::InvalidValue)));
+    }
+
+    #[test]
+    fn test_bcd_registers() {
+        assert_eq!(Bcd::<u16>::from_registers(&[0x9999], WordOrder::HighWordFirst).unwrap().get(), 9999);
+        assert_eq!(Bcd::<u32>::from_registers(&[0x5678, 0x1234], WordOrder::LowWordFirst).unwrap().get(), 12345678);
+        assert!(matches!(Bcd::<u16>::from_registers(&[0x0a00], WordOrder::HighWordFirst), Err(Error::InvalidData)));
+
+        assert_eq!(Bcd::<u32>::new(12345678).unwrap().to_registers(WordOrder::LowWordFirst), vec![0x5678, 0x1234]);
+        assert_eq!(Bcd::<u64>::new(1).unwrap().to_registers(WordOrder::HighWordFirst), vec![0x0000, 0x0000, 0x0000, 0x0001]);
+        assert!(matches!(Bcd::<u32>::new(100_000_000), Err(Error::InvalidValue)));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_layout() {
+        #[derive(Debug, PartialEq, ModbusLayout)]
+        struct Block {
+            flags: u16,
+            #[modbus(skip = 1)]
+            temperature: i16,
+            #[modbus(word_order = "high_word_first")]
+            counter: u32,
+            total: Bcd<u32>,
+        }
+
+        let block = Block {flags: 0x8001, temperature: -5, counter: 0x00010002, total: Bcd::<u32>::new(1234).unwrap()};
+        let registers = vec![0x8001, 0x0000, 0xfffb, 0x0001, 0x0002, 0x1234, 0x0000];
+
+        assert_eq!(Block::SIZE, 7);
+        assert_eq!(block.to_registers(WordOrder::LowWordFirst), registers);
+        assert_eq!(Block::from_registers(&registers, WordOrder::LowWordFirst).unwrap(), block);
+        assert!(matches!(Block::from_registers(&registers[1..], WordOrder::LowWordFirst), Err(Error::InvalidDataLength)));
+    }
+
+    #[test]
+    fn test_invalid_len() {
+        assert!(matches!(f32::from_registers(&[0x3fc0], WordOrder::HighWordFirst), Err(Error::InvalidDataLength)));
+        assert!(matches!(f32::from_registers(&[0x3fc0, 0x0000, 0x0000], WordOrder::HighWordFirst), Err(Error::InvalidDataLength)));
+        assert!(matches!(u64::from_registers(&[0x0000, 0x0000], WordOrder::HighWordFirst), Err(Error::InvalidDataLength)));
+    }
+}